@@ -0,0 +1,7 @@
+//! The shared library that Android loads the app from, which the main crate is not built as.
+
+#[cfg(target_os = "android")]
+#[no_mangle]
+fn android_main(android_app: winit::platform::android::activity::AndroidApp) {
+    global_clock::android_main(android_app);
+}
@@ -0,0 +1,38 @@
+struct Uniforms {
+    // Premultiplied.
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct Viewport {
+    proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> viewport: Viewport;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // Corners of the -1..1 square as a triangle strip.
+    let local = vec2<f32>(f32(index / 2u), f32(index % 2u)) * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.position = viewport.proj * vec4<f32>(local, 0.0, 1.0);
+    out.local = local;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Smooth the edge over about a pixel.
+    let radius = length(in.local);
+    let edge = fwidth(radius);
+    let coverage = 1.0 - smoothstep(1.0 - edge, 1.0, radius);
+    return uniforms.color * coverage;
+}
@@ -5,6 +5,7 @@ struct Uniforms {
     min_latitude: f32,
     max_latitude: f32,
     deflection_point: vec2<f32>,
+    terminator_tint: vec4<f32>,
 };
 
 @group(0) @binding(0)
@@ -99,9 +100,17 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var night_color: vec4<f32> = textureSample(globe_night_texture, globe_sampler, tex_coord);
     var globe_color: vec4<f32> = lerp4(night_day_blend, night_color, day_color);
 
+    // Peaks at 1.0 on the terminator, falling off to 0.0 in full day or night.
+    var twilight: f32 = 4.0 * night_day_blend * (1.0 - night_day_blend);
+    globe_color = vec4<f32>(
+        mix(globe_color.rgb, uniforms.terminator_tint.rgb, twilight * uniforms.terminator_tint.a),
+        globe_color.a,
+    );
+
     if (radius <= 1.0) {
         return globe_color;
     } else {
-        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        // Let the background show through around the globe.
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
 }
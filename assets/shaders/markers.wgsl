@@ -0,0 +1,38 @@
+struct Viewport {
+    proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> viewport: Viewport;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    // Position within the dot or across the line, which is covered out to a length of 1.0.
+    @location(1) local: vec2<f32>,
+    // Premultiplied.
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = viewport.proj * vec4<f32>(in.position, 0.0, 1.0);
+    out.local = in.local;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Smooth the edge over about a pixel.
+    let distance = length(in.local);
+    let edge = fwidth(distance);
+    let coverage = 1.0 - smoothstep(1.0 - edge, 1.0, distance);
+    return in.color * coverage;
+}
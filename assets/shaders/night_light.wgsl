@@ -0,0 +1,19 @@
+struct Uniforms {
+    // Linear factors that the red, green and blue of the frame are multiplied by.
+    tint: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // Corners of the whole target as a triangle strip.
+    let corner = vec2<f32>(f32(index / 2u), f32(index % 2u)) * 2.0 - 1.0;
+    return vec4<f32>(corner, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return uniforms.tint;
+}
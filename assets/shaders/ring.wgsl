@@ -0,0 +1,55 @@
+struct Uniforms {
+    // Premultiplied.
+    color: vec4<f32>,
+    // Part of the ring that is drawn, clockwise from the top.
+    fraction: f32,
+    inner_radius: f32,
+    outer_radius: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct Viewport {
+    proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> viewport: Viewport;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+};
+
+const TAU: f32 = 6.283185;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // Corners of the -1..1 square as a triangle strip.
+    let local = vec2<f32>(f32(index / 2u), f32(index % 2u)) * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.position = viewport.proj * vec4<f32>(local, 0.0, 1.0);
+    out.local = local;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Smooth the edges over about a pixel.
+    let radius = length(in.local);
+    let pixel = fwidth(radius);
+    let ring = smoothstep(uniforms.inner_radius - pixel, uniforms.inner_radius, radius)
+        * (1.0 - smoothstep(uniforms.outer_radius, uniforms.outer_radius + pixel, radius));
+
+    // Turns clockwise from the top, 0.0 to 1.0.
+    let turn = fract(atan2(in.local.x, in.local.y) / TAU);
+    // Distance along the ring from each end of the arc, in pixels.
+    let arc_pixels = TAU * radius / pixel;
+    var arc = clamp(turn * arc_pixels + 0.5, 0.0, 1.0)
+        * clamp((uniforms.fraction - turn) * arc_pixels + 0.5, 0.0, 1.0);
+    if (uniforms.fraction >= 1.0) {
+        arc = 1.0;
+    }
+    return uniforms.color * ring * arc;
+}
@@ -0,0 +1,45 @@
+struct Uniforms {
+    target_size: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct Quad {
+    // Left, top, right and bottom edges, in pixels from the top left corner of the target.
+    @location(0) rect: vec4<f32>,
+    // The same edges in the glyph atlas.
+    @location(1) uv_rect: vec4<f32>,
+    // Linear, premultiplied.
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, quad: Quad) -> VertexOutput {
+    // Corners of the quad as a triangle strip: top left, bottom left, top right, bottom right.
+    let corner = vec2<f32>(f32(index / 2u), f32(index % 2u));
+    let pixel = mix(quad.rect.xy, quad.rect.zw, corner);
+    let ndc = pixel / uniforms.target_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc, 0.0, 1.0);
+    out.uv = mix(quad.uv_rect.xy, quad.uv_rect.zw, corner);
+    out.color = quad.color;
+    return out;
+}
+
+@group(0) @binding(1)
+var t_sampler: sampler;
+@group(0) @binding(2)
+var atlas: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The atlas holds the coverage of each pixel by the glyphs.
+    return in.color * textureSample(atlas, t_sampler, in.uv).r;
+}
@@ -0,0 +1,144 @@
+use chrono::{NaiveTime, TimeZone, Utc};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use global_clock::clock_face::Renderer;
+use global_clock::{
+    Assets, Background, ClockFace, FrameInfo, Globe, GraphicsContext, GraphicsContextInner, Layer,
+    StagingPool, Theme, Viewport,
+};
+use std::sync::Arc;
+
+const SIZE: u32 = 512;
+
+/// Opens a device without a window, or returns `None` if there is none.
+fn open_device() -> Option<GraphicsContext> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&Default::default(), None)).ok()?;
+    Some(Arc::new(GraphicsContextInner {
+        device,
+        queue,
+        render_format: wgpu::TextureFormat::Rgba8UnormSrgb,
+    }))
+}
+
+fn frame(second: u32) -> FrameInfo {
+    let time = Utc.with_ymd_and_hms(2024, 6, 20, 0, 0, 0).unwrap()
+        + chrono::Duration::seconds(second.into());
+    FrameInfo {
+        time,
+        local_time: time.time(),
+        local_date: time.date_naive(),
+        timezone: Some("UTC"),
+    }
+}
+
+/// Draws one frame of `layer` and waits for the GPU to finish it.
+fn draw(
+    gfx: &GraphicsContext,
+    view: &wgpu::TextureView,
+    staging: &mut StagingPool,
+    viewport: &Viewport,
+    layer: &mut dyn Layer,
+) {
+    let mut encoder = gfx.device.create_command_encoder(&Default::default());
+    staging.recall();
+    layer.prepare(&mut encoder, staging);
+    staging.finish();
+    let mut render_pass = Background::transparent().begin_render_pass(&mut encoder, view);
+    layer.draw(&mut render_pass, viewport);
+    drop(render_pass);
+    gfx.queue.submit([encoder.finish()]);
+    gfx.device.poll(wgpu::Maintain::Wait);
+}
+
+fn clock_face_redraw(c: &mut Criterion) {
+    let mut renderer = Renderer::new(&Theme::default()).unwrap();
+    let mut group = c.benchmark_group("clock_face");
+    group.throughput(Throughput::Bytes(renderer.data().len() as u64));
+    let mut second = 0;
+    group.bench_function("redraw", |b| {
+        b.iter(|| {
+            second = (second + 60) % 86400;
+            renderer.set_time(&NaiveTime::from_num_seconds_from_midnight_opt(second, 0).unwrap());
+            renderer.redraw();
+        })
+    });
+    group.finish();
+}
+
+fn gpu(c: &mut Criterion) {
+    let Some(gfx) = open_device() else {
+        eprintln!("no graphics adapter; skipping GPU benchmarks");
+        return;
+    };
+    let assets = Assets::new(Vec::new());
+    let theme = Theme::default();
+    let mut viewport = Viewport::new(&gfx, SIZE, SIZE, Default::default());
+    let texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("bench.texture"),
+        size: wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            ..Default::default()
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: gfx.render_format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+    let mut staging = StagingPool::new(&gfx);
+
+    // Moves the hands on every frame, which redraws and uploads the part of the pixmap around them.
+    let mut clock_face = ClockFace::new(&gfx, &viewport, &assets, &theme, None).unwrap();
+    clock_face.update(&frame(0));
+    draw(&gfx, &view, &mut staging, &viewport, &mut clock_face);
+    clock_face.update(&frame(60));
+    draw(&gfx, &view, &mut staging, &viewport, &mut clock_face);
+    let mut group = c.benchmark_group("clock_face");
+    group.throughput(Throughput::Bytes(clock_face.uploaded_bytes()));
+    let mut second = 60;
+    group.bench_function("update_and_upload", |b| {
+        b.iter(|| {
+            second += 60;
+            clock_face.update(&frame(second));
+            draw(&gfx, &view, &mut staging, &viewport, &mut clock_face);
+        })
+    });
+    group.finish();
+
+    // Only writes uniforms, so this is mostly the cost of a render pass and a submission.
+    let mut globe = Globe::new(&gfx, &viewport, &assets, None, &theme, None).unwrap();
+    globe.finish_loading();
+    let mut group = c.benchmark_group("globe");
+    let mut second = 0;
+    group.bench_function("update_and_draw", |b| {
+        b.iter(|| {
+            second += 60;
+            globe.update(&frame(second));
+            draw(&gfx, &view, &mut staging, &viewport, &mut globe);
+        })
+    });
+    group.finish();
+
+    // Submitting frees the staging memory of the uniform writes.
+    let mut group = c.benchmark_group("viewport");
+    group.bench_function("set_camera", |b| {
+        b.iter(|| {
+            let mut camera = viewport.camera();
+            camera.rotate_by(0.01);
+            viewport.set_camera(camera);
+            gfx.queue.submit([]);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, clock_face_redraw, gpu);
+criterion_main!(benches);
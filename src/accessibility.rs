@@ -0,0 +1,149 @@
+//! Describes the main window to assistive technologies, e.g. screen readers, through AccessKit.
+
+use crate::config::AccessibilityConfig;
+use accesskit::{
+    ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, Live, Node, NodeId, Role,
+    Tree, TreeUpdate,
+};
+use std::sync::{Arc, Mutex};
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::Window;
+
+const ROOT: NodeId = NodeId(0);
+const TIME: NodeId = NodeId(1);
+const TIMEZONE: NodeId = NodeId(2);
+const ANNOUNCEMENT: NodeId = NodeId(3);
+/// The first of the nodes of the layers' descriptions.
+const LAYERS: u64 = 16;
+
+/// What is read out about the clock.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Description {
+    /// The window's title.
+    pub title: String,
+    /// The displayed time, to the minute.
+    pub time: String,
+    pub timezone: String,
+    /// Descriptions of the layers that have one, e.g. the time left on a countdown.
+    pub layers: Vec<String>,
+    /// Read out as soon as it changes, e.g. on the hour.
+    pub announcement: String,
+}
+
+impl Description {
+    fn tree(&self) -> TreeUpdate {
+        let label = |label: &str, value: &str| {
+            let mut node = Node::new(Role::Label);
+            node.set_label(label);
+            if !value.is_empty() {
+                node.set_value(value);
+            }
+            node
+        };
+        let mut announcement = Node::new(Role::Label);
+        announcement.set_label(self.announcement.as_str());
+        announcement.set_live(Live::Polite);
+        let layers = (0..self.layers.len() as u64).map(|index| NodeId(LAYERS + index));
+
+        let mut root = Node::new(Role::Window);
+        root.set_label(self.title.as_str());
+        let mut children = vec![TIME, TIMEZONE, ANNOUNCEMENT];
+        children.extend(layers.clone());
+        root.set_children(children);
+        let mut nodes = vec![
+            (ROOT, root),
+            (TIME, label("Time", &self.time)),
+            (TIMEZONE, label("Timezone", &self.timezone)),
+            (ANNOUNCEMENT, announcement),
+        ];
+        nodes.extend(
+            layers
+                .zip(&self.layers)
+                .map(|(id, text)| (id, label(text, ""))),
+        );
+        TreeUpdate {
+            nodes,
+            tree: Some(Tree::new(ROOT)),
+            focus: ROOT,
+        }
+    }
+}
+
+/// Gives the initial tree when an assistive technology starts reading the window, which may
+/// happen on another thread.
+struct Activation(Arc<Mutex<Description>>);
+
+impl ActivationHandler for Activation {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        Some(self.0.lock().unwrap().tree())
+    }
+}
+
+/// The clock has nothing to be done through assistive technologies, such as buttons.
+struct NoActions;
+
+impl ActionHandler for NoActions {
+    fn do_action(&mut self, _request: ActionRequest) {}
+}
+
+impl DeactivationHandler for NoActions {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+/// The accessibility tree of the main window.
+pub struct Accessibility {
+    adapter: accesskit_winit::Adapter,
+    description: Arc<Mutex<Description>>,
+    announce_hours: bool,
+    /// The hour shown when the description was last updated.
+    hour: Option<u32>,
+}
+
+impl Accessibility {
+    /// Attaches to `window`, which must not have been shown yet.
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        window: &Window,
+        config: &AccessibilityConfig,
+    ) -> Self {
+        let description = Arc::new(Mutex::new(Description {
+            title: window.title(),
+            ..Default::default()
+        }));
+        let adapter = accesskit_winit::Adapter::with_direct_handlers(
+            event_loop,
+            window,
+            Activation(description.clone()),
+            NoActions,
+            NoActions,
+        );
+        Self {
+            adapter,
+            description,
+            announce_hours: config.announce_hours,
+            hour: None,
+        }
+    }
+
+    /// Passes on an event of the window, before the app handles it.
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        self.adapter.process_event(window, event);
+    }
+
+    /// Updates the tree if anything read out has changed. `hour` is that of the displayed time,
+    /// which is announced when it changes if enabled.
+    pub fn update(&mut self, mut description: Description, hour: u32) {
+        let mut current = self.description.lock().unwrap();
+        description.title.clone_from(&current.title);
+        description.announcement.clone_from(&current.announcement);
+        if self.announce_hours && self.hour.is_some_and(|last| last != hour) {
+            description.announcement = format!("It is {}", description.time);
+        }
+        self.hour = Some(hour);
+        if description != *current {
+            *current = description;
+            self.adapter.update_if_active(|| current.tree());
+        }
+    }
+}
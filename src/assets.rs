@@ -0,0 +1,308 @@
+use anyhow::{anyhow, Context};
+use image::RgbaImage;
+use once_cell::sync::OnceCell;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+
+/// Asset directory used when an asset is not found anywhere else.
+///
+/// Packagers can set `GLOBAL_CLOCK_ASSET_DIR` at build time to point this at the installed
+/// location (e.g. `/usr/share/global-clock`); otherwise it is the source tree's `assets`
+/// directory, which is convenient during development.
+#[cfg(not(feature = "bundled-assets"))]
+const FALLBACK_DIR: &str = match option_env!("GLOBAL_CLOCK_ASSET_DIR") {
+    Some(dir) => dir,
+    None => concat!(env!("CARGO_MANIFEST_DIR"), "/assets"),
+};
+
+/// Assets embedded in the executable, used when an asset is not found on disk.
+#[cfg(feature = "bundled-assets")]
+const BUNDLED: &[(&str, &[u8])] = &[
+    (
+        "data/population.csv",
+        include_bytes!("../assets/data/population.csv"),
+    ),
+    (
+        "fonts/DejaVuSans.ttf",
+        include_bytes!("../assets/fonts/DejaVuSans.ttf"),
+    ),
+    (
+        "fonts/DejaVuSansMono.ttf",
+        include_bytes!("../assets/fonts/DejaVuSansMono.ttf"),
+    ),
+    (
+        "shaders/clock_face.wgsl",
+        include_bytes!("../assets/shaders/clock_face.wgsl"),
+    ),
+    (
+        "shaders/disc.wgsl",
+        include_bytes!("../assets/shaders/disc.wgsl"),
+    ),
+    (
+        "shaders/globe.wgsl",
+        include_bytes!("../assets/shaders/globe.wgsl"),
+    ),
+    (
+        "shaders/markers.wgsl",
+        include_bytes!("../assets/shaders/markers.wgsl"),
+    ),
+    (
+        "shaders/night_light.wgsl",
+        include_bytes!("../assets/shaders/night_light.wgsl"),
+    ),
+    (
+        "shaders/ring.wgsl",
+        include_bytes!("../assets/shaders/ring.wgsl"),
+    ),
+    (
+        "shaders/text.wgsl",
+        include_bytes!("../assets/shaders/text.wgsl"),
+    ),
+    (
+        "textures/globe_day.jpg",
+        include_bytes!("../assets/textures/globe_day.jpg"),
+    ),
+    (
+        "textures/globe_night.jpg",
+        include_bytes!("../assets/textures/globe_night.jpg"),
+    ),
+    (
+        "themes/default.toml",
+        include_bytes!("../assets/themes/default.toml"),
+    ),
+    (
+        "themes/high-contrast.toml",
+        include_bytes!("../assets/themes/high-contrast.toml"),
+    ),
+    (
+        "themes/midnight.toml",
+        include_bytes!("../assets/themes/midnight.toml"),
+    ),
+    (
+        "themes/paper.toml",
+        include_bytes!("../assets/themes/paper.toml"),
+    ),
+];
+
+type ImageCell = Arc<OnceCell<Arc<RgbaImage>>>;
+
+/// Loads shaders, textures and themes by their path relative to an asset directory, such as
+/// `shaders/globe.wgsl`.
+///
+/// Each asset is taken from the first directory in the search path that contains it, or else
+/// from the copy built into the executable (in builds with the `bundled-assets` feature) or the
+/// source tree. Cloning is cheap, and clones share a cache of decoded images.
+#[derive(Clone)]
+pub struct Assets {
+    search_path: Arc<Vec<PathBuf>>,
+    /// The assets packaged in the APK, which come after the search path.
+    #[cfg(target_os = "android")]
+    apk: Option<Arc<ndk::asset::AssetManager>>,
+    /// Decoded images by asset path. An image that is being decoded has an empty cell, which
+    /// others who want it wait on rather than decoding it again.
+    images: Arc<Mutex<HashMap<String, ImageCell>>>,
+    /// Where compressed textures are kept between runs.
+    cache_dir: Option<Arc<PathBuf>>,
+}
+
+impl Assets {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self {
+            search_path: Arc::new(search_path),
+            #[cfg(target_os = "android")]
+            apk: None,
+            images: Default::default(),
+            cache_dir: None,
+        }
+    }
+
+    /// Keeps compressed textures in `cache_dir`, so that they are not compressed again on every
+    /// launch.
+    pub fn with_cache_dir(mut self, cache_dir: &Path) -> Self {
+        self.cache_dir = Some(Arc::new(cache_dir.to_owned()));
+        self
+    }
+
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref().map(PathBuf::as_path)
+    }
+
+    /// Also loads assets from the `assets` directory of the app's APK.
+    #[cfg(target_os = "android")]
+    pub fn with_apk(mut self, asset_manager: ndk::asset::AssetManager) -> Self {
+        self.apk = Some(Arc::new(asset_manager));
+        self
+    }
+
+    /// Searches the standard directories, in order of precedence:
+    ///
+    /// 1. `asset_dir` from the config or command line,
+    /// 2. the user's data directory, `data_dir`,
+    /// 3. the assets in the app bundle on iOS, or the system data directories (`$XDG_DATA_DIRS`)
+    ///    on other Unix systems.
+    pub fn standard(asset_dir: Option<&Path>, data_dir: &Path) -> Self {
+        let mut dirs = Vec::new();
+        dirs.extend(asset_dir.map(Path::to_owned));
+        dirs.push(data_dir.to_owned());
+        if cfg!(target_os = "ios") {
+            // The bundle keeps its resources next to the executable.
+            let exe = std::env::current_exe().ok();
+            dirs.extend(exe.and_then(|exe| Some(exe.parent()?.join("assets"))));
+        } else if cfg!(unix) {
+            let data_dirs = std::env::var("XDG_DATA_DIRS")
+                .ok()
+                .filter(|dirs| !dirs.is_empty())
+                .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+            dirs.extend(
+                data_dirs
+                    .split(':')
+                    .filter(|dir| !dir.is_empty())
+                    .map(|dir| Path::new(dir).join("global-clock")),
+            );
+        }
+        Self::new(dirs)
+    }
+
+    fn search_path(&self) -> impl Iterator<Item = &Path> {
+        let search_path = self.search_path.iter().map(PathBuf::as_path);
+        #[cfg(not(feature = "bundled-assets"))]
+        let search_path = search_path.chain(std::iter::once(Path::new(FALLBACK_DIR)));
+        search_path
+    }
+
+    /// The file that `relative` is loaded from, or `None` if it is only available built in.
+    pub fn find(&self, relative: &str) -> Option<PathBuf> {
+        self.search_path()
+            .map(|dir| dir.join(relative))
+            .find(|path| path.exists())
+    }
+
+    pub fn read(&self, relative: &str) -> anyhow::Result<Cow<'static, [u8]>> {
+        if let Some(path) = self.find(relative) {
+            return std::fs::read(&path)
+                .map(Cow::Owned)
+                .with_context(|| format!("failed to read {}", path.display()));
+        }
+        #[cfg(target_os = "android")]
+        if let Some(apk) = &self.apk {
+            use std::io::Read;
+            let name = std::ffi::CString::new(relative)?;
+            if let Some(mut asset) = apk.open(&name) {
+                let mut contents = Vec::new();
+                asset
+                    .read_to_end(&mut contents)
+                    .with_context(|| format!("failed to read {} from the APK", relative))?;
+                return Ok(Cow::Owned(contents));
+            }
+        }
+        #[cfg(feature = "bundled-assets")]
+        if let Some((_, contents)) = BUNDLED.iter().find(|(name, _)| *name == relative) {
+            return Ok(Cow::Borrowed(contents));
+        }
+        let searched: Vec<_> = self
+            .search_path()
+            .map(|dir| dir.display().to_string())
+            .collect();
+        Err(anyhow!(
+            "asset {} not found in any of: {}; reinstall global-clock, or set `asset_dir` to a \
+             directory containing its assets",
+            relative,
+            searched.join(", ")
+        ))
+    }
+
+    pub fn read_to_string(&self, relative: &str) -> anyhow::Result<Cow<'static, str>> {
+        let invalid = || format!("asset {} is not valid UTF-8", relative);
+        match self.read(relative)? {
+            Cow::Borrowed(bytes) => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .with_context(invalid),
+            Cow::Owned(bytes) => String::from_utf8(bytes)
+                .map(Cow::Owned)
+                .with_context(invalid),
+        }
+    }
+
+    fn image_cell(&self, relative: &str) -> ImageCell {
+        let mut images = self.images.lock().unwrap();
+        images.entry(relative.to_owned()).or_default().clone()
+    }
+
+    /// Decodes an image asset, reusing the result of previous calls with the same asset, or
+    /// waiting for one that is decoding it.
+    pub fn image(&self, relative: &str) -> anyhow::Result<Arc<RgbaImage>> {
+        self.image_cell(relative)
+            .get_or_try_init(|| decode_image(&self.read(relative)?, relative))
+            .cloned()
+    }
+
+    /// Like [`Self::image`], but decodes the image on another thread. The asset is read right away,
+    /// so that a missing asset is still reported here.
+    pub fn image_in_background(&self, relative: &str) -> anyhow::Result<PendingImage> {
+        let cell = self.image_cell(relative);
+        if let Some(image) = cell.get() {
+            return Ok(PendingImage::ready(image.clone()));
+        }
+        let bytes = self.read(relative)?;
+        let relative = relative.to_owned();
+        Ok(PendingImage::spawn(move || {
+            cell.get_or_try_init(|| decode_image(&bytes, &relative))
+                .cloned()
+        }))
+    }
+
+    /// Forgets the decoded images, which are decoded again when next used.
+    pub fn clear_cache(&self) {
+        self.images.lock().unwrap().clear();
+    }
+}
+
+fn decode_image(bytes: &[u8], relative: &str) -> anyhow::Result<Arc<RgbaImage>> {
+    let start = instant::Instant::now();
+    let image = image::load_from_memory(bytes)
+        .with_context(|| format!("failed to decode {}", relative))?
+        .into_rgba8();
+    log::debug!("decoded {} in {:?}", relative, start.elapsed());
+    Ok(Arc::new(image))
+}
+
+/// Something, such as an image, that is being decoded on another thread.
+pub struct Pending<T> {
+    receiver: mpsc::Receiver<anyhow::Result<T>>,
+}
+
+pub type PendingImage = Pending<Arc<RgbaImage>>;
+
+impl<T: Send + 'static> Pending<T> {
+    /// Runs `decode` on a new thread.
+    pub fn spawn(decode: impl FnOnce() -> anyhow::Result<T> + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || sender.send(decode()));
+        Self { receiver }
+    }
+
+    pub fn ready(value: T) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        sender.send(Ok(value)).unwrap();
+        Self { receiver }
+    }
+
+    /// The decoded value, or `None` if it is not ready yet. Not to be called again after it
+    /// returns `Some`.
+    pub fn try_get(&self) -> Option<anyhow::Result<T>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(anyhow!("decoder panicked"))),
+        }
+    }
+
+    /// Blocks until the value is decoded.
+    pub fn wait(self) -> anyhow::Result<T> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(anyhow!("decoder panicked")))
+    }
+}
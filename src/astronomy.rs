@@ -0,0 +1,115 @@
+use crate::location::Location;
+use crate::model;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Timelike, Utc};
+use std::f64::consts::TAU;
+
+/// Altitude of the Sun's center at sunrise and sunset, in degrees. Its upper edge is on the
+/// horizon then, and refraction lifts it by a little more than its radius.
+pub const SUNRISE_ALTITUDE: f64 = -0.833;
+
+/// How far ahead [`next_sun_event`] looks, which is long enough for the polar night.
+const MAX_DAYS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SunEventKind {
+    Sunrise,
+    Sunset,
+}
+
+/// When the Sun rises or sets at a place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SunEvent {
+    pub kind: SunEventKind,
+    pub time: DateTime<Utc>,
+}
+
+/// The sunrise and sunset at `location` on the UTC `date`, with the same model of the Earth's
+/// orbit as the globe. Returns `Err(true)` during the midnight sun and `Err(false)` during the
+/// polar night.
+pub fn sun_events(location: &Location, date: chrono::NaiveDate) -> Result<[SunEvent; 2], bool> {
+    let day = date.ordinal0() as f32;
+    let declination = f64::from(model::axial_tilt(day));
+    let latitude = location.latitude.to_radians();
+    let cos_hour_angle = (SUNRISE_ALTITUDE.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if cos_hour_angle < -1.0 {
+        return Err(true);
+    }
+    if cos_hour_angle > 1.0 {
+        return Err(false);
+    }
+    // The Sun crosses the meridian 4 minutes later for every degree west, and earlier when
+    // sundials run ahead of clocks.
+    let noon_minutes =
+        12.0 * 60.0 - location.longitude * 4.0 - f64::from(model::equation_of_time(day));
+    let half_day_minutes = cos_hour_angle.acos() / TAU * 24.0 * 60.0;
+    let at = |minutes: f64| {
+        date.and_time(NaiveTime::MIN).and_utc() + Duration::milliseconds((minutes * 6e4) as i64)
+    };
+    Ok([
+        SunEvent {
+            kind: SunEventKind::Sunrise,
+            time: at(noon_minutes - half_day_minutes),
+        },
+        SunEvent {
+            kind: SunEventKind::Sunset,
+            time: at(noon_minutes + half_day_minutes),
+        },
+    ])
+}
+
+/// The first sunrise or sunset at `location` after `time`, or `None` if there is none for months,
+/// e.g. at the poles.
+pub fn next_sun_event(location: &Location, time: &DateTime<Utc>) -> Option<SunEvent> {
+    // Events of the previous UTC date can fall on this one far from Greenwich.
+    let start = time.date_naive().pred_opt()?;
+    start
+        .iter_days()
+        .take(MAX_DAYS)
+        .filter_map(|date| sun_events(location, date).ok())
+        .flatten()
+        .find(|event| event.time > *time)
+}
+
+/// Where the Sun is overhead at `time`, as (latitude, longitude) in degrees, with the same model
+/// of the Earth's orbit and rotation as the globe.
+pub fn subsolar_point(time: &DateTime<Utc>) -> (f64, f64) {
+    let seconds = time.num_seconds_from_midnight() as f64;
+    let latitude = f64::from(model::axial_tilt(time.ordinal0() as f32)).to_degrees();
+    // The Sun is over the prime meridian at noon UTC, and moves west.
+    let longitude = 180.0 - seconds / f64::from(model::SECONDS_PER_DAY) * 360.0;
+    (latitude, (longitude + 180.0).rem_euclid(360.0) - 180.0)
+}
+
+/// The line between day and night at `time`, where the center of the Sun is on the horizon, as
+/// `points` points of (latitude, longitude) in degrees from the antimeridian eastwards around to
+/// it again.
+pub fn terminator(time: &DateTime<Utc>, points: usize) -> Vec<(f64, f64)> {
+    let (sun_latitude, sun_longitude) = subsolar_point(time);
+    // At the equinoxes the line runs through the poles along two meridians, which this
+    // approaches without dividing by zero.
+    let declination = match sun_latitude.to_radians() {
+        d if d.abs() < 1e-6 => 1e-6_f64.copysign(d),
+        d => d,
+    };
+    (0..points)
+        .map(|index| {
+            let longitude = -180.0 + 360.0 * index as f64 / (points.max(2) - 1) as f64;
+            let hour_angle = (longitude - sun_longitude).to_radians();
+            let latitude = (-hour_angle.cos() / declination.tan()).atan().to_degrees();
+            (latitude, longitude)
+        })
+        .collect()
+}
+
+/// How high the Sun is above the horizon at `location` at `time`, in degrees, negative below it,
+/// with the same model of the Earth's orbit and rotation as the globe.
+pub fn sun_altitude(location: &Location, time: &DateTime<Utc>) -> f64 {
+    let (sun_latitude, sun_longitude) = subsolar_point(time);
+    let (latitude, declination) = (location.latitude.to_radians(), sun_latitude.to_radians());
+    let hour_angle = (location.longitude - sun_longitude).to_radians();
+    (latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos())
+        .clamp(-1.0, 1.0)
+        .asin()
+        .to_degrees()
+}
@@ -1,24 +1,44 @@
+use crate::theme::Theme;
 use crate::GraphicsContext;
 
-pub struct Background {}
+pub struct Background {
+    color: wgpu::Color,
+}
 
 impl Background {
-    pub fn new(_gfx: &GraphicsContext) -> Self {
-        Self {}
+    pub fn new(_gfx: &GraphicsContext, theme: &Theme) -> Self {
+        Self {
+            color: theme.background.to_wgpu(),
+        }
+    }
+
+    /// Clears the target to transparent, e.g. for a window that only shows a [`Disc`].
+    ///
+    /// [`Disc`]: crate::Disc
+    pub fn transparent() -> Self {
+        Self {
+            color: wgpu::Color::TRANSPARENT,
+        }
     }
 
-    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+    /// Begins the render pass that everything in a frame is drawn in, clearing `frame_view` to
+    /// the background color.
+    pub fn begin_render_pass<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        frame_view: &'a wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
         encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Background.render_pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                 view: frame_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Clear(self.color),
                     store: true,
                 },
             })],
             depth_stencil_attachment: None,
-        });
+        })
     }
 }
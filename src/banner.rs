@@ -0,0 +1,373 @@
+use crate::layer::{Control, FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::palette::Palette;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::{TextLayer, TextStyle};
+use crate::text_overlay::FOCUS_WIDTH;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use crate::zoneinfo::Zone;
+use anyhow::bail;
+use chrono::{DateTime, Duration as ChronoDuration, Local, Offset, TimeZone, Utc};
+use glam::Vec2;
+use instant::Instant;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Space between the banners and the top of the target, and between banners, in logical pixels.
+const MARGIN: f32 = 16.0;
+/// How long a banner takes to slide in, and out again.
+const SLIDE: Duration = Duration::from_millis(300);
+/// The longest that [`BannerSettings::seconds`] may be, an hour.
+const MAX_SECONDS: f32 = 3600.0;
+/// Banners shown at once; older ones are dismissed early to make room.
+const MAX_SHOWN: usize = 3;
+/// How far ahead changes of the displayed timezone's offset are warned about.
+const DST_WARNING_AHEAD: ChronoDuration = ChronoDuration::hours(24);
+
+/// How urgent a banner is, which sets its colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+struct Banner {
+    level: Level,
+    text: String,
+    posted: Instant,
+}
+
+/// Banners posted recently, oldest first, for every [`Banners`] layer to show.
+static POSTED: Mutex<Vec<Banner>> = Mutex::new(Vec::new());
+/// Counts changes to [`POSTED`], so that layers know when to be drawn again.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Shows `text` in a banner in every window with the [`Banners`] layer, e.g. for an alarm or an
+/// error. Posting the text of a banner that is still shown only restarts its time on screen.
+pub fn post(level: Level, text: impl Into<String>) {
+    let text = text.into();
+    log::info!("banner: {}", text);
+    let mut posted = POSTED.lock().unwrap();
+    posted.retain(|banner| banner.text != text);
+    posted.push(Banner {
+        level,
+        text,
+        posted: Instant::now(),
+    });
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Dismisses every banner.
+pub fn dismiss_all() {
+    POSTED.lock().unwrap().clear();
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Settings of the banners, from the config's `[layer.banners]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BannerSettings {
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+    /// How long each banner is shown before it is dismissed.
+    pub seconds: f32,
+    /// Warn a day ahead when the clocks of the displayed timezone change, e.g. for daylight
+    /// saving time.
+    pub dst_warning: bool,
+}
+
+impl Default for BannerSettings {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            seconds: 6.0,
+            dst_warning: true,
+        }
+    }
+}
+
+/// Banners that slide in from the top of the target and dismiss themselves after a while, for
+/// messages posted with [`post`] by the app and the other layers.
+pub struct Banners {
+    text: TextLayer,
+    settings: BannerSettings,
+    locale: Locale,
+    color: Color,
+    background: Color,
+    palette: Palette,
+    target_size: Vec2,
+    scale_factor: f32,
+    /// The [`GENERATION`] that was last drawn.
+    generation: u64,
+    /// The banners to draw, with how far each has slid in, from 0 to 1.
+    shown: Vec<(Banner, f32)>,
+    /// The timezone whose offset changes were last looked for, by name, or `None` for the
+    /// system's.
+    timezone: Option<&'static str>,
+    /// When to look for offset changes again.
+    next_dst_check: Option<DateTime<Utc>>,
+    /// Whether the banners have the keyboard focus.
+    focused: bool,
+}
+
+impl Banners {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: BannerSettings = cx.settings("banners")?;
+        if !(0.0..=MAX_SECONDS).contains(&settings.seconds) {
+            bail!(
+                "banners.seconds must be from 0 to {}, not {}",
+                MAX_SECONDS,
+                settings.seconds
+            );
+        }
+
+        let background = cx.theme.text_background;
+        Ok(Self {
+            text: TextLayer::new(cx.gfx, cx.assets)?,
+            settings,
+            locale: cx.locale.clone(),
+            color: cx.theme.text,
+            // Banners always have a box, as they stand out from the scene.
+            background: if background.a > 0 {
+                background
+            } else {
+                Color::rgba(0, 0, 0, 192)
+            },
+            palette: cx.palette,
+            target_size: cx.viewport.size(),
+            scale_factor: 1.0,
+            generation: u64::MAX,
+            shown: Vec::new(),
+            timezone: None,
+            next_dst_check: None,
+            focused: false,
+        })
+    }
+
+    fn show_for(&self) -> Duration {
+        Duration::from_secs_f32(self.settings.seconds) + 2 * SLIDE
+    }
+
+    /// Posts a warning if the offset of the displayed timezone changes soon after `frame`.
+    fn check_dst(&mut self, frame: &FrameInfo) {
+        if self.timezone != frame.timezone {
+            self.timezone = frame.timezone;
+            self.next_dst_check = None;
+        }
+        if self.next_dst_check.is_some_and(|next| frame.time < next) {
+            return;
+        }
+        self.next_dst_check = Some(frame.time + ChronoDuration::hours(1));
+        let zone = frame.timezone.and_then(Zone::load);
+        let offset = |time: &DateTime<Utc>| match &zone {
+            Some(zone) => zone.offset_at(time),
+            None => Local.offset_from_utc_datetime(&time.naive_utc()).fix(),
+        };
+        let Some(change) = next_offset_change(&frame.time, DST_WARNING_AHEAD, offset) else {
+            return;
+        };
+        let before = offset(&frame.time).local_minus_utc();
+        let shift = offset(&change).local_minus_utc() - before;
+        let local = change.naive_utc() + ChronoDuration::seconds(before as i64);
+        let day = if local.date() == frame.local_date {
+            "today"
+        } else {
+            "tomorrow"
+        };
+        post(
+            Level::Warning,
+            format!(
+                "Clocks go {} {} at {} {}",
+                if shift > 0 { "forward" } else { "back" },
+                self.locale
+                    .localize_digits(&format_shift(shift.unsigned_abs())),
+                self.locale.format_time(&local.time(), false),
+                day
+            ),
+        );
+    }
+
+    fn style(&self, level: Level) -> TextStyle {
+        let size = self.settings.size * self.scale_factor;
+        let (color, background) = match level {
+            Level::Info => (self.color, self.background),
+            Level::Warning => (
+                Color::rgba(255, 255, 255, 255),
+                self.palette.warning_background(),
+            ),
+            Level::Error => (
+                Color::rgba(255, 255, 255, 255),
+                self.palette.error_background(),
+            ),
+        };
+        TextStyle {
+            size,
+            color,
+            anchor: [0.5, 0.0],
+            background: Some(background),
+            padding: size * 0.6,
+        }
+    }
+}
+
+impl Layer for Banners {
+    fn name(&self) -> &str {
+        "banners"
+    }
+
+    /// Every frame while banners are shown, to slide them.
+    fn tick_interval(&self) -> Duration {
+        if self.shown.is_empty() {
+            Duration::from_secs(60)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        if self.settings.dst_warning {
+            self.check_dst(frame);
+        }
+        let now = Instant::now();
+        let show_for = self.show_for();
+        let mut posted = POSTED.lock().unwrap();
+        posted.retain(|banner| now.duration_since(banner.posted) < show_for);
+        let skip = posted.len().saturating_sub(MAX_SHOWN);
+        self.shown = posted[skip..]
+            .iter()
+            .map(|banner| {
+                let age = now.duration_since(banner.posted);
+                let left = show_for.saturating_sub(age);
+                let slid = age.min(left).as_secs_f32() / SLIDE.as_secs_f32();
+                (banner.clone(), slid.min(1.0))
+            })
+            .collect();
+        self.generation = GENERATION.load(Ordering::Relaxed);
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.text.set_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.target_size = viewport.size();
+    }
+
+    fn handle_action(&mut self, action: &str) -> bool {
+        match action {
+            "banners.dismiss" => dismiss_all(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The banners while any are shown, which are all dismissed by Enter.
+    fn controls(&self) -> Vec<Control> {
+        if self.shown.is_empty() {
+            return Vec::new();
+        }
+        vec![Control {
+            activate: "banners.dismiss",
+            reset: None,
+        }]
+    }
+
+    fn set_focus(&mut self, control: Option<usize>) {
+        self.focused = control.is_some();
+    }
+
+    fn poll(&mut self) -> bool {
+        GENERATION.load(Ordering::Relaxed) != self.generation
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        // Newest on top, pushing older banners down.
+        let mut y = MARGIN * self.scale_factor;
+        let mut focus: Option<[f32; 4]> = None;
+        for (banner, slid) in self.shown.iter().rev() {
+            let style = self.style(banner.level);
+            let height = self.text.measure(&banner.text, style.size)[1] + 2.0 * style.padding;
+            // Eased, and starting just above the top edge.
+            let slid = 1.0 - (1.0 - slid).powi(3);
+            let top = y - (1.0 - slid) * (y + height);
+            let position = [self.target_size.x / 2.0, top + style.padding];
+            self.text.queue(&banner.text, position, &style);
+            if self.focused {
+                // One outline around all of them, as they are dismissed together.
+                let rect = self.text.bounds(&banner.text, position, &style);
+                focus = Some(match focus {
+                    Some(focus) => [
+                        focus[0].min(rect[0]),
+                        focus[1].min(rect[1]),
+                        focus[2].max(rect[2]),
+                        focus[3].max(rect[3]),
+                    ],
+                    None => rect,
+                });
+            }
+            y += slid * (height + MARGIN * self.scale_factor / 2.0);
+        }
+        if let Some(rect) = focus {
+            self.text
+                .queue_outline(rect, FOCUS_WIDTH * self.scale_factor, self.color);
+        }
+        let target_size = (self.target_size.x as u32, self.target_size.y as u32);
+        self.text.prepare(target_size, encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.text.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.text.draw(render_pass);
+    }
+}
+
+/// The first instant within `within` after `time` at which `offset` changes, to the second.
+fn next_offset_change<O: PartialEq>(
+    time: &DateTime<Utc>,
+    within: ChronoDuration,
+    offset: impl Fn(&DateTime<Utc>) -> O,
+) -> Option<DateTime<Utc>> {
+    let step = ChronoDuration::hours(1);
+    let current = offset(time);
+    let mut before = *time;
+    while before < *time + within {
+        let mut after = before + step;
+        if offset(&after) != current {
+            while after - before > ChronoDuration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if offset(&middle) == current {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            return Some(after);
+        }
+        before = after;
+    }
+    None
+}
+
+/// A change of offset in seconds, e.g. `1 hour` or `30 minutes`.
+fn format_shift(seconds: u32) -> String {
+    let minutes = seconds / 60;
+    match (minutes / 60, minutes % 60) {
+        (1, 0) => "1 hour".to_owned(),
+        (hours, 0) => format!("{} hours", hours),
+        (0, minutes) => format!("{} minutes", minutes),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
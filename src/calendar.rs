@@ -0,0 +1,196 @@
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::{Corner, TextStyle};
+use crate::text_overlay::Readout;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Settings of the calendar, from the config's `[layer.calendar]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarSettings {
+    /// Corner of the target that the calendar is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::BottomLeft,
+            size: 14.0,
+        }
+    }
+}
+
+/// This month in the displayed timezone, one week per line starting on the locale's first day of
+/// the week, with today highlighted, in a corner of the target.
+pub struct Calendar {
+    readout: Readout,
+    settings: CalendarSettings,
+    locale: Locale,
+    /// Today's number is drawn in the background color over a box of the text color.
+    highlight: Color,
+    date: Option<NaiveDate>,
+    /// The month, without today's number.
+    month: String,
+    today: String,
+    /// Line and column of today's number in `month`, in characters.
+    today_at: (usize, usize),
+}
+
+impl Calendar {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: CalendarSettings = cx.settings("calendar")?;
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            locale: cx.locale.clone(),
+            highlight: Color {
+                a: 255,
+                ..cx.theme.background
+            },
+            date: None,
+            month: String::new(),
+            today: String::new(),
+            today_at: (0, 0),
+        })
+    }
+
+    fn lay_out(&mut self, date: NaiveDate) {
+        let first_weekday = self.locale.first_weekday;
+        let weekdays: Vec<_> = (0..7)
+            .map(|offset| {
+                let weekday = (0..offset).fold(first_weekday, |weekday, _| weekday.succ());
+                self.locale.short_weekday(weekday)
+            })
+            .collect();
+        // Columns fit two digits, or the longest name of a weekday, with a space between them.
+        let column = weekdays
+            .iter()
+            .map(|name| name.chars().count())
+            .fold(2, usize::max);
+        let mut lines = vec![format!(
+            "{:^width$}",
+            self.locale.format_month(&date),
+            width = 7 * (column + 1) - 1
+        )];
+        lines.push(
+            weekdays
+                .iter()
+                .map(|name| format!("{:^column$}", name, column = column))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+
+        let first = date.with_day(1).unwrap();
+        let blank =
+            (7 + first.weekday().num_days_from_monday() - first_weekday.num_days_from_monday()) % 7;
+        let empty = " ".repeat(column);
+        let mut week = vec![empty.clone(); blank as usize];
+        for day in first
+            .iter_days()
+            .take_while(|day| day.month() == date.month())
+        {
+            let number = self.locale.localize_digits(&day.day().to_string());
+            let number = format!("{:>column$}", number, column = column);
+            if day == date {
+                self.today = number;
+                self.today_at = (lines.len(), week.len() * (column + 1));
+                week.push(empty.clone());
+            } else {
+                week.push(number);
+            }
+            if week.len() == 7 {
+                lines.push(week.join(" "));
+                week.clear();
+            }
+        }
+        if !week.is_empty() {
+            lines.push(week.join(" "));
+        }
+        self.month = lines.join("\n");
+    }
+}
+
+impl Layer for Calendar {
+    fn name(&self) -> &str {
+        "calendar"
+    }
+
+    /// Every minute, as midnight in the displayed timezone is on a whole minute in UTC, but not
+    /// always on a whole hour.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        if self.date != Some(frame.local_date) {
+            self.date = Some(frame.local_date);
+            self.lay_out(frame.local_date);
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        let mut style = self.readout.style();
+        let size = style.size;
+        style.padding = size * 0.5;
+        let position = self
+            .readout
+            .queue_in_corner(&self.month, self.settings.corner, &style);
+
+        // The font is monospaced, so today's place follows from the size of a character.
+        let anchor = self.readout.corner(self.settings.corner).anchor();
+        let [width, height] = self.readout.measure(&self.month, size);
+        let [advance, one_line] = self.readout.measure("0", size);
+        let line_height = self.readout.measure("0\n0", size)[1] - one_line;
+        let left = (position[0] - anchor[0] * width).round();
+        let top = (position[1] - anchor[1] * height).round();
+        let (line, column) = self.today_at;
+        // Right-to-left, the weeks run from the right edge, each line the mirror image of its text.
+        let x = if self.locale.right_to_left {
+            left + width - (column + self.today.chars().count()) as f32 * advance
+        } else {
+            left + column as f32 * advance
+        };
+        self.readout.queue(
+            &self.today,
+            [x, top + line as f32 * line_height],
+            &TextStyle {
+                size,
+                color: self.highlight,
+                anchor: [0.0, 0.0],
+                background: Some(style.color),
+                padding: size * 0.1,
+            },
+        );
+
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
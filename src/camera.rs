@@ -0,0 +1,57 @@
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 8.0;
+
+/// The user's view of the scene, applied on top of the window's aspect ratio correction.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Camera {
+    pub zoom: f32,
+    /// Counterclockwise rotation of the whole scene, in radians.
+    pub rotation: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// The camera with its zoom within the limits of [`Self::zoom_by`] and its rotation within a
+    /// turn, e.g. after being read from a file that was edited by hand. Values that are not
+    /// finite numbers are reset.
+    pub fn clamped(self) -> Self {
+        let default = Self::default();
+        Self {
+            zoom: if self.zoom.is_finite() {
+                self.zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+            } else {
+                default.zoom
+            },
+            rotation: if self.rotation.is_finite() {
+                self.rotation.rem_euclid(std::f32::consts::TAU)
+            } else {
+                default.rotation
+            },
+        }
+    }
+
+    pub fn rotate_by(&mut self, angle: f32) {
+        self.rotation = (self.rotation + angle).rem_euclid(std::f32::consts::TAU);
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1.0))
+            * Mat4::from_rotation_z(self.rotation)
+    }
+}
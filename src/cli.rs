@@ -0,0 +1,366 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// A universal 24-hour analog clock that tells you the time everywhere!
+///
+/// Options given here take precedence over `GLOBAL_CLOCK_*` environment variables, which in turn
+/// take precedence over the config file.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path to the config file
+    #[arg(long, value_name = "PATH", env = crate::config::ENV_CONFIG_PATH, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Keep config, cache and state files beneath this directory instead of the platform's
+    /// standard locations
+    #[arg(long, value_name = "DIR", env = crate::config::ENV_BASE_DIR, global = true)]
+    pub base_dir: Option<PathBuf>,
+
+    /// Open the window in fullscreen mode
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Keep the window above other windows
+    #[arg(long)]
+    pub always_on_top: bool,
+
+    /// Float the clock over other windows, transparent and letting clicks through to them
+    #[arg(long)]
+    pub overlay: bool,
+
+    /// Show only the clock in a small window without decorations, like a desktop widget
+    #[arg(long)]
+    pub widget: bool,
+
+    /// Initial window size in logical pixels, e.g. `720x720`
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size, global = true)]
+    pub size: Option<(u32, u32)>,
+
+    /// Monitor to open the window on, by name or index
+    #[arg(long, value_name = "NAME|INDEX")]
+    pub monitor: Option<String>,
+
+    /// Show one scene across all monitors, with a window on each (`monitors`) or one window
+    /// covering them (`window`)
+    #[arg(long, value_name = "MODE")]
+    pub span: Option<String>,
+
+    /// IANA timezone displayed on the clock face, e.g. `America/Chicago`
+    #[arg(long, value_name = "ZONE", global = true)]
+    pub timezone: Option<String>,
+
+    /// Directory to search for shaders, textures and themes before the standard locations
+    #[arg(long, value_name = "DIR")]
+    pub asset_dir: Option<PathBuf>,
+
+    /// Directory containing `globe_day.jpg` and `globe_night.jpg`
+    #[arg(long, value_name = "DIR")]
+    pub texture_dir: Option<PathBuf>,
+
+    /// Start the clock at the given instant instead of the current time, e.g.
+    /// `2025-06-21T12:00:00Z`
+    #[arg(long, value_name = "RFC3339", global = true)]
+    pub time: Option<DateTime<Utc>>,
+
+    /// Run the clock at a multiple of real time; `0` freezes it
+    #[arg(
+        long,
+        value_name = "FACTOR",
+        allow_negative_numbers = true,
+        global = true
+    )]
+    pub speed: Option<f64>,
+
+    /// Render a single frame to the `--output` file instead of opening a window, e.g. on a server
+    /// without a display. The image is the size of the window.
+    #[arg(long, requires = "output")]
+    pub headless: bool,
+
+    /// Image file written by `--headless`; the format is chosen by the file extension
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    pub output: Option<PathBuf>,
+
+    /// Run as a screensaver: fullscreen on every monitor without window decorations, until there
+    /// is any input
+    #[arg(long)]
+    pub screensaver: bool,
+
+    /// Draw into an existing window instead of opening one, given by its X11 window id or Win32
+    /// handle in decimal or hexadecimal (`0x...`), e.g. for a screensaver preview
+    #[arg(long, value_name = "ID", value_parser = parse_window_id)]
+    pub screensaver_window: Option<u64>,
+
+    /// Show where the settings are, as screensaver hosts ask screensavers to
+    #[arg(long, hide = true)]
+    pub screensaver_configure: bool,
+
+    /// Lock the session, showing the clock on every monitor until the `--authenticator` command
+    /// succeeds. Needs a Wayland compositor that supports `ext-session-lock`
+    #[arg(long, requires = "authenticator")]
+    pub lock_screen: bool,
+
+    /// Shell command that unlocks the session started with `--lock-screen` when it succeeds, e.g.
+    /// `fprintd-verify`. It is run again whenever it fails
+    #[arg(long, value_name = "COMMAND", requires = "lock_screen")]
+    pub authenticator: Option<String>,
+
+    /// Show the clock through a DRM/KMS device without a display server, e.g. on a Raspberry Pi
+    /// picture frame. The device defaults to `/dev/dri/card0`
+    #[arg(
+        long,
+        value_name = "DEVICE",
+        num_args = 0..=1,
+        default_missing_value = "/dev/dri/card0"
+    )]
+    pub kms: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage the config file
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// List timezone names with their current UTC offsets, optionally filtered by a search term
+    Timezones {
+        /// Part of a timezone name, matched loosely, e.g. `york` or `amchi`
+        query: Option<String>,
+    },
+    /// Send a command to the running clock
+    #[command(subcommand)]
+    Ctl(CtlCommand),
+    /// Render a single frame of the scene without opening a window, like `--headless`, e.g.
+    /// `render --time 2025-06-21T12:00:00Z --size 1920x1080 --output out.png`
+    Render {
+        /// Image file to write; the format is chosen by the file extension
+        #[arg(long, short, value_name = "PATH")]
+        output: PathBuf,
+    },
+    /// Render the scene as time passes without opening a window, and serve it over HTTP as an
+    /// MJPEG stream at `/` and a JPEG image at `/frame.jpg`, e.g. for a dashboard or another
+    /// machine. The frames are the size of the window
+    Serve {
+        /// Address and port to listen on; `0.0.0.0:8080` lets other machines connect
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+        /// Frames per second
+        #[arg(long, default_value_t = 2.0)]
+        fps: f64,
+        /// JPEG quality, from 1 to 100
+        #[arg(long, default_value_t = 80)]
+        quality: u8,
+    },
+    /// Render the scene to an image every so often without opening a window, e.g. for a wallpaper
+    /// on desktops that cannot show a window as one. Runs until interrupted
+    Wallpaper {
+        /// Image file to write; the format is chosen by the file extension. The image is the size
+        /// of the window
+        #[arg(value_name = "PATH")]
+        output: PathBuf,
+        /// Time between frames, which are rendered on its multiples
+        #[arg(long, value_name = "MINUTES", default_value_t = 10)]
+        every: u32,
+        /// Shell command run after each frame is written, e.g. `feh --bg-fill ~/clock.png`. The
+        /// command run for the previous frame is stopped if it is still running, as `swaybg` is
+        #[arg(long, value_name = "COMMAND")]
+        command: Option<String>,
+    },
+    /// Save the clock face at `--time` or the current time as an SVG file, e.g. for print
+    ExportSvg {
+        #[arg(value_name = "PATH")]
+        output: PathBuf,
+    },
+    /// Print the line between day and night at `--time` or the current time as GeoJSON, with the
+    /// night as a polygon and the point where the Sun is overhead, e.g. for GIS tools
+    Terminator {
+        /// Write to a file instead of standard output
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Points along the line, from the antimeridian around to it again
+        #[arg(long, default_value_t = 361)]
+        points: usize,
+    },
+    /// Render the scene over a range of time without opening a window, starting from `--time` or
+    /// the current time, e.g. to share the day and night moving across the globe
+    Timelapse {
+        /// Animation to write: `.gif` is encoded directly, and other formats such as `.mp4` with
+        /// `ffmpeg`. The frames are the size of the window
+        #[arg(value_name = "PATH")]
+        output: PathBuf,
+        /// Simulated time covered
+        #[arg(long, value_name = "HOURS", default_value_t = 24.0)]
+        hours: f64,
+        /// Simulated time between frames
+        #[arg(long, value_name = "MINUTES", default_value_t = 1.0)]
+        step: f64,
+        /// Frames per second of the animation
+        #[arg(long, default_value_t = 30)]
+        fps: u32,
+    },
+    /// Render the scene as time passes without opening a window, writing the frames as raw RGBA
+    /// pixels, e.g. to a video player showing it as a live wallpaper. The frames are the size of
+    /// the window
+    Pipe {
+        /// File or named pipe to write to, instead of the standard output
+        #[arg(value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Frames per second
+        #[arg(long, default_value_t = 10.0)]
+        fps: f64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CtlCommand {
+    /// Change the timezone shown on the clock face, e.g. `Asia/Tokyo`
+    SetTimezone { name: String },
+    /// Show or hide a layer by name, e.g. `globe` or `calendar`
+    ToggleLayer { name: String },
+    /// Give an action to the layers, e.g. `stopwatch.toggle` or `countdown.set:5:00`
+    Action { action: String },
+    /// Switch to another color theme, e.g. `midnight`
+    SetTheme { name: String },
+    /// Print the instant being displayed, which differs from the current time when simulating
+    Time,
+    /// Save the current frame as an image; the format is chosen by the file extension
+    Screenshot {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+    /// Run the clock from a given instant and speed; with no options, return to real time
+    SetTime {
+        /// Instant to start from, e.g. `2025-06-21T12:00:00Z`, instead of the current time
+        #[arg(long, value_name = "TIME")]
+        time: Option<DateTime<Utc>>,
+        /// Multiple of real time to run at; `0` freezes the clock
+        #[arg(
+            long,
+            value_name = "FACTOR",
+            default_value_t = 1.0,
+            allow_negative_numbers = true
+        )]
+        speed: f64,
+    },
+    /// Freeze the displayed time, or run it again at the speed it was frozen at
+    PlayPause,
+    /// Run the displayed time at a multiple of real time from where it is, e.g. `86400` for a day
+    /// per second; negative runs it backwards
+    SetSpeed {
+        #[arg(value_name = "FACTOR", allow_negative_numbers = true)]
+        speed: f64,
+    },
+    /// Move the displayed time to an instant, e.g. `2025-12-21T12:00:00Z`, keeping its speed
+    JumpTo {
+        #[arg(value_name = "TIME")]
+        time: DateTime<Utc>,
+    },
+    /// Close the clock, e.g. one running as an overlay, which cannot be clicked
+    Quit,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration, after applying the environment and command line, with
+    /// the theme if it is a custom one
+    Export {
+        /// Write to a file instead of standard output
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Print where the config file, cache and state are stored
+    Paths,
+    /// Replace the config file with a settings bundle exported from another machine
+    ///
+    /// A custom theme in the bundle is installed into the themes directory. The previous config
+    /// file and theme, if any, are kept alongside with a `.bak` extension.
+    Import {
+        /// The settings bundle to import
+        #[arg(value_name = "PATH")]
+        input: PathBuf,
+    },
+}
+
+impl Args {
+    /// The config keys set by these arguments, as a layer to merge over the config file.
+    pub fn overrides(&self) -> toml::Table {
+        let mut table = toml::Table::new();
+        let mut window = toml::Table::new();
+        if self.fullscreen {
+            window.insert("fullscreen".into(), true.into());
+        }
+        if self.always_on_top {
+            window.insert("always_on_top".into(), true.into());
+        }
+        if self.overlay {
+            window.insert("overlay".into(), true.into());
+        }
+        if let Some((width, height)) = self.size {
+            window.insert("width".into(), i64::from(width).into());
+            window.insert("height".into(), i64::from(height).into());
+        }
+        if let Some(monitor) = &self.monitor {
+            let value = match monitor.parse::<i64>() {
+                Ok(index) => index.into(),
+                Err(_) => monitor.clone().into(),
+            };
+            window.insert("monitor".into(), value);
+        }
+        if let Some(span) = &self.span {
+            window.insert("span".into(), span.clone().into());
+        }
+        if !window.is_empty() {
+            table.insert("window".into(), window.into());
+        }
+        if self.widget {
+            let mut widget = toml::Table::new();
+            widget.insert("enabled".into(), true.into());
+            table.insert("widget".into(), widget.into());
+        }
+        if let Some(timezone) = &self.timezone {
+            table.insert("timezone".into(), timezone.clone().into());
+        }
+        if let Some(asset_dir) = &self.asset_dir {
+            table.insert(
+                "asset_dir".into(),
+                asset_dir.to_string_lossy().into_owned().into(),
+            );
+        }
+        if let Some(texture_dir) = &self.texture_dir {
+            table.insert(
+                "texture_dir".into(),
+                texture_dir.to_string_lossy().into_owned().into(),
+            );
+        }
+        if let Some(time) = self.time {
+            table.insert("time".into(), time.to_rfc3339().into());
+        }
+        if let Some(speed) = self.speed {
+            table.insert("speed".into(), speed.into());
+        }
+        table
+    }
+}
+
+fn parse_size(s: &str) -> anyhow::Result<(u32, u32)> {
+    let (width, height) = s
+        .split_once('x')
+        .context("expected a size of the form WIDTHxHEIGHT")?;
+    Ok((
+        width.parse().context("invalid width")?,
+        height.parse().context("invalid height")?,
+    ))
+}
+
+fn parse_window_id(s: &str) -> anyhow::Result<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .context("expected a window id, e.g. `0x3a00007` or `1234`")
+}
@@ -1,11 +1,21 @@
+use crate::assets::Assets;
+use crate::layer::{FrameInfo, Layer};
+use crate::model;
+use crate::staging::StagingPool;
+use crate::theme::Theme;
 use crate::viewport::Viewport;
-use crate::{asset_str, GraphicsContext};
+use crate::GraphicsContext;
+use anyhow::Context;
 use bytemuck::{Pod, Zeroable};
-use chrono::{NaiveTime, Timelike};
+use chrono::NaiveTime;
 use once_cell::sync::Lazy;
 use std::convert::TryInto;
 use std::f32::consts::TAU;
-use tiny_skia::{BlendMode, Color, LineCap, Paint, Path, PathBuilder, Pixmap, Stroke, Transform};
+use std::time::Duration;
+use tiny_skia::{
+    BlendMode, Color, IntRect, LineCap, Paint, Path, PathBuilder, PathSegment, Pixmap, Rect,
+    Stroke, Transform,
+};
 use wgpu::util::DeviceExt;
 
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -81,9 +91,16 @@ impl Default for Config {
     }
 }
 
-struct Renderer {
+/// Draws the clock face on the CPU, into the image that [`ClockFace`] shows.
+pub struct Renderer {
     pixmap: Pixmap,
-    paint: Paint<'static>,
+    /// The ticks, which do not move, drawn once to start each image from.
+    dial: Pixmap,
+    dial_paint: Paint<'static>,
+    hand_paint: Paint<'static>,
+    /// The colors of the paints, for [`Self::to_svg`].
+    dial_color: crate::theme::Color,
+    hand_color: crate::theme::Color,
     major_stroke: Stroke,
     minor_stroke: Stroke,
     transform: Transform,
@@ -93,29 +110,41 @@ struct Renderer {
     minute_hand_path: Path,
     hour_angle: f32,
     minute_angle: f32,
+    /// Where the hands are in `pixmap`, or `None` if all of it has to be drawn.
+    drawn_hands: Option<Rect>,
 }
 
 impl Renderer {
-    fn new(config: &Config) -> Self {
-        let mut paint = Paint::default();
-        paint.set_color(Color::from_rgba(1.0, 1.0, 1.0, 0.5).unwrap());
-        paint.anti_alias = true;
-        paint.blend_mode = BlendMode::Source;
-
-        let mut major_stroke = Stroke::default();
-        major_stroke.width = 0.02;
-        major_stroke.line_cap = LineCap::Round;
-
-        let mut minor_stroke = Stroke::default();
-        minor_stroke.width = 0.015;
-        minor_stroke.line_cap = LineCap::Round;
-
-        let pixmap = Pixmap::new(config.width, config.width).unwrap();
-        // Transform from normalized coordinates (-1.0..1.0) to pixels
-        // Also flip Y axis so +1.0 is up => row 0
-        let transform = Transform::identity()
-            .post_translate(1.0, -1.0)
-            .post_scale(config.width as f32 / 2.0, config.width as f32 / -2.0);
+    pub fn new(theme: &Theme) -> anyhow::Result<Self> {
+        Self::with_config(&Config::default(), theme)
+    }
+
+    fn with_config(config: &Config, theme: &Theme) -> anyhow::Result<Self> {
+        let paint = |color: crate::theme::Color| {
+            let mut paint = Paint::default();
+            paint.set_color(color.to_skia());
+            paint.anti_alias = true;
+            paint.blend_mode = BlendMode::Source;
+            paint
+        };
+        let dial_paint = paint(theme.dial);
+        let hand_paint = paint(theme.hands);
+
+        let major_stroke = Stroke {
+            width: 0.02 * theme.stroke_scale,
+            line_cap: LineCap::Round,
+            ..Default::default()
+        };
+
+        let minor_stroke = Stroke {
+            width: 0.015 * theme.stroke_scale,
+            line_cap: LineCap::Round,
+            ..Default::default()
+        };
+
+        let pixmap = Pixmap::new(config.width, config.width)
+            .with_context(|| format!("invalid clock face size {}", config.width))?;
+        let transform = transform(config.width);
 
         let major_tick_path = {
             let mut pb = PathBuilder::new();
@@ -173,9 +202,13 @@ impl Renderer {
             pb.finish().unwrap()
         };
 
-        Self {
+        let mut renderer = Self {
+            dial: pixmap.clone(),
             pixmap,
-            paint,
+            dial_paint,
+            hand_paint,
+            dial_color: theme.dial,
+            hand_color: theme.hands,
             major_stroke,
             minor_stroke,
             transform,
@@ -185,33 +218,86 @@ impl Renderer {
             minute_hand_path,
             hour_angle: 0.0,
             minute_angle: 0.0,
-        }
+            drawn_hands: None,
+        };
+        renderer.draw_dial();
+        Ok(renderer)
     }
 
-    fn set_time(&mut self, time: &NaiveTime) {
-        self.hour_angle = time.num_seconds_from_midnight() as f32 / 86400.0 * TAU;
-        self.minute_angle = time.num_seconds_from_midnight() as f32 / 3600.0 * TAU;
+    /// Width and height of the image, in pixels.
+    pub fn width(&self) -> u32 {
+        self.pixmap.width()
     }
 
-    fn redraw(&mut self) {
-        self.pixmap.fill(Color::TRANSPARENT);
-        self.pixmap.stroke_path(
+    /// Changes the resolution of the image, which is blank until the next [`Self::redraw`].
+    pub fn set_width(&mut self, width: u32) -> anyhow::Result<()> {
+        self.pixmap = Pixmap::new(width, width)
+            .with_context(|| format!("invalid clock face size {}", width))?;
+        self.dial = self.pixmap.clone();
+        self.transform = transform(width);
+        self.draw_dial();
+        self.drawn_hands = None;
+        Ok(())
+    }
+
+    /// The image drawn by the last call to [`Self::redraw`], as premultiplied RGBA.
+    pub fn data(&self) -> &[u8] {
+        self.pixmap.data()
+    }
+
+    /// Moves the hands to `time`. Returns whether they moved, i.e. whether [`Self::redraw`] would
+    /// draw a different image.
+    pub fn set_time(&mut self, time: &NaiveTime) -> bool {
+        let seconds = model::seconds_from_midnight(time);
+        let hour_angle = model::hour_hand_angle(seconds);
+        let minute_angle = model::minute_hand_angle(seconds);
+        let changed = (hour_angle, minute_angle) != (self.hour_angle, self.minute_angle);
+        self.hour_angle = hour_angle;
+        self.minute_angle = minute_angle;
+        changed
+    }
+
+    fn draw_dial(&mut self) {
+        self.dial.fill(Color::TRANSPARENT);
+        self.dial.stroke_path(
             &self.major_tick_path,
-            &self.paint,
+            &self.dial_paint,
             &self.major_stroke,
             self.transform,
             None,
         );
-        self.pixmap.stroke_path(
+        self.dial.stroke_path(
             &self.minor_tick_path,
-            &self.paint,
+            &self.dial_paint,
             &self.minor_stroke,
             self.transform,
             None,
         );
+    }
+
+    /// Draws the hands at the time that was set last. Returns the part of the image that changed,
+    /// which is where the hands were before and where they are now.
+    pub fn redraw(&mut self) -> IntRect {
+        let full = IntRect::from_xywh(0, 0, self.pixmap.width(), self.pixmap.height()).unwrap();
+        let hands = self.hand_bounds();
+        let changed = match (self.drawn_hands, hands) {
+            (Some(before), Some(now)) => union(before, now)
+                .round_out()
+                .and_then(|rect| rect.intersect(&full)),
+            _ => None,
+        }
+        .unwrap_or(full);
+
+        // Erase the hands by restoring the dial under them.
+        let stride = self.pixmap.width() as usize * 4;
+        let columns = changed.left() as usize * 4..changed.right() as usize * 4;
+        for y in changed.top() as usize..changed.bottom() as usize {
+            let row = y * stride + columns.start..y * stride + columns.end;
+            self.pixmap.data_mut()[row.clone()].copy_from_slice(&self.dial.data()[row]);
+        }
         self.pixmap.stroke_path(
             &self.hour_hand_path,
-            &self.paint,
+            &self.hand_paint,
             &self.major_stroke,
             self.transform
                 .pre_concat(Transform::from_rotate(-self.hour_angle.to_degrees())),
@@ -219,27 +305,199 @@ impl Renderer {
         );
         self.pixmap.stroke_path(
             &self.minute_hand_path,
-            &self.paint,
+            &self.hand_paint,
             &self.minor_stroke,
             self.transform
                 .pre_concat(Transform::from_rotate(-self.minute_angle.to_degrees())),
             None,
         );
+        self.drawn_hands = hands;
+        changed
+    }
+
+    /// The dial and the hands at the time that was set last, as an SVG document of the same
+    /// size as the image (see [`Self::width`]), e.g. for print. The shapes are the same as in the
+    /// image, without the background.
+    pub fn to_svg(&self) -> String {
+        let stroke = |path: &Path, color: crate::theme::Color, stroke: &Stroke, angle: f32| {
+            let rotate = if angle == 0.0 {
+                String::new()
+            } else {
+                format!(
+                    r#" transform="rotate({})""#,
+                    svg_number((-angle.to_degrees()).rem_euclid(360.0))
+                )
+            };
+            format!(
+                r#"    <path d="{}" stroke="{}" stroke-opacity="{}" stroke-width="{}"{}/>"#,
+                svg_path_data(path),
+                crate::theme::Color { a: 255, ..color },
+                svg_number(color.a as f32 / 255.0),
+                svg_number(stroke.width),
+                rotate
+            )
+        };
+        let width = self.width();
+        // Flipped, as the paths have +Y up like the scene.
+        [
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_owned(),
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="-1 -1 2 2">"#,
+                width
+            ),
+            r#"  <g transform="scale(1 -1)" fill="none" stroke-linecap="round">"#.to_owned(),
+            stroke(&self.major_tick_path, self.dial_color, &self.major_stroke, 0.0),
+            stroke(&self.minor_tick_path, self.dial_color, &self.minor_stroke, 0.0),
+            stroke(
+                &self.hour_hand_path,
+                self.hand_color,
+                &self.major_stroke,
+                self.hour_angle,
+            ),
+            stroke(
+                &self.minute_hand_path,
+                self.hand_color,
+                &self.minor_stroke,
+                self.minute_angle,
+            ),
+            "  </g>".to_owned(),
+            "</svg>".to_owned(),
+            String::new(),
+        ]
+        .join("\n")
     }
+
+    /// The pixels that the hands cover at the time that was set last.
+    fn hand_bounds(&self) -> Option<Rect> {
+        let bounds = |path: &Path, stroke: &Stroke, angle: f32| {
+            let transform = self
+                .transform
+                .pre_concat(Transform::from_rotate(-angle.to_degrees()));
+            // Round caps and anti-aliasing reach a little past the ends of the line.
+            let margin = stroke.width * self.transform.sx.abs() / 2.0 + 2.0;
+            path.clone()
+                .transform(transform)?
+                .bounds()
+                .outset(margin, margin)
+        };
+        Some(union(
+            bounds(&self.hour_hand_path, &self.major_stroke, self.hour_angle)?,
+            bounds(
+                &self.minute_hand_path,
+                &self.minor_stroke,
+                self.minute_angle,
+            )?,
+        ))
+    }
+}
+
+/// `path` as the `d` attribute of an SVG path.
+fn svg_path_data(path: &Path) -> String {
+    let mut data = String::new();
+    for segment in path.segments() {
+        let (command, points) = match segment {
+            PathSegment::MoveTo(p) => ('M', vec![p]),
+            PathSegment::LineTo(p) => ('L', vec![p]),
+            PathSegment::QuadTo(p1, p) => ('Q', vec![p1, p]),
+            PathSegment::CubicTo(p1, p2, p) => ('C', vec![p1, p2, p]),
+            PathSegment::Close => ('Z', vec![]),
+        };
+        data.push(command);
+        let coordinates: Vec<String> = points
+            .iter()
+            .flat_map(|p| [svg_number(p.x), svg_number(p.y)])
+            .collect();
+        data.push_str(&coordinates.join(" "));
+    }
+    data
+}
+
+/// `x` rounded to what is visible even in print, without trailing zeros.
+fn svg_number(x: f32) -> String {
+    let rounded = format!("{:.5}", x);
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_owned(),
+        trimmed => trimmed.to_owned(),
+    }
+}
+
+fn union(a: Rect, b: Rect) -> Rect {
+    Rect::from_ltrb(
+        a.left().min(b.left()),
+        a.top().min(b.top()),
+        a.right().max(b.right()),
+        a.bottom().max(b.bottom()),
+    )
+    .unwrap()
+}
+
+/// The resolution of the clock face where the viewport shows it, rounded up to a multiple of this
+/// so that resizing a window only redraws the dial every few pixels.
+const WIDTH_STEP: u32 = 128;
+
+/// Largest resolution of the clock face, beyond which it is scaled up rather than take more memory.
+const MAX_WIDTH: u32 = 4096;
+
+/// The width of the texture for a clock face shown in `viewport`, which takes at most
+/// `texture_budget` bytes if set. The face spans the scene's -1..1 square.
+fn texture_width(gfx: &GraphicsContext, viewport: &Viewport, texture_budget: Option<u64>) -> u32 {
+    let mut max_width = MAX_WIDTH.min(gfx.device.limits().max_texture_dimension_2d);
+    if let Some(budget) = texture_budget {
+        let budget_width = ((budget / 4) as f64).sqrt() as u32 / WIDTH_STEP * WIDTH_STEP;
+        max_width = max_width.min(budget_width.max(WIDTH_STEP));
+    }
+    let steps = (viewport.scene_size() / WIDTH_STEP as f32).ceil().max(1.0) as u32;
+    (steps * WIDTH_STEP).min(max_width)
+}
+
+/// Transform from normalized coordinates (-1.0..1.0) to the pixels of an image `width` wide.
+/// Also flip Y axis so +1.0 is up => row 0
+fn transform(width: u32) -> Transform {
+    Transform::identity()
+        .post_translate(1.0, -1.0)
+        .post_scale(width as f32 / 2.0, width as f32 / -2.0)
 }
 
 pub struct ClockFace {
     gfx: GraphicsContext,
+    assets: Assets,
+    pipeline_layout: wgpu::PipelineLayout,
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
     bind_group: wgpu::BindGroup,
     texture: wgpu::Texture,
     renderer: Renderer,
+    texture_budget: Option<u64>,
+    /// Whether the texture is out of date, and has to be drawn and uploaded again.
+    dirty: bool,
+    uploaded_bytes: u64,
 }
 
 impl ClockFace {
-    pub fn new(gfx: &GraphicsContext, viewport: &Viewport) -> anyhow::Result<Self> {
+    pub fn new(
+        gfx: &GraphicsContext,
+        viewport: &Viewport,
+        assets: &Assets,
+        theme: &Theme,
+        texture_budget: Option<u64>,
+    ) -> anyhow::Result<Self> {
+        // Drawing the dial takes a while, so it is done on another thread meanwhile.
+        let width = texture_width(gfx, viewport, texture_budget);
+        let renderer = std::thread::spawn({
+            let theme = theme.clone();
+            move || {
+                let config = Config {
+                    width,
+                    ..Default::default()
+                };
+                Renderer::with_config(&config, &theme)
+            }
+        });
+
         let bind_group_layout =
             gfx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -271,45 +529,7 @@ impl ClockFace {
                 push_constant_ranges: &[],
             });
 
-        let shader_module = gfx
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("ClockFace.shader_module"),
-                source: wgpu::ShaderSource::Wgsl(asset_str!("shaders/clock_face.wgsl")),
-            });
-
-        let render_pipeline = gfx
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("ClockFace.render_pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader_module,
-                    entry_point: "vs_main",
-                    buffers: &[Vertex::buffer_layout()],
-                },
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Cw,
-                    cull_mode: None,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                    unclipped_depth: false,
-                },
-                depth_stencil: None,
-                multisample: Default::default(),
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader_module,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: gfx.render_format,
-                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-            });
+        let render_pipeline = Self::create_render_pipeline(gfx, assets, &pipeline_layout)?;
 
         let vertex_buffer = gfx
             .device
@@ -333,12 +553,38 @@ impl ClockFace {
             min_filter: wgpu::FilterMode::Linear,
             ..Default::default()
         });
-        let config = Config::default();
+        let (texture, bind_group) = Self::create_texture(gfx, &bind_group_layout, &sampler, width);
+        let renderer = renderer.join().unwrap()?;
+
+        Ok(Self {
+            gfx: gfx.clone(),
+            assets: assets.clone(),
+            pipeline_layout,
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            bind_group_layout,
+            sampler,
+            bind_group,
+            texture,
+            renderer,
+            texture_budget,
+            dirty: true,
+            uploaded_bytes: 0,
+        })
+    }
+
+    fn create_texture(
+        gfx: &GraphicsContext,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        width: u32,
+    ) -> (wgpu::Texture, wgpu::BindGroup) {
         let texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
             label: Some("ClockFace.texture"),
             size: wgpu::Extent3d {
-                width: config.width,
-                height: config.width,
+                width,
+                height: width,
                 ..Default::default()
             },
             mip_level_count: 1,
@@ -349,15 +595,14 @@ impl ClockFace {
             view_formats: &[],
         });
         let texture_view = texture.create_view(&Default::default());
-        let renderer = Renderer::new(&config);
 
         let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("ClockFace.bind_group"),
-            layout: &bind_group_layout,
+            layout: bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    resource: wgpu::BindingResource::Sampler(sampler),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
@@ -365,62 +610,153 @@ impl ClockFace {
                 },
             ],
         });
+        (texture, bind_group)
+    }
 
-        Ok(Self {
-            gfx: gfx.clone(),
-            render_pipeline,
-            vertex_buffer,
-            index_buffer,
-            bind_group,
-            texture,
-            renderer,
+    fn create_render_pipeline(
+        gfx: &GraphicsContext,
+        assets: &Assets,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> anyhow::Result<wgpu::RenderPipeline> {
+        let shader_source = assets.read_to_string("shaders/clock_face.wgsl")?;
+        gfx.validate(|| {
+            let shader_module = gfx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("ClockFace.shader_module"),
+                    source: wgpu::ShaderSource::Wgsl(shader_source),
+                });
+
+            gfx.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("ClockFace.render_pipeline"),
+                    layout: Some(pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::buffer_layout()],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Cw,
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        conservative: false,
+                        unclipped_depth: false,
+                    },
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: gfx.render_format,
+                            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
         })
+        .context("invalid clock face shader")
     }
 
     pub fn set_time(&mut self, time: &NaiveTime) {
-        self.renderer.set_time(time)
+        self.dirty |= self.renderer.set_time(time);
     }
 
-    pub fn draw(
-        &mut self,
-        encoder: &mut wgpu::CommandEncoder,
-        frame_view: &wgpu::TextureView,
-        viewport: &Viewport,
-    ) {
-        self.renderer.redraw();
+    /// Draws the hands, and records uploading the part of the texture that changed.
+    fn upload(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        let changed = self.renderer.redraw();
         let pixmap = &self.renderer.pixmap;
-        self.gfx.queue.write_texture(
+        staging.write_texture(
+            encoder,
             wgpu::ImageCopyTexture {
                 texture: &self.texture,
                 mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
+                origin: wgpu::Origin3d {
+                    x: changed.x() as u32,
+                    y: changed.y() as u32,
+                    z: 0,
+                },
                 aspect: wgpu::TextureAspect::All,
             },
-            bytemuck::cast_slice(pixmap.pixels()),
+            pixmap.data(),
             wgpu::ImageDataLayout {
+                offset: (changed.y() as u64 * pixmap.width() as u64 + changed.x() as u64) * 4,
                 bytes_per_row: Some(pixmap.width() * 4),
-                ..Default::default()
+                rows_per_image: None,
             },
             wgpu::Extent3d {
-                width: pixmap.width(),
-                height: pixmap.height(),
+                width: changed.width(),
+                height: changed.height(),
                 ..Default::default()
             },
         );
+        self.uploaded_bytes = changed.width() as u64 * changed.height() as u64 * 4;
+        self.dirty = false;
+    }
+}
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("ClockFace.render_pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: frame_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
+impl Layer for ClockFace {
+    fn name(&self) -> &str {
+        "clock_face"
+    }
+
+    fn shaders(&self) -> &[&str] {
+        &["shaders/clock_face.wgsl"]
+    }
+
+    fn reload_shaders(&mut self) -> anyhow::Result<()> {
+        self.render_pipeline =
+            Self::create_render_pipeline(&self.gfx, &self.assets, &self.pipeline_layout)?;
+        Ok(())
+    }
+
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn sweeps(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        self.set_time(&frame.local_time);
+    }
+
+    /// Draws the clock face at about the resolution it is shown at, so that it stays sharp in
+    /// large windows and takes little memory in small ones.
+    fn resized(&mut self, viewport: &Viewport) {
+        let width = texture_width(&self.gfx, viewport, self.texture_budget);
+        if width == self.renderer.width() {
+            return;
+        }
+        // The width is never 0, which is the only size that a pixmap cannot have.
+        self.renderer.set_width(width).unwrap();
+        (self.texture, self.bind_group) =
+            Self::create_texture(&self.gfx, &self.bind_group_layout, &self.sampler, width);
+        self.dirty = true;
+    }
+
+    fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        let width = self.renderer.width() as u64;
+        width * width * 4 + self.vertex_buffer.size() + self.index_buffer.size()
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        self.uploaded_bytes = 0;
+        if self.dirty {
+            self.upload(encoder, staging);
+        }
+    }
 
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
         render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
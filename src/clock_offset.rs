@@ -0,0 +1,247 @@
+use crate::banner::{self, Level};
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::ntp;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::Corner;
+use crate::text_overlay::Readout;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use anyhow::Context;
+use serde::Deserialize;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// How long to wait for the time server to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait before asking again after the time server did not answer, e.g. while offline.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Settings of the clock offset readout, from the config's `[layer.clock_offset]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockOffsetSettings {
+    /// NTP server to compare the system clock with, optionally with a port.
+    pub server: String,
+    /// Minutes between asking the server.
+    pub refresh_minutes: u32,
+    /// Offset in seconds beyond which the readout turns into a warning.
+    pub threshold_seconds: f64,
+    /// Only show the readout while the clock is off by more than the threshold.
+    pub only_warnings: bool,
+    /// Corner of the target that the readout is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+}
+
+impl Default for ClockOffsetSettings {
+    fn default() -> Self {
+        Self {
+            server: "pool.ntp.org".to_owned(),
+            refresh_minutes: 15,
+            threshold_seconds: 1.0,
+            only_warnings: false,
+            corner: Corner::TopLeft,
+            size: 14.0,
+        }
+    }
+}
+
+/// The last measurement, and a signal for when it changes.
+type Latest = (Mutex<Option<Measurement>>, Condvar);
+
+/// The result of the last time the time server was asked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Measurement {
+    /// Seconds that the time server is ahead of the system clock.
+    Offset(f64),
+    Unreachable,
+}
+
+/// How far the system clock is off from an NTP server, e.g. `Clock 0.012 s behind`, in a corner
+/// of the target, turning into a warning when it is off by more than a threshold.
+pub struct ClockOffset {
+    readout: Readout,
+    settings: ClockOffsetSettings,
+    /// Color of the readout while it warns.
+    warning_color: Color,
+    /// Written by the measuring thread.
+    latest: Arc<Latest>,
+    measurement: Option<Measurement>,
+    locale: Locale,
+    line: String,
+    warning: bool,
+    /// Dropped with the layer, which stops the measuring thread.
+    _stop: mpsc::Sender<()>,
+}
+
+impl ClockOffset {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: ClockOffsetSettings = cx.settings("clock_offset")?;
+        let latest = Arc::new(Latest::default());
+        let (stop, stopped) = mpsc::channel();
+        {
+            let server = settings.server.clone();
+            let refresh = Duration::from_secs(settings.refresh_minutes.max(1) as u64 * 60);
+            let threshold = settings.threshold_seconds;
+            let latest = latest.clone();
+            std::thread::Builder::new()
+                .name("clock offset".into())
+                .spawn(move || run(&server, refresh, threshold, &latest, &stopped))
+                .context("failed to start measuring the clock's offset")?;
+        }
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            warning_color: cx.palette.warning(),
+            latest,
+            measurement: None,
+            locale: cx.locale.clone(),
+            line: String::new(),
+            warning: false,
+            _stop: stop,
+        })
+    }
+
+    fn format_line(&mut self) {
+        let (line, warning) = match self.measurement {
+            None => (String::new(), false),
+            Some(Measurement::Unreachable) => ("Time server unreachable".to_owned(), false),
+            Some(Measurement::Offset(offset)) => (
+                describe_offset(offset),
+                offset.abs() > self.settings.threshold_seconds,
+            ),
+        };
+        self.warning = warning;
+        self.line = if warning || !self.settings.only_warnings {
+            self.locale.localize_digits(&line)
+        } else {
+            String::new()
+        };
+    }
+}
+
+impl Layer for ClockOffset {
+    fn name(&self) -> &str {
+        "clock_offset"
+    }
+
+    /// Only changes when measured, which [`Layer::poll`] picks up.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    fn update(&mut self, _frame: &FrameInfo) {}
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn poll(&mut self) -> bool {
+        let latest = *self.latest.0.lock().unwrap();
+        if latest == self.measurement {
+            return false;
+        }
+        self.measurement = latest;
+        self.format_line();
+        true
+    }
+
+    fn finish_loading(&mut self) {
+        // The server's timeout does not cover looking up its address, which gets as long again.
+        let (latest, changed) = &*self.latest;
+        drop(
+            changed
+                .wait_timeout_while(latest.lock().unwrap(), 2 * TIMEOUT, |latest| {
+                    latest.is_none()
+                })
+                .unwrap(),
+        );
+        self.poll();
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if !self.line.is_empty() {
+            let mut style = self.readout.style();
+            if self.warning {
+                style.color = self.warning_color;
+            }
+            self.readout
+                .queue_in_corner(&self.line, self.settings.corner, &style);
+        }
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
+
+/// Measures the system clock's offset from `server` to `latest` every `refresh`, until `stop` is
+/// disconnected. A banner is shown when the offset grows beyond `threshold` seconds.
+fn run(
+    server: &str,
+    refresh: Duration,
+    threshold: f64,
+    (latest, changed): &Latest,
+    stop: &mpsc::Receiver<()>,
+) {
+    let mut warned = false;
+    loop {
+        let (measurement, wait) = match ntp::measure_offset(server, TIMEOUT) {
+            Ok(offset) => {
+                let offset = offset.num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+                log::info!("{}, by {}", describe_offset(offset), server);
+                let off = offset.abs() > threshold;
+                if off && !warned {
+                    banner::post(Level::Warning, describe_offset(offset));
+                }
+                warned = off;
+                (Measurement::Offset(offset), refresh)
+            }
+            Err(err) => {
+                log::warn!("{:#}", err);
+                (Measurement::Unreachable, RETRY_INTERVAL)
+            }
+        };
+        // A failure to measure does not hide the last offset measured.
+        {
+            let mut latest = latest.lock().unwrap();
+            if measurement != Measurement::Unreachable || latest.is_none() {
+                *latest = Some(measurement);
+            }
+        }
+        changed.notify_all();
+        match stop.recv_timeout(wait) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// E.g. `Clock 0.012 s behind` for a clock that is behind the time server by `offset` seconds.
+fn describe_offset(offset: f64) -> String {
+    let direction = if offset > 0.0 { "behind" } else { "ahead" };
+    match offset.abs() {
+        seconds if seconds < 0.0005 => "Clock in sync".to_owned(),
+        seconds if seconds < 10.0 => format!("Clock {:.3} s {}", seconds, direction),
+        seconds if seconds < 3600.0 => format!("Clock {:.0} s {}", seconds, direction),
+        seconds => format!("Clock {:.1} h {}", seconds / 3600.0, direction),
+    }
+}
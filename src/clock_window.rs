@@ -0,0 +1,1128 @@
+use crate::config::{Backdrop, Config, MiniConfig, WidgetConfig};
+use crate::display::{Display, WindowSurface};
+use crate::monitor;
+use crate::paths::Paths;
+use crate::timezone::Timezone;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use global_clock::layer::update_interval;
+use global_clock::viewport::Region;
+use global_clock::{
+    screenshot, Assets, Background, Camera, Disc, FrameInfo, GpuProfiler, GraphicsContext, Layer,
+    LayerContext, LayerRegistry, Locale, Location, NightLight, Palette, SharedState, StagingPool,
+    TextOverlay, Theme, Viewport,
+};
+use instant::{Duration, Instant};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{InnerSizeWriter, MouseScrollDelta, Touch, TouchPhase};
+use winit::keyboard::ModifiersState;
+use winit::window::{Fullscreen, WindowId, WindowLevel};
+
+/// Everything that layers are created from, shared by all windows.
+pub struct Resources {
+    pub assets: Assets,
+    pub theme: Theme,
+    pub texture_dir: Option<PathBuf>,
+    /// Bytes of GPU memory that each window's textures should fit in, if limited.
+    pub texture_budget: Option<u64>,
+    pub locale: Locale,
+    pub palette: Palette,
+    /// How many times as large as configured to draw the text of layers.
+    pub text_scale: f32,
+    /// Color temperature in kelvin that windows are tinted toward after sunset at home, if the
+    /// night light is on. Drawn only in windows, not in renders and screenshots.
+    pub night_light: Option<f32>,
+    pub home: Option<Location>,
+    /// Settings of individual layers, by layer name.
+    pub layer_settings: BTreeMap<String, toml::Table>,
+    pub registry: LayerRegistry,
+    /// What the layers of every window share.
+    pub shared: SharedState,
+}
+
+impl Resources {
+    /// Everything that layers are created from, as configured, with the assets from the standard
+    /// locations.
+    pub fn from_config(config: &Config, paths: &Paths) -> anyhow::Result<Self> {
+        let assets = Assets::standard(config.asset_dir.as_deref(), paths.data_dir())
+            .with_cache_dir(paths.cache_dir());
+        Self::with_assets(config, paths, assets)
+    }
+
+    /// Everything that layers are created from, as configured, with `assets`, e.g. ones that are
+    /// also read from the APK on Android.
+    pub fn with_assets(config: &Config, paths: &Paths, assets: Assets) -> anyhow::Result<Self> {
+        let theme = Theme::load(config.theme_name(), &paths.themes_dir(), &assets)?;
+        Ok(Self {
+            assets,
+            theme,
+            texture_dir: config.texture_dir.clone(),
+            texture_budget: config.texture_budget_bytes(),
+            locale: Locale::from_config(&config.locale),
+            palette: config.palette,
+            text_scale: config.text_scale,
+            night_light: config.night_light.temperature(),
+            home: config.home.clone(),
+            layer_settings: config.layer.clone(),
+            registry: Default::default(),
+            shared: Default::default(),
+        })
+    }
+}
+
+/// Size of the text in the frame statistics overlay, in pixels.
+const STATS_FONT_SIZE: f32 = 14.0;
+/// How long presented frames are counted for before the frame rate is updated.
+const FPS_PERIOD: Duration = Duration::from_secs(1);
+
+/// Zoom that makes the globe fill the shorter side of the window in presentation mode, as it is
+/// drawn at 0.8 times the size of the scene.
+const PRESENTATION_ZOOM: f32 = 1.25;
+
+/// Angle that the view turns by per press of an arrow key, in radians: a quarter of an hour on
+/// the 24-hour dial.
+const ROTATION_STEP: f32 = std::f32::consts::TAU / 96.0;
+
+/// Where a window was, and how large, before it went fullscreen or changed size for a mode.
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    position: Option<PhysicalPosition<i32>>,
+    size: PhysicalSize<u32>,
+}
+
+/// What a window was like before it was collapsed into a mini window.
+struct Mini {
+    /// The only layer shown in the mini window.
+    layer: &'static str,
+    camera: Camera,
+    geometry: Geometry,
+    decorated: bool,
+    always_on_top: bool,
+}
+
+/// Frame rate and timings, upload sizes, memory use and the graphics adapter, shown over the
+/// scene.
+struct Stats {
+    overlay: TextOverlay,
+    /// `None` if the graphics device cannot measure GPU time.
+    profiler: Option<GpuProfiler>,
+    /// CPU time spent recording and submitting the last frame.
+    cpu_time: Duration,
+    /// Filled in by the first frame drawn, which knows the display.
+    adapter: Option<wgpu::AdapterInfo>,
+    format: wgpu::TextureFormat,
+    last_present: Option<Instant>,
+    /// Time between the last two frames presented.
+    frame_time: Duration,
+    /// Frames presented since `fps_since`, which are counted for at least [`FPS_PERIOD`].
+    presented: u32,
+    fps_since: Instant,
+    fps: f64,
+}
+
+impl Stats {
+    fn new(
+        gfx: &GraphicsContext,
+        resources: &Resources,
+        scale_factor: f64,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            overlay: TextOverlay::new(
+                gfx,
+                &resources.assets,
+                STATS_FONT_SIZE * scale_factor as f32,
+            )?,
+            profiler: GpuProfiler::new(gfx),
+            cpu_time: Duration::ZERO,
+            adapter: None,
+            format: gfx.render_format,
+            last_present: None,
+            frame_time: Duration::ZERO,
+            presented: 0,
+            fps_since: Instant::now(),
+            fps: 0.0,
+        })
+    }
+
+    /// Counts a frame presented at `now`.
+    fn presented(&mut self, now: Instant) {
+        if let Some(last) = self.last_present {
+            self.frame_time = now - last;
+        }
+        self.last_present = Some(now);
+        self.presented += 1;
+        let elapsed = now - self.fps_since;
+        if elapsed >= FPS_PERIOD {
+            self.fps = f64::from(self.presented) / elapsed.as_secs_f64();
+            self.presented = 0;
+            self.fps_since = now;
+        }
+    }
+
+    fn lines(&self, layers: &[Box<dyn Layer>]) -> Vec<String> {
+        let mut lines = vec![
+            format!("FPS          {:7.1}", self.fps),
+            format!(
+                "Frame time   {:7.2} ms",
+                self.frame_time.as_secs_f64() * 1e3
+            ),
+            format!("CPU frame    {:7.2} ms", self.cpu_time.as_secs_f64() * 1e3),
+        ];
+        match &self.profiler {
+            Some(profiler) => lines.extend(
+                profiler
+                    .results()
+                    .iter()
+                    .map(|(label, ms)| format!("GPU {:<9}{:7.2} ms", label, ms)),
+            ),
+            None => lines.push("GPU timing unsupported".to_owned()),
+        }
+        let uploaded_bytes: u64 = layers.iter().map(|layer| layer.uploaded_bytes()).sum();
+        lines.push(format!(
+            "Uploaded     {:7.1} KiB",
+            uploaded_bytes as f64 / 1024.0
+        ));
+        let memory: u64 = layers.iter().map(|layer| layer.gpu_memory()).sum();
+        lines.push(format!("GPU memory   {:7.1} MiB", mebibytes(memory)));
+        lines.extend(layers.iter().map(|layer| {
+            format!(
+                "  {:<11}{:7.1} MiB",
+                layer.name(),
+                mebibytes(layer.gpu_memory())
+            )
+        }));
+        if let Some(adapter) = &self.adapter {
+            lines.push(format!("{} ({:?})", adapter.name, adapter.backend));
+        }
+        lines.push(format!("Surface      {:?}", self.format));
+        lines
+    }
+}
+
+/// A window, and the scene drawn in it.
+pub struct ClockWindow {
+    pub surface: WindowSurface,
+    title: String,
+    pub timezone: Timezone,
+    pub cursor: Option<PhysicalPosition<f64>>,
+    pub dragging: bool,
+    /// Fingers on the screen, in the order they touched it.
+    touches: Vec<(u64, PhysicalPosition<f64>)>,
+    viewport: Viewport,
+    background: Background,
+    /// Drawn over a transparent background in circular widgets.
+    disc: Option<Disc>,
+    layer_names: Vec<String>,
+    /// Drawn over the background, from bottom to top.
+    pub layers: Vec<Box<dyn Layer>>,
+    /// Tints everything else, last.
+    night_light: Option<NightLight>,
+    hidden_layers: HashSet<String>,
+    /// The layer and the control in it that have the keyboard focus, by index.
+    focus: Option<(usize, usize)>,
+    stats: Option<Stats>,
+    always_on_top: bool,
+    /// Set while fullscreen was entered with [`Self::toggle_fullscreen`] or
+    /// [`Self::toggle_presentation`].
+    windowed: Option<Geometry>,
+    /// The camera from before presentation mode, while it is on.
+    presentation: Option<Camera>,
+    pub modifiers: ModifiersState,
+    /// Set while the window is collapsed into a mini window.
+    mini: Option<Mini>,
+    /// Where the mini window was for each layer it showed, to reopen it there.
+    mini_geometry: HashMap<&'static str, Geometry>,
+    pub mini_config: MiniConfig,
+    /// Keep the size in physical pixels when the scale factor changes, instead of the size in
+    /// logical pixels.
+    pub keep_physical_size: bool,
+    /// The size that the surface was last configured for.
+    size: PhysicalSize<u32>,
+    /// Whether the window is a widget, which is moved by dragging it as it has no title bar.
+    pub widget: bool,
+}
+
+impl ClockWindow {
+    pub fn new(
+        gfx: &GraphicsContext,
+        surface: WindowSurface,
+        title: String,
+        timezone: Timezone,
+        camera: Camera,
+        layer_names: Vec<String>,
+        resources: &Resources,
+    ) -> anyhow::Result<Self> {
+        let size = surface.window.inner_size();
+        let viewport = Viewport::new(gfx, size.width, size.height, camera);
+        let background = Background::new(gfx, &resources.theme);
+        let layers = create_layers(
+            gfx,
+            &viewport,
+            resources,
+            &layer_names,
+            surface.window.scale_factor(),
+        )?;
+        let night_light = create_night_light(gfx, resources)?;
+        surface.window.set_title(&title);
+        set_window_theme(&surface, &resources.theme);
+        let window = Self {
+            surface,
+            title,
+            timezone,
+            cursor: None,
+            dragging: false,
+            touches: Vec::new(),
+            viewport,
+            background,
+            disc: None,
+            layer_names,
+            layers,
+            night_light,
+            hidden_layers: HashSet::new(),
+            focus: None,
+            stats: None,
+            always_on_top: false,
+            windowed: None,
+            presentation: None,
+            modifiers: ModifiersState::empty(),
+            mini: None,
+            mini_geometry: HashMap::new(),
+            mini_config: Default::default(),
+            keep_physical_size: false,
+            size,
+            widget: false,
+        };
+        window.surface.configure(gfx);
+        Ok(window)
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.surface.window.id()
+    }
+
+    pub fn request_redraw(&self) {
+        self.surface.window.request_redraw();
+    }
+
+    /// The user's view, not counting the zoom of presentation mode or the mini window.
+    pub fn camera(&self) -> Camera {
+        self.presentation
+            .or_else(|| self.mini.as_ref().map(|mini| mini.camera))
+            .unwrap_or_else(|| self.viewport.camera())
+    }
+
+    /// Turns the window into a widget, which the window itself has to be created for, without
+    /// decorations and transparent if it is circular or has a backdrop.
+    pub fn make_widget(
+        &mut self,
+        gfx: &GraphicsContext,
+        resources: &Resources,
+        config: &WidgetConfig,
+    ) -> anyhow::Result<()> {
+        self.widget = true;
+        let backdrop = cfg!(windows) && config.backdrop != Backdrop::None;
+        if !config.circular && !backdrop {
+            return Ok(());
+        }
+        if !self.surface.supports_transparency() {
+            log::warn!("transparent windows are not supported by this graphics backend");
+            return Ok(());
+        }
+        self.surface.transparent = true;
+        self.background = Background::transparent();
+        if !backdrop {
+            self.disc = Some(Disc::new(
+                gfx,
+                &self.viewport,
+                &resources.assets,
+                &resources.theme,
+            )?);
+        }
+        self.surface.configure(gfx);
+        Ok(())
+    }
+
+    /// Makes the window float over the others without taking any input, which the window itself
+    /// has to be created for, without decorations and transparent.
+    pub fn make_overlay(&mut self, gfx: &GraphicsContext) {
+        if self.surface.supports_transparency() {
+            self.surface.transparent = true;
+            self.background = Background::transparent();
+            self.surface.configure(gfx);
+        } else {
+            log::warn!("transparent windows are not supported by this graphics backend");
+        }
+        self.set_always_on_top(true);
+        if let Err(err) = self.surface.window.set_cursor_hittest(false) {
+            log::warn!("failed to let clicks through the window: {}", err);
+        }
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.always_on_top = always_on_top;
+        self.surface.window.set_window_level(if always_on_top {
+            WindowLevel::AlwaysOnTop
+        } else {
+            WindowLevel::Normal
+        });
+    }
+
+    pub fn toggle_always_on_top(&mut self) {
+        self.set_always_on_top(!self.always_on_top);
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        if self.mini.is_some() {
+            return;
+        }
+        if self.surface.window.fullscreen().is_some() {
+            self.exit_fullscreen();
+        } else {
+            self.enter_fullscreen();
+        }
+    }
+
+    /// Presentation mode is fullscreen with only the globe, as large as fits in the window.
+    pub fn toggle_presentation(&mut self) {
+        if self.mini.is_some() {
+            return;
+        }
+        if self.presentation.is_some() {
+            self.exit_fullscreen();
+            return;
+        }
+        let camera = self.viewport.camera();
+        self.presentation = Some(camera);
+        self.move_camera(Camera {
+            zoom: PRESENTATION_ZOOM,
+            ..camera
+        });
+        self.enter_fullscreen();
+        self.request_redraw();
+    }
+
+    fn enter_fullscreen(&mut self) {
+        let window = &self.surface.window;
+        if window.fullscreen().is_some() {
+            return;
+        }
+        self.windowed = Some(Geometry {
+            position: window.outer_position().ok(),
+            size: window.inner_size(),
+        });
+        window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+    }
+
+    /// Leaves fullscreen and presentation mode. The surface and viewport follow the new size
+    /// when it arrives in a `Resized` event.
+    pub fn exit_fullscreen(&mut self) {
+        if let Some(camera) = self.presentation.take() {
+            self.move_camera(camera);
+            self.request_redraw();
+        }
+        let window = &self.surface.window;
+        window.set_fullscreen(None);
+        if let Some(geometry) = self.windowed.take() {
+            let _ = window.request_inner_size(geometry.size);
+            if let Some(position) = geometry.position {
+                window.set_outer_position(position);
+            }
+        }
+    }
+
+    pub fn is_mini(&self) -> bool {
+        self.mini.is_some()
+    }
+
+    /// Collapses the window into a small window without decorations that shows only `layer`,
+    /// docked to a corner of the monitor, or restores it if it is already collapsed.
+    pub fn toggle_mini(&mut self, layer: &'static str) {
+        if self.mini.is_some() {
+            self.exit_mini();
+            return;
+        }
+        if let Some(camera) = self.presentation.take() {
+            self.move_camera(camera);
+        }
+        let window = &self.surface.window;
+        // Leaving fullscreen, restore the geometry from before it instead.
+        let geometry = self.windowed.take().unwrap_or_else(|| Geometry {
+            position: window.outer_position().ok(),
+            size: window.inner_size(),
+        });
+        window.set_fullscreen(None);
+        self.mini = Some(Mini {
+            layer,
+            camera: self.viewport.camera(),
+            geometry,
+            decorated: window.is_decorated(),
+            always_on_top: self.always_on_top,
+        });
+
+        let size = LogicalSize::new(self.mini_config.size, self.mini_config.size);
+        let mini_geometry = self
+            .mini_geometry
+            .get(layer)
+            .copied()
+            .unwrap_or_else(|| Geometry {
+                position: window.current_monitor().map(|monitor| {
+                    monitor::cornered(&monitor, size.into(), self.mini_config.corner)
+                }),
+                size: size.to_physical(window.scale_factor()),
+            });
+        window.set_decorations(false);
+        let _ = window.request_inner_size(mini_geometry.size);
+        if let Some(position) = mini_geometry.position {
+            window.set_outer_position(position);
+        }
+        self.set_always_on_top(true);
+        self.move_camera(Default::default());
+        self.request_redraw();
+    }
+
+    /// Restores the window from the mini window, remembering where the mini window was.
+    pub fn exit_mini(&mut self) {
+        let Some(mini) = self.mini.take() else {
+            return;
+        };
+        let window = &self.surface.window;
+        self.mini_geometry.insert(
+            mini.layer,
+            Geometry {
+                position: window.outer_position().ok(),
+                size: window.inner_size(),
+            },
+        );
+        window.set_decorations(mini.decorated);
+        let _ = window.request_inner_size(mini.geometry.size);
+        if let Some(position) = mini.geometry.position {
+            window.set_outer_position(position);
+        }
+        self.set_always_on_top(mini.always_on_top);
+        self.move_camera(mini.camera);
+        self.request_redraw();
+    }
+
+    /// Shows `error` in the title bar after the window's own title, or only the title if `None`.
+    pub fn show_error(&self, error: Option<&str>) {
+        let title = match error {
+            None => self.title.clone(),
+            Some(error) => format!("{} - {}", self.title, error),
+        };
+        self.surface.window.set_title(&title);
+    }
+
+    /// Recreates every GPU resource with a new graphics device.
+    pub fn recreate(&mut self, gfx: &GraphicsContext, resources: &Resources) -> anyhow::Result<()> {
+        let size = self.surface.window.inner_size();
+        let region = self.viewport.region();
+        self.viewport = Viewport::new(gfx, size.width, size.height, self.viewport.camera());
+        self.viewport.set_region(region);
+        self.background = if self.surface.transparent {
+            Background::transparent()
+        } else {
+            Background::new(gfx, &resources.theme)
+        };
+        if self.disc.is_some() {
+            self.disc = Some(Disc::new(
+                gfx,
+                &self.viewport,
+                &resources.assets,
+                &resources.theme,
+            )?);
+        }
+        // The old layers are kept if this fails, so that the focus still points at one of them.
+        let mut layers = create_layers(
+            gfx,
+            &self.viewport,
+            resources,
+            &self.layer_names,
+            self.surface.window.scale_factor(),
+        )?;
+        let mut states: HashMap<String, Box<dyn Any>> = self
+            .layers
+            .iter_mut()
+            .filter_map(|layer| Some((layer.name().to_owned(), layer.take_state()?)))
+            .collect();
+        for layer in &mut layers {
+            if let Some(state) = states.remove(layer.name()) {
+                layer.restore_state(state);
+            }
+        }
+        self.layers = layers;
+        let focus = self.focus.take();
+        self.set_focus(focus);
+        self.check_focus();
+        self.night_light = create_night_light(gfx, resources)?;
+        set_window_theme(&self.surface, &resources.theme);
+        if self.stats.is_some() {
+            self.stats = Some(Stats::new(
+                gfx,
+                resources,
+                self.surface.window.scale_factor(),
+            )?);
+        }
+        self.surface.configure(gfx);
+        Ok(())
+    }
+
+    /// Redraws the window if any layer finished loading something in the background.
+    pub fn poll_layers(&mut self) {
+        let mut changed = false;
+        for layer in &mut self.layers {
+            changed |= layer.poll();
+        }
+        if changed {
+            self.log_memory();
+            self.request_redraw();
+        }
+    }
+
+    /// Logs how much GPU memory the window's layers take.
+    pub fn log_memory(&self) {
+        let total: u64 = self.layers.iter().map(|layer| layer.gpu_memory()).sum();
+        let layers: Vec<String> = self
+            .layers
+            .iter()
+            .map(|layer| format!("{} {:.1} MiB", layer.name(), mebibytes(layer.gpu_memory())))
+            .collect();
+        log::info!(
+            "GPU memory of {:?}: {:.1} MiB ({})",
+            self.title,
+            mebibytes(total),
+            layers.join(", ")
+        );
+    }
+
+    /// Updates the layers for a frame at `time`, with frames drawn every `frame_interval`.
+    pub fn update(&mut self, time: &DateTime<Utc>, frame_interval: Duration) {
+        let frame = FrameInfo {
+            time: *time,
+            local_time: self.timezone.time_of(time),
+            local_date: self.timezone.date_of(time),
+            timezone: self.timezone.name(),
+        };
+        for layer in &mut self.layers {
+            layer.update(&frame.floor(update_interval(&**layer, frame_interval)));
+        }
+        if let Some(night_light) = &mut self.night_light {
+            // The Sun outside the window, whatever time is shown.
+            night_light.update(&Utc::now());
+        }
+    }
+
+    /// Draws and presents a frame. Returns `false` if the graphics device was lost, in which case
+    /// nothing was presented and the device has to be recovered before drawing again.
+    pub fn redraw(
+        &mut self,
+        gfx: &GraphicsContext,
+        display: &Display,
+        staging: &mut StagingPool,
+    ) -> bool {
+        let Some(surface) = &self.surface.surface else {
+            return true;
+        };
+        let mut reconfigured = false;
+        let frame = loop {
+            match surface.get_current_texture() {
+                Ok(frame) => break frame,
+                Err(wgpu::SurfaceError::Lost) if !reconfigured => {
+                    self.surface.configure(gfx);
+                    reconfigured = true;
+                }
+                Err(wgpu::SurfaceError::Timeout) | Err(wgpu::SurfaceError::Outdated) => {
+                    return true;
+                }
+                Err(wgpu::SurfaceError::Lost) | Err(wgpu::SurfaceError::OutOfMemory) => {
+                    display.mark_lost();
+                    return false;
+                }
+            }
+        };
+
+        let start = Instant::now();
+        let frame_view = frame.texture.create_view(&Default::default());
+        let mut encoder = gfx.device.create_command_encoder(&Default::default());
+        self.draw(&mut encoder, staging, &frame_view);
+        if display.is_lost() {
+            // Submitting work that refers to a lost device panics, so start over instead.
+            return false;
+        }
+        gfx.queue.submit([encoder.finish()]);
+        if let Some(stats) = &mut self.stats {
+            stats.cpu_time = start.elapsed();
+            if let Some(profiler) = &mut stats.profiler {
+                profiler.end_frame();
+            }
+        }
+        frame.present();
+        if let Some(stats) = &mut self.stats {
+            stats.presented(Instant::now());
+            if stats.adapter.is_none() {
+                stats.adapter = Some(display.adapter_info());
+            }
+        }
+        true
+    }
+
+    fn draw(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        staging: &mut StagingPool,
+        view: &wgpu::TextureView,
+    ) {
+        self.check_focus();
+        let shown: Vec<bool> = self
+            .layers
+            .iter()
+            .map(|layer| self.layer_shown(layer.name()))
+            .collect();
+        staging.recall();
+        for (layer, _) in self
+            .layers
+            .iter_mut()
+            .zip(&shown)
+            .filter(|(_, shown)| **shown)
+        {
+            layer.prepare(encoder, staging);
+        }
+
+        let (mut profiler, overlay) = match &mut self.stats {
+            Some(stats) if self.presentation.is_none() && self.mini.is_none() => {
+                stats.overlay.set_lines(stats.lines(&self.layers));
+                let size = self.surface.window.inner_size();
+                stats
+                    .overlay
+                    .prepare((size.width, size.height), encoder, staging);
+                (stats.profiler.as_mut(), Some(&stats.overlay))
+            }
+            Some(stats) => (stats.profiler.as_mut(), None),
+            None => (None, None),
+        };
+        if let Some(night_light) = &mut self.night_light {
+            night_light.prepare(encoder, staging);
+        }
+        staging.finish();
+        // Without timestamps inside passes, only the whole frame can be timed.
+        let per_layer = profiler
+            .as_ref()
+            .is_some_and(|profiler| profiler.inside_passes());
+        if let Some(profiler) = &mut profiler {
+            profiler.begin_frame();
+            if !per_layer {
+                profiler.begin(encoder, "frame");
+            }
+        }
+
+        let mut render_pass = self.background.begin_render_pass(encoder, view);
+        if let Some(disc) = &self.disc {
+            disc.draw(&mut render_pass, &self.viewport);
+        }
+        for (layer, _) in self.layers.iter().zip(&shown).filter(|(_, shown)| **shown) {
+            if let (true, Some(profiler)) = (per_layer, &mut profiler) {
+                profiler.end(&mut render_pass);
+                profiler.begin(&mut render_pass, layer.name());
+            }
+            layer.draw(&mut render_pass, &self.viewport);
+        }
+        if let Some(overlay) = overlay {
+            overlay.draw(&mut render_pass);
+        }
+        if let Some(night_light) = &self.night_light {
+            night_light.draw(&mut render_pass);
+        }
+        if let Some(profiler) = &mut profiler {
+            profiler.end(&mut render_pass);
+        }
+        drop(render_pass);
+        if let Some(profiler) = profiler {
+            profiler.resolve(encoder);
+        }
+    }
+
+    /// Shows or hides the frame statistics overlay.
+    pub fn toggle_stats(
+        &mut self,
+        gfx: &GraphicsContext,
+        resources: &Resources,
+    ) -> anyhow::Result<()> {
+        self.stats = match self.stats {
+            Some(_) => None,
+            None => Some(Stats::new(
+                gfx,
+                resources,
+                self.surface.window.scale_factor(),
+            )?),
+        };
+        self.request_redraw();
+        Ok(())
+    }
+
+    /// Gives `action` to the layers, e.g. for a hotkey. Returns whether any of them knew it.
+    pub fn layer_action(&mut self, action: &str) -> bool {
+        let mut handled = false;
+        for layer in &mut self.layers {
+            handled |= layer.handle_action(action);
+        }
+        if handled {
+            self.request_redraw();
+        }
+        handled
+    }
+
+    /// Whether the named layer is drawn, rather than hidden or left out of presentation mode or the
+    /// mini window.
+    fn layer_shown(&self, name: &str) -> bool {
+        match (&self.presentation, &self.mini) {
+            (Some(_), _) => name == "globe",
+            (None, Some(mini)) => name == mini.layer,
+            (None, None) => !self.hidden_layers.contains(name),
+        }
+    }
+
+    /// Moves the keyboard focus to the next control of the shown layers, from bottom to top, or
+    /// to the previous one if `backwards`, wrapping around.
+    pub fn move_focus(&mut self, backwards: bool) {
+        let controls: Vec<(usize, usize)> = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| self.layer_shown(layer.name()))
+            .flat_map(|(index, layer)| {
+                (0..layer.controls().len()).map(move |control| (index, control))
+            })
+            .collect();
+        let current = self
+            .focus
+            .and_then(|focus| controls.iter().position(|&control| control == focus));
+        let next = match current {
+            Some(at) if backwards => controls.get((at + controls.len() - 1) % controls.len()),
+            Some(at) => controls.get((at + 1) % controls.len()),
+            None if backwards => controls.last(),
+            None => controls.first(),
+        };
+        self.set_focus(next.copied());
+    }
+
+    /// Takes the keyboard focus away from the controls, returning whether one had it.
+    pub fn clear_focus(&mut self) -> bool {
+        let had_focus = self.focus.is_some();
+        self.set_focus(None);
+        had_focus
+    }
+
+    /// Does what the control with the keyboard focus does for Enter, or resets it if `reset`.
+    /// Returns whether a control had the focus and did anything.
+    pub fn activate_focus(&mut self, reset: bool) -> bool {
+        let Some((layer, control)) = self.focus else {
+            return false;
+        };
+        let Some(layer) = self.layers.get(layer) else {
+            return false;
+        };
+        let action = match layer.controls().get(control) {
+            Some(control) if reset => control.reset,
+            Some(control) => Some(control.activate),
+            None => None,
+        };
+        action.is_some_and(|action| self.layer_action(action))
+    }
+
+    fn set_focus(&mut self, focus: Option<(usize, usize)>) {
+        if let Some(layer) = self.focus.and_then(|(layer, _)| self.layers.get_mut(layer)) {
+            layer.set_focus(None);
+        }
+        let focus = focus.filter(|&(layer, control)| {
+            self.layers
+                .get(layer)
+                .is_some_and(|layer| control < layer.controls().len())
+        });
+        if let Some((layer, control)) = focus {
+            if let Some(layer) = self.layers.get_mut(layer) {
+                layer.set_focus(Some(control));
+            }
+        }
+        self.focus = focus;
+        self.request_redraw();
+    }
+
+    /// Drops the keyboard focus if its control is gone, e.g. when the banners were dismissed or
+    /// its layer was hidden.
+    fn check_focus(&mut self) {
+        if let Some((layer, control)) = self.focus {
+            let gone = match self.layers.get(layer) {
+                Some(layer) => !self.layer_shown(layer.name()) || control >= layer.controls().len(),
+                None => true,
+            };
+            if gone {
+                self.set_focus(None);
+            }
+        }
+    }
+
+    /// Shows the named layer if it is hidden, or hides it if it is shown, returning whether it is
+    /// now visible.
+    pub fn toggle_layer(&mut self, name: &str) -> Result<bool, String> {
+        if !self.layers.iter().any(|layer| layer.name() == name) {
+            let names: Vec<&str> = self.layers.iter().map(|layer| layer.name()).collect();
+            return Err(format!(
+                "unknown layer {:?}; expected one of {}",
+                name,
+                names.join(", ")
+            ));
+        }
+        if self.hidden_layers.remove(name) {
+            Ok(true)
+        } else {
+            self.hidden_layers.insert(name.to_owned());
+            Ok(false)
+        }
+    }
+
+    pub fn screenshot(
+        &mut self,
+        gfx: &GraphicsContext,
+        staging: &mut StagingPool,
+        time: &DateTime<Utc>,
+        frame_interval: Duration,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        self.update(time, frame_interval);
+        let size = self.surface.window.inner_size();
+        let image = screenshot::capture(gfx, size.width, size.height, |encoder, view| {
+            self.draw(encoder, staging, view)
+        })?;
+        image
+            .save(path)
+            .with_context(|| format!("failed to save screenshot to {}", path.display()))
+    }
+
+    pub fn zoom(&mut self, delta: MouseScrollDelta) {
+        let steps = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+        };
+        self.zoom_steps(steps);
+    }
+
+    /// Zooms in by `steps` notches of a mouse wheel, or out if negative.
+    pub fn zoom_steps(&mut self, steps: f32) {
+        let mut camera = self.viewport.camera();
+        camera.zoom_by(1.1f32.powf(steps));
+        self.move_camera(camera);
+        self.request_redraw();
+    }
+
+    /// Rotates the view counterclockwise by `steps` of [`ROTATION_STEP`], or clockwise if
+    /// negative, e.g. with the arrow keys.
+    pub fn rotate_steps(&mut self, steps: f32) {
+        let mut camera = self.viewport.camera();
+        camera.rotate_by(steps * ROTATION_STEP);
+        self.move_camera(camera);
+        self.request_redraw();
+    }
+
+    pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        if let (true, Some(previous)) = (self.dragging, self.cursor) {
+            self.drag(previous, position);
+        }
+        self.cursor = Some(position);
+    }
+
+    /// Rotates the view by the angle that a pointer swept around the center of the scene.
+    fn drag(&mut self, from: PhysicalPosition<f64>, to: PhysicalPosition<f64>) {
+        let center = self.viewport.center();
+        let angle_of = |p: PhysicalPosition<f64>| {
+            let x = p.x - center.x as f64;
+            let y = center.y as f64 - p.y;
+            y.atan2(x) as f32
+        };
+        let mut camera = self.viewport.camera();
+        camera.rotate_by(angle_of(to) - angle_of(from));
+        self.move_camera(camera);
+        self.request_redraw();
+    }
+
+    /// One finger drags the view around like the mouse, and two fingers pinch to zoom and twist
+    /// to rotate it.
+    pub fn touch(&mut self, touch: Touch) {
+        let previous = self.touches.iter().position(|(id, _)| *id == touch.id);
+        match (touch.phase, previous) {
+            (TouchPhase::Started, None) => self.touches.push((touch.id, touch.location)),
+            (TouchPhase::Moved, Some(index)) => {
+                let from = self.touches[index].1;
+                self.touches[index].1 = touch.location;
+                match self.touches.as_slice() {
+                    [_] => self.drag(from, touch.location),
+                    [a, b] => {
+                        let other = if index == 0 { b.1 } else { a.1 };
+                        self.pinch(other, from, touch.location);
+                    }
+                    // Three or more fingers are probably not meant for the clock.
+                    _ => {}
+                }
+            }
+            (TouchPhase::Ended | TouchPhase::Cancelled, Some(index)) => {
+                self.touches.remove(index);
+            }
+            _ => {}
+        }
+    }
+
+    /// Zooms and rotates the view as one finger moves from `from` to `to` while another stays at
+    /// `pivot`.
+    fn pinch(
+        &mut self,
+        pivot: PhysicalPosition<f64>,
+        from: PhysicalPosition<f64>,
+        to: PhysicalPosition<f64>,
+    ) {
+        let (x0, y0) = (from.x - pivot.x, pivot.y - from.y);
+        let (x1, y1) = (to.x - pivot.x, pivot.y - to.y);
+        let (d0, d1) = (x0.hypot(y0), x1.hypot(y1));
+        if d0 < 1.0 || d1 < 1.0 {
+            return;
+        }
+        let mut camera = self.viewport.camera();
+        camera.zoom_by((d1 / d0) as f32);
+        camera.rotate_by((y1.atan2(x1) - y0.atan2(x0)) as f32);
+        self.move_camera(camera);
+        self.request_redraw();
+    }
+
+    /// Shows the view of another window that shows the same scene.
+    pub fn set_camera(&mut self, camera: Camera) {
+        if camera != self.viewport.camera() {
+            self.move_camera(camera);
+            self.request_redraw();
+        }
+    }
+
+    /// Shows only part of the scene, e.g. when the windows on several monitors show one scene.
+    pub fn set_region(&mut self, region: Option<Region>) {
+        self.viewport.set_region(region);
+        for layer in &mut self.layers {
+            layer.resized(&self.viewport);
+        }
+    }
+
+    /// Points the viewport's camera at another part of the scene, and lets the layers follow.
+    fn move_camera(&mut self, camera: Camera) {
+        self.viewport.set_camera(camera);
+        for layer in &mut self.layers {
+            layer.resized(&self.viewport);
+        }
+    }
+
+    pub fn set_text_scale(&mut self, scale: f32) {
+        for layer in &mut self.layers {
+            layer.set_text_scale(scale);
+        }
+        self.request_redraw();
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.move_camera(Default::default());
+        self.request_redraw();
+    }
+
+    /// Follows a change of the window's scale factor, e.g. after it moved to another monitor. The
+    /// surface follows the new size when it arrives in a `Resized` event.
+    pub fn scale_factor_changed(
+        &mut self,
+        scale_factor: f64,
+        mut inner_size_writer: InnerSizeWriter,
+    ) {
+        if self.keep_physical_size {
+            let _ = inner_size_writer.request_inner_size(self.size);
+        }
+        for layer in &mut self.layers {
+            layer.set_scale_factor(scale_factor);
+        }
+        if let Some(stats) = &mut self.stats {
+            stats
+                .overlay
+                .set_font_size(STATS_FONT_SIZE * scale_factor as f32);
+        }
+        self.request_redraw();
+    }
+
+    pub fn resized(&mut self, gfx: &GraphicsContext) {
+        let size = self.surface.window.inner_size();
+        self.size = size;
+        self.viewport.resize(size.width, size.height);
+        for layer in &mut self.layers {
+            layer.resized(&self.viewport);
+        }
+        self.surface.configure(gfx);
+        self.request_redraw();
+    }
+}
+
+/// Matches the title bar to the scene, including the decorations that windows draw themselves on
+/// Wayland.
+fn set_window_theme(surface: &WindowSurface, theme: &Theme) {
+    surface.window.set_theme(Some(if theme.is_dark() {
+        winit::window::Theme::Dark
+    } else {
+        winit::window::Theme::Light
+    }));
+}
+
+/// Creates the named layers, from bottom to top, for a target with `scale_factor` physical pixels
+/// per logical pixel.
+pub fn create_layers(
+    gfx: &GraphicsContext,
+    viewport: &Viewport,
+    resources: &Resources,
+    names: &[String],
+    scale_factor: f64,
+) -> anyhow::Result<Vec<Box<dyn Layer>>> {
+    let cx = LayerContext {
+        gfx,
+        viewport,
+        assets: &resources.assets,
+        theme: &resources.theme,
+        texture_dir: resources.texture_dir.as_deref(),
+        texture_budget: resources
+            .registry
+            .texture_budget(resources.texture_budget, names),
+        locale: &resources.locale,
+        palette: resources.palette,
+        home: resources.home.as_ref(),
+        settings: &resources.layer_settings,
+        shared: &resources.shared,
+    };
+    names
+        .iter()
+        .map(|name| {
+            let mut layer = resources.registry.create(name, &cx)?;
+            layer.set_scale_factor(scale_factor);
+            layer.set_text_scale(resources.text_scale);
+            Ok(layer)
+        })
+        .collect()
+}
+
+/// The night light for a window, if it is on and the home location is known.
+fn create_night_light(
+    gfx: &GraphicsContext,
+    resources: &Resources,
+) -> anyhow::Result<Option<NightLight>> {
+    match (resources.night_light, &resources.home) {
+        (Some(temperature), Some(home)) => Ok(Some(NightLight::new(
+            gfx,
+            &resources.assets,
+            home.clone(),
+            temperature,
+        )?)),
+        _ => Ok(None),
+    }
+}
+
+fn mebibytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
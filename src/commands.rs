@@ -0,0 +1,339 @@
+use crate::cli::{Args, Command, ConfigCommand, CtlCommand};
+use crate::config::Config;
+use crate::control::{self, Response};
+use crate::headless::{self, Timelapse};
+use crate::ipc;
+use crate::paths::Paths;
+use crate::stream;
+use crate::timezone::Timezone;
+use anyhow::{bail, Context};
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use global_clock::clock_face::Renderer;
+use global_clock::locale::{Locale, LocaleConfig};
+use global_clock::zoneinfo::Zone;
+use global_clock::{astronomy, time_source, Assets, Theme};
+use serde_json::json;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+pub fn run(command: &Command, args: &Args, paths: &Paths) -> anyhow::Result<()> {
+    match command {
+        Command::Config(ConfigCommand::Export { output }) => {
+            let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+            export_config(&config, paths, output.as_deref())
+        }
+        Command::Config(ConfigCommand::Paths) => {
+            println!(
+                "config: {}",
+                args.config
+                    .clone()
+                    .unwrap_or_else(|| paths.config_file())
+                    .display()
+            );
+            println!("data:   {}", paths.data_dir().display());
+            println!("cache:  {}", paths.cache_dir().display());
+            println!("state:  {}", paths.state_dir().display());
+            Ok(())
+        }
+        Command::Config(ConfigCommand::Import { input }) => {
+            let dest = args.config.clone().unwrap_or_else(|| paths.config_file());
+            import_config(input, &dest, paths)
+        }
+        Command::Timezones { query } => match list_timezones(query.as_deref()) {
+            // Output was piped into something like `head` that stopped reading.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result.context("failed to write output"),
+        },
+        Command::Ctl(command) => ctl(command, paths),
+        Command::Render { output } => headless::render(args, paths, output),
+        Command::Serve {
+            listen,
+            fps,
+            quality,
+        } => stream::serve(args, paths, *listen, *fps, *quality),
+        Command::Wallpaper {
+            output,
+            every,
+            command,
+        } => {
+            let interval = Duration::from_secs(u64::from(*every) * 60);
+            headless::wallpaper(args, paths, output, interval, command.as_deref())
+        }
+        Command::Terminator { output, points } => {
+            let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+            let time = time_source::from_settings(config.time, config.speed).now();
+            let geojson = terminator_geojson(&time, *points);
+            let contents = serde_json::to_string_pretty(&geojson)? + "\n";
+            match output {
+                Some(path) => std::fs::write(path, contents)
+                    .with_context(|| format!("failed to write {}", path.display())),
+                None => {
+                    print!("{}", contents);
+                    Ok(())
+                }
+            }
+        }
+        Command::ExportSvg { output } => {
+            let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+            export_svg(&config, paths, output)
+        }
+        Command::Timelapse {
+            output,
+            hours,
+            step,
+            fps,
+        } => {
+            let timelapse = Timelapse {
+                hours: *hours,
+                step_minutes: *step,
+                fps: *fps,
+            };
+            headless::timelapse(args, paths, output, &timelapse)
+        }
+        Command::Pipe { output, fps } => headless::pipe(args, paths, output.as_deref(), *fps),
+    }
+}
+
+fn ctl(command: &CtlCommand, paths: &Paths) -> anyhow::Result<()> {
+    let command = match command {
+        CtlCommand::SetTimezone { name } => control::Command::SetTimezone(name.clone()),
+        CtlCommand::ToggleLayer { name } => control::Command::ToggleLayer(name.clone()),
+        CtlCommand::Action { action } => control::Command::LayerAction(action.clone()),
+        CtlCommand::SetTheme { name } => control::Command::SetTheme(name.clone()),
+        CtlCommand::Time => control::Command::CurrentTime,
+        // The clock may be running in a different working directory.
+        CtlCommand::Screenshot { path } => control::Command::Screenshot(
+            std::path::absolute(path)
+                .with_context(|| format!("invalid path {}", path.display()))?,
+        ),
+        CtlCommand::SetTime { time, speed } => control::Command::SetSimulatedTime {
+            time: *time,
+            speed: *speed,
+        },
+        CtlCommand::PlayPause => control::Command::PlayPause,
+        CtlCommand::SetSpeed { speed } => control::Command::SetSpeed(*speed),
+        CtlCommand::JumpTo { time } => control::Command::JumpTo(*time),
+        CtlCommand::Quit => control::Command::Quit,
+    };
+    match ipc::send(paths, &command)? {
+        Response::Done => {}
+        Response::LayerVisible(visible) => {
+            println!("{}", if visible { "shown" } else { "hidden" })
+        }
+        Response::Time(time) => println!("{}", time.to_rfc3339()),
+    }
+    Ok(())
+}
+
+/// Writes the effective configuration as a settings bundle: the config, plus the theme under
+/// `[bundle.theme]` if it is one of the user's own rather than a bundled one.
+fn export_config(config: &Config, paths: &Paths, output: Option<&Path>) -> anyhow::Result<()> {
+    let mut bundle = toml::Table::try_from(config).context("failed to serialize config")?;
+    let theme_path = paths.themes_dir().join(format!("{}.toml", config.theme));
+    if theme_path.exists() {
+        let theme: toml::Table = std::fs::read_to_string(&theme_path)
+            .with_context(|| format!("failed to read theme {}", theme_path.display()))?
+            .parse()
+            .with_context(|| format!("invalid theme {}", theme_path.display()))?;
+        let mut extra = toml::Table::new();
+        extra.insert("theme".to_owned(), theme.into());
+        bundle.insert("bundle".to_owned(), extra.into());
+    }
+    let contents = toml::to_string_pretty(&bundle).context("failed to serialize config")?;
+    match output {
+        Some(path) => std::fs::write(path, contents)
+            .with_context(|| format!("failed to write {}", path.display())),
+        None => {
+            print!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+/// Saves the clock face at the configured time, in the configured timezone and theme, to `output`.
+fn export_svg(config: &Config, paths: &Paths, output: &Path) -> anyhow::Result<()> {
+    let assets = Assets::standard(config.asset_dir.as_deref(), paths.data_dir());
+    let theme = Theme::load(&config.theme, &paths.themes_dir(), &assets)?;
+    let timezone = Timezone::from_config(config.timezone.as_deref())?;
+    let time = time_source::from_settings(config.time, config.speed).now();
+    let mut renderer = Renderer::new(&theme)?;
+    renderer.set_time(&timezone.time_of(&time));
+    std::fs::write(output, renderer.to_svg())
+        .with_context(|| format!("failed to write {}", output.display()))
+}
+
+/// The terminator at `time` with `points` points, the night side, and the subsolar point, as a
+/// GeoJSON feature collection with coordinates in degrees.
+fn terminator_geojson(time: &DateTime<Utc>, points: usize) -> serde_json::Value {
+    // About 10 cm on the ground, as GeoJSON suggests.
+    let round = |degrees: f64| (degrees * 1e6).round() / 1e6 + 0.0;
+    let line: Vec<[f64; 2]> = astronomy::terminator(time, points.max(2))
+        .into_iter()
+        .map(|(latitude, longitude)| [round(longitude), round(latitude)])
+        .collect();
+    let (sun_latitude, sun_longitude) = astronomy::subsolar_point(time);
+    let sun = [round(sun_longitude), round(sun_latitude)];
+    // Around the pole that the Sun is away from, which is in the night.
+    let pole = if sun_latitude >= 0.0 { -90.0 } else { 90.0 };
+    let mut night = line.clone();
+    night.extend([[180.0, pole], [-180.0, pole], line[0]]);
+    if pole < 0.0 {
+        // Counterclockwise, as GeoJSON wants the outside of polygons.
+        night.reverse();
+    }
+    let time = time.to_rfc3339_opts(SecondsFormat::Secs, true);
+    json!({
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": { "name": "terminator", "time": time },
+                "geometry": { "type": "LineString", "coordinates": line },
+            },
+            {
+                "type": "Feature",
+                "properties": { "name": "night", "time": time },
+                "geometry": { "type": "Polygon", "coordinates": [night] },
+            },
+            {
+                "type": "Feature",
+                "properties": { "name": "subsolar point", "time": time },
+                "geometry": { "type": "Point", "coordinates": sun },
+            },
+        ],
+    })
+}
+
+/// Installs a settings bundle from [`export_config`] as the config file `dest`, and its theme, if
+/// it has one, into the themes directory.
+fn import_config(input: &Path, dest: &Path, paths: &Paths) -> anyhow::Result<()> {
+    let mut source = std::fs::read_to_string(input)
+        .with_context(|| format!("failed to read {}", input.display()))?;
+    let mut bundle: toml::Table = source
+        .parse()
+        .with_context(|| format!("invalid settings bundle {}", input.display()))?;
+    let theme = match bundle.remove("bundle") {
+        None => None,
+        Some(toml::Value::Table(mut extra)) => {
+            let theme = extra.remove("theme");
+            if let Some(key) = extra.keys().next() {
+                bail!("unknown key bundle.{} in {}", key, input.display());
+            }
+            source = toml::to_string_pretty(&bundle).context("failed to serialize config")?;
+            theme
+        }
+        Some(_) => bail!("bundle in {} must be a table", input.display()),
+    };
+
+    // Refuse to install a bundle that would prevent the app from starting.
+    let config = Config::load_source(input, &source)?;
+    let theme_file = match theme {
+        Some(theme) => {
+            if config.theme.is_empty()
+                || config.theme.starts_with('.')
+                || config.theme.contains(['/', '\\'])
+            {
+                bail!(
+                    "invalid theme name {:?} in {}",
+                    config.theme,
+                    input.display()
+                );
+            }
+            let contents = toml::to_string_pretty(&theme).context("failed to serialize theme")?;
+            toml::from_str::<Theme>(&contents).with_context(|| {
+                format!("invalid theme {:?} in {}", config.theme, input.display())
+            })?;
+            let path = paths.themes_dir().join(format!("{}.toml", config.theme));
+            Some((path, contents))
+        }
+        None => {
+            let assets = Assets::standard(config.asset_dir.as_deref(), paths.data_dir());
+            Theme::load(&config.theme, &paths.themes_dir(), &assets)?;
+            None
+        }
+    };
+
+    if let Some((path, contents)) = theme_file {
+        replace_file(&path, &contents, "theme")?;
+    }
+    replace_file(dest, &source, "config")?;
+    eprintln!("imported {} into {}", input.display(), dest.display());
+    Ok(())
+}
+
+/// Writes `contents` to `path`, keeping what was there before alongside with a `.bak` extension.
+fn replace_file(path: &Path, contents: &str, what: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    if path.exists() {
+        let backup = path.with_extension("toml.bak");
+        std::fs::copy(path, &backup)
+            .with_context(|| format!("failed to back up {} to {}", what, backup.display()))?;
+        eprintln!("previous {} saved to {}", what, backup.display());
+    }
+    std::fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn list_timezones(query: Option<&str>) -> io::Result<()> {
+    let mut zones: Vec<(u32, Tz)> = TZ_VARIANTS
+        .iter()
+        .filter_map(|&tz| match query {
+            Some(query) => match_score(query, tz.name()).map(|score| (score, tz)),
+            None => Some((0, tz)),
+        })
+        .collect();
+    zones.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.name().cmp(b.name())));
+
+    // The config may be what's broken, so use the system locale rather than loading it.
+    let locale = Locale::from_config(&LocaleConfig::default());
+    let now = Utc::now();
+    let mut out = io::stdout().lock();
+    for (_, tz) in zones {
+        // The system's rules, where it has them, may be newer than the compiled-in names.
+        let Some(zone) = Zone::load(tz.name()) else {
+            continue;
+        };
+        let local = zone.local(&now);
+        let offset = local.offset().local_minus_utc();
+        writeln!(
+            out,
+            "UTC{}{:02}:{:02}  {:>11}  {:<6}  {}",
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 3600,
+            offset.abs() / 60 % 60,
+            locale.format_time(&local.time(), false),
+            zone.abbreviation_at(&now),
+            tz.name(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Scores how well `name` matches `query`, or `None` if it doesn't match at all.
+///
+/// Names containing the query as a substring rank highest, followed by names containing the
+/// query's characters in order, with fewer gaps between them ranking higher.
+fn match_score(query: &str, name: &str) -> Option<u32> {
+    let query = query.to_lowercase().replace(' ', "_");
+    let name = name.to_lowercase();
+    if name.contains(&query) {
+        return Some(1000 - name.len() as u32);
+    }
+
+    let mut gaps = 0;
+    let mut name_chars = name.chars();
+    for q in query.chars() {
+        loop {
+            match name_chars.next() {
+                Some(c) if c == q => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(500u32.saturating_sub(gaps))
+}
@@ -0,0 +1,1212 @@
+use crate::contrast;
+use crate::paths::Paths;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use global_clock::locale::LocaleConfig;
+use global_clock::night_light::DAYLIGHT_TEMPERATURE;
+use global_clock::zoneinfo::Zone;
+use global_clock::{gps, theme, LayerRegistry, Location, Palette};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use winit::dpi::{LogicalSize, PhysicalSize, Size};
+
+/// The range of [`Config::text_scale`], which the text scale hotkeys also stay within.
+pub const MIN_TEXT_SCALE: f32 = 0.5;
+pub const MAX_TEXT_SCALE: f32 = 4.0;
+
+/// The warmest [`NightLightConfig::temperature`], that of candlelight.
+pub const MIN_NIGHT_TEMPERATURE: f32 = 1000.0;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// IANA name of the timezone displayed on the clock face, or the system timezone if unset.
+    pub timezone: Option<String>,
+    /// Name of the color theme, either bundled or a file in the `themes` config directory.
+    pub theme: String,
+    /// Use the `high-contrast` theme instead of `theme` when the system asks for high contrast.
+    pub follow_system_contrast: bool,
+    /// Colors of overlays that carry meaning, e.g. warnings, which can be chosen to suit a color
+    /// vision deficiency.
+    pub palette: Palette,
+    /// How many times as large as configured to draw the text of overlays, independent of the
+    /// monitor's scale factor, e.g. 2 to read it from across a room.
+    pub text_scale: f32,
+    /// Directory to search for assets before the standard locations.
+    pub asset_dir: Option<PathBuf>,
+    /// Directory to load the globe textures from, instead of the built-in assets.
+    pub texture_dir: Option<PathBuf>,
+    /// GPU memory in MiB that each window's textures should fit in, e.g. on integrated GPUs with
+    /// little memory. Textures are drawn or loaded at a lower resolution to fit; unlimited if
+    /// unset.
+    pub texture_budget: Option<u32>,
+    /// Start the clock at this instant instead of the current time.
+    pub time: Option<DateTime<Utc>>,
+    /// Rate at which the clock runs relative to real time; 0 freezes it.
+    pub speed: f64,
+    pub locale: LocaleConfig,
+    /// Where the user is, for layers that show information about it, such as the weather.
+    pub home: Option<Location>,
+    pub gps: GpsConfig,
+    pub redraw: RedrawConfig,
+    pub window: WindowConfig,
+    /// Windows to open in addition to the main window.
+    pub windows: Vec<ExtraWindowConfig>,
+    pub control: ControlConfig,
+    pub tray: TrayConfig,
+    pub accessibility: AccessibilityConfig,
+    pub night_light: NightLightConfig,
+    pub widget: WidgetConfig,
+    pub mini: MiniConfig,
+    /// Window settings that apply when the window is on a particular monitor, keyed by monitor
+    /// name or index.
+    pub monitors: BTreeMap<String, MonitorConfig>,
+    /// Settings of individual layers, keyed by layer name, e.g. `[layer.digital_clock]`.
+    pub layer: BTreeMap<String, toml::Table>,
+}
+
+impl Config {
+    /// The name of the theme to start with: [`Self::theme`], or the high contrast theme if the
+    /// system asks for it.
+    pub fn theme_name(&self) -> &str {
+        if self.follow_system_contrast && contrast::system_prefers_high_contrast() {
+            log::info!("the system asks for high contrast");
+            return theme::HIGH_CONTRAST;
+        }
+        &self.theme
+    }
+
+    /// [`Self::texture_budget`] in bytes.
+    pub fn texture_budget_bytes(&self) -> Option<u64> {
+        self.texture_budget.map(|mib| u64::from(mib) << 20)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timezone: None,
+            theme: "default".into(),
+            follow_system_contrast: true,
+            palette: Default::default(),
+            text_scale: 1.0,
+            asset_dir: None,
+            texture_dir: None,
+            texture_budget: None,
+            time: None,
+            speed: 1.0,
+            locale: Default::default(),
+            home: None,
+            gps: Default::default(),
+            redraw: Default::default(),
+            window: Default::default(),
+            windows: Default::default(),
+            control: Default::default(),
+            tray: Default::default(),
+            accessibility: Default::default(),
+            night_light: Default::default(),
+            widget: Default::default(),
+            mini: Default::default(),
+            monitors: Default::default(),
+            layer: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RedrawConfig {
+    /// Shortest time between redraws, in seconds. Layers that change less often, such as the
+    /// globe, are only redrawn when they change. Use 1 for minimal power use, or e.g. 0.016 for
+    /// smooth animation.
+    pub interval: f64,
+    /// Upper limit on the number of frames drawn per second, regardless of `interval`.
+    pub max_fps: Option<f64>,
+    pub present_mode: PresentMode,
+}
+
+impl Default for RedrawConfig {
+    fn default() -> Self {
+        Self {
+            interval: 1.0,
+            max_fps: None,
+            present_mode: PresentMode::Fifo,
+        }
+    }
+}
+
+/// How frames are handed to the monitor. Where the surface does not support the chosen mode,
+/// `Fifo` is used instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentMode {
+    /// Frames wait for the monitor's refresh (vsync), which uses the least power. Always
+    /// supported.
+    Fifo,
+    /// Frames wait for the refresh, but a newer frame replaces one that is still waiting, for
+    /// less latency without tearing.
+    Mailbox,
+    /// Frames are shown right away, for the least latency, but may tear.
+    Immediate,
+}
+
+impl RedrawConfig {
+    pub fn frame_interval(&self) -> Duration {
+        let min_interval = self.max_fps.map_or(0.0, |fps| fps.recip());
+        Duration::from_secs_f64(self.interval.max(min_interval))
+    }
+
+    /// The time between frames of short animations, e.g. the hands catching up with the system
+    /// clock being set: as often as `max_fps` allows, and 60 times per second without it.
+    pub fn animation_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.max_fps.unwrap_or(60.0).recip())
+    }
+}
+
+/// Ways for other programs to control the running clock.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    /// Serve the `io.github.agausmann.GlobalClock1` interface on the D-Bus session bus. Only
+    /// available on Linux.
+    pub dbus: bool,
+    /// Accept commands from `global-clock ctl` on a local socket.
+    pub socket: bool,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            dbus: true,
+            socket: true,
+        }
+    }
+}
+
+/// The system tray icon, in builds with the `tray` feature.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrayConfig {
+    pub enabled: bool,
+    /// Hide the windows when the main window is closed, instead of quitting. They can be shown
+    /// again from the tray menu.
+    pub close_to_tray: bool,
+    /// IANA name of the timezone shown next to the icon in the macOS menu bar, e.g. that of a
+    /// headquarters, or UTC if unset.
+    pub timezone: Option<String>,
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            close_to_tray: false,
+            timezone: None,
+        }
+    }
+}
+
+/// What assistive technologies, such as screen readers, are told about the main window, in builds
+/// with the `accesskit` feature.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    pub enabled: bool,
+    /// Read out the time when the hour changes.
+    pub announce_hours: bool,
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            announce_hours: false,
+        }
+    }
+}
+
+/// A warm tint over windows after sunset at the home location.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NightLightConfig {
+    pub enabled: bool,
+    /// Color temperature to tint toward at night, in kelvin; lower is warmer, and 6500 is
+    /// daylight, which leaves colors as they are.
+    pub temperature: f32,
+}
+
+impl NightLightConfig {
+    /// The temperature to tint windows toward, if the night light is on.
+    pub fn temperature(&self) -> Option<f32> {
+        self.enabled.then_some(self.temperature)
+    }
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            temperature: 3400.0,
+        }
+    }
+}
+
+/// A GPS receiver to take the time and the home location from, e.g. on a boat without a network.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GpsConfig {
+    pub enabled: bool,
+    /// Address of gpsd, which shares the receiver between programs.
+    pub gpsd: String,
+    /// Serial device that the receiver writes NMEA sentences to, e.g. `/dev/ttyUSB0`, to read
+    /// instead of asking gpsd.
+    pub device: Option<PathBuf>,
+    /// Show the receiver's time instead of the system clock's, unless `time` or `speed` are set.
+    pub time: bool,
+    /// Set the home location to the receiver's position, keeping the name of `home`.
+    pub location: bool,
+}
+
+impl GpsConfig {
+    pub fn source(&self) -> gps::Source {
+        match &self.device {
+            Some(path) => gps::Source::Device(path.clone()),
+            None => gps::Source::Gpsd(self.gpsd.clone()),
+        }
+    }
+}
+
+impl Default for GpsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gpsd: "localhost:2947".to_owned(),
+            device: None,
+            time: true,
+            location: true,
+        }
+    }
+}
+
+/// Shows the main window as a small widget with only the clock, without a title bar or border,
+/// e.g. to pin to a corner of the desktop. This replaces the window's size and fullscreen setting.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WidgetConfig {
+    pub enabled: bool,
+    /// Width and height of the window, in logical pixels.
+    pub size: u32,
+    /// Make the window round, showing the desktop around the clock, where the platform supports
+    /// transparent windows.
+    pub circular: bool,
+    /// Corner of the monitor to place the window in, or its center if unset.
+    pub corner: Option<Corner>,
+    /// Translucent material to show behind the clock on Windows 11, instead of the theme's
+    /// background. The window is rectangular with a backdrop, even if `circular` is set.
+    pub backdrop: Backdrop,
+    /// Give the window a taskbar button on Windows.
+    pub show_in_taskbar: bool,
+}
+
+impl Default for WidgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: 200,
+            circular: true,
+            corner: None,
+            backdrop: Backdrop::None,
+            show_in_taskbar: false,
+        }
+    }
+}
+
+/// The small window that `M` collapses a window into, showing only the clock face or the globe.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MiniConfig {
+    /// Width and height of the window, in logical pixels.
+    pub size: u32,
+    /// Corner of the monitor that the window first docks to. It is reopened where it was last
+    /// moved to.
+    pub corner: Corner,
+}
+
+impl Default for MiniConfig {
+    fn default() -> Self {
+        Self {
+            size: 160,
+            corner: Corner::BottomRight,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backdrop {
+    None,
+    /// The blurred material of menus and flyouts.
+    Acrylic,
+    /// The tinted material of main windows.
+    Mica,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub fullscreen: bool,
+    /// Keep the window above other windows.
+    pub always_on_top: bool,
+    /// Float the clock over other windows without getting in their way: the window has no
+    /// background or decorations, stays on top, and lets clicks through to the windows beneath.
+    pub overlay: bool,
+    /// Initial size of the window, in `size_unit`s.
+    pub width: u32,
+    pub height: u32,
+    /// Whether window sizes, including those of the `windows` and `monitors` tables, are in
+    /// logical pixels, which grow with the monitor's scale factor, or in physical pixels. Windows
+    /// sized in physical pixels also keep their size in pixels when moved to a monitor with
+    /// other scaling.
+    pub size_unit: SizeUnit,
+    /// Monitor to open the window on, or the platform's choice if unset.
+    pub monitor: Option<MonitorSelector>,
+    /// Show one scene across all monitors, e.g. on a wall of displays, instead of a window on one
+    /// monitor. This replaces the other settings of the main window.
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Span {
+    /// A fullscreen window on each monitor, each showing its part of the scene.
+    Monitors,
+    /// A single window without decorations, covering every monitor. This suits platforms that
+    /// treat several displays as one screen.
+    Window,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeUnit {
+    Logical,
+    Physical,
+}
+
+impl SizeUnit {
+    pub fn size(self, width: u32, height: u32) -> Size {
+        match self {
+            Self::Logical => LogicalSize::new(width, height).into(),
+            Self::Physical => PhysicalSize::new(width, height).into(),
+        }
+    }
+}
+
+/// An additional window, e.g. a clock for another timezone.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExtraWindowConfig {
+    /// Window title, or one naming the timezone if unset.
+    pub title: Option<String>,
+    /// IANA name of the timezone displayed on the clock face, or the main window's if unset.
+    pub timezone: Option<String>,
+    /// Names of the layers to show, from bottom to top.
+    pub layers: Vec<String>,
+    /// Keep the window above other windows.
+    pub always_on_top: bool,
+    /// Initial size of the window, in the main window's `size_unit`s.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ExtraWindowConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            timezone: None,
+            layers: LayerRegistry::default()
+                .names()
+                .map(str::to_owned)
+                .collect(),
+            always_on_top: false,
+            width: 360,
+            height: 360,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MonitorSelector {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub fullscreen: Option<bool>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            always_on_top: false,
+            overlay: false,
+            width: 720,
+            height: 720,
+            size_unit: SizeUnit::Logical,
+            monitor: None,
+            span: None,
+        }
+    }
+}
+
+/// Prefix of environment variables that override config keys.
+///
+/// Nested keys are separated by a double underscore, e.g. `GLOBAL_CLOCK_WINDOW__WIDTH=1024`
+/// overrides `width` in the `[window]` table.
+pub const ENV_PREFIX: &str = "GLOBAL_CLOCK_";
+
+/// Environment variable naming the config file, which is not itself a config key.
+pub const ENV_CONFIG_PATH: &str = "GLOBAL_CLOCK_CONFIG";
+
+/// Environment variable overriding the base directory, which is not itself a config key.
+pub const ENV_BASE_DIR: &str = "GLOBAL_CLOCK_BASE_DIR";
+
+const MAX_WINDOW_SIZE: u32 = 16384;
+
+/// The longest [`RedrawConfig::interval`], a day.
+const MAX_REDRAW_INTERVAL: f64 = 86400.0;
+
+/// The lowest [`RedrawConfig::max_fps`], a frame every 1000 seconds.
+const MIN_MAX_FPS: f64 = 0.001;
+
+impl Config {
+    /// Loads the config file at `path`, or at the location given by `paths` if `None`, and then applies
+    /// overrides from the environment and from `cli`, in that order.
+    ///
+    /// A missing file at the default location is not an error; the defaults are used instead.
+    /// Every problem found in the merged configuration is reported at once, annotated with where
+    /// the offending value came from.
+    pub fn load(path: Option<&Path>, paths: &Paths, cli: toml::Table) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => Some(path.to_owned()),
+            None => Some(paths.config_file()).filter(|path| path.exists()),
+        };
+        let source = match &path {
+            Some(path) => Some(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read config file {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let file = path.as_deref().zip(source.as_deref());
+        Self::load_layers(file, std::env::vars(), cli)
+    }
+
+    /// Loads and validates the config `source`, read from `path`, ignoring the environment.
+    pub fn load_source(path: &Path, source: &str) -> anyhow::Result<Self> {
+        Self::load_layers(Some((path, source)), std::iter::empty(), toml::Table::new())
+    }
+
+    fn load_layers(
+        file: Option<(&Path, &str)>,
+        env: impl IntoIterator<Item = (String, String)>,
+        cli: toml::Table,
+    ) -> anyhow::Result<Self> {
+        let mut layers = Layers::default();
+        let mut problems = Vec::new();
+        if let Some((path, source)) = file {
+            match FileLayer::parse(path, source) {
+                Ok(file) => file.merge_into(&mut layers),
+                Err(problem) => problems.push(problem),
+            }
+        }
+        layers.merge_env(env);
+        layers.merge(cli, &|_| Origin::CommandLine);
+
+        let config = layers.deserialize(&mut problems);
+        config.validate(&layers, &mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(Problems(problems).into())
+        }
+    }
+
+    fn validate(&self, layers: &Layers, problems: &mut Vec<Problem>) {
+        let mut check = |key: &str, ok: bool, message: String| {
+            if !ok {
+                problems.push(Problem {
+                    origin: layers.origin_of(key),
+                    message,
+                });
+            }
+        };
+
+        let registry = LayerRegistry::default();
+        let mut timezones = vec![("timezone".to_owned(), &self.timezone)];
+        for (index, window) in self.windows.iter().enumerate() {
+            timezones.push((format!("windows[{}].timezone", index), &window.timezone));
+            let key = format!("windows[{}].layers", index);
+            for name in &window.layers {
+                check(
+                    &key,
+                    registry.contains(name),
+                    format!(
+                        "unknown layer {:?} in {}; expected one of {}",
+                        name,
+                        key,
+                        registry.names().collect::<Vec<_>>().join(", ")
+                    ),
+                );
+            }
+        }
+        check(
+            "text_scale",
+            (MIN_TEXT_SCALE..=MAX_TEXT_SCALE).contains(&self.text_scale),
+            format!(
+                "text_scale {} is out of range; expected {} to {}",
+                self.text_scale, MIN_TEXT_SCALE, MAX_TEXT_SCALE
+            ),
+        );
+        check(
+            "night_light.temperature",
+            (MIN_NIGHT_TEMPERATURE..=DAYLIGHT_TEMPERATURE).contains(&self.night_light.temperature),
+            format!(
+                "night light temperature {} K is out of range; expected {} to {}",
+                self.night_light.temperature, MIN_NIGHT_TEMPERATURE, DAYLIGHT_TEMPERATURE
+            ),
+        );
+        check(
+            "night_light",
+            !self.night_light.enabled
+                || self.home.is_some()
+                || (self.gps.enabled && self.gps.location),
+            "the night light needs a home location to know when the sun sets; set `home`"
+                .to_owned(),
+        );
+        if let Some(home) = &self.home {
+            check(
+                "home",
+                home.is_valid(),
+                format!(
+                    "home location {}, {} is not on Earth; latitude must be within -90..90 and \
+                     longitude within -180..180 degrees",
+                    home.latitude, home.longitude
+                ),
+            );
+        }
+        for (name, table) in &self.layer {
+            let key = format!("layer.{}", name);
+            check(
+                &key,
+                registry.contains(name),
+                format!(
+                    "settings for unknown layer {:?}; expected one of {}",
+                    name,
+                    registry.names().collect::<Vec<_>>().join(", ")
+                ),
+            );
+            for problem in registry.check_settings(name, table) {
+                let key = match problem.key.as_str() {
+                    "" => key.clone(),
+                    path => format!("{}.{}", key, path),
+                };
+                check(
+                    &key,
+                    false,
+                    format!(
+                        "invalid setting `{}`: {}",
+                        key,
+                        first_line(&problem.message)
+                    ),
+                );
+            }
+        }
+        for (key, timezone) in timezones {
+            if let Some(timezone) = timezone {
+                check(
+                    &key,
+                    Zone::load(timezone).is_some(),
+                    format!(
+                        "unknown timezone {:?} in {}; run `global-clock timezones` to search for \
+                         valid names",
+                        timezone, key
+                    ),
+                );
+            }
+        }
+        if let Some(asset_dir) = &self.asset_dir {
+            check(
+                "asset_dir",
+                asset_dir.is_dir(),
+                format!("asset directory {} does not exist", asset_dir.display()),
+            );
+        }
+        if let Some(texture_dir) = &self.texture_dir {
+            check(
+                "texture_dir",
+                texture_dir.is_dir(),
+                format!("texture directory {} does not exist", texture_dir.display()),
+            );
+        }
+        check(
+            "texture_budget",
+            self.texture_budget != Some(0),
+            "texture budget must be a positive number of MiB".to_owned(),
+        );
+        check(
+            "redraw.interval",
+            self.redraw.interval > 0.0 && self.redraw.interval <= MAX_REDRAW_INTERVAL,
+            format!(
+                "redraw interval must be a positive number of seconds up to {}, not {}",
+                MAX_REDRAW_INTERVAL, self.redraw.interval
+            ),
+        );
+        if let Some(max_fps) = self.redraw.max_fps {
+            check(
+                "redraw.max_fps",
+                max_fps.is_finite() && max_fps >= MIN_MAX_FPS,
+                format!(
+                    "max_fps must be a number of at least {}, not {}",
+                    MIN_MAX_FPS, max_fps
+                ),
+            );
+        }
+        check(
+            "speed",
+            self.speed.is_finite(),
+            format!("speed must be a finite number, not {}", self.speed),
+        );
+        let mut sizes = vec![
+            ("window.width".to_owned(), self.window.width),
+            ("window.height".to_owned(), self.window.height),
+        ];
+        for (name, monitor) in &self.monitors {
+            sizes.extend(
+                monitor
+                    .width
+                    .map(|w| (format!("monitors.{}.width", name), w)),
+            );
+            sizes.extend(
+                monitor
+                    .height
+                    .map(|h| (format!("monitors.{}.height", name), h)),
+            );
+        }
+        for (index, window) in self.windows.iter().enumerate() {
+            sizes.push((format!("windows[{}].width", index), window.width));
+            sizes.push((format!("windows[{}].height", index), window.height));
+        }
+        for (key, value) in sizes {
+            check(
+                &key,
+                (1..=MAX_WINDOW_SIZE).contains(&value),
+                format!(
+                    "{} must be between 1 and {}, not {}",
+                    key, MAX_WINDOW_SIZE, value
+                ),
+            );
+        }
+    }
+}
+
+/// Where a config value was set.
+#[derive(Debug, Clone)]
+pub enum Origin {
+    Default,
+    File {
+        path: PathBuf,
+        position: Option<(usize, usize)>,
+    },
+    Env(String),
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default value"),
+            Self::File {
+                path,
+                position: Some((line, column)),
+            } => write!(f, "{}:{}:{}", path.display(), line, column),
+            Self::File {
+                path,
+                position: None,
+            } => write!(f, "{}", path.display()),
+            Self::Env(name) => write!(f, "environment variable {}", name),
+            Self::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    pub origin: Origin,
+    pub message: String,
+}
+
+/// All of the problems found while loading the config.
+#[derive(Debug)]
+pub struct Problems(pub Vec<Problem>);
+
+impl fmt::Display for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  {}: {}", problem.origin, problem.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Problems {}
+
+/// The merged config table, along with the origin of each leaf value.
+#[derive(Default)]
+struct Layers {
+    table: toml::Table,
+    origins: HashMap<String, Origin>,
+}
+
+impl Layers {
+    fn merge(&mut self, layer: toml::Table, origin_of: &dyn Fn(&str) -> Origin) {
+        fn merge_table(
+            dest: &mut toml::Table,
+            src: toml::Table,
+            prefix: &str,
+            origins: &mut HashMap<String, Origin>,
+            origin_of: &dyn Fn(&str) -> Origin,
+        ) {
+            for (key, value) in src {
+                let path = join_key(prefix, &key);
+                match (dest.get_mut(&key), value) {
+                    (Some(toml::Value::Table(dest)), toml::Value::Table(src)) => {
+                        merge_table(dest, src, &path, origins, origin_of);
+                    }
+                    (_, toml::Value::Table(src)) => {
+                        let mut table = toml::Table::new();
+                        merge_table(&mut table, src, &path, origins, origin_of);
+                        dest.insert(key, toml::Value::Table(table));
+                    }
+                    (_, value) => {
+                        origins.insert(path.clone(), origin_of(&path));
+                        dest.insert(key, value);
+                    }
+                }
+            }
+        }
+        merge_table(&mut self.table, layer, "", &mut self.origins, origin_of);
+    }
+
+    fn merge_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        for (name, raw) in vars {
+            if name == ENV_CONFIG_PATH || name == ENV_BASE_DIR {
+                continue;
+            }
+            let key = match name.strip_prefix(ENV_PREFIX) {
+                Some(key) if !key.is_empty() => key.to_lowercase(),
+                _ => continue,
+            };
+
+            let mut segments: Vec<&str> = key.split("__").collect();
+            let mut value = parse_env_value(raw);
+            while let Some(segment) = segments.pop() {
+                let mut table = toml::Table::new();
+                table.insert(segment.to_owned(), value);
+                value = toml::Value::Table(table);
+            }
+            if let toml::Value::Table(layer) = value {
+                self.merge(layer, &|_| Origin::Env(name.clone()));
+            }
+        }
+    }
+
+    fn origin_of(&self, key: &str) -> Origin {
+        // Values inside arrays are not tracked individually, so use the origin of the array.
+        let key = key.split('[').next().unwrap_or(key);
+        let nested = format!("{}.", key);
+        self.origins
+            .get(key)
+            .or_else(|| {
+                self.origins
+                    .iter()
+                    .find(|(path, _)| path.starts_with(&nested))
+                    .map(|(_, origin)| origin)
+            })
+            .cloned()
+            .unwrap_or(Origin::Default)
+    }
+
+    /// Deserializes the merged table, reporting unknown keys and dropping invalid values so the
+    /// remaining keys can still be checked.
+    fn deserialize(&mut self, problems: &mut Vec<Problem>) -> Config {
+        loop {
+            let mut unknown: Vec<String> = Vec::new();
+            let deserializer = toml::Value::Table(self.table.clone());
+            let result = serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+                deserializer,
+                &mut |path: serde_ignored::Path| unknown.push(path.to_string()),
+            ));
+            match result {
+                Ok(config) => {
+                    for key in unknown {
+                        match self.origin_of(&key) {
+                            // Unrelated or stale variables may be set in the environment.
+                            origin @ Origin::Env(_) => {
+                                log::warn!("ignoring {}: unknown key `{}`", origin, key);
+                            }
+                            origin => problems.push(Problem {
+                                origin,
+                                message: format!("unknown key `{}`", key),
+                            }),
+                        }
+                    }
+                    return config;
+                }
+                Err(err) => {
+                    let key = err.path().to_string();
+                    // Environment variables have no types, so one that was read as a number or
+                    // boolean where text is expected, e.g. a theme named `1984`, is text.
+                    if matches!(self.origin_of(&key), Origin::Env(_)) && self.stringify(&key) {
+                        continue;
+                    }
+                    problems.push(Problem {
+                        origin: self.origin_of(&key),
+                        message: format!(
+                            "invalid value for `{}`: {}",
+                            key,
+                            first_line(&err.inner().to_string())
+                        ),
+                    });
+                    if !self.remove(&key) {
+                        return Config::default();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replaces the number, boolean or date at `key` with its text, returning whether there was
+    /// one.
+    fn stringify(&mut self, key: &str) -> bool {
+        let mut segments: Vec<&str> = key.split('.').collect();
+        let leaf = match segments.pop() {
+            Some(leaf) => leaf,
+            None => return false,
+        };
+        let mut table = &mut self.table;
+        for segment in segments {
+            table = match table.get_mut(segment) {
+                Some(toml::Value::Table(table)) => table,
+                _ => return false,
+            };
+        }
+        match table.get_mut(leaf) {
+            Some(
+                value @ (toml::Value::Integer(_)
+                | toml::Value::Float(_)
+                | toml::Value::Boolean(_)
+                | toml::Value::Datetime(_)),
+            ) => {
+                *value = toml::Value::String(value.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        let mut segments: Vec<&str> = key.split('.').collect();
+        let leaf = match segments.pop() {
+            Some(leaf) => leaf,
+            None => return false,
+        };
+        let mut table = &mut self.table;
+        for segment in segments {
+            table = match table.get_mut(segment) {
+                Some(toml::Value::Table(table)) => table,
+                _ => return false,
+            };
+        }
+        table.remove(leaf).is_some()
+    }
+}
+
+/// A parsed config file, with the position of each key.
+struct FileLayer {
+    path: PathBuf,
+    table: toml::Table,
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl FileLayer {
+    fn parse(path: &Path, source: &str) -> Result<Self, Problem> {
+        let document = toml_edit::ImDocument::parse(source).map_err(|err| Problem {
+            origin: Origin::File {
+                path: path.to_owned(),
+                position: err.span().map(|span| line_column(source, span.start)),
+            },
+            message: err.message().lines().collect::<Vec<_>>().join(", "),
+        })?;
+        let table = toml::from_str(source).map_err(|err: toml::de::Error| Problem {
+            origin: Origin::File {
+                path: path.to_owned(),
+                position: err.span().map(|span| line_column(source, span.start)),
+            },
+            message: err.message().lines().collect::<Vec<_>>().join(", "),
+        })?;
+
+        fn collect_positions(
+            table: &dyn toml_edit::TableLike,
+            prefix: &str,
+            source: &str,
+            positions: &mut HashMap<String, (usize, usize)>,
+        ) {
+            for (key, item) in table.iter() {
+                let path = join_key(prefix, key);
+                let span = table
+                    .get_key_value(key)
+                    .and_then(|(key, _)| key.span())
+                    .or_else(|| item.span());
+                if let Some(span) = span {
+                    positions.insert(path.clone(), line_column(source, span.start));
+                }
+                if let Some(table) = item.as_table_like() {
+                    collect_positions(table, &path, source, positions);
+                }
+            }
+        }
+        let mut positions = HashMap::new();
+        collect_positions(document.as_table(), "", source, &mut positions);
+
+        Ok(Self {
+            path: path.to_owned(),
+            table,
+            positions,
+        })
+    }
+
+    fn merge_into(self, layers: &mut Layers) {
+        let Self {
+            path,
+            table,
+            positions,
+        } = self;
+        layers.merge(table, &|key| Origin::File {
+            path: path.clone(),
+            position: positions.get(key).copied(),
+        });
+    }
+}
+
+/// Reads the value of an environment variable as a TOML value, e.g. `12`, `true` or `["a", "b"]`,
+/// or as text if it is not one. Values that turn out to be text after all are converted back when
+/// the config is deserialized.
+fn parse_env_value(raw: String) -> toml::Value {
+    match format!("value = {}", raw).parse::<toml::Table>() {
+        Ok(mut table) if table.len() == 1 => table.remove("value").unwrap(),
+        _ => toml::Value::String(raw),
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// Converts a byte offset into a 1-based line and column.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn load_env(vars: &[(&str, &str)]) -> anyhow::Result<Config> {
+        Config::load_layers(None, env(vars), toml::Table::new())
+    }
+
+    fn problems(result: anyhow::Result<Config>) -> Vec<Problem> {
+        result.unwrap_err().downcast::<Problems>().unwrap().0
+    }
+
+    #[test]
+    fn env_values_are_typed() {
+        let config = load_env(&[
+            ("GLOBAL_CLOCK_SPEED", "2.5"),
+            ("GLOBAL_CLOCK_TEXT_SCALE", "2"),
+            ("GLOBAL_CLOCK_FOLLOW_SYSTEM_CONTRAST", "false"),
+        ])
+        .unwrap();
+        assert_eq!(config.speed, 2.5);
+        assert_eq!(config.text_scale, 2.0);
+        assert!(!config.follow_system_contrast);
+    }
+
+    #[test]
+    fn env_values_are_text_where_text_is_expected() {
+        // Not TOML at all, and a number where the key expects text.
+        assert_eq!(
+            load_env(&[("GLOBAL_CLOCK_THEME", "dark")]).unwrap().theme,
+            "dark"
+        );
+        assert_eq!(
+            load_env(&[("GLOBAL_CLOCK_THEME", "1984")]).unwrap().theme,
+            "1984"
+        );
+        // Text is not a number where one is expected, though.
+        let problems = problems(load_env(&[("GLOBAL_CLOCK_SPEED", "fast")]));
+        assert_eq!(problems.len(), 1);
+        assert!(matches!(&problems[0].origin, Origin::Env(name) if name == "GLOBAL_CLOCK_SPEED"));
+    }
+
+    #[test]
+    fn env_names_nest_on_double_underscores() {
+        let config = load_env(&[
+            ("GLOBAL_CLOCK_WINDOW__WIDTH", "640"),
+            ("GLOBAL_CLOCK_WINDOW__ALWAYS_ON_TOP", "true"),
+        ])
+        .unwrap();
+        assert_eq!(config.window.width, 640);
+        assert!(config.window.always_on_top);
+    }
+
+    #[test]
+    fn env_names_outside_the_prefix_are_ignored() {
+        let config = load_env(&[
+            ("THEME", "1984"),
+            ("GLOBAL_CLOCK_", "x"),
+            ("GLOBAL_CLOCK_CONFIG", "/nowhere.toml"),
+        ])
+        .unwrap();
+        assert_eq!(config.theme, Config::default().theme);
+    }
+
+    #[test]
+    fn unknown_env_keys_are_ignored() {
+        let config = load_env(&[
+            ("GLOBAL_CLOCK_COLOUR", "red"),
+            ("GLOBAL_CLOCK_WINDOW__COLOUR", "red"),
+            ("GLOBAL_CLOCK_THEME", "dark"),
+        ])
+        .unwrap();
+        assert_eq!(config.theme, "dark");
+    }
+
+    #[test]
+    fn origins_are_the_last_layer_to_set_a_value() {
+        let path = Path::new("/config.toml");
+        let source = "theme = \"dark\"\nspeed = 2\n\n[window]\nwidth = 800\n";
+        let mut layers = Layers::default();
+        FileLayer::parse(path, source)
+            .unwrap()
+            .merge_into(&mut layers);
+        layers.merge_env(env(&[("GLOBAL_CLOCK_SPEED", "3")]));
+        let mut cli = toml::Table::new();
+        cli.insert("text_scale".to_owned(), toml::Value::Float(1.5));
+        layers.merge(cli, &|_| Origin::CommandLine);
+
+        assert!(matches!(
+            layers.origin_of("theme"),
+            Origin::File { path: file, position: Some((1, 1)) } if file == path
+        ));
+        assert!(matches!(
+            layers.origin_of("window.width"),
+            Origin::File {
+                position: Some((5, 1)),
+                ..
+            }
+        ));
+        // A table is reported where its values were set.
+        assert!(matches!(layers.origin_of("window"), Origin::File { .. }));
+        assert!(
+            matches!(layers.origin_of("speed"), Origin::Env(name) if name == "GLOBAL_CLOCK_SPEED")
+        );
+        assert!(matches!(
+            layers.origin_of("text_scale"),
+            Origin::CommandLine
+        ));
+        assert!(matches!(layers.origin_of("timezone"), Origin::Default));
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let path = Path::new("/config.toml");
+        let source = "text_scale = 100\nspeed = \"fast\"\ncolour = \"red\"\n";
+        let problems = problems(Config::load_layers(
+            Some((path, source)),
+            env(&[("GLOBAL_CLOCK_WINDOW__WIDTH", "0")]),
+            toml::Table::new(),
+        ));
+        let mut reported: Vec<String> = problems
+            .iter()
+            .map(|problem| problem.origin.to_string())
+            .collect();
+        reported.sort();
+        assert_eq!(
+            reported,
+            [
+                "/config.toml:1:1",
+                "/config.toml:2:1",
+                "/config.toml:3:1",
+                "environment variable GLOBAL_CLOCK_WINDOW__WIDTH",
+            ]
+        );
+    }
+
+    #[test]
+    fn layer_settings_are_checked_when_loading() {
+        let path = Path::new("/config.toml");
+        let source = "[layer.banners]\nsize = \"large\"\nseconds = 3\ncolour = \"red\"\n";
+        let problems = problems(Config::load_source(path, source));
+        let reported: Vec<String> = problems
+            .iter()
+            .map(|problem| format!("{}: {}", problem.origin, problem.message))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                "/config.toml:4:1: invalid setting `layer.banners.colour`: unknown field \
+                 `colour`, expected one of `size`, `seconds`, `dst_warning`",
+                "/config.toml:2:1: invalid setting `layer.banners.size`: invalid type: string \
+                 \"large\", expected f32",
+            ]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Whether the system asks apps for high contrast, e.g. for users with low vision.
+
+/// Whether the system's accessibility settings ask for high contrast. Where this cannot be told,
+/// it does not.
+///
+/// On Linux, this is the contrast setting of the desktop portal, which GNOME and KDE provide; on
+/// Windows, the high contrast mode; and on macOS, the "Increase contrast" display setting.
+pub fn system_prefers_high_contrast() -> bool {
+    match query() {
+        Ok(high) => high,
+        Err(err) => {
+            log::debug!("failed to read the system's contrast setting: {:#}", err);
+            false
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn query() -> anyhow::Result<bool> {
+    use std::convert::TryFrom;
+    use zbus::zvariant::OwnedValue;
+
+    let connection = zbus::blocking::Connection::session()?;
+    let read = |method: &str| {
+        connection.call_method(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            Some("org.freedesktop.portal.Settings"),
+            method,
+            &("org.freedesktop.appearance", "contrast"),
+        )
+    };
+    // `Read` is deprecated for `ReadOne`, which older portals lack, and wraps the value in
+    // another variant.
+    let value: OwnedValue = match read("ReadOne") {
+        Ok(reply) => reply.body().deserialize()?,
+        Err(_) => {
+            let outer: OwnedValue = read("Read")?.body().deserialize()?;
+            match &*outer {
+                zbus::zvariant::Value::Value(inner) => inner.try_to_owned()?,
+                _ => outer,
+            }
+        }
+    };
+    // 0 is no preference, and 1 high contrast.
+    Ok(u32::try_from(value)? == 1)
+}
+
+#[cfg(windows)]
+fn query() -> anyhow::Result<bool> {
+    use windows_sys::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETHIGHCONTRAST};
+
+    let mut settings = HIGHCONTRASTW {
+        cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+        dwFlags: 0,
+        lpszDefaultScheme: std::ptr::null_mut(),
+    };
+    // Safety: `settings` is valid for writes, with its size set as the call requires.
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            settings.cbSize,
+            &mut settings as *mut HIGHCONTRASTW as *mut std::ffi::c_void,
+            0,
+        )
+    };
+    if ok == 0 {
+        anyhow::bail!("{}", std::io::Error::last_os_error());
+    }
+    Ok(settings.dwFlags & HCF_HIGHCONTRASTON != 0)
+}
+
+#[cfg(target_os = "macos")]
+fn query() -> anyhow::Result<bool> {
+    let output = std::process::Command::new("defaults")
+        .args(["read", "com.apple.universalaccess", "increaseContrast"])
+        .output()?;
+    // The key is missing until the setting is first changed.
+    Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "1")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn query() -> anyhow::Result<bool> {
+    anyhow::bail!("not supported on this platform")
+}
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use winit::event_loop::EventLoopProxy;
+
+/// An action requested by another process, e.g. over D-Bus or the control socket.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    SetTimezone(String),
+    /// Shows the named layer if it is hidden, or hides it if it is shown.
+    ToggleLayer(String),
+    /// Gives an action to the layers, such as `stopwatch.toggle`.
+    LayerAction(String),
+    /// Saves the current frame as an image.
+    Screenshot(PathBuf),
+    /// Runs the clock from `time` (or the current time, if `None`) at `speed` times real time.
+    SetSimulatedTime {
+        time: Option<DateTime<Utc>>,
+        speed: f64,
+    },
+    /// Freezes the displayed time, or runs it again at the speed it was frozen at.
+    PlayPause,
+    /// Runs the displayed time at a multiple of real time from where it is, or sets the speed to
+    /// resume at while it is frozen.
+    SetSpeed(f64),
+    /// Moves the displayed time to an instant, keeping its speed.
+    JumpTo(DateTime<Utc>),
+    /// Switches to the named color theme.
+    SetTheme(String),
+    /// Hides the windows if they are shown, or shows them if they are hidden.
+    ToggleVisible,
+    /// Shows the windows and brings the main window to the front.
+    Show,
+    /// Returns the instant being displayed, which differs from the current time when simulating.
+    CurrentTime,
+    Quit,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Done,
+    LayerVisible(bool),
+    Time(DateTime<Utc>),
+}
+
+/// A command delivered to the event loop, along with a way to report its outcome.
+#[derive(Debug)]
+pub struct Request {
+    pub command: Command,
+    reply: async_channel::Sender<Result<Response, String>>,
+}
+
+impl Request {
+    pub fn respond(self, result: Result<Response, String>) {
+        // The sender may have given up waiting, which is fine.
+        let _ = self.reply.try_send(result);
+    }
+}
+
+/// Sends commands into the event loop from other threads.
+pub struct Controller {
+    proxy: Mutex<EventLoopProxy<Request>>,
+}
+
+impl Controller {
+    pub fn new(proxy: EventLoopProxy<Request>) -> Self {
+        Self {
+            proxy: Mutex::new(proxy),
+        }
+    }
+
+    fn dispatch(
+        &self,
+        command: Command,
+    ) -> Result<async_channel::Receiver<Result<Response, String>>, String> {
+        let (reply, receiver) = async_channel::bounded(1);
+        self.proxy
+            .lock()
+            .unwrap()
+            .send_event(Request { command, reply })
+            .map_err(|_| "the app is shutting down".to_owned())?;
+        Ok(receiver)
+    }
+
+    pub async fn send(&self, command: Command) -> Result<Response, String> {
+        self.dispatch(command)?
+            .recv()
+            .await
+            .unwrap_or_else(|_| Err("the app is shutting down".into()))
+    }
+
+    /// Sends a command without waiting for its outcome, e.g. from a thread that the event loop
+    /// may be waiting on.
+    #[cfg(feature = "tray")]
+    pub fn post(&self, command: Command) {
+        if let Err(err) = self.dispatch(command) {
+            log::warn!("{}", err);
+        }
+    }
+
+    pub fn send_blocking(&self, command: Command) -> Result<Response, String> {
+        self.dispatch(command)?
+            .recv_blocking()
+            .unwrap_or_else(|_| Err("the app is shutting down".into()))
+    }
+}
@@ -0,0 +1,109 @@
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::Corner;
+use crate::text_overlay::Readout;
+use crate::viewport::Viewport;
+use chrono::Datelike;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Settings of the date strip, from the config's `[layer.date_strip]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DateStripSettings {
+    /// Corner of the target that the strip is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+    /// Show the ISO 8601 week number.
+    pub week_number: bool,
+    /// Show the day of the year, counting January 1 as day 1.
+    pub day_of_year: bool,
+}
+
+impl Default for DateStripSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::TopLeft,
+            size: 18.0,
+            week_number: true,
+            day_of_year: true,
+        }
+    }
+}
+
+/// Today's date in the displayed timezone, e.g. `Saturday, 21 June 2025 · Week 25 · Day 172`, in
+/// a corner of the target.
+pub struct DateStrip {
+    readout: Readout,
+    settings: DateStripSettings,
+    locale: Locale,
+    line: String,
+}
+
+impl DateStrip {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: DateStripSettings = cx.settings("date_strip")?;
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            locale: cx.locale.clone(),
+            line: String::new(),
+        })
+    }
+}
+
+impl Layer for DateStrip {
+    fn name(&self) -> &str {
+        "date_strip"
+    }
+
+    /// Every minute, as midnight in the displayed timezone is on a whole minute in UTC, but not
+    /// always on a whole hour.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        let date = frame.local_date;
+        let mut parts = vec![self.locale.format_long_date(&date)];
+        if self.settings.week_number {
+            let week = format!("Week {}", date.iso_week().week());
+            parts.push(self.locale.localize_digits(&week));
+        }
+        if self.settings.day_of_year {
+            let day = format!("Day {}", date.ordinal());
+            parts.push(self.locale.localize_digits(&day));
+        }
+        self.line = parts.join(" · ");
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        let style = self.readout.style();
+        self.readout
+            .queue_in_corner(&self.line, self.settings.corner, &style);
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
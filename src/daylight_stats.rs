@@ -0,0 +1,243 @@
+use crate::astronomy;
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::Corner;
+use crate::text_overlay::Readout;
+use crate::viewport::Viewport;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use glam::Vec3;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// People per cell of a coarse grid over the Earth.
+const POPULATION_GRID: &str = "data/population.csv";
+
+/// Settings of the daylight statistics, from the config's `[layer.daylight_stats]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaylightStatsSettings {
+    /// Corner of the target that the readout is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+    /// Also show the share of the world's population in daylight.
+    pub population: bool,
+}
+
+impl Default for DaylightStatsSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::BottomLeft,
+            size: 18.0,
+            population: true,
+        }
+    }
+}
+
+/// A cell of the population grid.
+struct Cell {
+    /// Unit vector from the Earth's center to the cell, with the north pole at +Z and longitude
+    /// 0 at +X.
+    direction: Vec3,
+    people: f32,
+}
+
+/// How much of the Earth's surface, and of its population, is in daylight, e.g.
+/// `Daylight: 50.7% of the surface · 46.2% of people`, in a corner of the target.
+///
+/// The terminator is a circle around the point where the Sun is overhead, so the share of the
+/// surface in daylight stays the same all year: slightly more than half, as the Sun is still up
+/// for a while after its center has set. The share of people changes as the terminator sweeps
+/// over the continents.
+pub struct DaylightStats {
+    readout: Readout,
+    settings: DaylightStatsSettings,
+    /// Empty if the population is not shown.
+    population: Vec<Cell>,
+    locale: Locale,
+    line: String,
+}
+
+impl DaylightStats {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: DaylightStatsSettings = cx.settings("daylight_stats")?;
+        let population = if settings.population {
+            let grid = cx.assets.read_to_string(POPULATION_GRID)?;
+            parse_grid(&grid).with_context(|| format!("invalid {}", POPULATION_GRID))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            population,
+            locale: cx.locale.clone(),
+            line: String::new(),
+        })
+    }
+}
+
+impl Layer for DaylightStats {
+    fn name(&self) -> &str {
+        "daylight_stats"
+    }
+
+    /// The terminator only moves a quarter of a degree per minute.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        let (surface, people) = daylight_shares(&frame.time, &self.population);
+        self.line = format!("Daylight: {:.1}% of the surface", surface * 100.0);
+        if let Some(people) = people {
+            self.line += &format!(" · {:.1}% of people", people * 100.0);
+        }
+        self.line = self.locale.localize_digits(&self.line);
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        let style = self.readout.style();
+        self.readout
+            .queue_in_corner(&self.line, self.settings.corner, &style);
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
+
+/// The shares of the Earth's surface and of the people in `population` that are in daylight at
+/// `time`, the latter `None` if `population` is empty.
+fn daylight_shares(time: &DateTime<Utc>, population: &[Cell]) -> (f32, Option<f32>) {
+    let (latitude, longitude) = astronomy::subsolar_point(time);
+    let sun = direction(
+        (latitude as f32).to_radians(),
+        (longitude as f32).to_radians(),
+    );
+    // Points see the Sun above the horizon where the cosine of their angle from it is above
+    // this, which is slightly negative as it sets a little below the horizon.
+    let threshold = (astronomy::SUNRISE_ALTITUDE as f32).to_radians().sin();
+    // The area of a spherical cap is proportional to its height.
+    let surface = (1.0 - threshold) / 2.0;
+    if population.is_empty() {
+        return (surface, None);
+    }
+    let (lit, total) = population.iter().fold((0.0, 0.0), |(lit, total), cell| {
+        let in_daylight = cell.direction.dot(sun) > threshold;
+        (
+            lit + if in_daylight { cell.people } else { 0.0 },
+            total + cell.people,
+        )
+    });
+    (surface, Some(lit / total))
+}
+
+fn direction(latitude: f32, longitude: f32) -> Vec3 {
+    Vec3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    )
+}
+
+/// Parses lines of `latitude,longitude,millions`, after a header line. Lines starting with `#`
+/// are comments.
+fn parse_grid(text: &str) -> anyhow::Result<Vec<Cell>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .skip(1)
+        .map(|(index, line)| {
+            let fields: Vec<f32> = line
+                .split(',')
+                .map(|field| field.trim().parse())
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("line {}", index + 1))?;
+            let [latitude, longitude, millions] = fields[..] else {
+                return Err(anyhow!("line {}: expected 3 fields", index + 1));
+            };
+            Ok(Cell {
+                direction: direction(latitude.to_radians(), longitude.to_radians()),
+                people: millions,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// A cell of one person at `degrees` from the point where the Sun is overhead at `time`,
+    /// along its meridian.
+    fn cell_from_sun(time: &DateTime<Utc>, degrees: f64) -> Cell {
+        let (latitude, longitude) = astronomy::subsolar_point(time);
+        Cell {
+            direction: direction(
+                ((latitude + degrees) as f32).to_radians(),
+                (longitude as f32).to_radians(),
+            ),
+            people: 1.0,
+        }
+    }
+
+    fn times() -> Vec<DateTime<Utc>> {
+        [(3, 20, 0), (6, 21, 9), (9, 23, 15), (12, 21, 21)]
+            .iter()
+            .map(|&(month, day, hour)| Utc.with_ymd_and_hms(2024, month, day, hour, 30, 0).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn slightly_more_than_half_the_surface_is_lit_on_any_date() {
+        for time in times() {
+            let (surface, people) = daylight_shares(&time, &[]);
+            assert!((surface - 0.507).abs() < 0.0005, "{}: {}", time, surface);
+            assert_eq!(people, None);
+        }
+    }
+
+    #[test]
+    fn the_subsolar_point_is_lit_and_its_antipode_is_not() {
+        for time in times() {
+            let overhead = cell_from_sun(&time, 0.0);
+            assert_eq!(daylight_shares(&time, &[overhead]).1, Some(1.0), "{}", time);
+            let opposite = cell_from_sun(&time, 180.0);
+            assert_eq!(daylight_shares(&time, &[opposite]).1, Some(0.0), "{}", time);
+        }
+    }
+
+    #[test]
+    fn people_just_after_sunset_of_the_center_are_lit_like_the_surface() {
+        for time in times() {
+            // The center of the Sun is half a degree below the horizon, which is less than the
+            // cutoff that the surface share uses.
+            let dusk = cell_from_sun(&time, 90.5);
+            assert_eq!(daylight_shares(&time, &[dusk]).1, Some(1.0), "{}", time);
+            let night = cell_from_sun(&time, 91.0);
+            assert_eq!(daylight_shares(&time, &[night]).1, Some(0.0), "{}", time);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::control::{Command, Controller, Response};
+use zbus::fdo;
+
+pub const BUS_NAME: &str = "io.github.agausmann.GlobalClock";
+pub const OBJECT_PATH: &str = "/io/github/agausmann/GlobalClock";
+
+struct Service {
+    controller: Controller,
+}
+
+impl Service {
+    async fn send(&self, command: Command) -> fdo::Result<Response> {
+        self.controller
+            .send(command)
+            .await
+            .map_err(fdo::Error::Failed)
+    }
+}
+
+#[zbus::interface(name = "io.github.agausmann.GlobalClock1")]
+impl Service {
+    /// Changes the timezone shown on the clock face to the given IANA name.
+    async fn set_timezone(&self, name: String) -> fdo::Result<()> {
+        self.send(Command::SetTimezone(name)).await.map(drop)
+    }
+
+    /// Shows or hides a layer by name, e.g. `globe`, returning whether it is now visible.
+    async fn toggle_layer(&self, name: String) -> fdo::Result<bool> {
+        match self.send(Command::ToggleLayer(name)).await? {
+            Response::LayerVisible(visible) => Ok(visible),
+            response => Err(fdo::Error::Failed(format!(
+                "unexpected response {:?}",
+                response
+            ))),
+        }
+    }
+
+    /// Gives an action to the layers, such as `stopwatch.toggle`.
+    async fn layer_action(&self, action: String) -> fdo::Result<()> {
+        self.send(Command::LayerAction(action)).await.map(drop)
+    }
+
+    /// Switches to the named color theme, e.g. `midnight`.
+    async fn set_theme(&self, name: String) -> fdo::Result<()> {
+        self.send(Command::SetTheme(name)).await.map(drop)
+    }
+
+    /// Saves the current frame to the given path; the format is chosen by its extension.
+    async fn screenshot(&self, path: String) -> fdo::Result<()> {
+        self.send(Command::Screenshot(path.into())).await.map(drop)
+    }
+
+    /// Runs the clock from an RFC 3339 instant at `speed` times real time. An empty `time` starts
+    /// from the current time; an empty `time` with a speed of 1 returns to real time.
+    async fn set_simulated_time(&self, time: String, speed: f64) -> fdo::Result<()> {
+        let time = if time.is_empty() {
+            None
+        } else {
+            Some(
+                chrono::DateTime::parse_from_rfc3339(&time)
+                    .map_err(|err| fdo::Error::InvalidArgs(format!("invalid time: {}", err)))?
+                    .into(),
+            )
+        };
+        if !speed.is_finite() {
+            return Err(fdo::Error::InvalidArgs("speed must be finite".into()));
+        }
+        self.send(Command::SetSimulatedTime { time, speed })
+            .await
+            .map(drop)
+    }
+
+    /// Freezes the displayed time, or runs it again at the speed it was frozen at.
+    async fn play_pause(&self) -> fdo::Result<()> {
+        self.send(Command::PlayPause).await.map(drop)
+    }
+
+    /// Runs the displayed time at `speed` times real time from where it is; negative runs it
+    /// backwards.
+    async fn set_speed(&self, speed: f64) -> fdo::Result<()> {
+        if !speed.is_finite() {
+            return Err(fdo::Error::InvalidArgs("speed must be finite".into()));
+        }
+        self.send(Command::SetSpeed(speed)).await.map(drop)
+    }
+
+    /// Moves the displayed time to an RFC 3339 instant, keeping its speed.
+    async fn jump_to(&self, time: String) -> fdo::Result<()> {
+        let time = chrono::DateTime::parse_from_rfc3339(&time)
+            .map_err(|err| fdo::Error::InvalidArgs(format!("invalid time: {}", err)))?;
+        self.send(Command::JumpTo(time.into())).await.map(drop)
+    }
+}
+
+/// Registers the control interface on the session bus. The service runs until the returned
+/// connection is dropped.
+pub fn start(controller: Controller) -> zbus::Result<zbus::blocking::Connection> {
+    zbus::blocking::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, Service { controller })?
+        .build()
+}
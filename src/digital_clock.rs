@@ -0,0 +1,164 @@
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::{Corner, TextStyle};
+use crate::text_overlay::Readout;
+use crate::viewport::Viewport;
+use chrono::Timelike;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Size of the timezone label relative to the digits.
+const LABEL_SCALE: f32 = 0.35;
+
+/// Settings of the digital clock, from the config's `[layer.digital_clock]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigitalClockSettings {
+    /// Corner of the target that the readout is placed in.
+    pub corner: Corner,
+    /// Height of the digits, in logical pixels.
+    pub size: f32,
+    /// Show the seconds as well as the hours and minutes.
+    pub seconds: bool,
+    /// Hide the colons every other second.
+    pub blink: bool,
+    /// Show the name of the timezone below the time.
+    pub timezone_label: bool,
+}
+
+impl Default for DigitalClockSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::TopRight,
+            size: 48.0,
+            seconds: true,
+            blink: false,
+            timezone_label: true,
+        }
+    }
+}
+
+/// The displayed time as digits, e.g. `18:30:00`, in a corner of the target, with the theme's
+/// text colors.
+pub struct DigitalClock {
+    readout: Readout,
+    settings: DigitalClockSettings,
+    locale: Locale,
+    time: String,
+    label: String,
+}
+
+impl DigitalClock {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: DigitalClockSettings = cx.settings("digital_clock")?;
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            locale: cx.locale.clone(),
+            time: String::new(),
+            label: String::new(),
+        })
+    }
+
+    /// The style of the time, and of the label when scaled by [`LABEL_SCALE`].
+    fn style(&self, scale: f32) -> TextStyle {
+        let size = self.readout.style().size * scale;
+        TextStyle {
+            size,
+            anchor: [self.readout.corner(self.settings.corner).anchor()[0], 0.0],
+            padding: size * 0.15,
+            ..self.readout.style()
+        }
+    }
+}
+
+impl Layer for DigitalClock {
+    fn name(&self) -> &str {
+        "digital_clock"
+    }
+
+    fn tick_interval(&self) -> Duration {
+        if self.settings.seconds || self.settings.blink {
+            Duration::from_secs(1)
+        } else {
+            Duration::from_secs(60)
+        }
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        self.time = self
+            .locale
+            .format_time(&frame.local_time, self.settings.seconds);
+        if self.settings.blink && frame.local_time.second() % 2 == 1 {
+            // Spaces take the colons' place, so that the digits stay put in the monospace font.
+            self.time = self.time.replace(':', " ");
+        }
+        self.label = match frame.timezone {
+            Some(name) => name.replace('_', " "),
+            None => self.locale.localize_digits(&utc_offset(frame)),
+        };
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        let time_style = self.style(1.0);
+        let label_style = self.style(LABEL_SCALE);
+        let time_height = self.readout.measure(&self.time, time_style.size)[1];
+        // From the top of the time to the bottom of the label, which goes below it in whichever
+        // corner they are, with their boxes a label's padding apart.
+        let label_top = time_height + time_style.padding + label_style.padding * 2.0;
+        let height = if self.settings.timezone_label {
+            label_top + self.readout.measure(&self.label, label_style.size)[1]
+        } else {
+            time_height
+        };
+
+        let corner = self.readout.corner(self.settings.corner);
+        let [x, y] = self.readout.corner_position(corner, time_style.padding);
+        let top = y - corner.anchor()[1] * height;
+        self.readout.queue(&self.time, [x, top], &time_style);
+        if self.settings.timezone_label {
+            self.readout
+                .queue(&self.label, [x, top + label_top], &label_style);
+        }
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
+
+/// The offset of the displayed time from UTC, e.g. `UTC+5:30`, for when the timezone has no name.
+fn utc_offset(frame: &FrameInfo) -> String {
+    // Wrapped into the range of offsets in use, as only the times of day are known.
+    let mut minutes = (frame.local_time - frame.time.time()).num_minutes();
+    if minutes > 14 * 60 {
+        minutes -= 24 * 60;
+    } else if minutes < -12 * 60 {
+        minutes += 24 * 60;
+    }
+    let sign = if minutes < 0 { '-' } else { '+' };
+    match (minutes.abs() / 60, minutes.abs() % 60) {
+        (0, 0) => "UTC".to_owned(),
+        (hours, 0) => format!("UTC{}{}", sign, hours),
+        (hours, minutes) => format!("UTC{}{}:{:02}", sign, hours, minutes),
+    }
+}
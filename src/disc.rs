@@ -0,0 +1,119 @@
+use crate::assets::Assets;
+use crate::theme::Theme;
+use crate::viewport::Viewport;
+use crate::GraphicsContext;
+use anyhow::Context;
+use wgpu::util::DeviceExt;
+
+/// A circle filling the -1..1 square of the scene in the theme's background color, for windows
+/// that show only the clock face and leave their corners transparent.
+pub struct Disc {
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    // Kept alive for the bind group.
+    _uniform_buffer: wgpu::Buffer,
+}
+
+impl Disc {
+    pub fn new(
+        gfx: &GraphicsContext,
+        viewport: &Viewport,
+        assets: &Assets,
+        theme: &Theme,
+    ) -> anyhow::Result<Self> {
+        let color = theme.background.to_wgpu();
+        let premultiplied = [
+            (color.r * color.a) as f32,
+            (color.g * color.a) as f32,
+            (color.b * color.a) as f32,
+            color.a as f32,
+        ];
+        let uniform_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Disc.uniform_buffer"),
+                contents: bytemuck::cast_slice(&premultiplied),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group_layout =
+            gfx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Disc.bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Disc.bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Disc.pipeline_layout"),
+                bind_group_layouts: &[&bind_group_layout, viewport.bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+        let shader_source = assets.read_to_string("shaders/disc.wgsl")?;
+        let render_pipeline = gfx
+            .validate(|| {
+                let shader_module = gfx
+                    .device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Disc.shader_module"),
+                        source: wgpu::ShaderSource::Wgsl(shader_source),
+                    });
+                gfx.device
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Disc.render_pipeline"),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader_module,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                        },
+                        primitive: wgpu::PrimitiveState {
+                            topology: wgpu::PrimitiveTopology::TriangleStrip,
+                            ..Default::default()
+                        },
+                        depth_stencil: None,
+                        multisample: Default::default(),
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader_module,
+                            entry_point: "fs_main",
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format: gfx.render_format,
+                                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
+                        }),
+                        multiview: None,
+                    })
+            })
+            .context("invalid disc shader")?;
+
+        Ok(Self {
+            render_pipeline,
+            bind_group,
+            _uniform_buffer: uniform_buffer,
+        })
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, viewport.bind_group(), &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
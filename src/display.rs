@@ -0,0 +1,355 @@
+use crate::config::PresentMode;
+use anyhow::Context;
+use global_clock::{GraphicsContext, GraphicsContextInner};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use winit::window::Window;
+
+/// The graphics device, which is shared by every window.
+pub struct Display {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    /// Set when the graphics device stops working, e.g. because the GPU was reset or its driver
+    /// was updated.
+    lost: Arc<AtomicBool>,
+    /// Whether a software renderer was requested because no other adapter worked.
+    force_fallback: bool,
+}
+
+/// A window, and the surface that its frames are presented to.
+pub struct WindowSurface {
+    // Declared before the window, so that it is dropped first.
+    /// `None` while the app is suspended on platforms that destroy the native window meanwhile.
+    pub surface: Option<wgpu::Surface>,
+    pub surface_caps: wgpu::SurfaceCapabilities,
+    pub window: Window,
+    /// Whether the window shows what is behind it where its frames are transparent.
+    pub transparent: bool,
+    present_mode: PresentMode,
+}
+
+impl Display {
+    /// Opens a graphics device that can draw to `window`.
+    ///
+    /// The primary backends (Vulkan, Metal and DirectX 12) are tried first, then every backend,
+    /// and finally a software renderer such as lavapipe or WARP, which is slow but works in VMs
+    /// without GPU acceleration.
+    pub async fn new(window: Window) -> anyhow::Result<(Self, WindowSurface, GraphicsContext)> {
+        let attempts = [
+            (wgpu::Backends::PRIMARY, false),
+            (wgpu::Backends::all(), false),
+            (wgpu::Backends::all(), true),
+        ];
+        let mut last_error = None;
+        for (backends, force_fallback) in attempts {
+            let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends,
+                ..Default::default()
+            });
+            let surface = unsafe { instance.create_surface(&window) }
+                .context("failed to create a surface for the window")?;
+            let lost = Arc::new(AtomicBool::new(false));
+            let (adapter, gfx) = match open_device(&instance, &surface, &lost, force_fallback).await
+            {
+                Ok(opened) => opened,
+                Err(err) => {
+                    log::info!("{:#} (backends: {:?})", err, backends);
+                    last_error = Some(err);
+                    continue;
+                }
+            };
+            let surface_caps = surface.get_capabilities(&adapter);
+            let display = Self {
+                instance,
+                adapter,
+                lost,
+                force_fallback,
+            };
+            if display.is_software() {
+                log::warn!(
+                    "using the software renderer {:?}; drawing will be slow",
+                    display.adapter.get_info().name
+                );
+            }
+            let surface = WindowSurface {
+                surface: Some(surface),
+                surface_caps,
+                window,
+                transparent: false,
+                present_mode: PresentMode::Fifo,
+            };
+            return Ok((display, surface, gfx));
+        }
+        Err(last_error.unwrap())
+    }
+
+    /// The name, backend and kind of the graphics adapter drawn with.
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    /// Whether drawing is done by the CPU rather than a GPU.
+    pub fn is_software(&self) -> bool {
+        self.force_fallback || self.adapter.get_info().device_type == wgpu::DeviceType::Cpu
+    }
+
+    /// Creates a surface for another window, drawn to with the same device.
+    pub fn create_surface(&self, window: Window) -> anyhow::Result<WindowSurface> {
+        let surface = unsafe { self.instance.create_surface(&window) }
+            .context("failed to create a surface for the window")?;
+        if !self.adapter.is_surface_supported(&surface) {
+            anyhow::bail!("the graphics adapter cannot draw to this window");
+        }
+        Ok(WindowSurface {
+            surface_caps: surface.get_capabilities(&self.adapter),
+            surface: Some(surface),
+            window,
+            transparent: false,
+            present_mode: PresentMode::Fifo,
+        })
+    }
+
+    /// Replaces a window's surface, e.g. after the platform destroyed it while the app was
+    /// suspended.
+    pub fn recreate_surface(&self, surface: &mut WindowSurface) -> anyhow::Result<()> {
+        // Drop the old surface first, as some platforms allow only one per window.
+        surface.surface = None;
+        let new_surface = unsafe { self.instance.create_surface(&surface.window) }
+            .context("failed to create a surface for the window")?;
+        surface.surface_caps = new_surface.get_capabilities(&self.adapter);
+        surface.surface = Some(new_surface);
+        Ok(())
+    }
+
+    /// Queries what a window's surface supports again, as it can change when the window moves to
+    /// another monitor. Returns whether the supported formats or alpha modes changed.
+    pub fn refresh_capabilities(&self, surface: &mut WindowSurface) -> bool {
+        let Some(current) = &surface.surface else {
+            return false;
+        };
+        let caps = current.get_capabilities(&self.adapter);
+        let changed = caps.formats != surface.surface_caps.formats
+            || caps.alpha_modes != surface.surface_caps.alpha_modes;
+        surface.surface_caps = caps;
+        changed
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    pub fn mark_lost(&self) {
+        self.lost.store(true, Ordering::Relaxed);
+    }
+
+    /// Opens a new graphics device to replace one that was lost. Everything created with the old
+    /// device has to be recreated with the new one.
+    pub async fn reopen_device(
+        &mut self,
+        surfaces: &mut [&mut WindowSurface],
+    ) -> anyhow::Result<GraphicsContext> {
+        let compatible = surfaces
+            .iter()
+            .find_map(|surface| surface.surface.as_ref())
+            .context("there are no windows to draw to")?;
+        let lost = Arc::new(AtomicBool::new(false));
+        let (adapter, gfx) =
+            open_device(&self.instance, compatible, &lost, self.force_fallback).await?;
+        for surface in surfaces {
+            if let Some(current) = &surface.surface {
+                surface.surface_caps = current.get_capabilities(&adapter);
+            }
+        }
+        self.adapter = adapter;
+        self.lost = lost;
+        Ok(gfx)
+    }
+}
+
+impl WindowSurface {
+    /// The format that this window's frames are best drawn in, e.g. one that suits an HDR monitor
+    /// or an SDR one.
+    pub fn preferred_format(&self) -> Option<wgpu::TextureFormat> {
+        preferred_format(&self.surface_caps)
+    }
+
+    /// Frames are drawn with premultiplied alpha, which transparent windows need the surface to
+    /// respect. Where it cannot, they are opaque instead.
+    fn alpha_mode(&self) -> wgpu::CompositeAlphaMode {
+        let modes = &self.surface_caps.alpha_modes;
+        if self.transparent {
+            for mode in [
+                wgpu::CompositeAlphaMode::PreMultiplied,
+                wgpu::CompositeAlphaMode::Inherit,
+            ] {
+                if modes.contains(&mode) {
+                    return mode;
+                }
+            }
+        }
+        modes[0]
+    }
+
+    /// Presents frames with `mode` from the next [`Self::configure`], or with `Fifo` while the
+    /// surface does not support it.
+    pub fn set_present_mode(&mut self, mode: PresentMode) {
+        self.present_mode = mode;
+        if self.wgpu_present_mode() != to_wgpu(mode) {
+            log::warn!(
+                "the {:?} present mode is not supported here; using Fifo instead",
+                mode
+            );
+        }
+    }
+
+    fn wgpu_present_mode(&self) -> wgpu::PresentMode {
+        let mode = to_wgpu(self.present_mode);
+        if self.surface_caps.present_modes.contains(&mode) {
+            mode
+        } else {
+            wgpu::PresentMode::Fifo
+        }
+    }
+
+    /// Whether the surface can show what is behind a transparent window, which depends on the
+    /// platform and graphics backend.
+    pub fn supports_transparency(&self) -> bool {
+        self.surface_caps
+            .alpha_modes
+            .iter()
+            .any(|mode| *mode != wgpu::CompositeAlphaMode::Opaque)
+    }
+
+    /// Drops the surface, which must not outlive the native window on platforms that destroy it
+    /// while the app is suspended, such as Android. [`Display::recreate_surface`] replaces it.
+    pub fn release(&mut self) {
+        self.surface = None;
+    }
+
+    pub fn configure(&self, gfx: &GraphicsContext) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        let size = self.window.inner_size();
+        surface.configure(
+            &gfx.device,
+            &wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: gfx.render_format,
+                width: size.width,
+                height: size.height,
+                present_mode: self.wgpu_present_mode(),
+                alpha_mode: self.alpha_mode(),
+                view_formats: vec![],
+            },
+        );
+    }
+}
+
+fn to_wgpu(mode: PresentMode) -> wgpu::PresentMode {
+    match mode {
+        PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+        PresentMode::Immediate => wgpu::PresentMode::Immediate,
+    }
+}
+
+/// Opens a graphics device for rendering offscreen, without a window.
+pub async fn open_headless() -> anyhow::Result<GraphicsContext> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    let adapter = match instance.request_adapter(&Default::default()).await {
+        Some(adapter) => Some(adapter),
+        None => {
+            instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    force_fallback_adapter: true,
+                    ..Default::default()
+                })
+                .await
+        }
+    };
+    let adapter = adapter.context(
+        "no graphics adapter was found; make sure that graphics drivers supporting Vulkan, \
+             Metal, DirectX 12 or OpenGL are installed, or a software renderer such as lavapipe",
+    )?;
+    let (device, queue) = adapter
+        .request_device(&Default::default(), None)
+        .await
+        .context("failed to open the graphics device")?;
+    Ok(Arc::new(GraphicsContextInner {
+        device,
+        queue,
+        render_format: wgpu::TextureFormat::Rgba8UnormSrgb,
+    }))
+}
+
+/// Opens a graphics device that can draw to `surface`. `lost` is set if the device stops working.
+pub async fn open_device(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface,
+    lost: &Arc<AtomicBool>,
+    force_fallback: bool,
+) -> anyhow::Result<(wgpu::Adapter, GraphicsContext)> {
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: force_fallback,
+            compatible_surface: Some(surface),
+        })
+        .await
+        .context(
+            "no compatible graphics adapter was found; make sure that graphics drivers \
+             supporting Vulkan, Metal, DirectX 12 or OpenGL are installed, or a software \
+             renderer such as lavapipe",
+        )?;
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                // Used by the frame statistics and for compressed textures, if available.
+                features: adapter.features()
+                    & (wgpu::Features::TIMESTAMP_QUERY
+                        | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES
+                        | wgpu::Features::TEXTURE_COMPRESSION_BC),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .context("failed to open the graphics device")?;
+
+    // Errors that are not caught by an error scope end up here; by default they panic.
+    let handler_lost = lost.clone();
+    device.on_uncaptured_error(Box::new(move |err| match err {
+        wgpu::Error::OutOfMemory { .. } => handler_lost.store(true, Ordering::Relaxed),
+        wgpu::Error::Validation { description, .. }
+            if description.contains("device is lost")
+                || description.contains("device is invalid") =>
+        {
+            handler_lost.store(true, Ordering::Relaxed)
+        }
+        err => log::error!("{}", err),
+    }));
+
+    let render_format = preferred_format(&surface.get_capabilities(&adapter))
+        .context("the graphics adapter cannot draw to this window")?;
+
+    let gfx = Arc::new(GraphicsContextInner {
+        device,
+        queue,
+        render_format,
+    });
+    Ok((adapter, gfx))
+}
+
+fn preferred_format(caps: &wgpu::SurfaceCapabilities) -> Option<wgpu::TextureFormat> {
+    caps.formats
+        .iter()
+        .copied()
+        .find(|f| f.is_srgb())
+        .or_else(|| caps.formats.first().copied())
+}
@@ -0,0 +1,40 @@
+use native_dialog::{MessageDialog, MessageType};
+use std::fmt::Write;
+use std::io::IsTerminal;
+
+/// Shows a fatal error, along with its causes, in a dialog box.
+pub fn show(err: &anyhow::Error) {
+    let mut text = err.to_string();
+    for cause in err.chain().skip(1) {
+        write!(text, "\n\nCaused by: {}", cause).unwrap();
+    }
+    alert(&text);
+}
+
+/// Makes panics show a dialog box in addition to the usual message on standard error.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        alert(&format!(
+            "Global Clock crashed unexpectedly. Please report this at \
+             https://github.com/agausmann/global-clock/issues, including the message below.\n\n{}",
+            info
+        ));
+    }));
+}
+
+fn alert(text: &str) {
+    // When started from a terminal, the message printed to standard error is enough.
+    if std::io::stderr().is_terminal() {
+        return;
+    }
+    let result = MessageDialog::new()
+        .set_type(MessageType::Error)
+        .set_title("Global Clock")
+        .set_text(text)
+        .show_alert();
+    if let Err(err) = result {
+        log::error!("failed to show error dialog: {}", err);
+    }
+}
@@ -1,12 +1,22 @@
+use crate::assets::{Assets, Pending};
+use crate::layer::{FrameInfo, Layer};
+use crate::model;
+use crate::staging::StagingPool;
+use crate::texture::{self, TextureData};
+use crate::theme::Theme;
 use crate::viewport::Viewport;
-use crate::{asset_bytes, asset_str, GraphicsContext};
+use crate::GraphicsContext;
 use anyhow::Context;
 use bytemuck::{Pod, Zeroable};
-use chrono::{DateTime, Datelike, Timelike, Utc};
-use glam::{Mat4, Vec3};
+use chrono::{DateTime, Datelike, Utc};
+use glam::{Mat4, Vec2, Vec3};
+use image::RgbaImage;
 use once_cell::sync::Lazy;
 use std::convert::TryInto;
-use std::f32::consts::TAU;
+use std::f32::consts::{PI, TAU};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use wgpu::util::DeviceExt;
 
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -54,7 +64,19 @@ const VERTICES: [Vertex; 4] = [
 
 const INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
 
-#[derive(Clone, Copy, Pod, Zeroable)]
+/// Radius of the globe in the scene's units.
+const SCALE: f32 = 0.8;
+
+/// Latitudes at the center and the edge of the globe, in radians. It is seen from below the south
+/// pole.
+const MIN_LATITUDE: f32 = -TAU / 4.0;
+const MAX_LATITUDE: f32 = TAU / 4.0;
+
+/// Fraction of the radius and latitude where the radius stops growing with latitude at one rate and
+/// goes on at another, to give the continents more room than the southern ocean.
+const DEFLECTION_POINT: [f32; 2] = [0.55, 0.65];
+
+#[derive(Clone, Copy, PartialEq, Pod, Zeroable)]
 #[repr(C)]
 struct Uniforms {
     local_transform: [[f32; 4]; 4],
@@ -64,35 +86,61 @@ struct Uniforms {
     max_latitude: f32,
     deflection_point: [f32; 2],
     _padding: [u8; 8],
+    terminator_tint: [f32; 4],
 }
 
 impl Default for Uniforms {
     fn default() -> Self {
         Self {
-            local_transform: Mat4::from_scale(Vec3::splat(0.8)).to_cols_array_2d(),
+            local_transform: Mat4::from_scale(Vec3::splat(SCALE)).to_cols_array_2d(),
             rotation: 0.0,
             axial_tilt: 0.0,
-            min_latitude: -TAU / 4.0,
-            max_latitude: TAU / 4.0,
-            deflection_point: [0.55, 0.65],
+            min_latitude: MIN_LATITUDE,
+            max_latitude: MAX_LATITUDE,
+            deflection_point: DEFLECTION_POINT,
             _padding: [0; 8],
+            terminator_tint: [0.0; 4],
         }
     }
 }
 
 pub struct Globe {
     gfx: GraphicsContext,
+    assets: Assets,
+    pipeline_layout: wgpu::PipelineLayout,
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
     bind_group: wgpu::BindGroup,
+    /// The day and night textures, which are placeholders until their images are decoded.
+    texture_views: [wgpu::TextureView; 2],
+    /// Sizes of the textures in `texture_views`.
+    texture_bytes: [u64; 2],
+    pending: [Option<Pending<TextureData>>; 2],
 
     uniforms: Uniforms,
+    /// What the uniform buffer holds, or `None` before it is first written.
+    written_uniforms: Option<Uniforms>,
 }
 
+const TEXTURE_NAMES: [&str; 2] = ["globe_day.jpg", "globe_night.jpg"];
+const TEXTURE_LABELS: [&str; 2] = ["Globe.day_texture", "Globe.night_texture"];
+
+/// Colors shown until the day and night textures are loaded: plain ocean.
+const PLACEHOLDER_COLORS: [[u8; 4]; 2] = [[24, 58, 104, 255], [4, 10, 20, 255]];
+
 impl Globe {
-    pub fn new(gfx: &GraphicsContext, viewport: &Viewport) -> anyhow::Result<Self> {
+    pub fn new(
+        gfx: &GraphicsContext,
+        viewport: &Viewport,
+        assets: &Assets,
+        texture_dir: Option<&Path>,
+        theme: &Theme,
+        texture_budget: Option<u64>,
+    ) -> anyhow::Result<Self> {
         let bind_group_layout =
             gfx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -144,45 +192,7 @@ impl Globe {
                 push_constant_ranges: &[],
             });
 
-        let shader_module = gfx
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("Globe.shader_module"),
-                source: wgpu::ShaderSource::Wgsl(asset_str!("shaders/globe.wgsl")),
-            });
-
-        let render_pipeline = gfx
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Globe.render_pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader_module,
-                    entry_point: "vs_main",
-                    buffers: &[Vertex::buffer_layout()],
-                },
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Cw,
-                    cull_mode: None,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                    unclipped_depth: false,
-                },
-                depth_stencil: None,
-                multisample: Default::default(),
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader_module,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: gfx.render_format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-            });
+        let render_pipeline = Self::create_render_pipeline(gfx, assets, &pipeline_layout)?;
 
         let vertex_buffer = gfx
             .device
@@ -214,136 +224,268 @@ impl Globe {
             ..Default::default()
         });
 
-        fn load_texture(
-            gfx: &GraphicsContext,
-            image_source: &[u8],
-            label: &str,
-        ) -> anyhow::Result<wgpu::Texture> {
-            let image = image::load_from_memory(image_source)
-                .context("failed to parse texture")?
-                .into_rgba8();
-            let size = wgpu::Extent3d {
-                width: image.width(),
-                height: image.height(),
-                ..Default::default()
-            };
-            let texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
-                label: Some(label),
-                size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
-            gfx.queue.write_texture(
-                wgpu::ImageCopyTexture {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                &image,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(size.width * 4),
-                    rows_per_image: Some(size.height),
-                },
-                size,
-            );
-            Ok(texture)
-        }
-
-        let day_texture = load_texture(
-            gfx,
-            &*asset_bytes!("textures/globe_day.jpg"),
-            "Globe.day_texture",
-        )?;
-        let day_texture_view = day_texture.create_view(&Default::default());
-        let night_texture = load_texture(
-            gfx,
-            &*asset_bytes!("textures/globe_night.jpg"),
-            "Globe.night_texture",
-        )?;
-        let night_texture_view = night_texture.create_view(&Default::default());
-
-        let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Globe.bind_group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniform_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&day_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::TextureView(&night_texture_view),
-                },
-            ],
+        // Decoding the textures takes a while, so the globe is drawn with placeholders meanwhile.
+        let cache_dir = assets
+            .cache_dir()
+            .filter(|_| texture::supports_bc7(gfx))
+            .map(Path::to_owned);
+        // The budget is shared between the day and night textures.
+        let max_bytes = texture_budget.map(|budget| budget / 2);
+        let load = |name: &'static str| -> anyhow::Result<Pending<TextureData>> {
+            match (texture_dir, &cache_dir) {
+                (Some(dir), cache_dir) => {
+                    let path = dir.join(name);
+                    let bytes = std::fs::read(&path)
+                        .with_context(|| format!("failed to load texture {}", path.display()))?;
+                    let cache_dir = cache_dir.clone();
+                    Ok(Pending::spawn(move || {
+                        let decode = || {
+                            let image = image::load_from_memory(&bytes).with_context(|| {
+                                format!("failed to decode texture {}", path.display())
+                            })?;
+                            Ok(Arc::new(image.into_rgba8()))
+                        };
+                        match cache_dir {
+                            Some(cache_dir) => texture::load_compressed(
+                                &bytes, name, &cache_dir, max_bytes, decode,
+                            ),
+                            None => texture::load_rgba(name, max_bytes, decode),
+                        }
+                    }))
+                }
+                (None, Some(cache_dir)) => {
+                    let relative = format!("textures/{}", name);
+                    let bytes = assets.read(&relative)?;
+                    let cache_dir = cache_dir.clone();
+                    let assets = assets.clone();
+                    // Decoded through the assets, so that this waits for `Globe::preload` rather
+                    // than decoding the image again.
+                    Ok(Pending::spawn(move || {
+                        texture::load_compressed(&bytes, name, &cache_dir, max_bytes, || {
+                            assets.image(&relative)
+                        })
+                    }))
+                }
+                (None, None) => {
+                    let image = assets.image_in_background(&format!("textures/{}", name))?;
+                    Ok(Pending::spawn(move || {
+                        texture::load_rgba(name, max_bytes, || image.wait())
+                    }))
+                }
+            }
+        };
+        let pending = [Some(load(TEXTURE_NAMES[0])?), Some(load(TEXTURE_NAMES[1])?)];
+        let texture_views = [0, 1].map(|index| {
+            let placeholder = RgbaImage::from_pixel(1, 1, image::Rgba(PLACEHOLDER_COLORS[index]));
+            TextureData::Rgba(Arc::new(placeholder))
+                .create_texture(gfx, TEXTURE_LABELS[index])
+                .create_view(&Default::default())
         });
+        let bind_group = create_bind_group(
+            gfx,
+            &bind_group_layout,
+            &uniform_buffer,
+            &sampler,
+            &texture_views,
+        );
 
         Ok(Self {
             gfx: gfx.clone(),
+            assets: assets.clone(),
+            pipeline_layout,
             render_pipeline,
             vertex_buffer,
             index_buffer,
             uniform_buffer,
+            bind_group_layout,
+            sampler,
             bind_group,
-            uniforms: Default::default(),
+            texture_views,
+            texture_bytes: [4; 2],
+            pending,
+            uniforms: Uniforms {
+                terminator_tint: theme.terminator_tint.to_linear(),
+                ..Default::default()
+            },
+            written_uniforms: None,
         })
     }
 
+    /// Starts decoding the textures that [`Self::new`] will need, e.g. while the graphics device
+    /// is being opened, where they are not already cached compressed.
+    pub fn preload(assets: &Assets) {
+        for name in TEXTURE_NAMES {
+            let relative = format!("textures/{}", name);
+            let Ok(bytes) = assets.read(&relative) else {
+                // Reported by `Self::new`.
+                continue;
+            };
+            let cached = assets
+                .cache_dir()
+                .is_some_and(|dir| texture::is_cached(&bytes, name, dir));
+            if !cached {
+                // The image is kept in the assets' cache, where `Self::new` finds it.
+                let _ = assets.image_in_background(&relative);
+            }
+        }
+    }
+
+    fn create_render_pipeline(
+        gfx: &GraphicsContext,
+        assets: &Assets,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> anyhow::Result<wgpu::RenderPipeline> {
+        let shader_source = assets.read_to_string("shaders/globe.wgsl")?;
+        gfx.validate(|| {
+            let shader_module = gfx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("Globe.shader_module"),
+                    source: wgpu::ShaderSource::Wgsl(shader_source),
+                });
+
+            gfx.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Globe.render_pipeline"),
+                    layout: Some(pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::buffer_layout()],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Cw,
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        conservative: false,
+                        unclipped_depth: false,
+                    },
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: gfx.render_format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
+        })
+        .context("invalid globe shader")
+    }
+
+    /// Replaces the placeholder textures with the decoded images in `ready`, by index. Returns
+    /// whether any were replaced.
+    fn load_textures(&mut self, ready: Vec<(usize, anyhow::Result<TextureData>)>) -> bool {
+        let mut changed = false;
+        for (index, result) in ready {
+            match result {
+                Ok(data) => {
+                    self.texture_bytes[index] = data.byte_size() as u64;
+                    self.texture_views[index] = data
+                        .create_texture(&self.gfx, TEXTURE_LABELS[index])
+                        .create_view(&Default::default());
+                    changed = true;
+                }
+                Err(err) => log::error!("{:#}", err),
+            }
+        }
+        if changed {
+            self.bind_group = create_bind_group(
+                &self.gfx,
+                &self.bind_group_layout,
+                &self.uniform_buffer,
+                &self.sampler,
+                &self.texture_views,
+            );
+        }
+        changed
+    }
+
     pub fn set_date(&mut self, date: &DateTime<Utc>) {
-        const SECONDS_PER_DAY: f32 = 86400.0;
-        // Offset to compensate for angle 0 being at 6:00 AM UTC
-        const ANGLE_OFFSET: f32 = TAU / 4.0;
+        self.uniforms.rotation = model::globe_rotation(model::seconds_from_midnight(&date.time()));
+        self.uniforms.axial_tilt = model::axial_tilt(date.ordinal0() as f32);
+    }
+}
 
-        self.uniforms.rotation =
-            (date.num_seconds_from_midnight() as f32) / SECONDS_PER_DAY * TAU + ANGLE_OFFSET;
+impl Layer for Globe {
+    fn name(&self) -> &str {
+        "globe"
+    }
 
-        // Don't care about leap years, this is precise enough.
-        const DAYS_PER_YEAR: f32 = 365.0;
-        // Day 0 -> roughly March 20 (I'm too lazy to calculate this more precisely)
-        const EQUINOX_OFFSET: f32 = -78.0;
-        const MAX_AXIAL_TILT: f32 = 23.4 / 360.0 * TAU;
+    fn shaders(&self) -> &[&str] {
+        &["shaders/globe.wgsl"]
+    }
 
-        self.uniforms.axial_tilt = MAX_AXIAL_TILT
-            * ((date.ordinal0() as f32 + EQUINOX_OFFSET) / DAYS_PER_YEAR * TAU).sin();
+    fn reload_shaders(&mut self) -> anyhow::Result<()> {
+        self.render_pipeline =
+            Self::create_render_pipeline(&self.gfx, &self.assets, &self.pipeline_layout)?;
+        Ok(())
     }
 
-    pub fn draw(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        frame_view: &wgpu::TextureView,
-        viewport: &Viewport,
-    ) {
-        // Update uniforms
-        self.gfx
-            .queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniforms));
-
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Globe.render_pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: frame_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
+    /// The terminator only moves a quarter of a degree per minute.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn sweeps(&self) -> bool {
+        true
+    }
 
+    fn update(&mut self, frame: &FrameInfo) {
+        self.set_date(&frame.time);
+    }
+
+    fn poll(&mut self) -> bool {
+        let ready = self
+            .pending
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, pending)| {
+                let result = pending.as_ref()?.try_get()?;
+                *pending = None;
+                Some((index, result))
+            })
+            .collect();
+        self.load_textures(ready)
+    }
+
+    fn finish_loading(&mut self) {
+        let ready = self
+            .pending
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, pending)| Some((index, pending.take()?.wait())))
+            .collect();
+        self.load_textures(ready);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if self.written_uniforms == Some(self.uniforms) {
+            return;
+        }
+        staging.write_buffer(
+            encoder,
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        self.written_uniforms = Some(self.uniforms);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.texture_bytes.iter().sum::<u64>()
+            + self.vertex_buffer.size()
+            + self.index_buffer.size()
+            + self.uniform_buffer.size()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
         render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
@@ -352,3 +494,51 @@ impl Globe {
         render_pass.draw_indexed(0..INDICES.len().try_into().unwrap(), 0, 0..1);
     }
 }
+
+/// Where the point at `latitude` and `longitude`, in radians, is drawn in the scene when the globe
+/// is turned by `rotation`, as from [`model::globe_rotation`]. This is the inverse of the mapping
+/// in the globe's shader, for layers that draw on the globe.
+pub fn project(latitude: f32, longitude: f32, rotation: f32) -> Vec2 {
+    let [deflection_radius, deflection_latitude] = DEFLECTION_POINT;
+    let radius = if latitude < deflection_latitude {
+        (latitude - MIN_LATITUDE) / (deflection_latitude - MIN_LATITUDE) * deflection_radius
+    } else {
+        deflection_radius
+            + (latitude - deflection_latitude) / (MAX_LATITUDE - deflection_latitude)
+                * (1.0 - deflection_radius)
+    };
+    // The texture starts at 180° west.
+    let angle = rotation + longitude + PI;
+    SCALE * radius * Vec2::new(angle.cos(), -angle.sin())
+}
+
+fn create_bind_group(
+    gfx: &GraphicsContext,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    sampler: &wgpu::Sampler,
+    [day_texture_view, night_texture_view]: &[wgpu::TextureView; 2],
+) -> wgpu::BindGroup {
+    gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Globe.bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(day_texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(night_texture_view),
+            },
+        ],
+    })
+}
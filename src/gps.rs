@@ -0,0 +1,251 @@
+//! Reading the time and position from a GPS receiver, through gpsd or as NMEA sentences from a
+//! serial device, e.g. on a boat without a network.
+
+use crate::location::Location;
+use crate::time_source::TimeOffset;
+use anyhow::{bail, Context};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait before reading the receiver again after it failed or went away.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// How many of the last reports the correction of the clock is taken from.
+const TIME_REPORTS: usize = 16;
+/// How long to wait for a report before checking whether to stop.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reads one line of a source's reports.
+type Parser = fn(&str) -> Option<Reading>;
+
+/// Where the receiver's reports come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The address of gpsd, e.g. `localhost:2947`.
+    Gpsd(String),
+    /// A serial device that the receiver writes NMEA sentences to, e.g. `/dev/ttyUSB0`.
+    Device(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Gpsd(address) => write!(f, "gpsd at {}", address),
+            Self::Device(path) => write!(f, "GPS device {}", path.display()),
+        }
+    }
+}
+
+/// What one report of a receiver with a fix says.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub time: Option<DateTime<Utc>>,
+    pub location: Option<Location>,
+}
+
+/// A GPS receiver, read on a background thread.
+pub struct Receiver {
+    /// Written by the reading thread.
+    location: Arc<Mutex<Option<Location>>>,
+    /// Dropped with the receiver, which stops the reading thread.
+    _stop: mpsc::Sender<()>,
+}
+
+impl Receiver {
+    /// Starts reading `source`. If `offset` is given, it is kept set to the correction that
+    /// brings the system clock to the receiver's time.
+    pub fn start(source: Source, offset: Option<TimeOffset>) -> anyhow::Result<Self> {
+        let location = Arc::new(Mutex::new(None));
+        let (stop, stopped) = mpsc::channel();
+        {
+            let location = location.clone();
+            std::thread::Builder::new()
+                .name("gps".into())
+                .spawn(move || run(&source, offset.as_ref(), &location, &stopped))
+                .context("failed to start reading the GPS receiver")?;
+        }
+        Ok(Self {
+            location,
+            _stop: stop,
+        })
+    }
+
+    /// The last position reported, if the receiver has had a fix.
+    pub fn location(&self) -> Option<Location> {
+        self.location.lock().unwrap().clone()
+    }
+}
+
+/// Reads `source` until `stop` is disconnected, reading it again whenever it fails.
+fn run(
+    source: &Source,
+    offset: Option<&TimeOffset>,
+    location: &Mutex<Option<Location>>,
+    stop: &mpsc::Receiver<()>,
+) {
+    let mut corrections = VecDeque::with_capacity(TIME_REPORTS);
+    loop {
+        let result = read(source, stop, |reading| {
+            if let (Some(time), Some(offset)) = (reading.time, offset) {
+                if corrections.len() == TIME_REPORTS {
+                    corrections.pop_front();
+                }
+                corrections.push_back(time - Utc::now());
+                // The median, so that one report with a bogus time does not move the clock.
+                let mut sorted: Vec<_> = corrections.iter().copied().collect();
+                sorted.sort();
+                offset.set(sorted[sorted.len() / 2]);
+            }
+            if let Some(reported) = reading.location {
+                let mut location = location.lock().unwrap();
+                if location.is_none() {
+                    log::info!(
+                        "GPS fix at {:.4}, {:.4}",
+                        reported.latitude,
+                        reported.longitude
+                    );
+                }
+                *location = Some(reported);
+            }
+        });
+        match result {
+            Ok(()) => return,
+            Err(err) => log::warn!("{:#}", err),
+        }
+        match stop.recv_timeout(RETRY_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Passes the readings in `source`'s reports to `report` until `stop` is disconnected, or until
+/// the source fails or ends.
+fn read(
+    source: &Source,
+    stop: &mpsc::Receiver<()>,
+    mut report: impl FnMut(Reading),
+) -> anyhow::Result<()> {
+    let (reader, parse): (Box<dyn BufRead + Send>, Parser) = match source {
+        Source::Gpsd(address) => {
+            let mut stream = TcpStream::connect(address)
+                .with_context(|| format!("failed to connect to {}", source))?;
+            stream
+                .write_all(b"?WATCH={\"enable\":true,\"json\":true}\n")
+                .with_context(|| format!("failed to ask {} for reports", source))?;
+            (Box::new(BufReader::new(stream)), parse_gpsd)
+        }
+        Source::Device(path) => {
+            let device = File::open(path).with_context(|| format!("failed to open {}", source))?;
+            (Box::new(BufReader::new(device)), parse_nmea)
+        }
+    };
+    // Read on another thread, as a serial device cannot be read with a timeout, so that `stop` is
+    // checked while the receiver is silent. The thread ends with the next line after this returns.
+    let (send_line, lines) = mpsc::sync_channel(TIME_REPORTS);
+    std::thread::Builder::new()
+        .name("gps reader".into())
+        .spawn(move || {
+            for line in reader.split(b'\n') {
+                let failed = line.is_err();
+                if send_line.send(line).is_err() || failed {
+                    return;
+                }
+            }
+        })
+        .with_context(|| format!("failed to start reading {}", source))?;
+    loop {
+        match stop.try_recv() {
+            Err(TryRecvError::Empty) => {}
+            Ok(()) | Err(TryRecvError::Disconnected) => return Ok(()),
+        }
+        let line = match lines.recv_timeout(STOP_CHECK_INTERVAL) {
+            Ok(line) => line.with_context(|| format!("failed to read from {}", source))?,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        // Noise on a serial line, e.g. at the wrong baud rate, is skipped like other sentences.
+        if let Some(reading) = parse(String::from_utf8_lossy(&line).trim()) {
+            report(reading);
+        }
+    }
+    bail!("{} stopped sending reports", source)
+}
+
+/// Reads the time and position from an NMEA `RMC` sentence, e.g. `$GPRMC,...`. Other sentences,
+/// ones without a fix, and ones without a correct checksum give `None`.
+pub fn parse_nmea(sentence: &str) -> Option<Reading> {
+    let (body, checksum) = sentence.strip_prefix('$')?.split_once('*')?;
+    let checksum = u8::from_str_radix(checksum, 16).ok()?;
+    if body.bytes().fold(0, |sum, byte| sum ^ byte) != checksum {
+        return None;
+    }
+    let fields: Vec<&str> = body.split(',').collect();
+    // From any talker, e.g. `GP` for GPS alone or `GN` for several satellite systems.
+    if fields.len() < 10 || fields[0].len() != 5 || !fields[0].ends_with("RMC") {
+        return None;
+    }
+    if fields[2] != "A" {
+        return None;
+    }
+    let time = NaiveTime::parse_from_str(fields[1], "%H%M%S%.f").ok();
+    let date = NaiveDate::parse_from_str(fields[9], "%d%m%y").ok();
+    let time = date
+        .zip(time)
+        .map(|(date, time)| Utc.from_utc_datetime(&date.and_time(time)));
+    let location = nmea_coordinate(fields[3], fields[4], "S", 90.0)
+        .zip(nmea_coordinate(fields[5], fields[6], "W", 180.0))
+        .map(|(latitude, longitude)| Location {
+            latitude,
+            longitude,
+            name: None,
+        });
+    Some(Reading { time, location })
+}
+
+/// Degrees from an NMEA coordinate in degrees and minutes, e.g. `4807.038` for 48° 7.038′, and
+/// its hemisphere, which is negative if it is `negative`. Coordinates beyond `max` degrees give
+/// `None`.
+fn nmea_coordinate(value: &str, hemisphere: &str, negative: &str, max: f64) -> Option<f64> {
+    let value: f64 = value.parse().ok()?;
+    let degrees = (value / 100.0).trunc() + (value % 100.0) / 60.0;
+    if !(0.0..=max).contains(&degrees) {
+        return None;
+    }
+    Some(if hemisphere == negative {
+        -degrees
+    } else {
+        degrees
+    })
+}
+
+/// Reads the time and position from a gpsd `TPV` report. Other reports, and ones without a fix,
+/// give `None`.
+pub fn parse_gpsd(report: &str) -> Option<Reading> {
+    let report: serde_json::Value = serde_json::from_str(report).ok()?;
+    // Mode 2 is a 2D fix, and 3 a 3D fix.
+    if report["class"] != "TPV" || report["mode"].as_u64().unwrap_or(0) < 2 {
+        return None;
+    }
+    let time = report["time"]
+        .as_str()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Utc));
+    let location =
+        report["lat"]
+            .as_f64()
+            .zip(report["lon"].as_f64())
+            .map(|(latitude, longitude)| Location {
+                latitude,
+                longitude,
+                name: None,
+            });
+    Some(Reading { time, location })
+}
@@ -0,0 +1,367 @@
+use crate::cli::Args;
+use crate::clock_window::{create_layers, Resources};
+use crate::config::Config;
+use crate::display;
+use crate::paths::Paths;
+use crate::timezone::Timezone;
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use global_clock::layer::{draw_layers, floor_time, update_interval};
+use global_clock::process::shell;
+use global_clock::{
+    screenshot, time_source, Background, FrameInfo, Globe, GraphicsContext, Layer, StagingPool,
+    Viewport,
+};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageFormat, RgbaImage};
+use pollster::block_on;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The lowest frame rate of [`pipe`] and the stream, a frame every 1000 seconds.
+const MIN_FPS: f64 = 0.001;
+
+/// The most frames of a time-lapse, more than a day at a frame every simulated second.
+const MAX_TIMELAPSE_FRAMES: f64 = 100_000.0;
+
+/// Renders the main window's scene once, without opening a window, and saves it to `output`.
+pub fn render(args: &Args, paths: &Paths, output: &Path) -> anyhow::Result<()> {
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let time = time_source::from_settings(config.time, config.speed).now();
+    let mut scene = Scene::new(config, paths)?;
+    scene
+        .render(&time)?
+        .save(output)
+        .with_context(|| format!("failed to save {}", output.display()))
+}
+
+/// How a time-lapse covers the displayed time.
+#[derive(Debug, Clone, Copy)]
+pub struct Timelapse {
+    /// Simulated time covered.
+    pub hours: f64,
+    /// Simulated time between frames.
+    pub step_minutes: f64,
+    pub fps: u32,
+}
+
+/// Renders the main window's scene over a range of time, starting from `--time` or the current
+/// time, without opening a window, and saves it as an animation to `output`: an animated GIF for
+/// a `.gif` extension, or a video encoded by `ffmpeg` for other extensions such as `.mp4`.
+pub fn timelapse(
+    args: &Args,
+    paths: &Paths,
+    output: &Path,
+    timelapse: &Timelapse,
+) -> anyhow::Result<()> {
+    let Timelapse {
+        hours,
+        step_minutes,
+        fps,
+    } = *timelapse;
+    let positive = |value: f64| value > 0.0 && value.is_finite();
+    if !positive(hours) || !positive(step_minutes) || fps == 0 {
+        bail!("the time-lapse needs a positive length, step and frame rate");
+    }
+    let frames = (hours * 60.0 / step_minutes).ceil();
+    if frames > MAX_TIMELAPSE_FRAMES {
+        bail!(
+            "the time-lapse would have {} frames, more than {}; use a longer step",
+            frames,
+            MAX_TIMELAPSE_FRAMES
+        );
+    }
+    let step = chrono::Duration::try_milliseconds((step_minutes * 60_000.0).round() as i64)
+        .filter(|step| *step > chrono::Duration::zero())
+        .context("the time-lapse's step must be from a millisecond up")?;
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let start = config.time.unwrap_or_else(Utc::now);
+    // All of the times are checked before any frame is rendered.
+    let times = (0..frames as i32)
+        .map(|index| {
+            step.checked_mul(index)
+                .and_then(|offset| start.checked_add_signed(offset))
+                .context("the time-lapse goes past the range of dates")
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut scene = Scene::new(config, paths)?;
+    let mut encoder = AnimationEncoder::create(output, scene.size(), fps)?;
+    for (index, time) in times.iter().enumerate() {
+        encoder.add(scene.render(time)?)?;
+        eprint!("\rRendered {}/{} frames", index + 1, times.len());
+    }
+    eprintln!();
+    encoder.finish()
+}
+
+/// Renders the main window's scene `fps` times per second as time passes, without opening a
+/// window, and writes the frames to `output` or to the standard output as raw RGBA pixels, e.g.
+/// for a video player drawing a live wallpaper. Stops when the reader goes away.
+pub fn pipe(args: &Args, paths: &Paths, output: Option<&Path>, fps: f64) -> anyhow::Result<()> {
+    let interval = fps_interval(fps)?;
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let time_source = time_source::from_settings(config.time, config.speed);
+    let mut scene = Scene::new(config, paths)?;
+    let mut writer: Box<dyn Write> = match output {
+        // Named pipes are opened as they are, and other files replaced.
+        Some(path) => Box::new(BufWriter::new(
+            File::options()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    scene.set_frame_interval(interval);
+    let mut next = Instant::now();
+    loop {
+        let image = scene.render(&time_source.now())?;
+        let written = writer
+            .write_all(image.as_raw())
+            .and_then(|()| writer.flush());
+        match written {
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            result => result.context("failed to write a frame")?,
+        }
+        next += interval;
+        let now = Instant::now();
+        match next.checked_duration_since(now) {
+            Some(wait) => std::thread::sleep(wait),
+            // Late frames are not made up for, so that a slow reader does not fall behind.
+            None => next = now,
+        }
+    }
+}
+
+/// The time between frames at `fps` frames per second, or an error if that is not a frame rate
+/// from [`MIN_FPS`] up.
+pub fn fps_interval(fps: f64) -> anyhow::Result<Duration> {
+    if !(fps >= MIN_FPS && fps.is_finite()) {
+        bail!(
+            "the frame rate must be a finite number of at least {}, not {}",
+            MIN_FPS,
+            fps
+        );
+    }
+    Ok(Duration::from_secs_f64(fps.recip()))
+}
+
+/// Renders the main window's scene to `output` every `interval` on the multiples of it, without
+/// opening a window, and runs `command` with the shell after each frame, e.g. to set the file as
+/// the wallpaper. Runs until interrupted.
+pub fn wallpaper(
+    args: &Args,
+    paths: &Paths,
+    output: &Path,
+    interval: Duration,
+    command: Option<&str>,
+) -> anyhow::Result<()> {
+    if interval.is_zero() {
+        bail!("the interval must be positive");
+    }
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let time_source = time_source::from_settings(config.time, config.speed);
+    let mut scene = Scene::new(config, paths)?;
+    let format = ImageFormat::from_path(output)
+        .with_context(|| format!("unknown image format of {}", output.display()))?;
+    // Written next to the output and moved over it, so that the output is never incomplete.
+    let mut partial = output.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let mut running: Option<Child> = None;
+    loop {
+        scene
+            .render(&time_source.now())?
+            .save_with_format(&partial, format)
+            .with_context(|| format!("failed to save {}", partial.display()))?;
+        std::fs::rename(&partial, output)
+            .with_context(|| format!("failed to replace {}", output.display()))?;
+        if let Some(command) = command {
+            match shell(command).spawn() {
+                Ok(child) => {
+                    // Commands that keep showing the image, like `swaybg`, are only stopped once
+                    // the next one has started, so that the wallpaper does not flicker.
+                    if let Some(mut previous) = running.replace(child) {
+                        let _ = previous.kill();
+                        let _ = previous.wait();
+                    }
+                }
+                Err(err) => log::warn!("failed to run {:?}: {}", command, err),
+            }
+        }
+        let now = Utc::now();
+        let next = floor_time(&now, interval) + chrono::Duration::from_std(interval)?;
+        std::thread::sleep((next - now).to_std().unwrap_or_default());
+    }
+}
+
+/// The main window's scene, drawn into images instead of a window.
+pub struct Scene {
+    gfx: GraphicsContext,
+    timezone: Timezone,
+    width: u32,
+    height: u32,
+    viewport: Viewport,
+    background: Background,
+    layers: Vec<Box<dyn Layer>>,
+    staging: StagingPool,
+    /// How often frames are rendered, if as time passes.
+    frame_interval: Option<Duration>,
+}
+
+impl Scene {
+    pub fn new(mut config: Config, paths: &Paths) -> anyhow::Result<Self> {
+        // The system's contrast setting is not followed, so that the output only depends on the
+        // config.
+        config.follow_system_contrast = false;
+        let resources = Resources::from_config(&config, paths)?;
+        let timezone = Timezone::from_config(config.timezone.as_deref())?;
+        let (width, height) = (config.window.width, config.window.height);
+
+        // Decoding the globe's textures overlaps with opening the graphics device.
+        if config.texture_dir.is_none() {
+            Globe::preload(&resources.assets);
+        }
+        let gfx = block_on(display::open_headless())?;
+        // The saved view is not used, so that the output only depends on the config.
+        let viewport = Viewport::new(&gfx, width, height, Default::default());
+        let background = Background::new(&gfx, &resources.theme);
+        let names: Vec<String> = resources.registry.names().map(str::to_owned).collect();
+        let mut layers = create_layers(&gfx, &viewport, &resources, &names, 1.0)?;
+        for layer in &mut layers {
+            layer.finish_loading();
+        }
+        Ok(Self {
+            staging: StagingPool::new(&gfx),
+            gfx,
+            timezone,
+            width,
+            height,
+            viewport,
+            background,
+            layers,
+            frame_interval: None,
+        })
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Renders frames every `interval` from now on, as time passes, which sweeping layers are then
+    /// updated for at the exact time of each frame.
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.frame_interval = Some(interval);
+    }
+
+    /// Draws the scene at `time`.
+    pub fn render(&mut self, time: &DateTime<Utc>) -> anyhow::Result<RgbaImage> {
+        let frame = FrameInfo {
+            time: *time,
+            local_time: self.timezone.time_of(time),
+            local_date: self.timezone.date_of(time),
+            timezone: self.timezone.name(),
+        };
+        for layer in &mut self.layers {
+            let interval = match self.frame_interval {
+                Some(frame_interval) => update_interval(&**layer, frame_interval),
+                None => layer.tick_interval(),
+            };
+            layer.update(&frame.floor(interval));
+        }
+        let (width, height) = self.size();
+        let Self {
+            gfx,
+            viewport,
+            background,
+            layers,
+            staging,
+            ..
+        } = self;
+        screenshot::capture(gfx, width, height, |encoder, view| {
+            draw_layers(encoder, staging, view, background, layers, viewport);
+        })
+    }
+}
+
+/// Writes frames of an animation to a file.
+enum AnimationEncoder {
+    Gif(GifEncoder<BufWriter<File>>, Delay),
+    /// `ffmpeg`, reading raw frames from its standard input.
+    Ffmpeg(Child),
+}
+
+impl AnimationEncoder {
+    fn create(path: &Path, (width, height): (u32, u32), fps: u32) -> anyhow::Result<Self> {
+        let is_gif = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+        if is_gif {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            // Faster than the default, at little cost in quality for the few colors of the scene.
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            return Ok(Self::Gif(encoder, Delay::from_numer_denom_ms(1000, fps)));
+        }
+        let ffmpeg = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pixel_format",
+                "rgba",
+            ])
+            .args(["-video_size", &format!("{}x{}", width, height)])
+            .args(["-framerate", &fps.to_string(), "-i", "-"])
+            // Most players only support 4:2:0 chroma, which needs an even width and height.
+            .args([
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to run ffmpeg, which encodes videos; is it installed?")?;
+        Ok(Self::Ffmpeg(ffmpeg))
+    }
+
+    fn add(&mut self, image: RgbaImage) -> anyhow::Result<()> {
+        match self {
+            Self::Gif(encoder, delay) => encoder
+                .encode_frame(Frame::from_parts(image, 0, 0, *delay))
+                .context("failed to encode a GIF frame"),
+            Self::Ffmpeg(ffmpeg) => ffmpeg
+                .stdin
+                .as_mut()
+                .unwrap()
+                .write_all(image.as_raw())
+                .context("failed to write a frame to ffmpeg"),
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            // Dropping the encoder writes the end of the file.
+            Self::Gif(..) => {}
+            Self::Ffmpeg(mut ffmpeg) => {
+                // Closing its input ends the video.
+                drop(ffmpeg.stdin.take());
+                let status = ffmpeg.wait().context("failed to wait for ffmpeg")?;
+                if !status.success() {
+                    bail!("ffmpeg failed with {}", status);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+use objc2_foundation::MainThreadMarker;
+use objc2_ui_kit::UIApplication;
+
+/// Keeps the screen from dimming and locking while the app is in the foreground, as a clock on a
+/// nightstand or desk is expected to. iOS lets it lock again while the app is in the background.
+pub fn keep_screen_on() {
+    let Some(mtm) = MainThreadMarker::new() else {
+        log::warn!("cannot keep the screen on from outside the main thread");
+        return;
+    };
+    // Safety: called on the main thread, which UIKit requires.
+    unsafe { UIApplication::sharedApplication(mtm).setIdleTimerDisabled(true) };
+}
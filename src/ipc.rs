@@ -0,0 +1,123 @@
+use crate::control::{Command, Controller, Response};
+use crate::paths::Paths;
+use anyhow::{bail, Context};
+use interprocess::local_socket::prelude::*;
+use interprocess::local_socket::{ListenerOptions, Name, Stream};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a client may take to send a request before it is disconnected.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Reply {
+    Ok(Response),
+    Error(String),
+}
+
+#[cfg(windows)]
+fn socket_name(_paths: &Paths) -> io::Result<Name<'static>> {
+    use interprocess::local_socket::GenericNamespaced;
+    // Named pipes are shared by every user of the machine, unlike the state directory.
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    format!("global-clock-{}", user).to_ns_name::<GenericNamespaced>()
+}
+
+#[cfg(not(windows))]
+fn socket_name(paths: &Paths) -> io::Result<Name<'static>> {
+    use interprocess::local_socket::GenericFilePath;
+    paths
+        .state_dir()
+        .join("control.sock")
+        .to_fs_name::<GenericFilePath>()
+}
+
+/// Starts accepting commands on the control socket (a Unix socket, or a named pipe on Windows) in
+/// a background thread.
+///
+/// Each request is a line of JSON containing a [`Command`], such as
+/// `{"set_timezone":"Asia/Tokyo"}`, and is answered with a line containing either
+/// `{"ok":<response>}` or `{"error":"<message>"}`.
+pub fn serve(controller: Controller, paths: &Paths) -> anyhow::Result<()> {
+    let name = socket_name(paths).context("invalid socket name")?;
+    if Stream::connect(name.borrow()).is_ok() {
+        bail!("another instance is already listening");
+    }
+    std::fs::create_dir_all(paths.state_dir())
+        .with_context(|| format!("failed to create {}", paths.state_dir().display()))?;
+    // A socket file left behind by an instance that did not exit cleanly is replaced.
+    let listener = ListenerOptions::new()
+        .name(name)
+        .try_overwrite(true)
+        .create_sync()
+        .context("failed to create control socket")?;
+
+    let controller = Arc::new(controller);
+    std::thread::Builder::new()
+        .name("control socket".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    // Each client has its own thread, so that one that sends nothing does not
+                    // hold up the others.
+                    Ok(stream) => {
+                        let controller = controller.clone();
+                        let spawned = std::thread::Builder::new()
+                            .name("control client".into())
+                            .spawn(move || {
+                                if let Err(err) = handle_connection(stream, &controller) {
+                                    log::warn!("control connection failed: {}", err);
+                                }
+                            });
+                        if let Err(err) = spawned {
+                            log::warn!("failed to handle control connection: {}", err);
+                        }
+                    }
+                    Err(err) => log::warn!("failed to accept control connection: {}", err),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn handle_connection(stream: Stream, controller: &Controller) -> io::Result<()> {
+    // Not supported by named pipes, where an idle client keeps its thread until it disconnects.
+    let _ = stream.set_recv_timeout(Some(CLIENT_TIMEOUT));
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    while stream.read_line(&mut line)? != 0 {
+        let reply = match serde_json::from_str(&line) {
+            Ok(command) => match controller.send_blocking(command) {
+                Ok(response) => Reply::Ok(response),
+                Err(err) => Reply::Error(err),
+            },
+            Err(err) => Reply::Error(format!("invalid request: {}", err)),
+        };
+        let mut reply = serde_json::to_string(&reply)?;
+        reply.push('\n');
+        stream.get_mut().write_all(reply.as_bytes())?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Sends a command to the running instance and waits for its response.
+pub fn send(paths: &Paths, command: &Command) -> anyhow::Result<Response> {
+    let name = socket_name(paths).context("invalid socket name")?;
+    let stream = Stream::connect(name).context("failed to connect; is global-clock running?")?;
+    let mut stream = BufReader::new(stream);
+
+    let mut request = serde_json::to_string(command)?;
+    request.push('\n');
+    stream.get_mut().write_all(request.as_bytes())?;
+
+    let mut line = String::new();
+    stream.read_line(&mut line)?;
+    match serde_json::from_str(&line).context("invalid response")? {
+        Reply::Ok(response) => Ok(response),
+        Reply::Error(err) => bail!("{}", err),
+    }
+}
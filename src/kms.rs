@@ -0,0 +1,618 @@
+use crate::cli::Args;
+use crate::clock_window::{create_layers, Resources};
+use crate::config::Config;
+use crate::display;
+use crate::paths::Paths;
+use crate::scheduler::FrameScheduler;
+use crate::timezone::Timezone;
+use anyhow::Context;
+use global_clock::layer::{draw_layers, update_interval};
+use global_clock::{screenshot, time_source, Background, FrameInfo, StagingPool, Viewport};
+use pollster::block_on;
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Set by SIGINT and SIGTERM, to restore the console before exiting.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Longest time to sleep between checks for a signal.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longest time to wait for a page flip, in case the driver never reports it.
+const FLIP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Shows the clock on a monitor through DRM/KMS `device` (e.g. `/dev/dri/card0`), without a
+/// display server, e.g. on a Raspberry Pi running as a picture frame. Frames are drawn offscreen
+/// and copied into a framebuffer for the first connected monitor, at its preferred mode.
+pub fn run(args: &Args, paths: &Paths, device: &Path) -> anyhow::Result<()> {
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let timezone = Timezone::from_config(config.timezone.as_deref())?;
+    let time_source = time_source::from_settings(config.time, config.speed);
+
+    let mut card = Card::open(device)?;
+    let (width, height) = card.size();
+    log::info!(
+        "showing the clock at {}x{} on {}",
+        width,
+        height,
+        device.display()
+    );
+
+    let gfx = block_on(display::open_headless())?;
+    let resources = Resources::from_config(&config, paths)?;
+    let viewport = Viewport::new(&gfx, width, height, Default::default());
+    let background = Background::new(&gfx, &resources.theme);
+    let names: Vec<String> = resources.registry.names().map(str::to_owned).collect();
+    let mut layers = create_layers(&gfx, &viewport, &resources, &names, 1.0)?;
+
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // Safety: the handler only stores to an atomic, which is async-signal-safe.
+        unsafe {
+            libc::signal(
+                signal,
+                on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            )
+        };
+    }
+    let mut staging = StagingPool::new(&gfx);
+    let frame_interval = config.redraw.frame_interval();
+    let mut scheduler = FrameScheduler::new(frame_interval, config.redraw.animation_interval());
+    scheduler.set_tick_intervals(
+        layers
+            .iter()
+            .map(|layer| update_interval(&**layer, frame_interval)),
+    );
+    while !STOP.load(Ordering::Relaxed) {
+        let time = time_source.now();
+        let mut loaded = false;
+        for layer in &mut layers {
+            loaded |= layer.poll();
+        }
+        if loaded || scheduler.needs_redraw(&time) {
+            let frame = FrameInfo {
+                time,
+                local_time: timezone.time_of(&time),
+                local_date: timezone.date_of(&time),
+                timezone: timezone.name(),
+            };
+            for layer in &mut layers {
+                layer.update(&frame.floor(update_interval(&**layer, frame_interval)));
+            }
+            let image = screenshot::capture(&gfx, width, height, |encoder, view| {
+                draw_layers(
+                    encoder,
+                    &mut staging,
+                    view,
+                    &background,
+                    &mut layers,
+                    &viewport,
+                );
+            })?;
+            if !card.present(&image)? {
+                // The monitor is still busy with an earlier frame; draw this one again shortly.
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            scheduler.drawn(time);
+        }
+        let wakeup = scheduler.next_wakeup(&time_source);
+        while !STOP.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            if now >= wakeup || scheduler.check_jumped() {
+                break;
+            }
+            std::thread::sleep((wakeup - now).min(POLL_INTERVAL));
+        }
+    }
+    Ok(())
+}
+
+extern "C" fn on_signal(_signal: libc::c_int) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+/// A DRM device showing one of two framebuffers on a monitor, while the other is drawn to. The
+/// monitor is given back to the console when this is dropped.
+struct Card {
+    file: File,
+    connector_id: u32,
+    crtc_id: u32,
+    mode: ModeInfo,
+    /// What the CRTC showed before, e.g. the console.
+    saved_crtc: Crtc,
+    buffers: [DumbBuffer; 2],
+    /// Index of the buffer being shown.
+    front: usize,
+    /// Whether a page flip was asked for and has not happened yet, so that the back buffer may
+    /// still be on screen.
+    flip_pending: bool,
+}
+
+impl Card {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+
+        let mut resources = CardRes::default();
+        ioctl(&file, DRM_IOCTL_MODE_GETRESOURCES, &mut resources)
+            .with_context(|| format!("{} does not support KMS", path.display()))?;
+        let mut crtcs = vec![0u32; resources.count_crtcs as usize];
+        let mut connectors = vec![0u32; resources.count_connectors as usize];
+        resources = CardRes {
+            crtc_id_ptr: crtcs.as_mut_ptr() as u64,
+            connector_id_ptr: connectors.as_mut_ptr() as u64,
+            count_crtcs: crtcs.len() as u32,
+            count_connectors: connectors.len() as u32,
+            ..Default::default()
+        };
+        ioctl(&file, DRM_IOCTL_MODE_GETRESOURCES, &mut resources)?;
+        crtcs.truncate(resources.count_crtcs as usize);
+        connectors.truncate(resources.count_connectors as usize);
+
+        let (connector, modes, encoders) = connectors
+            .iter()
+            .map(|&id| get_connector(&file, id))
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .find(|(connector, modes, _)| {
+                connector.connection == DRM_MODE_CONNECTED && !modes.is_empty()
+            })
+            .with_context(|| format!("no monitor is connected to {}", path.display()))?;
+        let mode = modes
+            .iter()
+            .find(|mode| mode.type_ & DRM_MODE_TYPE_PREFERRED != 0)
+            .unwrap_or(&modes[0])
+            .clone();
+
+        // Keep the CRTC that the connector is already driven by, or find one that can drive it.
+        let current_crtc = match connector.encoder_id {
+            0 => 0,
+            id => get_encoder(&file, id)?.crtc_id,
+        };
+        let crtc_id = match current_crtc {
+            0 => encoders
+                .iter()
+                .map(|&id| get_encoder(&file, id))
+                .collect::<io::Result<Vec<_>>>()?
+                .iter()
+                .find_map(|encoder| {
+                    crtcs
+                        .iter()
+                        .enumerate()
+                        .find(|(index, _)| encoder.possible_crtcs & (1 << index) != 0)
+                        .map(|(_, &crtc)| crtc)
+                })
+                .context("no display controller can drive the monitor")?,
+            id => id,
+        };
+        let mut saved_crtc = Crtc {
+            crtc_id,
+            ..Default::default()
+        };
+        ioctl(&file, DRM_IOCTL_MODE_GETCRTC, &mut saved_crtc)?;
+
+        let (width, height) = (mode.hdisplay.into(), mode.vdisplay.into());
+        let buffers = [
+            DumbBuffer::new(&file, width, height)?,
+            DumbBuffer::new(&file, width, height)?,
+        ];
+        let card = Self {
+            file,
+            connector_id: connector.connector_id,
+            crtc_id,
+            mode,
+            saved_crtc,
+            buffers,
+            front: 0,
+            flip_pending: false,
+        };
+        card.set_crtc(card.buffers[0].fb_id)
+            .context("failed to set the display mode; is another program using the monitor?")?;
+        Ok(card)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.mode.hdisplay.into(), self.mode.vdisplay.into())
+    }
+
+    fn set_crtc(&self, fb_id: u32) -> io::Result<()> {
+        let mut connectors = [self.connector_id];
+        let mut crtc = Crtc {
+            set_connectors_ptr: connectors.as_mut_ptr() as u64,
+            count_connectors: 1,
+            crtc_id: self.crtc_id,
+            fb_id,
+            mode_valid: 1,
+            mode: self.mode.clone(),
+            ..Default::default()
+        };
+        ioctl(&self.file, DRM_IOCTL_MODE_SETCRTC, &mut crtc)
+    }
+
+    /// Copies `image` into the back buffer, once the last frame is on screen, and shows it from
+    /// the next vertical blank. Returns `false` if the monitor could not take the frame yet, in
+    /// which case it is not shown.
+    fn present(&mut self, image: &image::RgbaImage) -> anyhow::Result<bool> {
+        self.wait_for_flip()?;
+        let back = 1 - self.front;
+        self.buffers[back].write(image);
+        let mut flip = PageFlip {
+            crtc_id: self.crtc_id,
+            fb_id: self.buffers[back].fb_id,
+            flags: DRM_MODE_PAGE_FLIP_EVENT,
+            ..Default::default()
+        };
+        match ioctl(&self.file, DRM_IOCTL_MODE_PAGE_FLIP, &mut flip) {
+            Ok(()) => {
+                self.front = back;
+                self.flip_pending = true;
+            }
+            // A flip that this did not ask for, e.g. by the console, has not happened yet.
+            Err(err) if err.raw_os_error() == Some(libc::EBUSY) => return Ok(false),
+            // Some drivers cannot flip, but can always set the mode again.
+            Err(_) => {
+                self.set_crtc(self.buffers[back].fb_id)
+                    .context("failed to show the frame")?;
+                self.front = back;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Waits for the last page flip to happen, after which the buffer it replaced is no longer
+    /// on screen and can be drawn to.
+    fn wait_for_flip(&mut self) -> anyhow::Result<()> {
+        while self.flip_pending {
+            let mut poll = libc::pollfd {
+                fd: self.file.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // Safety: `poll` is a single valid `pollfd`.
+            let ready =
+                unsafe { libc::poll(&mut poll, 1, FLIP_TIMEOUT.as_millis() as libc::c_int) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                return Err(err).context("failed to wait for the monitor");
+            }
+            if ready == 0 {
+                log::warn!("the monitor did not report showing the last frame");
+                self.flip_pending = false;
+                break;
+            }
+            // Each event starts with its type and its length in bytes.
+            let mut events = [0u8; 1024];
+            let read = (&self.file)
+                .read(&mut events)
+                .context("failed to read events from the monitor")?;
+            let mut offset = 0;
+            while offset + 8 <= read {
+                let field = |at: usize| {
+                    u32::from_ne_bytes(events[offset + at..offset + at + 4].try_into().unwrap())
+                };
+                if field(0) == DRM_EVENT_FLIP_COMPLETE {
+                    self.flip_pending = false;
+                }
+                offset += (field(4) as usize).max(8);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Card {
+    fn drop(&mut self) {
+        let saved = &mut self.saved_crtc;
+        if saved.fb_id != 0 && saved.mode_valid != 0 {
+            let mut connectors = [self.connector_id];
+            saved.set_connectors_ptr = connectors.as_mut_ptr() as u64;
+            saved.count_connectors = 1;
+            if let Err(err) = ioctl(&self.file, DRM_IOCTL_MODE_SETCRTC, saved) {
+                log::warn!("failed to restore the console: {}", err);
+            }
+        }
+        for buffer in &self.buffers {
+            buffer.destroy(&self.file);
+        }
+    }
+}
+
+/// A framebuffer in memory that the CPU can write to, in XRGB8888 format.
+struct DumbBuffer {
+    handle: u32,
+    fb_id: u32,
+    width: u32,
+    height: u32,
+    pitch: u32,
+    map: *mut u8,
+    size: usize,
+}
+
+impl DumbBuffer {
+    fn new(file: &File, width: u32, height: u32) -> anyhow::Result<Self> {
+        let mut create = CreateDumb {
+            width,
+            height,
+            bpp: 32,
+            ..Default::default()
+        };
+        ioctl(file, DRM_IOCTL_MODE_CREATE_DUMB, &mut create)
+            .context("failed to allocate a framebuffer")?;
+        let mut fb = FbCmd {
+            width,
+            height,
+            pitch: create.pitch,
+            bpp: 32,
+            depth: 24,
+            handle: create.handle,
+            ..Default::default()
+        };
+        ioctl(file, DRM_IOCTL_MODE_ADDFB, &mut fb).context("failed to add a framebuffer")?;
+        let mut map = MapDumb {
+            handle: create.handle,
+            ..Default::default()
+        };
+        ioctl(file, DRM_IOCTL_MODE_MAP_DUMB, &mut map).context("failed to map a framebuffer")?;
+        let size = create.size as usize;
+        // Safety: the kernel provides an offset that maps the buffer, which is `size` bytes.
+        let pointer = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                map.offset as libc::off_t,
+            )
+        };
+        if pointer == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error()).context("failed to map a framebuffer");
+        }
+        Ok(Self {
+            handle: create.handle,
+            fb_id: fb.fb_id,
+            width,
+            height,
+            pitch: create.pitch,
+            map: pointer.cast(),
+            size,
+        })
+    }
+
+    fn write(&mut self, image: &image::RgbaImage) {
+        // Safety: the mapping is `size` bytes long and lives until `destroy`.
+        let pixels = unsafe { std::slice::from_raw_parts_mut(self.map, self.size) };
+        let width = self.width.min(image.width()) as usize;
+        for (y, row) in image.rows().take(self.height as usize).enumerate() {
+            let start = y * self.pitch as usize;
+            let dst = &mut pixels[start..start + width * 4];
+            for (dst, src) in dst.chunks_exact_mut(4).zip(row) {
+                // XRGB8888 is stored little-endian, i.e. blue first.
+                let [r, g, b, _] = src.0;
+                dst.copy_from_slice(&[b, g, r, 0xff]);
+            }
+        }
+    }
+
+    fn destroy(&self, file: &File) {
+        // Safety: the mapping is not used after this.
+        unsafe { libc::munmap(self.map.cast(), self.size) };
+        let mut fb_id = self.fb_id;
+        let _ = ioctl(file, DRM_IOCTL_MODE_RMFB, &mut fb_id);
+        let mut destroy = DestroyDumb {
+            handle: self.handle,
+        };
+        let _ = ioctl(file, DRM_IOCTL_MODE_DESTROY_DUMB, &mut destroy);
+    }
+}
+
+fn get_connector(file: &File, id: u32) -> io::Result<(Connector, Vec<ModeInfo>, Vec<u32>)> {
+    // The first call probes the monitor for its modes and reports how many there are.
+    let mut connector = Connector {
+        connector_id: id,
+        ..Default::default()
+    };
+    ioctl(file, DRM_IOCTL_MODE_GETCONNECTOR, &mut connector)?;
+    let mut modes = vec![ModeInfo::default(); connector.count_modes as usize];
+    let mut encoders = vec![0u32; connector.count_encoders as usize];
+    connector = Connector {
+        connector_id: id,
+        modes_ptr: modes.as_mut_ptr() as u64,
+        count_modes: modes.len() as u32,
+        encoders_ptr: encoders.as_mut_ptr() as u64,
+        count_encoders: encoders.len() as u32,
+        ..Default::default()
+    };
+    ioctl(file, DRM_IOCTL_MODE_GETCONNECTOR, &mut connector)?;
+    modes.truncate(connector.count_modes as usize);
+    encoders.truncate(connector.count_encoders as usize);
+    Ok((connector, modes, encoders))
+}
+
+fn get_encoder(file: &File, id: u32) -> io::Result<Encoder> {
+    let mut encoder = Encoder {
+        encoder_id: id,
+        ..Default::default()
+    };
+    ioctl(file, DRM_IOCTL_MODE_GETENCODER, &mut encoder)?;
+    Ok(encoder)
+}
+
+fn ioctl<T>(file: &File, request: libc::c_ulong, arg: &mut T) -> io::Result<()> {
+    loop {
+        // Safety: `request` is one that reads and writes a `T`.
+        if unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg as *mut T) } == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if !matches!(err.raw_os_error(), Some(libc::EINTR | libc::EAGAIN)) {
+            return Err(err);
+        }
+    }
+}
+
+// The parts of the kernel's DRM interface (`drm_mode.h`) used above.
+
+const fn drm_iowr<T>(nr: u32) -> libc::c_ulong {
+    const READ_WRITE: u32 = 3;
+    ((READ_WRITE << 30) | ((std::mem::size_of::<T>() as u32) << 16) | ((b'd' as u32) << 8) | nr)
+        as libc::c_ulong
+}
+
+const DRM_IOCTL_MODE_GETRESOURCES: libc::c_ulong = drm_iowr::<CardRes>(0xa0);
+const DRM_IOCTL_MODE_GETCRTC: libc::c_ulong = drm_iowr::<Crtc>(0xa1);
+const DRM_IOCTL_MODE_SETCRTC: libc::c_ulong = drm_iowr::<Crtc>(0xa2);
+const DRM_IOCTL_MODE_GETENCODER: libc::c_ulong = drm_iowr::<Encoder>(0xa6);
+const DRM_IOCTL_MODE_GETCONNECTOR: libc::c_ulong = drm_iowr::<Connector>(0xa7);
+const DRM_IOCTL_MODE_ADDFB: libc::c_ulong = drm_iowr::<FbCmd>(0xae);
+const DRM_IOCTL_MODE_RMFB: libc::c_ulong = drm_iowr::<u32>(0xaf);
+const DRM_IOCTL_MODE_PAGE_FLIP: libc::c_ulong = drm_iowr::<PageFlip>(0xb0);
+const DRM_IOCTL_MODE_CREATE_DUMB: libc::c_ulong = drm_iowr::<CreateDumb>(0xb2);
+const DRM_IOCTL_MODE_MAP_DUMB: libc::c_ulong = drm_iowr::<MapDumb>(0xb3);
+const DRM_IOCTL_MODE_DESTROY_DUMB: libc::c_ulong = drm_iowr::<DestroyDumb>(0xb4);
+
+const DRM_MODE_CONNECTED: u32 = 1;
+const DRM_MODE_TYPE_PREFERRED: u32 = 1 << 3;
+const DRM_MODE_PAGE_FLIP_EVENT: u32 = 0x01;
+const DRM_EVENT_FLIP_COMPLETE: u32 = 0x02;
+
+#[repr(C)]
+#[derive(Default)]
+struct CardRes {
+    fb_id_ptr: u64,
+    crtc_id_ptr: u64,
+    connector_id_ptr: u64,
+    encoder_id_ptr: u64,
+    count_fbs: u32,
+    count_crtcs: u32,
+    count_connectors: u32,
+    count_encoders: u32,
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Default)]
+struct ModeInfo {
+    clock: u32,
+    hdisplay: u16,
+    hsync_start: u16,
+    hsync_end: u16,
+    htotal: u16,
+    hskew: u16,
+    vdisplay: u16,
+    vsync_start: u16,
+    vsync_end: u16,
+    vtotal: u16,
+    vscan: u16,
+    vrefresh: u32,
+    flags: u32,
+    type_: u32,
+    name: [u8; 32],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Connector {
+    encoders_ptr: u64,
+    modes_ptr: u64,
+    props_ptr: u64,
+    prop_values_ptr: u64,
+    count_modes: u32,
+    count_props: u32,
+    count_encoders: u32,
+    encoder_id: u32,
+    connector_id: u32,
+    connector_type: u32,
+    connector_type_id: u32,
+    connection: u32,
+    mm_width: u32,
+    mm_height: u32,
+    subpixel: u32,
+    pad: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Encoder {
+    encoder_id: u32,
+    encoder_type: u32,
+    crtc_id: u32,
+    possible_crtcs: u32,
+    possible_clones: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Crtc {
+    set_connectors_ptr: u64,
+    count_connectors: u32,
+    crtc_id: u32,
+    fb_id: u32,
+    x: u32,
+    y: u32,
+    gamma_size: u32,
+    mode_valid: u32,
+    mode: ModeInfo,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct FbCmd {
+    fb_id: u32,
+    width: u32,
+    height: u32,
+    pitch: u32,
+    bpp: u32,
+    depth: u32,
+    handle: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct PageFlip {
+    crtc_id: u32,
+    fb_id: u32,
+    flags: u32,
+    reserved: u32,
+    user_data: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CreateDumb {
+    height: u32,
+    width: u32,
+    bpp: u32,
+    flags: u32,
+    handle: u32,
+    pitch: u32,
+    size: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapDumb {
+    handle: u32,
+    pad: u32,
+    offset: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct DestroyDumb {
+    handle: u32,
+}
@@ -0,0 +1,199 @@
+use crate::background::Background;
+use crate::staging::StagingPool;
+use crate::viewport::Viewport;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use std::any::Any;
+use std::time::Duration;
+
+/// What layers need to know about the frame being drawn.
+#[derive(Debug, Clone)]
+pub struct FrameInfo {
+    /// The instant being displayed.
+    pub time: DateTime<Utc>,
+    /// The time of day at that instant in the displayed timezone.
+    pub local_time: NaiveTime,
+    /// The date at that instant in the displayed timezone.
+    pub local_date: NaiveDate,
+    /// IANA name of the displayed timezone, or `None` for the system's.
+    pub timezone: Option<&'static str>,
+}
+
+impl FrameInfo {
+    /// The frame at the last multiple of `interval` since the Unix epoch, e.g. the start of the
+    /// minute for an interval of 60 seconds.
+    pub fn floor(&self, interval: Duration) -> Self {
+        let time = floor_time(&self.time, interval);
+        let local = self.local_date.and_time(self.local_time) - (self.time - time);
+        Self {
+            time,
+            local_time: local.time(),
+            local_date: local.date(),
+            timezone: self.timezone,
+        }
+    }
+}
+
+/// Frames drawn more often than this count as smooth animation, e.g. with the redraw interval set
+/// below a second, which sweeping layers are updated for at the exact time of each frame.
+const SMOOTH_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The interval that `layer` is updated at when frames are drawn every `frame_interval`: its
+/// tick interval, unless it sweeps (see [`Layer::sweeps`]) and frames are drawn smoothly, in which
+/// case every frame.
+pub fn update_interval(layer: &dyn Layer, frame_interval: Duration) -> Duration {
+    let tick_interval = layer.tick_interval();
+    if layer.sweeps() && frame_interval < SMOOTH_FRAME_INTERVAL.min(tick_interval) {
+        Duration::ZERO
+    } else {
+        tick_interval
+    }
+}
+
+/// Rounds `time` down to a multiple of `interval` since the Unix epoch. A zero interval leaves it
+/// unchanged.
+pub fn floor_time(time: &DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let interval = interval.as_nanos() as i128;
+    if interval == 0 {
+        return *time;
+    }
+    let nanos = time.timestamp() as i128 * 1_000_000_000 + time.timestamp_subsec_nanos() as i128;
+    *time - chrono::Duration::nanoseconds(nanos.rem_euclid(interval) as i64)
+}
+
+/// A part of a layer that can take the keyboard focus, such as a timer, with the actions that
+/// the keys do to it while it has the focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Control {
+    /// Sent to [`Layer::handle_action`] by Enter, e.g. `stopwatch.toggle`.
+    pub activate: &'static str,
+    /// Sent by Delete or Backspace, if the control can be reset.
+    pub reset: Option<&'static str>,
+}
+
+/// Something drawn on top of the background, such as the globe or the clock face.
+///
+/// Layers are drawn in order in one render pass, which the [`Background`] clears the target in.
+/// Anything a layer uploads to the GPU is written in [`Layer::prepare`], before the pass begins,
+/// through a [`StagingPool`] that all layers share.
+pub trait Layer {
+    /// Name used to refer to the layer, e.g. when toggling its visibility.
+    fn name(&self) -> &str;
+
+    /// Shader assets used by the layer, which are watched for changes during development.
+    fn shaders(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Recreates the layer's render pipelines from the current shader sources. On failure, the
+    /// previous pipelines are kept.
+    fn reload_shaders(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// How often the layer changes as the displayed time passes. The app updates it with the time
+    /// rounded down with [`FrameInfo::floor`], and only draws a new frame when that changes for
+    /// some layer. Zero, the default, updates it for every frame.
+    fn tick_interval(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Whether the layer can show the instants between its ticks, e.g. hands that sweep rather
+    /// than step, which it is then updated with when frames are drawn smoothly. See
+    /// [`update_interval`].
+    fn sweeps(&self) -> bool {
+        false
+    }
+
+    fn update(&mut self, frame: &FrameInfo);
+
+    /// Called with the number of physical pixels per logical pixel of the target, e.g. when a
+    /// window moves to a monitor with other scaling, for layers that draw images of their own to
+    /// match its resolution.
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+
+    /// Called with how many times as large as configured to draw text, independent of the scale
+    /// factor, e.g. to read it from across a room.
+    fn set_text_scale(&mut self, _scale: f32) {}
+
+    /// Called when the scene's size or placement in the target changes, e.g. when a window is
+    /// resized or the camera moves, for layers that draw images of their own to match its
+    /// resolution or place text over the scene.
+    fn resized(&mut self, _viewport: &Viewport) {}
+
+    /// Carries out an action named by the app, e.g. for a hotkey, such as `pomodoro.toggle`.
+    /// Returns whether the layer knows the action.
+    fn handle_action(&mut self, _action: &str) -> bool {
+        false
+    }
+
+    /// The parts of the layer that can take the keyboard focus, in the order that Tab moves
+    /// through them.
+    fn controls(&self) -> Vec<Control> {
+        Vec::new()
+    }
+
+    /// Called with the index in [`Layer::controls`] of the control that has the keyboard focus,
+    /// or `None` when the layer has none, to draw an indicator around it.
+    fn set_focus(&mut self, _control: Option<usize>) {}
+
+    /// A short sentence about what the layer shows that is not read from the clock itself, for
+    /// assistive technologies, e.g. the time left on a countdown. It should change at most once
+    /// a minute while nothing is done, so that it is not read out constantly.
+    fn describe(&self) -> Option<String> {
+        None
+    }
+
+    /// Takes what the layer should remember when it is created again, e.g. for another theme or
+    /// graphics device, which is then given to the new layer with [`Layer::restore_state`].
+    fn take_state(&mut self) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn restore_state(&mut self, _state: Box<dyn Any>) {}
+
+    /// Picks up anything the layer loads in the background. Returns whether that changed how the
+    /// layer looks, in which case it should be drawn again.
+    fn poll(&mut self) -> bool {
+        false
+    }
+
+    /// Blocks until everything the layer loads in the background is ready, e.g. before drawing a
+    /// single image of it.
+    fn finish_loading(&mut self) {}
+
+    /// Records writing what the next [`Layer::draw`] needs to the GPU, such as textures and
+    /// uniforms, into `encoder`.
+    fn prepare(&mut self, _encoder: &mut wgpu::CommandEncoder, _staging: &mut StagingPool) {}
+
+    /// Bytes of texture data uploaded to the GPU by the last call to [`Layer::prepare`].
+    fn uploaded_bytes(&self) -> u64 {
+        0
+    }
+
+    /// Bytes of GPU memory held by the layer's textures and buffers, for reporting.
+    fn gpu_memory(&self) -> u64 {
+        0
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport);
+}
+
+/// Prepares `layers`, and draws them over `background` into `frame_view` in one render pass.
+pub fn draw_layers(
+    encoder: &mut wgpu::CommandEncoder,
+    staging: &mut StagingPool,
+    frame_view: &wgpu::TextureView,
+    background: &Background,
+    layers: &mut [Box<dyn Layer>],
+    viewport: &Viewport,
+) {
+    staging.recall();
+    for layer in layers.iter_mut() {
+        layer.prepare(encoder, staging);
+    }
+    staging.finish();
+    let mut render_pass = background.begin_render_pass(encoder, frame_view);
+    for layer in layers.iter() {
+        layer.draw(&mut render_pass, viewport);
+    }
+}
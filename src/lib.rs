@@ -0,0 +1,145 @@
+//! Globe and 24-hour clock face widgets, for drawing into any wgpu render target.
+
+pub mod assets;
+pub mod astronomy;
+pub mod background;
+pub mod banner;
+#[cfg(feature = "calendar")]
+pub mod calendar;
+pub mod camera;
+pub mod clock_face;
+#[cfg(feature = "clock-offset")]
+pub mod clock_offset;
+#[cfg(feature = "date-strip")]
+pub mod date_strip;
+#[cfg(feature = "daylight-stats")]
+pub mod daylight_stats;
+#[cfg(feature = "digital-clock")]
+pub mod digital_clock;
+pub mod disc;
+pub mod globe;
+pub mod gps;
+pub mod layer;
+pub mod locale;
+pub mod location;
+#[cfg(feature = "markers")]
+pub mod markers;
+pub mod model;
+pub mod night_light;
+pub mod notification;
+pub mod ntp;
+pub mod palette;
+#[cfg(feature = "pomodoro")]
+pub mod pomodoro;
+pub mod process;
+pub mod profiler;
+pub mod registry;
+pub mod ring;
+pub mod screenshot;
+pub mod staging;
+#[cfg(feature = "sun-countdown")]
+pub mod sun_countdown;
+#[cfg(all(test, any(feature = "pomodoro", feature = "timers")))]
+mod test_support;
+pub mod text;
+pub mod text_overlay;
+pub mod texture;
+pub mod theme;
+#[cfg(feature = "ticker")]
+pub mod ticker;
+pub mod time_source;
+#[cfg(feature = "time-sync")]
+pub mod time_sync;
+#[cfg(feature = "timers")]
+pub mod timers;
+pub mod viewport;
+#[cfg(feature = "weather")]
+pub mod weather;
+pub mod zoneinfo;
+
+pub use self::assets::Assets;
+pub use self::background::Background;
+pub use self::banner::Banners;
+#[cfg(feature = "calendar")]
+pub use self::calendar::Calendar;
+pub use self::camera::Camera;
+pub use self::clock_face::ClockFace;
+#[cfg(feature = "clock-offset")]
+pub use self::clock_offset::ClockOffset;
+#[cfg(feature = "date-strip")]
+pub use self::date_strip::DateStrip;
+#[cfg(feature = "daylight-stats")]
+pub use self::daylight_stats::DaylightStats;
+#[cfg(feature = "digital-clock")]
+pub use self::digital_clock::DigitalClock;
+pub use self::disc::Disc;
+pub use self::globe::Globe;
+pub use self::layer::{Control, FrameInfo, Layer};
+pub use self::locale::Locale;
+pub use self::location::Location;
+#[cfg(feature = "markers")]
+pub use self::markers::Markers;
+pub use self::night_light::NightLight;
+pub use self::palette::Palette;
+#[cfg(feature = "pomodoro")]
+pub use self::pomodoro::Pomodoro;
+pub use self::profiler::GpuProfiler;
+pub use self::registry::{LayerContext, LayerRegistry, SharedState};
+pub use self::ring::Ring;
+pub use self::staging::StagingPool;
+#[cfg(feature = "sun-countdown")]
+pub use self::sun_countdown::SunCountdown;
+pub use self::text::{Corner, TextLayer, TextStyle};
+pub use self::text_overlay::{Readout, TextOverlay};
+pub use self::theme::Theme;
+#[cfg(feature = "ticker")]
+pub use self::ticker::Ticker;
+pub use self::time_source::TimeSource;
+#[cfg(feature = "time-sync")]
+pub use self::time_sync::TimeSync;
+#[cfg(feature = "timers")]
+pub use self::timers::Timers;
+pub use self::viewport::Viewport;
+#[cfg(feature = "weather")]
+pub use self::weather::Weather;
+
+// The app itself, which Android loads from this library rather than from an executable. Its
+// modules refer to each other through the crate root, and to the widgets by the crate's name.
+#[cfg(target_os = "android")]
+extern crate self as global_clock;
+#[cfg(target_os = "android")]
+#[path = "main.rs"]
+mod app;
+#[cfg(target_os = "android")]
+use self::app::*;
+
+/// Runs the app, for the shared library in `android/` that Android loads it from.
+#[cfg(target_os = "android")]
+pub fn android_main(android_app: winit::platform::android::activity::AndroidApp) {
+    app::android_main(android_app);
+}
+
+use anyhow::anyhow;
+use std::sync::Arc;
+
+pub type GraphicsContext = Arc<GraphicsContextInner>;
+
+/// The device that the widgets are created on, and the format of the textures they draw into.
+pub struct GraphicsContextInner {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub render_format: wgpu::TextureFormat,
+}
+
+impl GraphicsContextInner {
+    /// Runs `f`, returning the validation errors it causes (such as shader compile errors) instead
+    /// of panicking on them.
+    pub fn validate<T>(&self, f: impl FnOnce() -> T) -> anyhow::Result<T> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let value = f();
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(err) => Err(anyhow!("{}", err)),
+            None => Ok(value),
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use crate::text::Corner;
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Conventions for displaying times and dates in text.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LocaleConfig {
+    /// BCP 47 tag such as `en-US`, or the system locale if unset. In builds with ICU, Unicode
+    /// extensions such as `-u-nu-arab` for Arabic-Indic digits are honored.
+    pub name: Option<String>,
+    /// Either `12` or `24`.
+    pub hour_cycle: Option<HourCycle>,
+    pub date_order: Option<DateOrder>,
+    pub first_weekday: Option<Weekday>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum HourCycle {
+    H12,
+    H24,
+}
+
+impl TryFrom<u8> for HourCycle {
+    type Error = String;
+
+    fn try_from(hours: u8) -> Result<Self, String> {
+        match hours {
+            12 => Ok(Self::H12),
+            24 => Ok(Self::H24),
+            _ => Err(format!("hour cycle must be 12 or 24, not {}", hours)),
+        }
+    }
+}
+
+impl From<HourCycle> for u8 {
+    fn from(cycle: HourCycle) -> u8 {
+        match cycle {
+            HourCycle::H12 => 12,
+            HourCycle::H24 => 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    Dmy,
+    Mdy,
+    Ymd,
+}
+
+/// Locale conventions with every setting resolved, either from the config or from the locale.
+///
+/// In builds with the `icu` feature, the conventions, names and digits come from the Unicode CLDR
+/// data through ICU. Otherwise they come from a table of regions, with English names and ASCII
+/// digits.
+#[derive(Debug, Clone)]
+pub struct Locale {
+    pub name: String,
+    pub hour_cycle: HourCycle,
+    pub date_order: DateOrder,
+    pub first_weekday: Weekday,
+    /// The digits from 0 to 9 in the locale's numbering system.
+    pub digits: [char; 10],
+    /// Whether the locale's script is written from right to left, e.g. Arabic or Hebrew, in which
+    /// case readouts are placed in the mirrored corner.
+    pub right_to_left: bool,
+    /// The locale with the hour cycle set, if ICU knows it.
+    #[cfg(feature = "icu")]
+    icu: Option<icu::locid::Locale>,
+}
+
+// Regional conventions, by ISO 3166 country code, for locales that ICU does not know or in builds
+// without it. Everywhere else defaults to a 24-hour clock, day-month-year dates, and weeks
+// starting on Monday.
+const HOUR_CYCLE_12: &[&str] = &[
+    "US", "CA", "AU", "NZ", "IN", "PH", "PK", "BD", "EG", "SA", "AE", "JO", "KW", "MY",
+];
+const MONTH_FIRST: &[&str] = &["US", "PH", "FM", "PW", "MH"];
+const YEAR_FIRST: &[&str] = &["CN", "JP", "KR", "TW", "HU", "LT", "MN", "IR", "SE"];
+const SUNDAY_FIRST: &[&str] = &[
+    "US", "CA", "JP", "BR", "MX", "IL", "IN", "PH", "KR", "TW", "HK", "ZA", "AU", "PE", "CO",
+];
+const SATURDAY_FIRST: &[&str] = &["EG", "SA", "AE", "IR", "JO", "KW", "QA", "SY"];
+
+// Languages written from right to left, by ISO 639 code, for builds without ICU.
+const RIGHT_TO_LEFT: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "iw", "ks", "ku", "ps", "sd", "syr", "ug", "ur", "yi",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+impl Locale {
+    pub fn from_config(config: &LocaleConfig) -> Self {
+        let name = config
+            .name
+            .clone()
+            .or_else(sys_locale::get_locale)
+            .unwrap_or_else(|| "en-US".into());
+        let region = region_of(&name);
+        let in_region = |list: &[&str]| region.as_deref().is_some_and(|r| list.contains(&r));
+        #[cfg(feature = "icu")]
+        let icu = cldr::parse(&name);
+        #[cfg(feature = "icu")]
+        let cldr = icu.as_ref().map(cldr::conventions).unwrap_or_default();
+        #[cfg(not(feature = "icu"))]
+        let cldr = Conventions::default();
+
+        let hour_cycle =
+            config
+                .hour_cycle
+                .or(cldr.hour_cycle)
+                .unwrap_or(if in_region(HOUR_CYCLE_12) {
+                    HourCycle::H12
+                } else {
+                    HourCycle::H24
+                });
+        let date_order =
+            config
+                .date_order
+                .or(cldr.date_order)
+                .unwrap_or(if in_region(MONTH_FIRST) {
+                    DateOrder::Mdy
+                } else if in_region(YEAR_FIRST) {
+                    DateOrder::Ymd
+                } else {
+                    DateOrder::Dmy
+                });
+        let first_weekday =
+            config
+                .first_weekday
+                .or(cldr.first_weekday)
+                .unwrap_or(if in_region(SUNDAY_FIRST) {
+                    Weekday::Sun
+                } else if in_region(SATURDAY_FIRST) {
+                    Weekday::Sat
+                } else {
+                    Weekday::Mon
+                });
+        let digits = cldr
+            .digits
+            .unwrap_or(['0', '1', '2', '3', '4', '5', '6', '7', '8', '9']);
+        let right_to_left = cldr
+            .right_to_left
+            .unwrap_or_else(|| RIGHT_TO_LEFT.contains(&language_of(&name).as_str()));
+
+        Self {
+            name,
+            hour_cycle,
+            date_order,
+            first_weekday,
+            digits,
+            right_to_left,
+            #[cfg(feature = "icu")]
+            icu: icu.map(|locale| cldr::with_hour_cycle(locale, hour_cycle)),
+        }
+    }
+
+    /// `text` with its ASCII digits in the locale's numbering system, e.g. for numbers formatted
+    /// with `format!`.
+    pub fn localize_digits(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| match c.to_digit(10) {
+                Some(digit) if c.is_ascii_digit() => self.digits[digit as usize],
+                _ => c,
+            })
+            .collect()
+    }
+
+    pub fn format_time(&self, time: &NaiveTime, seconds: bool) -> String {
+        #[cfg(feature = "icu")]
+        if let Some(text) = self
+            .icu
+            .as_ref()
+            .and_then(|l| cldr::format_time(l, time, seconds))
+        {
+            return text;
+        }
+        let (hour, suffix) = match self.hour_cycle {
+            HourCycle::H24 => (time.hour(), ""),
+            HourCycle::H12 => {
+                let (pm, hour) = time.hour12();
+                (hour, if pm { " PM" } else { " AM" })
+            }
+        };
+        if seconds {
+            format!(
+                "{:02}:{:02}:{:02}{}",
+                hour,
+                time.minute(),
+                time.second(),
+                suffix
+            )
+        } else {
+            format!("{:02}:{:02}{}", hour, time.minute(), suffix)
+        }
+    }
+
+    /// The date with the weekday and month spelled out, e.g. `Sunday, 31 December 2000`. Without
+    /// ICU, the names are in English.
+    pub fn format_long_date(&self, date: &NaiveDate) -> String {
+        #[cfg(feature = "icu")]
+        if let Some(text) = self
+            .icu
+            .as_ref()
+            .and_then(|l| cldr::format_long_date(l, date))
+        {
+            return text;
+        }
+        let weekday = WEEKDAY_NAMES[date.weekday().num_days_from_monday() as usize];
+        let month = MONTH_NAMES[date.month0() as usize];
+        let (y, d) = (date.year(), date.day());
+        match self.date_order {
+            DateOrder::Dmy => format!("{}, {} {} {}", weekday, d, month, y),
+            DateOrder::Mdy => format!("{}, {} {}, {}", weekday, month, d, y),
+            DateOrder::Ymd => format!("{}, {} {} {}", weekday, y, month, d),
+        }
+    }
+
+    /// The month and year of the date, e.g. `December 2000`. Without ICU, the name is in English.
+    pub fn format_month(&self, date: &NaiveDate) -> String {
+        #[cfg(feature = "icu")]
+        if let Some(text) = self.icu.as_ref().and_then(|l| cldr::format_month(l, date)) {
+            return text;
+        }
+        let month = MONTH_NAMES[date.month0() as usize];
+        match self.date_order {
+            DateOrder::Ymd => format!("{} {}", date.year(), month),
+            DateOrder::Dmy | DateOrder::Mdy => format!("{} {}", month, date.year()),
+        }
+    }
+
+    /// The abbreviated name of the weekday, e.g. `Sun`, or without ICU the first two letters of
+    /// its English name, e.g. `Su`.
+    pub fn short_weekday(&self, weekday: Weekday) -> String {
+        #[cfg(feature = "icu")]
+        if let Some(text) = self
+            .icu
+            .as_ref()
+            .and_then(|l| cldr::short_weekday(l, weekday))
+        {
+            return text;
+        }
+        WEEKDAY_NAMES[weekday.num_days_from_monday() as usize][..2].to_owned()
+    }
+
+    /// The corner to place a readout set to be in `corner` in: the mirrored one if the locale is
+    /// right-to-left.
+    pub fn corner(&self, corner: Corner) -> Corner {
+        if self.right_to_left {
+            corner.mirrored()
+        } else {
+            corner
+        }
+    }
+
+    /// How long ago something happened `minutes` ago, e.g. `5 min ago` or `3 h ago` from two hours
+    /// on. Without ICU, the units are in English.
+    pub fn format_age(&self, minutes: i64) -> String {
+        #[cfg(feature = "icu")]
+        if let Some(text) = self.icu.as_ref().and_then(|l| cldr::format_age(l, minutes)) {
+            return text;
+        }
+        self.localize_digits(&if minutes < 120 {
+            format!("{} min ago", minutes)
+        } else {
+            format!("{} h ago", minutes / 60)
+        })
+    }
+
+    /// The date in digits, in the order of [`Self::date_order`].
+    pub fn format_date(&self, date: &NaiveDate) -> String {
+        let (y, m, d) = (date.year(), date.month(), date.day());
+        self.localize_digits(&match self.date_order {
+            DateOrder::Dmy => format!("{:02}.{:02}.{}", d, m, y),
+            DateOrder::Mdy => format!("{:02}/{:02}/{}", m, d, y),
+            DateOrder::Ymd => format!("{}-{:02}-{:02}", y, m, d),
+        })
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sample = NaiveDate::from_ymd_opt(2000, 12, 31)
+            .unwrap()
+            .and_hms_opt(18, 30, 0)
+            .unwrap();
+        write!(
+            f,
+            "{} (e.g. {} {}, weeks start on {})",
+            self.name,
+            self.format_date(&sample.date()),
+            self.format_time(&sample.time(), false),
+            self.first_weekday,
+        )
+    }
+}
+
+/// Conventions of a locale that are known from elsewhere than the region tables.
+#[derive(Debug, Default)]
+struct Conventions {
+    hour_cycle: Option<HourCycle>,
+    date_order: Option<DateOrder>,
+    first_weekday: Option<Weekday>,
+    digits: Option<[char; 10]>,
+    right_to_left: Option<bool>,
+}
+
+/// Extracts the lowercase language subtag from a locale name like `en-US` or `en_US.UTF-8`.
+fn language_of(name: &str) -> String {
+    name.split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or(name)
+        .to_ascii_lowercase()
+}
+
+/// Extracts the uppercase region subtag from a locale name like `en-US` or `en_US.UTF-8`.
+fn region_of(name: &str) -> Option<String> {
+    let name = name.split(['.', '@']).next().unwrap_or(name);
+    name.split(['-', '_'])
+        .skip(1)
+        .find(|subtag| subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+        .map(|subtag| subtag.to_ascii_uppercase())
+}
+
+/// Conventions, names and digits from the Unicode CLDR, through ICU. Each returns `None` where ICU
+/// has no answer, in which case the conventions of the region tables or English names are used.
+#[cfg(feature = "icu")]
+mod cldr {
+    use super::{Conventions, DateOrder, HourCycle};
+    use chrono::{Datelike, NaiveDate, NaiveTime, Timelike, Weekday};
+    use icu::calendar::week::WeekCalculator;
+    use icu::calendar::{types::IsoWeekday, Date, DateTime, Iso};
+    use icu::datetime::options::{components, length};
+    use icu::datetime::{DateFormatter, DateTimeFormatter, TimeFormatter};
+    use icu::decimal::FixedDecimalFormatter;
+    use icu::experimental::relativetime::RelativeTimeFormatter;
+    use icu::locid::extensions::unicode::{key, value};
+    use icu::locid::Locale;
+    use icu::locid_transform::{Direction, LocaleDirectionality};
+    use writeable::Writeable;
+
+    /// Parses a locale name, either a BCP 47 tag or a POSIX name like `de_DE.UTF-8`.
+    pub fn parse(name: &str) -> Option<Locale> {
+        let name = name.split(['.', '@']).next().unwrap_or(name);
+        if name == "C" || name == "POSIX" {
+            return None;
+        }
+        name.replace('_', "-").parse().ok()
+    }
+
+    pub fn with_hour_cycle(mut locale: Locale, hour_cycle: HourCycle) -> Locale {
+        let cycle = match hour_cycle {
+            HourCycle::H12 => value!("h12"),
+            HourCycle::H24 => value!("h23"),
+        };
+        locale.extensions.unicode.keywords.set(key!("hc"), cycle);
+        locale
+    }
+
+    /// `locale` with ASCII digits, for reading conventions out of formatted samples.
+    fn latin(locale: &Locale) -> Locale {
+        let mut locale = locale.clone();
+        locale
+            .extensions
+            .unicode
+            .keywords
+            .set(key!("nu"), value!("latn"));
+        locale
+    }
+
+    fn iso(date: &NaiveDate, time: &NaiveTime) -> Option<DateTime<Iso>> {
+        DateTime::try_new_iso_datetime(
+            date.year(),
+            date.month() as u8,
+            date.day() as u8,
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+        )
+        .ok()
+    }
+
+    fn iso_date(date: &NaiveDate) -> Option<Date<Iso>> {
+        Date::try_new_iso_date(date.year(), date.month() as u8, date.day() as u8).ok()
+    }
+
+    pub fn conventions(locale: &Locale) -> Conventions {
+        Conventions {
+            hour_cycle: hour_cycle(locale),
+            date_order: date_order(locale),
+            first_weekday: first_weekday(locale),
+            digits: digits(locale),
+            right_to_left: LocaleDirectionality::new()
+                .get(&locale.id)
+                .map(|direction| direction == Direction::RightToLeft),
+        }
+    }
+
+    fn hour_cycle(locale: &Locale) -> Option<HourCycle> {
+        let evening = NaiveTime::from_hms_opt(18, 0, 0)?;
+        let text = format_time(&latin(locale), &evening, false)?;
+        Some(if text.contains("18") {
+            HourCycle::H24
+        } else {
+            HourCycle::H12
+        })
+    }
+
+    /// The order of the day, month and year in the locale's short dates.
+    fn date_order(locale: &Locale) -> Option<DateOrder> {
+        let formatter =
+            DateFormatter::try_new_with_length(&latin(locale).into(), length::Date::Short).ok()?;
+        let date = iso_date(&NaiveDate::from_ymd_opt(2000, 12, 31)?)?.to_any();
+        let text = formatter.format_to_string(&date).ok()?;
+        let fields: String = text
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|number| !number.is_empty())
+            .map(|number| match number {
+                "31" => 'd',
+                "12" => 'm',
+                _ => 'y',
+            })
+            .collect();
+        match fields.as_str() {
+            "dmy" => Some(DateOrder::Dmy),
+            "mdy" => Some(DateOrder::Mdy),
+            "ymd" => Some(DateOrder::Ymd),
+            _ => None,
+        }
+    }
+
+    fn first_weekday(locale: &Locale) -> Option<Weekday> {
+        let calculator = WeekCalculator::try_new(&locale.into()).ok()?;
+        Some(match calculator.first_weekday {
+            IsoWeekday::Monday => Weekday::Mon,
+            IsoWeekday::Tuesday => Weekday::Tue,
+            IsoWeekday::Wednesday => Weekday::Wed,
+            IsoWeekday::Thursday => Weekday::Thu,
+            IsoWeekday::Friday => Weekday::Fri,
+            IsoWeekday::Saturday => Weekday::Sat,
+            IsoWeekday::Sunday => Weekday::Sun,
+        })
+    }
+
+    fn digits(locale: &Locale) -> Option<[char; 10]> {
+        let formatter = FixedDecimalFormatter::try_new(&locale.into(), Default::default()).ok()?;
+        let mut digits = ['0'; 10];
+        for (digit, c) in digits.iter_mut().enumerate() {
+            *c = formatter
+                .format_to_string(&(digit as i32).into())
+                .chars()
+                .next()?;
+        }
+        Some(digits)
+    }
+
+    pub fn format_time(locale: &Locale, time: &NaiveTime, seconds: bool) -> Option<String> {
+        let length = if seconds {
+            length::Time::Medium
+        } else {
+            length::Time::Short
+        };
+        let formatter = TimeFormatter::try_new_with_length(&locale.into(), length).ok()?;
+        let date = NaiveDate::from_ymd_opt(2000, 1, 1)?;
+        Some(formatter.format_to_string(&iso(&date, time)?))
+    }
+
+    pub fn format_long_date(locale: &Locale, date: &NaiveDate) -> Option<String> {
+        let formatter =
+            DateFormatter::try_new_with_length(&locale.into(), length::Date::Full).ok()?;
+        formatter.format_to_string(&iso_date(date)?.to_any()).ok()
+    }
+
+    fn format_components(
+        locale: &Locale,
+        bag: components::Bag,
+        date: &NaiveDate,
+    ) -> Option<String> {
+        let formatter = DateTimeFormatter::try_new_experimental(&locale.into(), bag.into()).ok()?;
+        let time = NaiveTime::from_hms_opt(12, 0, 0)?;
+        formatter.format_to_string(&iso(date, &time)?.to_any()).ok()
+    }
+
+    pub fn format_month(locale: &Locale, date: &NaiveDate) -> Option<String> {
+        let mut bag = components::Bag::default();
+        bag.year = Some(components::Year::Numeric);
+        bag.month = Some(components::Month::Long);
+        format_components(locale, bag, date)
+    }
+
+    pub fn format_age(locale: &Locale, minutes: i64) -> Option<String> {
+        let (formatter, value) = if minutes < 120 {
+            (
+                RelativeTimeFormatter::try_new_short_minute(&locale.into(), Default::default()),
+                minutes,
+            )
+        } else {
+            (
+                RelativeTimeFormatter::try_new_short_hour(&locale.into(), Default::default()),
+                minutes / 60,
+            )
+        };
+        // Negative for the past.
+        Some(
+            formatter
+                .ok()?
+                .format((-value).into())
+                .write_to_string()
+                .into_owned(),
+        )
+    }
+
+    pub fn short_weekday(locale: &Locale, weekday: Weekday) -> Option<String> {
+        let mut bag = components::Bag::default();
+        bag.weekday = Some(components::Text::Short);
+        // A week that starts on a Monday.
+        let monday = NaiveDate::from_ymd_opt(2001, 1, 1)?;
+        let date = monday + chrono::Duration::days(weekday.num_days_from_monday().into());
+        format_components(locale, bag, &date)
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// A place on Earth, such as the user's home.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Location {
+    /// Degrees north of the equator; negative in the south.
+    pub latitude: f64,
+    /// Degrees east of Greenwich; negative in the west.
+    pub longitude: f64,
+    /// Name shown with information about the place, if set.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl Location {
+    /// Whether the coordinates are on Earth.
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude) && (-180.0..=180.0).contains(&self.longitude)
+    }
+
+    /// Distance to `other` along the surface of the Earth, taken as a sphere, in kilometers.
+    pub fn distance_km(&self, other: &Location) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let half_lat = (lat2 - lat1) / 2.0;
+        let half_lon = (other.longitude - self.longitude).to_radians() / 2.0;
+        let a = half_lat.sin().powi(2) + lat1.cos() * lat2.cos() * half_lon.sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
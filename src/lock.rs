@@ -0,0 +1,494 @@
+use crate::cli::Args;
+use crate::clock_window::{create_layers, Resources};
+use crate::config::Config;
+use crate::display;
+use crate::paths::Paths;
+use crate::timezone::Timezone;
+use anyhow::Context;
+use global_clock::layer::{draw_layers, update_interval};
+use global_clock::{
+    time_source, Background, FrameInfo, GraphicsContext, Layer, StagingPool, TimeSource, Viewport,
+};
+use pollster::block_on;
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+    WaylandDisplayHandle, WaylandWindowHandle,
+};
+use smithay_client_toolkit::compositor::{CompositorHandler, CompositorState};
+use smithay_client_toolkit::output::{OutputHandler, OutputState};
+use smithay_client_toolkit::reexports::calloop::EventLoop;
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
+use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::session_lock::{
+    SessionLock, SessionLockHandler, SessionLockState, SessionLockSurface,
+    SessionLockSurfaceConfigure,
+};
+use smithay_client_toolkit::{
+    delegate_compositor, delegate_output, delegate_registry, delegate_session_lock,
+    registry_handlers,
+};
+use std::ffi::c_void;
+use std::process::{Child, Command};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wayland_client::globals::registry_queue_init;
+use wayland_client::protocol::{wl_output, wl_surface};
+use wayland_client::{Connection, Proxy, QueueHandle};
+
+/// How long to wait before starting the authenticator again after it failed, e.g. so as not to
+/// spin when it cannot run at all.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Locks the Wayland session with the `ext-session-lock` protocol, showing the clock on every
+/// output, until `authenticator` (a shell command, such as `fprintd-verify`) succeeds.
+///
+/// If the clock stops without unlocking, e.g. because it crashed, the compositor keeps the session
+/// locked.
+pub fn run(args: &Args, paths: &Paths, authenticator: &str) -> anyhow::Result<()> {
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let timezone = Timezone::from_config(config.timezone.as_deref())?;
+
+    let conn = Connection::connect_to_env().context("locking the screen needs Wayland")?;
+    let (globals, event_queue) =
+        registry_queue_init(&conn).context("failed to list the Wayland globals")?;
+    let qh = event_queue.handle();
+    let mut event_loop =
+        EventLoop::<Locker>::try_new().context("failed to create the event loop")?;
+    WaylandSource::new(conn.clone(), event_queue)
+        .insert(event_loop.handle())
+        .map_err(|err| anyhow::Error::new(err.error))
+        .context("failed to listen to the Wayland connection")?;
+
+    let mut locker = Locker {
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        compositor_state: CompositorState::bind(&globals, &qh)
+            .context("the compositor has no wl_compositor")?,
+        conn: conn.clone(),
+        instance: wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        }),
+        gpu: None,
+        session_lock: None,
+        surfaces: Vec::new(),
+        resources: Resources::from_config(&config, paths)?,
+        timezone,
+        time_source: time_source::from_settings(config.time, config.speed),
+        frame_interval: config.redraw.frame_interval(),
+        finished: false,
+        error: None,
+    };
+    locker.session_lock = Some(
+        SessionLockState::new(&globals, &qh)
+            .lock(&qh)
+            .context("the compositor does not support ext-session-lock")?,
+    );
+
+    let frame_interval = locker.frame_interval;
+    let mut next_frame = Instant::now();
+    let mut running: Option<Child> = None;
+    let mut retry_at = Instant::now();
+    loop {
+        event_loop
+            .dispatch(Some(Duration::from_millis(100)), &mut locker)
+            .context("lost the connection to the compositor")?;
+        if let Some(err) = locker.error.take() {
+            return Err(err);
+        }
+        let Some(session_lock) = &locker.session_lock else {
+            return Ok(());
+        };
+        if locker.finished {
+            if session_lock.is_locked() {
+                // E.g. another locker replaced this one.
+                return Ok(());
+            }
+            anyhow::bail!("the compositor refused to lock the session");
+        }
+        if !session_lock.is_locked() {
+            continue;
+        }
+
+        let now = Instant::now();
+        match &mut running {
+            None if now >= retry_at => {
+                running = Some(
+                    Command::new("sh")
+                        .arg("-c")
+                        .arg(authenticator)
+                        .spawn()
+                        .with_context(|| format!("failed to run {:?}", authenticator))?,
+                );
+            }
+            None => {}
+            Some(child) => {
+                if let Some(status) = child.try_wait()? {
+                    if status.success() {
+                        break;
+                    }
+                    log::info!("authentication failed ({})", status);
+                    running = None;
+                    retry_at = now + RETRY_DELAY;
+                }
+            }
+        }
+        if now >= next_frame {
+            locker.draw();
+            next_frame = now + frame_interval;
+        }
+    }
+
+    locker.surfaces.clear();
+    if let Some(session_lock) = locker.session_lock.take() {
+        session_lock.unlock();
+    }
+    // Make sure that the compositor has received the unlock request before exiting.
+    conn.roundtrip().context("failed to unlock the session")?;
+    Ok(())
+}
+
+/// The raw handles that wgpu creates a surface for a Wayland surface from.
+struct WaylandHandle {
+    display: *mut c_void,
+    surface: *mut c_void,
+}
+
+unsafe impl HasRawDisplayHandle for WaylandHandle {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        let mut handle = WaylandDisplayHandle::empty();
+        handle.display = self.display;
+        RawDisplayHandle::Wayland(handle)
+    }
+}
+
+unsafe impl HasRawWindowHandle for WaylandHandle {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let mut handle = WaylandWindowHandle::empty();
+        handle.surface = self.surface;
+        RawWindowHandle::Wayland(handle)
+    }
+}
+
+/// The graphics device, which is opened for the first lock surface.
+struct Gpu {
+    adapter: wgpu::Adapter,
+    gfx: GraphicsContext,
+    staging: StagingPool,
+    background: Background,
+}
+
+/// The clock on one output.
+struct LockSurface {
+    // Declared first, so that the wgpu surface is dropped before the Wayland surface.
+    scene: Option<Scene>,
+    lock_surface: SessionLockSurface,
+    output: wl_output::WlOutput,
+    /// The size configured by the compositor, in logical pixels.
+    size: (u32, u32),
+}
+
+struct Scene {
+    surface: wgpu::Surface,
+    viewport: Viewport,
+    layers: Vec<Box<dyn Layer>>,
+}
+
+struct Locker {
+    registry_state: RegistryState,
+    output_state: OutputState,
+    compositor_state: CompositorState,
+    conn: Connection,
+    instance: wgpu::Instance,
+    gpu: Option<Gpu>,
+    session_lock: Option<SessionLock>,
+    surfaces: Vec<LockSurface>,
+    resources: Resources,
+    timezone: Timezone,
+    time_source: Box<dyn TimeSource>,
+    frame_interval: Duration,
+    /// Set when the compositor ends the lock, or refuses it.
+    finished: bool,
+    /// Set when drawing fails, which stops the locker without unlocking.
+    error: Option<anyhow::Error>,
+}
+
+impl Locker {
+    fn add_surface(&mut self, output: wl_output::WlOutput, qh: &QueueHandle<Self>) {
+        let Some(session_lock) = &self.session_lock else {
+            return;
+        };
+        let surface = self.compositor_state.create_surface(qh);
+        self.surfaces.push(LockSurface {
+            scene: None,
+            lock_surface: session_lock.create_lock_surface(surface, &output, qh),
+            output,
+            size: (0, 0),
+        });
+    }
+
+    /// Sets up the surface at `index` for its configured size and the scale of its output, and
+    /// draws to it, as the compositor shows nothing on that output until then.
+    fn configure(&mut self, index: usize, scale: i32) -> anyhow::Result<()> {
+        let entry = &mut self.surfaces[index];
+        let wl_surface = entry.lock_surface.wl_surface();
+        wl_surface.set_buffer_scale(scale);
+        let width = entry.size.0 * scale as u32;
+        let height = entry.size.1 * scale as u32;
+
+        if entry.scene.is_none() {
+            let handle = WaylandHandle {
+                display: self.conn.backend().display_ptr() as *mut c_void,
+                surface: wl_surface.id().as_ptr() as *mut c_void,
+            };
+            // Safety: the Wayland surface outlives the wgpu surface, as they are dropped in that
+            // order.
+            let surface = unsafe { self.instance.create_surface(&handle) }
+                .context("failed to create a surface for the lock screen")?;
+            if self.gpu.is_none() {
+                // Device loss is not recovered from, so nothing checks for it.
+                let lost = Arc::new(AtomicBool::new(false));
+                let (adapter, gfx) =
+                    block_on(display::open_device(&self.instance, &surface, &lost, false))?;
+                let background = Background::new(&gfx, &self.resources.theme);
+                self.gpu = Some(Gpu {
+                    adapter,
+                    staging: StagingPool::new(&gfx),
+                    gfx,
+                    background,
+                });
+            }
+            let gfx = &self.gpu.as_ref().unwrap().gfx;
+            let viewport = Viewport::new(gfx, width, height, Default::default());
+            let names: Vec<String> = self.resources.registry.names().map(str::to_owned).collect();
+            let layers = create_layers(gfx, &viewport, &self.resources, &names, scale.into())?;
+            entry.scene = Some(Scene {
+                surface,
+                viewport,
+                layers,
+            });
+        }
+
+        let gpu = self.gpu.as_ref().unwrap();
+        let scene = entry.scene.as_mut().unwrap();
+        scene.viewport.resize(width, height);
+        for layer in &mut scene.layers {
+            layer.set_scale_factor(scale.into());
+            layer.resized(&scene.viewport);
+        }
+        let caps = scene.surface.get_capabilities(&gpu.adapter);
+        scene.surface.configure(
+            &gpu.gfx.device,
+            &wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: gpu.gfx.render_format,
+                width,
+                height,
+                present_mode: wgpu::PresentMode::Fifo,
+                alpha_mode: caps.alpha_modes[0],
+                view_formats: vec![],
+            },
+        );
+        self.draw();
+        Ok(())
+    }
+
+    fn draw(&mut self) {
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+        let time = self.time_source.now();
+        let frame = FrameInfo {
+            time,
+            local_time: self.timezone.time_of(&time),
+            local_date: self.timezone.date_of(&time),
+            timezone: self.timezone.name(),
+        };
+        for scene in self
+            .surfaces
+            .iter_mut()
+            .filter_map(|entry| entry.scene.as_mut())
+        {
+            let texture = match scene.surface.get_current_texture() {
+                Ok(texture) => texture,
+                Err(err) => {
+                    log::warn!("failed to draw the lock screen: {}", err);
+                    continue;
+                }
+            };
+            let view = texture.texture.create_view(&Default::default());
+            let mut encoder = gpu.gfx.device.create_command_encoder(&Default::default());
+            for layer in &mut scene.layers {
+                layer.poll();
+                layer.update(&frame.floor(update_interval(&**layer, self.frame_interval)));
+            }
+            draw_layers(
+                &mut encoder,
+                &mut gpu.staging,
+                &view,
+                &gpu.background,
+                &mut scene.layers,
+                &scene.viewport,
+            );
+            gpu.gfx.queue.submit([encoder.finish()]);
+            texture.present();
+        }
+    }
+
+    fn scale_of(&self, output: &wl_output::WlOutput) -> i32 {
+        self.output_state
+            .info(output)
+            .map_or(1, |info| info.scale_factor)
+            .max(1)
+    }
+}
+
+impl SessionLockHandler for Locker {
+    fn locked(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, _session_lock: SessionLock) {
+        log::info!("session locked");
+        for output in self.output_state.outputs().collect::<Vec<_>>() {
+            self.add_surface(output, qh);
+        }
+    }
+
+    fn finished(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _session_lock: SessionLock,
+    ) {
+        self.finished = true;
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        surface: SessionLockSurface,
+        configure: SessionLockSurfaceConfigure,
+        _serial: u32,
+    ) {
+        let Some(index) = self
+            .surfaces
+            .iter()
+            .position(|entry| entry.lock_surface.wl_surface() == surface.wl_surface())
+        else {
+            return;
+        };
+        self.surfaces[index].size = configure.new_size;
+        let scale = self.scale_of(&self.surfaces[index].output);
+        if let Err(err) = self.configure(index, scale) {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl CompositorHandler for Locker {
+    fn scale_factor_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        new_factor: i32,
+    ) {
+        let index = self
+            .surfaces
+            .iter()
+            .position(|entry| entry.lock_surface.wl_surface() == surface && entry.scene.is_some());
+        if let Some(index) = index {
+            if let Err(err) = self.configure(index, new_factor.max(1)) {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    fn transform_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_transform: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _time: u32,
+    ) {
+    }
+
+    fn surface_enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for Locker {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        // Outputs plugged in while locked need a lock surface too, or they show the compositor's
+        // fallback.
+        if self
+            .session_lock
+            .as_ref()
+            .is_some_and(SessionLock::is_locked)
+        {
+            self.add_surface(output, qh);
+        }
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        self.surfaces.retain(|entry| entry.output != output);
+    }
+}
+
+impl ProvidesRegistryState for Locker {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    registry_handlers![OutputState];
+}
+
+delegate_compositor!(Locker);
+delegate_output!(Locker);
+delegate_session_lock!(Locker);
+delegate_registry!(Locker);
@@ -0,0 +1,47 @@
+use muda::{AboutMetadata, Menu, PredefinedMenuItem, Submenu};
+
+/// Installs the application menu with the items that every macOS app has. It stays in place
+/// while the returned menu is alive.
+pub fn install_app_menu() -> anyhow::Result<Menu> {
+    let app_menu = Submenu::with_items(
+        "Global Clock",
+        true,
+        &[
+            &PredefinedMenuItem::about(
+                None,
+                Some(AboutMetadata {
+                    name: Some("Global Clock".into()),
+                    version: Some(env!("CARGO_PKG_VERSION").into()),
+                    ..Default::default()
+                }),
+            ),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::services(None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::hide(None),
+            &PredefinedMenuItem::hide_others(None),
+            &PredefinedMenuItem::show_all(None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::quit(None),
+        ],
+    )?;
+    let view_menu = Submenu::with_items("View", true, &[&PredefinedMenuItem::fullscreen(None)])?;
+    let window_menu = Submenu::with_items(
+        "Window",
+        true,
+        &[
+            &PredefinedMenuItem::minimize(None),
+            &PredefinedMenuItem::maximize(None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::close_window(None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::bring_all_to_front(None),
+        ],
+    )?;
+
+    let menu = Menu::with_items(&[&app_menu, &view_menu, &window_menu])?;
+    menu.init_for_nsapp();
+    // Lists the open windows at the end of this menu.
+    window_menu.set_as_windows_menu_for_nsapp();
+    Ok(menu)
+}
@@ -1,202 +1,1475 @@
-mod background;
-mod clock_face;
-mod globe;
-pub(crate) mod macros;
-mod viewport;
-
-use self::background::Background;
-use self::clock_face::ClockFace;
-use self::globe::Globe;
-use self::viewport::Viewport;
-use anyhow::Context;
-use chrono::{Local, Utc};
-use instant::{Duration, Instant};
+// On Android, this file is also a module of the library, which Android loads the app from. The
+// modules are visible to the library's root there, and the command-line entry points are unused,
+// as they are on iOS.
+#![cfg_attr(any(target_os = "android", target_os = "ios"), allow(dead_code))]
+
+#[cfg(feature = "accesskit")]
+pub(crate) mod accessibility;
+pub(crate) mod cli;
+pub(crate) mod clock_window;
+pub(crate) mod commands;
+pub(crate) mod config;
+pub(crate) mod contrast;
+pub(crate) mod control;
+#[cfg(target_os = "linux")]
+pub(crate) mod dbus;
+pub(crate) mod display;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub(crate) mod error_dialog;
+pub(crate) mod headless;
+#[cfg(target_os = "ios")]
+pub(crate) mod ios;
+pub(crate) mod ipc;
+#[cfg(target_os = "linux")]
+pub(crate) mod kms;
+#[cfg(target_os = "linux")]
+pub(crate) mod lock;
+#[cfg(target_os = "macos")]
+pub(crate) mod macos;
+pub(crate) mod monitor;
+pub(crate) mod paths;
+pub(crate) mod scheduler;
+pub(crate) mod screensaver;
+pub(crate) mod shader_watch;
+pub(crate) mod span;
+pub(crate) mod state;
+pub(crate) mod stream;
+pub(crate) mod timezone;
+#[cfg(feature = "tray")]
+pub(crate) mod tray;
+
+use self::cli::Args;
+use self::clock_window::{ClockWindow, Resources};
+use self::config::{Backdrop, Config, ExtraWindowConfig, SizeUnit};
+use self::control::{Command, Request, Response};
+use self::display::Display;
+use self::monitor::MonitorLayout;
+use self::paths::Paths;
+use self::scheduler::FrameScheduler;
+use self::screensaver::Screensaver;
+use self::state::State;
+use self::timezone::Timezone;
+use chrono::{DateTime, Duration, Utc};
+use clap::Parser;
+use global_clock::banner::{self, Level};
+use global_clock::layer::update_interval;
+use global_clock::time_source::{CorrectedTime, SmoothedTime, SystemTime, TimeOffset};
+#[cfg(target_os = "android")]
+use global_clock::Assets;
+use global_clock::{
+    gps, time_source, Globe, GraphicsContext, Location, StagingPool, Theme, TimeSource,
+};
+use instant::Instant;
 use pollster::block_on;
-use std::sync::Arc;
-use winit::dpi::LogicalSize;
-use winit::event::{Event, StartCause, WindowEvent};
-use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::{Window, WindowBuilder};
-
-pub type GraphicsContext = Arc<GraphicsContextInner>;
-
-pub struct GraphicsContextInner {
-    pub window: Window,
-    pub surface: wgpu::Surface,
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
-    pub surface_caps: wgpu::SurfaceCapabilities,
-    pub render_format: wgpu::TextureFormat,
-}
+use winit::application::ApplicationHandler;
+use winit::event::{ElementState, KeyEvent, MouseButton, StartCause, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+#[cfg(target_os = "android")]
+use winit::platform::android::activity::AndroidApp;
+use winit::window::{Window, WindowAttributes, WindowId};
 
-impl GraphicsContextInner {
-    async fn new(window: Window) -> anyhow::Result<Self> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
-        let surface =
-            unsafe { instance.create_surface(&window) }.context("failed to create surface")?;
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                ..Default::default()
-            })
-            .await
-            .context("failed to create adapter")?;
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await?;
+const TITLE: &str = "Global Clock";
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let render_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+/// How far the GPS receiver moves before the home location follows it, in kilometers, as that
+/// recreates the layers, e.g. fetching the weather again.
+const GPS_HOME_DISTANCE_KM: f64 = 5.0;
 
-        Ok(Self {
-            window,
-            surface,
-            device,
-            queue,
-            surface_caps,
-            render_format,
-        })
-    }
+/// Speeds that `[` and `]` step through, in multiples of real time: from a second to a day per
+/// second, forwards and backwards.
+const SPEEDS: [f64; 12] = [
+    -86400.0, -21600.0, -3600.0, -600.0, -60.0, -1.0, 1.0, 60.0, 600.0, 3600.0, 21600.0, 86400.0,
+];
+
+/// How much each press of `Ctrl+` or `Ctrl-` changes the text scale by.
+const TEXT_SCALE_STEP: f32 = 0.1;
+
+/// Identifies the windows to the window manager, e.g. as the Wayland app ID and the X11 window
+/// class, which desktops match with the `.desktop` file of the same name.
+#[cfg(target_os = "linux")]
+const APP_ID: &str = "global-clock";
+
+/// What is needed to start the app, which can only happen once the event loop is running.
+struct Startup {
+    config: Config,
+    resources: Resources,
+    paths: Paths,
+    monitor_layout: MonitorLayout,
+    screensaver: Option<screensaver::Mode>,
+    /// Receives the commands of the tray menu, if the tray icon is enabled.
+    #[cfg(feature = "tray")]
+    tray: Option<control::Controller>,
 }
 
 struct App {
+    paths: Paths,
+    /// Set while the app is in the background, e.g. on mobile platforms, when there is nothing to
+    /// draw to.
+    suspended: bool,
+    scheduler: FrameScheduler,
+    monitor_layout: MonitorLayout,
+    time_source: Box<dyn TimeSource>,
+    /// The speed to run the displayed time at again, while it is frozen by [`App::play_pause`].
+    paused_speed: Option<f64>,
+    /// The GPS receiver, if enabled.
+    gps: Option<gps::Receiver>,
+    /// The correction of the system clock to the GPS receiver's time, if the time is taken from
+    /// it.
+    gps_offset: Option<TimeOffset>,
+    /// Move the home location along with the GPS receiver.
+    home_from_gps: bool,
+    resources: Resources,
+    display: Display,
     gfx: GraphicsContext,
-    viewport: Viewport,
-    background: Background,
-    globe: Globe,
-    clock_face: ClockFace,
+    /// Uploads for every window's frames go through it.
+    staging: StagingPool,
+    /// The main window comes first. Closing it quits the app, and control commands apply to it.
+    windows: Vec<ClockWindow>,
+    shader_watcher: shader_watch::ShaderWatcher,
+    /// Cleared while the windows are hidden in the system tray.
+    visible: bool,
+    /// Hide the windows instead of quitting when the main window is closed.
+    close_to_tray: bool,
+    /// Set by a request to quit, which takes effect once it has been answered.
+    quit: bool,
+    #[cfg(feature = "tray")]
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    tray: Option<tray::Tray>,
+    #[cfg(target_os = "macos")]
+    _app_menu: muda::Menu,
+    /// Describes the main window to assistive technologies, if enabled.
+    #[cfg(feature = "accesskit")]
+    accessibility: Option<accessibility::Accessibility>,
+    /// Set when running as a screensaver, in which case the view is not saved and the windows
+    /// are not moved between monitors.
+    screensaver: Option<Screensaver>,
+    /// How many windows, from the first, show parts of one scene spanning the monitors. They
+    /// share a camera, and are not moved between monitors.
+    spanned: usize,
+    /// The text scale from the config, which the text scale hotkeys return to.
+    configured_text_scale: f32,
 }
 
 impl App {
-    async fn new(window: Window) -> anyhow::Result<Self> {
-        let gfx = Arc::new(GraphicsContextInner::new(window).await?);
-        let viewport = Viewport::new(&gfx);
-        let background = Background::new(&gfx);
-        let globe = Globe::new(&gfx, &viewport)?;
-        let clock_face = ClockFace::new(&gfx, &viewport)?;
+    async fn new(event_loop: &ActiveEventLoop, startup: Startup) -> anyhow::Result<Self> {
+        let Startup {
+            config,
+            resources,
+            paths,
+            mut monitor_layout,
+            screensaver,
+            #[cfg(feature = "tray")]
+            tray,
+        } = startup;
+        // The parts of one scene shown by the first windows, if they span the monitors.
+        let mut regions = Vec::new();
+        #[cfg(feature = "accesskit")]
+        let mut accessibility = None;
+        let (window, extra_windows) = match (screensaver, config.window.span) {
+            (Some(mode), _) => {
+                let mut windows = screensaver::window_attributes(mode, event_loop, TITLE)?
+                    .into_iter()
+                    .map(|attributes| {
+                        let window = event_loop.create_window(with_app_id(attributes))?;
+                        if let screensaver::Mode::Fullscreen = mode {
+                            window.set_cursor_visible(false);
+                        }
+                        // Every monitor shows the same clock as the main window.
+                        let extra = ExtraWindowConfig {
+                            title: Some(TITLE.to_owned()),
+                            ..Default::default()
+                        };
+                        Ok((window, extra))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let (window, _) = windows.remove(0);
+                (window, windows)
+            }
+            (None, Some(span)) => {
+                let mut windows = span::window_attributes(span, event_loop, TITLE)?
+                    .into_iter()
+                    .map(|(attributes, region)| {
+                        let window = event_loop.create_window(with_app_id(attributes))?;
+                        regions.push(region);
+                        let extra = ExtraWindowConfig {
+                            title: Some(TITLE.to_owned()),
+                            ..Default::default()
+                        };
+                        Ok((window, extra))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let (window, _) = windows.remove(0);
+                windows.extend(open_extra_windows(event_loop, &config)?);
+                (window, windows)
+            }
+            (None, None) => {
+                let mut attributes = monitor_layout
+                    .window_attributes(with_app_id(Window::default_attributes()), event_loop)
+                    .with_title(TITLE);
+                if config.widget.enabled {
+                    let backdrop = cfg!(windows) && config.widget.backdrop != Backdrop::None;
+                    attributes = attributes
+                        .with_decorations(false)
+                        .with_resizable(false)
+                        .with_transparent(config.widget.circular || backdrop);
+                    #[cfg(windows)]
+                    {
+                        use winit::platform::windows::{BackdropType, WindowAttributesExtWindows};
+                        attributes = attributes
+                            .with_skip_taskbar(!config.widget.show_in_taskbar)
+                            .with_system_backdrop(match config.widget.backdrop {
+                                Backdrop::None => BackdropType::Auto,
+                                Backdrop::Acrylic => BackdropType::TransientWindow,
+                                Backdrop::Mica => BackdropType::MainWindow,
+                            });
+                    }
+                }
+                if config.window.overlay {
+                    attributes = attributes.with_decorations(false).with_transparent(true);
+                }
+                #[cfg(target_os = "ios")]
+                {
+                    use winit::platform::ios::WindowAttributesExtIOS;
+                    attributes = attributes
+                        .with_prefers_status_bar_hidden(true)
+                        .with_prefers_home_indicator_hidden(true);
+                }
+                // AccessKit must be attached to the window before it is first shown.
+                #[cfg(feature = "accesskit")]
+                if config.accessibility.enabled {
+                    attributes = attributes.with_visible(false);
+                }
+                let window = event_loop.create_window(attributes)?;
+                #[cfg(feature = "accesskit")]
+                if config.accessibility.enabled {
+                    accessibility = Some(accessibility::Accessibility::new(
+                        event_loop,
+                        &window,
+                        &config.accessibility,
+                    ));
+                    window.set_visible(true);
+                }
+                (window, open_extra_windows(event_loop, &config)?)
+            }
+        };
+
+        let state = State::load(&paths);
+        let gps_offset = (config.gps.enabled && config.gps.time).then(TimeOffset::default);
+        let time_source = time_source_for(config.time, config.speed, gps_offset.as_ref());
+        let gps = if config.gps.enabled {
+            gps::Receiver::start(config.gps.source(), gps_offset.clone())
+                .map_err(|err| log::warn!("{:#}", err))
+                .ok()
+        } else {
+            None
+        };
+        let timezone = Timezone::from_config(config.timezone.as_deref())?;
+        let start = Instant::now();
+        // Decoding the globe's textures overlaps with opening the graphics device.
+        if config.texture_dir.is_none() {
+            Globe::preload(&resources.assets);
+        }
+        let (display, surface, gfx) = Display::new(window).await?;
+        let device_time = start.elapsed();
+        let title = |title: String| {
+            if display.is_software() {
+                format!("{} (software rendering)", title)
+            } else {
+                title
+            }
+        };
+        let all_layers = resources.registry.names().map(str::to_owned).collect();
+        let mut windows = vec![ClockWindow::new(
+            &gfx,
+            surface,
+            title(TITLE.to_owned()),
+            timezone,
+            state.camera,
+            all_layers,
+            &resources,
+        )?];
+        if screensaver.is_none() && regions.is_empty() {
+            windows[0].set_always_on_top(config.window.always_on_top);
+            if config.widget.enabled {
+                windows[0].make_widget(&gfx, &resources, &config.widget)?;
+            }
+            if config.window.overlay {
+                windows[0].make_overlay(&gfx);
+            }
+        }
+        for (window, extra) in extra_windows {
+            let surface = display.create_surface(window)?;
+            let timezone_name = extra.timezone.as_ref().or(config.timezone.as_ref());
+            let extra_title = extra.title.unwrap_or_else(|| {
+                format!(
+                    "{} - {}",
+                    TITLE,
+                    timezone_name.map_or("local time", String::as_str)
+                )
+            });
+            let mut window = ClockWindow::new(
+                &gfx,
+                surface,
+                title(extra_title),
+                Timezone::from_config(timezone_name.map(String::as_str))?,
+                Default::default(),
+                extra.layers,
+                &resources,
+            )?;
+            window.set_always_on_top(extra.always_on_top);
+            windows.push(window);
+        }
+        for window in &mut windows {
+            window.mini_config = config.mini.clone();
+            window.keep_physical_size =
+                config.window.size_unit == SizeUnit::Physical && !window.widget;
+            window.surface.set_present_mode(config.redraw.present_mode);
+            window.surface.configure(&gfx);
+        }
+        for (window, region) in windows.iter_mut().zip(&regions) {
+            window.set_region(*region);
+        }
+        log::info!(
+            "started in {:?}: graphics device {:?}, windows and layers {:?}",
+            start.elapsed(),
+            device_time,
+            start.elapsed() - device_time
+        );
+        for window in &windows {
+            window.log_memory();
+        }
 
+        #[cfg(feature = "tray")]
+        let tray = match tray {
+            Some(controller) => {
+                let themes = Theme::available(&paths.themes_dir());
+                tray::start(controller, themes, config.tray.timezone.as_deref())
+                    .map_err(|err| log::warn!("system tray unavailable: {:#}", err))
+                    .ok()
+            }
+            None => None,
+        };
+        #[cfg(feature = "tray")]
+        let close_to_tray = tray.is_some() && config.tray.close_to_tray;
+        #[cfg(not(feature = "tray"))]
+        let close_to_tray = false;
+
+        let shader_watcher = shader_watch::ShaderWatcher::new(
+            resources.assets.clone(),
+            windows.iter().flat_map(|window| &window.layers),
+        );
         Ok(Self {
+            paths,
+            suspended: false,
+            scheduler: FrameScheduler::new(
+                config.redraw.frame_interval(),
+                config.redraw.animation_interval(),
+            ),
+            monitor_layout,
+            time_source,
+            paused_speed: None,
+            gps,
+            gps_offset,
+            home_from_gps: config.gps.location,
+            resources,
+            display,
+            staging: StagingPool::new(&gfx),
             gfx,
-            viewport,
-            background,
-            globe,
-            clock_face,
+            windows,
+            shader_watcher,
+            visible: true,
+            close_to_tray,
+            quit: false,
+            #[cfg(feature = "tray")]
+            tray,
+            #[cfg(target_os = "macos")]
+            _app_menu: macos::install_app_menu()?,
+            #[cfg(feature = "accesskit")]
+            accessibility,
+            screensaver: screensaver.map(Screensaver::new),
+            spanned: regions.len(),
+            configured_text_scale: config.text_scale,
         })
     }
 
-    fn update(&mut self) {
-        let date = Utc::now();
-        self.globe.set_date(&date);
-        self.clock_face.set_time(&date.with_timezone(&Local).time())
+    fn main_window(&mut self) -> &mut ClockWindow {
+        &mut self.windows[0]
     }
 
-    fn redraw(&mut self) -> anyhow::Result<()> {
-        let frame = loop {
-            match self.gfx.surface.get_current_texture() {
-                Ok(frame) => break frame,
-                Err(wgpu::SurfaceError::Lost) => {
-                    self.reconfigure();
-                }
-                Err(wgpu::SurfaceError::Timeout) | Err(wgpu::SurfaceError::Outdated) => {
-                    return Ok(());
-                }
-                Err(err) => {
-                    return Err(err.into());
-                }
+    /// Runs periodic checks, and redraws if the time shown has changed since the last frame.
+    fn tick(&mut self) {
+        if self.suspended {
+            return;
+        }
+        self.check_monitors();
+        self.check_shaders();
+        self.check_gps();
+        if let Err(err) = self.check_surface_formats() {
+            // Try again when redrawing, which gives up if it still fails.
+            log::error!("{:#}", err);
+            self.display.mark_lost();
+        }
+        let now = self.time_source.now();
+        #[cfg(all(feature = "tray", target_os = "macos"))]
+        if let Some(tray) = &mut self.tray {
+            tray.show_time(&now);
+        }
+        for window in &mut self.windows {
+            window.poll_layers();
+        }
+        #[cfg(feature = "accesskit")]
+        self.describe(&now);
+        let frame_interval = self.scheduler.interval();
+        let layers = self.windows.iter().flat_map(|window| &window.layers);
+        self.scheduler
+            .set_tick_intervals(layers.map(|layer| update_interval(&**layer, frame_interval)));
+        if self.scheduler.needs_redraw(&now) {
+            for window in &self.windows {
+                window.request_redraw();
+            }
+        }
+    }
+
+    /// Tells assistive technologies about what the main window shows at `now`.
+    #[cfg(feature = "accesskit")]
+    fn describe(&mut self, now: &DateTime<Utc>) {
+        use chrono::Timelike;
+
+        let Some(accessibility) = &mut self.accessibility else {
+            return;
+        };
+        let window = &self.windows[0];
+        let time = window.timezone.time_of(now);
+        let description = accessibility::Description {
+            time: self.resources.locale.format_time(&time, false),
+            timezone: window.timezone.name().unwrap_or("Local time").to_owned(),
+            layers: window
+                .layers
+                .iter()
+                .filter_map(|layer| layer.describe())
+                .collect(),
+            ..Default::default()
+        };
+        accessibility.update(description, time.hour());
+    }
+
+    /// Moves the home location to the GPS receiver's position once it is far enough from it,
+    /// recreating the layers that show information about it.
+    fn check_gps(&mut self) {
+        if !self.home_from_gps {
+            return;
+        }
+        let Some(position) = self.gps.as_ref().and_then(gps::Receiver::location) else {
+            return;
+        };
+        let home = &mut self.resources.home;
+        if home
+            .as_ref()
+            .is_some_and(|home| home.distance_km(&position) < GPS_HOME_DISTANCE_KM)
+        {
+            return;
+        }
+        log::info!(
+            "moving home to the GPS position {:.4}, {:.4}",
+            position.latitude,
+            position.longitude
+        );
+        *home = Some(Location {
+            name: home.take().and_then(|home| home.name),
+            ..position
+        });
+        for window in &mut self.windows {
+            if let Err(err) = window.recreate(&self.gfx, &self.resources) {
+                // Recreated again when redrawing, which gives up if it still fails.
+                log::error!("{:#}", err);
+                self.display.mark_lost();
+            }
+        }
+    }
+
+    fn check_monitors(&mut self) {
+        if self.screensaver.is_some() || self.spanned > 0 {
+            return;
+        }
+        self.monitor_layout.check(&self.windows[0].surface.window);
+    }
+
+    /// Reconfigures windows whose surfaces' capabilities changed, e.g. because they were moved
+    /// between SDR and HDR monitors. If the main window now prefers another format, everything is
+    /// recreated to draw in that format instead.
+    fn check_surface_formats(&mut self) -> anyhow::Result<()> {
+        if self.suspended {
+            return Ok(());
+        }
+        for window in &mut self.windows {
+            if self.display.refresh_capabilities(&mut window.surface) {
+                window.surface.configure(&self.gfx);
+                window.request_redraw();
+            }
+        }
+        let Some(preferred) = self.windows[0].surface.preferred_format() else {
+            return Ok(());
+        };
+        if preferred != self.gfx.render_format {
+            log::info!(
+                "surface format changed from {:?} to {:?}; recreating render pipelines",
+                self.gfx.render_format,
+                preferred
+            );
+            // The format is fixed for the lifetime of a graphics context, and its device cannot
+            // be shared with another one, so reopen the device as when it is lost.
+            self.reopen_device()?;
+            for window in &self.windows {
+                window.request_redraw();
             }
+        }
+        Ok(())
+    }
+
+    /// Reloads shaders that were edited, showing any errors in the window titles until they are
+    /// fixed.
+    fn check_shaders(&mut self) {
+        let layers = self
+            .windows
+            .iter_mut()
+            .flat_map(|window| &mut window.layers);
+        if !self.shader_watcher.check(layers) {
+            return;
+        }
+        let error = self.shader_watcher.error();
+        for window in &self.windows {
+            window.show_error(error.as_deref());
+            window.request_redraw();
+        }
+    }
+
+    fn save_state(&self) {
+        if self.screensaver.is_some() {
+            return;
+        }
+        let state = State {
+            camera: self.windows[0].camera(),
+        };
+        if let Err(err) = state.save(&self.paths) {
+            log::warn!("{:#}", err);
+        }
+    }
+
+    fn redraw(&mut self, id: WindowId) -> anyhow::Result<()> {
+        if self.suspended {
+            return Ok(());
+        }
+        if self.display.is_lost() {
+            self.recover()?;
+        }
+
+        let time = self.time_source.now();
+        let Some(window) = self.windows.iter_mut().find(|window| window.id() == id) else {
+            return Ok(());
+        };
+        window.update(&time, self.scheduler.interval());
+        self.scheduler.drawn(time);
+        if !window.redraw(&self.gfx, &self.display, &mut self.staging) {
+            self.recover()?;
+            for window in &self.windows {
+                window.request_redraw();
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops drawing until `resume` is called, as the windows' surfaces may be destroyed. The view
+    /// is saved too, as mobile platforms may kill a suspended app without telling it.
+    fn suspend(&mut self) {
+        self.suspended = true;
+        self.save_state();
+        for window in &mut self.windows {
+            window.surface.release();
+        }
+    }
+
+    /// Recreates the windows' surfaces, which some platforms destroy while the app is suspended.
+    fn resume(&mut self) -> anyhow::Result<()> {
+        if !self.suspended {
+            return Ok(());
+        }
+        for window in &mut self.windows {
+            self.display.recreate_surface(&mut window.surface)?;
+            window.surface.configure(&self.gfx);
+            window.request_redraw();
+        }
+        self.suspended = false;
+        Ok(())
+    }
+
+    /// Runs the displayed time from `time` (or the current time, if `None`) at `speed` times real
+    /// time.
+    fn simulate(&mut self, time: Option<DateTime<Utc>>, speed: f64) {
+        self.run_time(time, speed);
+        self.paused_speed = None;
+        if time.is_none() && speed == 1.0 {
+            banner::post(Level::Info, "Real time");
+        } else {
+            banner::post(Level::Info, describe_speed(speed));
+        }
+    }
+
+    fn run_time(&mut self, time: Option<DateTime<Utc>>, speed: f64) {
+        self.time_source = time_source_for(time, speed, self.gps_offset.as_ref());
+        for window in &self.windows {
+            window.request_redraw();
+        }
+    }
+
+    /// Freezes the displayed time, or runs it again at the speed it was frozen at.
+    fn play_pause(&mut self) {
+        let now = self.time_source.now();
+        let speed = self.time_source.speed();
+        if speed == 0.0 {
+            let speed = self.paused_speed.take().unwrap_or(1.0);
+            self.run_time(Some(now), speed);
+            banner::post(Level::Info, describe_speed(speed));
+        } else {
+            self.run_time(Some(now), 0.0);
+            self.paused_speed = Some(speed);
+            banner::post(Level::Info, describe_speed(0.0));
+        }
+    }
+
+    /// Runs the displayed time at `speed` from where it is, or sets the speed to run it at again
+    /// while it is frozen.
+    fn set_speed(&mut self, speed: f64) {
+        if self.paused_speed.is_some() && speed != 0.0 {
+            self.paused_speed = Some(speed);
+            banner::post(
+                Level::Info,
+                format!("Paused; {} when resumed", describe_speed(speed)),
+            );
+        } else {
+            self.simulate(Some(self.time_source.now()), speed);
+        }
+    }
+
+    /// Steps the speed to the next of [`SPEEDS`], faster or slower.
+    fn step_speed(&mut self, faster: bool) {
+        let speed = self
+            .paused_speed
+            .unwrap_or_else(|| self.time_source.speed());
+        let next = if faster {
+            SPEEDS.iter().find(|&&next| next > speed)
+        } else {
+            SPEEDS.iter().rev().find(|&&next| next < speed)
         };
+        if let Some(&next) = next {
+            self.set_speed(next);
+        }
+    }
 
-        let frame_view = frame.texture.create_view(&Default::default());
-        let mut encoder = self.gfx.device.create_command_encoder(&Default::default());
+    /// Moves the displayed time to `time`, keeping its speed.
+    fn jump_to(&mut self, time: DateTime<Utc>) {
+        self.run_time(Some(time), self.time_source.speed());
+        banner::post(
+            Level::Info,
+            format!("Jumped to {}", time.format("%Y-%m-%d %H:%M UTC")),
+        );
+    }
+
+    /// Handles the keys that control the displayed time.
+    fn simulation_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Space => self.play_pause(),
+            KeyCode::BracketRight => self.step_speed(true),
+            KeyCode::BracketLeft => self.step_speed(false),
+            KeyCode::Period => self.jump_to(self.time_source.now() + Duration::days(1)),
+            KeyCode::Comma => self.jump_to(self.time_source.now() - Duration::days(1)),
+            KeyCode::KeyN => self.simulate(None, 1.0),
+            _ => {}
+        }
+    }
+
+    /// Whether the key that shortcuts are pressed with is held in the window: `Cmd` on macOS, and
+    /// `Ctrl` elsewhere.
+    fn command_held(&self, id: WindowId) -> bool {
+        self.windows
+            .iter()
+            .find(|window| window.id() == id)
+            .is_some_and(|window| {
+                if cfg!(target_os = "macos") {
+                    window.modifiers.super_key()
+                } else {
+                    window.modifiers.control_key()
+                }
+            })
+    }
+
+    /// Makes the text of every window larger or smaller for `Ctrl` with `+` or `-`, or returns it
+    /// to the configured scale for `Ctrl+0`.
+    fn text_scale_key(&mut self, code: KeyCode) {
+        // In whole steps, so that going up and down again returns to the same scale.
+        let steps = (self.resources.text_scale / TEXT_SCALE_STEP).round();
+        let scale = match code {
+            KeyCode::Equal | KeyCode::NumpadAdd => (steps + 1.0) * TEXT_SCALE_STEP,
+            KeyCode::Minus | KeyCode::NumpadSubtract => (steps - 1.0) * TEXT_SCALE_STEP,
+            _ => self.configured_text_scale,
+        }
+        .clamp(config::MIN_TEXT_SCALE, config::MAX_TEXT_SCALE);
+        log::info!("text scale: {:.0}%", scale * 100.0);
+        self.resources.text_scale = scale;
+        for window in &mut self.windows {
+            window.set_text_scale(scale);
+        }
+    }
+
+    fn set_theme(&mut self, name: &str) -> anyhow::Result<()> {
+        self.resources.theme = Theme::load(name, &self.paths.themes_dir(), &self.resources.assets)?;
+        for window in &mut self.windows {
+            window.recreate(&self.gfx, &self.resources)?;
+        }
+        Ok(())
+    }
+
+    /// Shows or hides every window, e.g. to keep the clock running in the system tray.
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        for window in &self.windows {
+            window.surface.window.set_visible(visible);
+        }
+        if visible {
+            self.windows[0].surface.window.focus_window();
+        }
+    }
 
-        self.background.draw(&mut encoder, &frame_view);
-        self.globe.draw(&mut encoder, &frame_view, &self.viewport);
-        self.clock_face
-            .draw(&mut encoder, &frame_view, &self.viewport);
-        self.gfx.queue.submit([encoder.finish()]);
-        frame.present();
+    /// Replaces a lost graphics device, recreating every GPU resource with the new one.
+    fn recover(&mut self) -> anyhow::Result<()> {
+        log::warn!("graphics device lost; reopening it");
+        self.reopen_device()
+    }
 
+    fn reopen_device(&mut self) -> anyhow::Result<()> {
+        let mut surfaces: Vec<_> = self
+            .windows
+            .iter_mut()
+            .map(|window| &mut window.surface)
+            .collect();
+        self.gfx = block_on(self.display.reopen_device(&mut surfaces))?;
+        self.staging = StagingPool::new(&self.gfx);
+        for window in &mut self.windows {
+            window.recreate(&self.gfx, &self.resources)?;
+        }
         Ok(())
     }
 
-    fn window_resized(&mut self) {
-        self.viewport.window_resized();
-        self.reconfigure();
+    fn handle_request(&mut self, request: Request) {
+        let result = match &request.command {
+            Command::SetTimezone(name) => Timezone::from_config(Some(name))
+                .map(|timezone| {
+                    self.main_window().timezone = timezone;
+                    Response::Done
+                })
+                .map_err(|err| format!("{:#}", err)),
+            Command::ToggleLayer(name) => self
+                .main_window()
+                .toggle_layer(name)
+                .map(Response::LayerVisible),
+            Command::LayerAction(action) => {
+                if self.main_window().layer_action(action) {
+                    Ok(Response::Done)
+                } else {
+                    Err(format!("no layer handles the action {:?}", action))
+                }
+            }
+            Command::Screenshot(path) => {
+                let time = self.time_source.now();
+                let frame_interval = self.scheduler.interval();
+                self.windows[0]
+                    .screenshot(&self.gfx, &mut self.staging, &time, frame_interval, path)
+                    .map(|()| Response::Done)
+                    .map_err(|err| format!("{:#}", err))
+            }
+            Command::SetSimulatedTime { time, speed } => {
+                self.simulate(*time, *speed);
+                Ok(Response::Done)
+            }
+            Command::PlayPause => {
+                self.play_pause();
+                Ok(Response::Done)
+            }
+            Command::SetSpeed(speed) => {
+                self.set_speed(*speed);
+                Ok(Response::Done)
+            }
+            Command::JumpTo(time) => {
+                self.jump_to(*time);
+                Ok(Response::Done)
+            }
+            Command::SetTheme(name) => self
+                .set_theme(name)
+                .map(|()| Response::Done)
+                .map_err(|err| format!("{:#}", err)),
+            Command::ToggleVisible => {
+                self.set_visible(!self.visible);
+                Ok(Response::Done)
+            }
+            Command::Show => {
+                self.set_visible(true);
+                Ok(Response::Done)
+            }
+            Command::CurrentTime => Ok(Response::Time(self.time_source.now())),
+            Command::Quit => {
+                self.quit = true;
+                Ok(Response::Done)
+            }
+        };
+        if let Err(err) = &result {
+            log::warn!("control request {:?} failed: {}", request.command, err);
+        }
+        request.respond(result);
+        for window in &self.windows {
+            window.request_redraw();
+        }
     }
+}
 
-    fn reconfigure(&self) {
-        self.gfx.surface.configure(
-            &self.gfx.device,
-            &wgpu::SurfaceConfiguration {
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                format: self.gfx.render_format,
-                width: self.gfx.window.inner_size().width,
-                height: self.gfx.window.inner_size().height,
-                present_mode: wgpu::PresentMode::Fifo,
-                alpha_mode: self.gfx.surface_caps.alpha_modes[0],
-                view_formats: vec![],
-            },
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let args = Args::parse_from(screensaver::translate_args(std::env::args_os()));
+    let paths = Paths::new(args.base_dir.as_deref())?;
+    if let Some(command) = &args.command {
+        return commands::run(command, &args, &paths);
+    }
+    if let (true, Some(output)) = (args.headless, &args.output) {
+        return headless::render(&args, &paths, output);
+    }
+    if args.screensaver_configure {
+        return screensaver::configure(&paths);
+    }
+    if let (true, Some(authenticator)) = (args.lock_screen, &args.authenticator) {
+        #[cfg(target_os = "linux")]
+        return lock::run(&args, &paths, authenticator);
+        #[cfg(not(target_os = "linux"))]
+        anyhow::bail!(
+            "cannot run {:?}: locking the screen is only supported on Wayland",
+            authenticator
+        );
+    }
+    if let Some(device) = &args.kms {
+        #[cfg(target_os = "linux")]
+        return kms::run(&args, &paths, device);
+        #[cfg(not(target_os = "linux"))]
+        anyhow::bail!(
+            "cannot use {}: DRM/KMS is only supported on Linux",
+            device.display()
         );
     }
+
+    error_dialog::install_panic_hook();
+    run(args, paths).inspect_err(error_dialog::show)
 }
 
+/// iOS apps are started without arguments, and keep their files in the app's sandbox.
+#[cfg(target_os = "ios")]
 fn main() -> anyhow::Result<()> {
     env_logger::init();
+    let paths = Paths::new(None)?;
+    run(Args::parse_from(["global-clock"]), paths)
+}
+
+/// Called from `android_main` in the library, which the shared library in `android/` calls.
+#[cfg(target_os = "android")]
+pub(crate) fn android_main(android_app: AndroidApp) {
+    android_logger::init_once(
+        android_logger::Config::default().with_max_level(log::LevelFilter::Info),
+    );
+    let result = Paths::new(android_app.internal_data_path().as_deref())
+        .and_then(|paths| run(Args::parse_from(["global-clock"]), paths, android_app));
+    if let Err(err) = result {
+        log::error!("{:#}", err);
+    }
+}
+
+/// Opens the clock windows and runs them until the main window is closed, or until drawing
+/// fails.
+fn run(
+    args: Args,
+    paths: Paths,
+    #[cfg(target_os = "android")] android_app: AndroidApp,
+) -> anyhow::Result<()> {
+    let config = Config::load(args.config.as_deref(), &paths, args.overrides())?;
+    #[cfg(not(target_os = "android"))]
+    let resources = Resources::from_config(&config, &paths)?;
+    #[cfg(target_os = "android")]
+    let resources = Resources::with_assets(
+        &config,
+        &paths,
+        Assets::standard(config.asset_dir.as_deref(), paths.data_dir())
+            .with_cache_dir(paths.cache_dir())
+            .with_apk(android_app.asset_manager()),
+    )?;
+    log::info!("locale: {}", resources.locale);
+
+    let mut builder = EventLoop::<Request>::with_user_event();
+    // Wayland is used when available. `WINIT_UNIX_BACKEND` chooses a backend instead, e.g. to run
+    // through XWayland.
+    #[cfg(target_os = "linux")]
+    match std::env::var("WINIT_UNIX_BACKEND").as_deref() {
+        Ok("x11") => {
+            use winit::platform::x11::EventLoopBuilderExtX11;
+            builder.with_x11();
+        }
+        Ok("wayland") => {
+            use winit::platform::wayland::EventLoopBuilderExtWayland;
+            builder.with_wayland();
+        }
+        Ok(other) => log::warn!(
+            "unknown WINIT_UNIX_BACKEND {:?}; expected x11 or wayland",
+            other
+        ),
+        Err(_) => {}
+    }
+    #[cfg(target_os = "android")]
+    {
+        use winit::platform::android::EventLoopBuilderExtAndroid;
+        builder.with_android_app(android_app);
+    }
+    let event_loop = builder.build()?;
+
+    // Screensavers are started and stopped often, sometimes several at once for previews, so
+    // they are not controlled remotely.
+    let screensaver = screensaver::Mode::from_args(&args);
+    let control = screensaver.is_none();
 
-    // The window decorations provided by winit when using wayland do not match the native system
-    // theme, so fallback to X11 via XWayland if possible.
-    std::env::set_var("WINIT_UNIX_BACKEND", "x11");
+    // Kept until the event loop exits.
+    #[cfg(target_os = "linux")]
+    let _dbus = if control && config.control.dbus {
+        let controller = control::Controller::new(event_loop.create_proxy());
+        dbus::start(controller)
+            .map_err(|err| log::warn!("D-Bus control interface unavailable: {}", err))
+            .ok()
+    } else {
+        None
+    };
+
+    if control && config.control.socket {
+        let controller = control::Controller::new(event_loop.create_proxy());
+        if let Err(err) = ipc::serve(controller, &paths) {
+            log::warn!("control socket unavailable: {:#}", err);
+        }
+    }
+
+    let monitor_layout = MonitorLayout::new(&config);
+    #[cfg(feature = "tray")]
+    let tray = (config.tray.enabled && screensaver.is_none())
+        .then(|| control::Controller::new(event_loop.create_proxy()));
+    let mut handler = Handler {
+        startup: Some(Startup {
+            config,
+            resources,
+            paths,
+            monitor_layout,
+            screensaver,
+            #[cfg(feature = "tray")]
+            tray,
+        }),
+        app: None,
+        error: None,
+    };
+    if let Err(err) = event_loop.run_app(&mut handler) {
+        // E.g. the compositor crashed or restarted, which Wayland clients cannot survive. Keep the
+        // view for the next launch.
+        if let Some(app) = &handler.app {
+            app.save_state();
+        }
+        return Err(anyhow::Error::new(err).context("lost the connection to the display server"));
+    }
+    match handler.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_inner_size(LogicalSize::new(720, 720))
-        .with_title("Global Clock")
-        .build(&event_loop)?;
+/// Receives events from the event loop, and starts the app when it is first resumed.
+struct Handler {
+    startup: Option<Startup>,
+    app: Option<App>,
+    /// The error that stopped the event loop, if any.
+    error: Option<anyhow::Error>,
+}
 
-    let mut app = block_on(App::new(window))?;
-    app.reconfigure();
+impl Handler {
+    fn fail(&mut self, event_loop: &ActiveEventLoop, err: anyhow::Error) {
+        self.error = Some(err);
+        event_loop.exit();
+    }
+}
 
-    event_loop.run(move |event, _, control_flow| match event {
-        Event::NewEvents(StartCause::Init) => {
-            // Get the ball rolling with an initial timeout of NOW
-            *control_flow = ControlFlow::WaitUntil(Instant::now());
+impl ApplicationHandler<Request> for Handler {
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        let Some(app) = &mut self.app else {
+            return;
+        };
+        // After sleeping or the clock being set, the schedule is anchored again right away,
+        // whatever woke the loop.
+        let jumped = app.scheduler.check_jumped();
+        if jumped || matches!(cause, StartCause::ResumeTimeReached { .. }) {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(
+                app.scheduler.next_wakeup(&app.time_source),
+            ));
+            app.tick();
         }
-        Event::NewEvents(StartCause::ResumeTimeReached {
-            requested_resume, ..
-        }) => {
-            *control_flow = ControlFlow::WaitUntil(requested_resume + Duration::from_secs(1));
-            app.gfx.window.request_redraw();
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let result = match (&mut self.app, self.startup.take()) {
+            (Some(app), _) => app.resume(),
+            (None, Some(startup)) => {
+                block_on(App::new(event_loop, startup)).map(|app| self.app = Some(app))
+            }
+            (None, None) => Ok(()),
+        };
+        if let Err(err) = result {
+            self.fail(event_loop, err);
+            return;
+        }
+        #[cfg(target_os = "ios")]
+        ios::keep_screen_on();
+        // Get the ball rolling with an initial timeout of NOW
+        event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now()));
+    }
+
+    /// Runs however the app is stopped, including from the macOS application menu.
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(app) = &self.app {
+            app.save_state();
+        }
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(app) = &mut self.app {
+            app.suspend();
+        }
+        event_loop.set_control_flow(ControlFlow::Wait);
+    }
+
+    /// Frees what can be loaded again, before iOS or Android kill the app to reclaim memory.
+    fn memory_warning(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(app) = &self.app {
+            log::warn!("running low on memory; clearing the image cache");
+            app.resources.assets.clear_cache();
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, request: Request) {
+        match &mut self.app {
+            Some(app) => {
+                app.handle_request(request);
+                if app.quit {
+                    event_loop.exit();
+                }
+            }
+            None => request.respond(Err("the clock has not started yet".into())),
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(app) = &mut self.app else {
+            return;
+        };
+        #[cfg(feature = "accesskit")]
+        if let Some(accessibility) = &mut app.accessibility {
+            if app.windows[0].id() == window_id {
+                accessibility.process_event(&app.windows[0].surface.window, &event);
+            }
         }
-        Event::RedrawRequested(..) => {
-            app.update();
-            app.redraw().unwrap();
+        if let Some(screensaver) = &mut app.screensaver {
+            if screensaver.should_exit(window_id, &event) {
+                event_loop.exit();
+                return;
+            }
         }
-        Event::WindowEvent { event, .. } => match event {
+        match event {
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = app.redraw(window_id) {
+                    self.fail(event_loop, err);
+                }
+            }
+            WindowEvent::Moved(..) => {
+                if let Err(err) = app.check_surface_formats() {
+                    self.fail(event_loop, err);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key:
+                            PhysicalKey::Code(
+                                code @ (KeyCode::Space
+                                | KeyCode::BracketLeft
+                                | KeyCode::BracketRight
+                                | KeyCode::Comma
+                                | KeyCode::Period
+                                | KeyCode::KeyN),
+                            ),
+                        repeat,
+                        ..
+                    },
+                ..
+            } if !(repeat && code == KeyCode::Space) => {
+                app.simulation_key(code);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key:
+                            PhysicalKey::Code(
+                                code @ (KeyCode::Equal
+                                | KeyCode::NumpadAdd
+                                | KeyCode::Minus
+                                | KeyCode::NumpadSubtract
+                                | KeyCode::Digit0
+                                | KeyCode::Numpad0),
+                            ),
+                        ..
+                    },
+                ..
+            } if app.command_held(window_id) => {
+                app.text_scale_key(code);
+            }
+            WindowEvent::CloseRequested if app.windows[0].id() == window_id => {
+                if app.close_to_tray {
+                    app.set_visible(false);
+                } else {
+                    event_loop.exit();
+                }
+            }
             WindowEvent::CloseRequested => {
-                *control_flow = ControlFlow::Exit;
+                app.windows.retain(|window| window.id() != window_id);
             }
-            WindowEvent::Resized(..) | WindowEvent::ScaleFactorChanged { .. } => {
-                app.window_resized();
+            event => {
+                let index = app
+                    .windows
+                    .iter()
+                    .position(|window| window.id() == window_id);
+                if let Some(index) = index {
+                    window_input(&mut app.windows[index], &app.gfx, &app.resources, event);
+                    if index < app.spanned {
+                        let camera = app.windows[index].camera();
+                        for window in &mut app.windows[..app.spanned] {
+                            window.set_camera(camera);
+                        }
+                    }
+                }
             }
-            _ => {}
+        }
+    }
+}
+
+/// Opens the windows configured in addition to the main window.
+fn open_extra_windows(
+    event_loop: &ActiveEventLoop,
+    config: &Config,
+) -> anyhow::Result<Vec<(Window, ExtraWindowConfig)>> {
+    config
+        .windows
+        .iter()
+        .map(|extra| {
+            let window = event_loop.create_window(
+                with_app_id(Window::default_attributes())
+                    .with_title(TITLE)
+                    .with_inner_size(config.window.size_unit.size(extra.width, extra.height)),
+            )?;
+            Ok((window, extra.clone()))
+        })
+        .collect()
+}
+
+/// E.g. `3600× real time`, or `Paused` for a speed of 0.
+fn describe_speed(speed: f64) -> String {
+    if speed == 0.0 {
+        "Paused".to_owned()
+    } else if speed < 0.0 {
+        format!("Backwards at {}× real time", -speed)
+    } else {
+        format!("{}× real time", speed)
+    }
+}
+
+/// The time source for `start` and `speed`, as from [`time_source::from_settings`], except that
+/// the real time is corrected by `gps_offset`, if given.
+fn time_source_for(
+    start: Option<DateTime<Utc>>,
+    speed: f64,
+    gps_offset: Option<&TimeOffset>,
+) -> Box<dyn TimeSource> {
+    match gps_offset {
+        Some(offset) if start.is_none() && speed == 1.0 => Box::new(SmoothedTime::new(
+            CorrectedTime::with_offset(SystemTime, offset.clone()),
+        )),
+        _ => time_source::from_settings(start, speed),
+    }
+}
+
+fn with_app_id(attributes: WindowAttributes) -> WindowAttributes {
+    #[cfg(target_os = "linux")]
+    {
+        // Sets both the Wayland app ID and the X11 window class.
+        use winit::platform::wayland::WindowAttributesExtWayland;
+        attributes.with_name(APP_ID, APP_ID)
+    }
+    #[cfg(not(target_os = "linux"))]
+    attributes
+}
+
+/// Handles mouse, keyboard and resize events for one window.
+fn window_input(
+    window: &mut ClockWindow,
+    gfx: &GraphicsContext,
+    resources: &Resources,
+    event: WindowEvent,
+) {
+    match event {
+        WindowEvent::MouseWheel { delta, .. } => {
+            window.zoom(delta);
+        }
+        WindowEvent::MouseInput {
+            state,
+            button: MouseButton::Left,
+            ..
+        } => {
+            if window.is_mini() && state == ElementState::Pressed {
+                window.exit_mini();
+            } else if window.widget && state == ElementState::Pressed {
+                if let Err(err) = window.surface.window.drag_window() {
+                    log::warn!("failed to move the window: {}", err);
+                }
+            } else {
+                window.dragging = state == ElementState::Pressed;
+            }
+        }
+        WindowEvent::CursorMoved { position, .. } => {
+            window.cursor_moved(position);
+        }
+        WindowEvent::CursorLeft { .. } => {
+            window.cursor = None;
+        }
+        WindowEvent::Touch(touch) => {
+            window.touch(touch);
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::Digit0 | KeyCode::Numpad0),
+                    ..
+                },
+            ..
+        } => {
+            window.reset_camera();
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key:
+                        PhysicalKey::Code(
+                            code @ (KeyCode::ArrowLeft
+                            | KeyCode::ArrowRight
+                            | KeyCode::Equal
+                            | KeyCode::NumpadAdd
+                            | KeyCode::Minus
+                            | KeyCode::NumpadSubtract),
+                        ),
+                    ..
+                },
+            ..
+        } => match code {
+            KeyCode::ArrowLeft => window.rotate_steps(1.0),
+            KeyCode::ArrowRight => window.rotate_steps(-1.0),
+            KeyCode::Equal | KeyCode::NumpadAdd => window.zoom_steps(1.0),
+            _ => window.zoom_steps(-1.0),
         },
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::F3),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            if let Err(err) = window.toggle_stats(gfx, resources) {
+                log::error!("failed to show frame statistics: {:#}", err);
+            }
+        }
+        WindowEvent::ModifiersChanged(modifiers) => {
+            window.modifiers = modifiers.state();
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::F11),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            if window.modifiers.shift_key() {
+                window.toggle_presentation();
+            } else {
+                window.toggle_fullscreen();
+            }
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::Escape),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            // Only the focus first, if a control has it.
+            if window.clear_focus() {
+                return;
+            }
+            window.exit_mini();
+            window.exit_fullscreen();
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::Tab),
+                    ..
+                },
+            ..
+        } => {
+            window.move_focus(window.modifiers.shift_key());
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key:
+                        PhysicalKey::Code(
+                            code @ (KeyCode::Enter
+                            | KeyCode::NumpadEnter
+                            | KeyCode::Delete
+                            | KeyCode::Backspace),
+                        ),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            window.activate_focus(matches!(code, KeyCode::Delete | KeyCode::Backspace));
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::KeyM),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            window.toggle_mini(if window.modifiers.shift_key() {
+                "globe"
+            } else {
+                "clock_face"
+            });
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::KeyT),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            window.toggle_always_on_top();
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::KeyC),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            if let Err(err) = window.toggle_layer("calendar") {
+                log::info!("cannot toggle the calendar: {}", err);
+            }
+            window.request_redraw();
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::KeyP),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            let action = if window.modifiers.shift_key() {
+                "pomodoro.reset"
+            } else {
+                "pomodoro.toggle"
+            };
+            if !window.layer_action(action) {
+                log::info!("no layer handles {}", action);
+            }
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(code @ (KeyCode::KeyS | KeyCode::KeyD)),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => {
+            let timer = if code == KeyCode::KeyS {
+                "stopwatch"
+            } else {
+                "countdown"
+            };
+            let action = if window.modifiers.shift_key() {
+                format!("{}.reset", timer)
+            } else {
+                format!("{}.toggle", timer)
+            };
+            if !window.layer_action(&action) {
+                log::info!("no layer handles {}", action);
+            }
+        }
+        WindowEvent::ScaleFactorChanged {
+            scale_factor,
+            inner_size_writer,
+        } => {
+            window.scale_factor_changed(scale_factor, inner_size_writer);
+        }
+        WindowEvent::Resized(..) => {
+            window.resized(gfx);
+        }
         _ => {}
-    })
+    }
 }
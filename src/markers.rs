@@ -0,0 +1,721 @@
+use crate::assets::Assets;
+use crate::globe;
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::model;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::{TextLayer, TextStyle};
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use crate::zoneinfo::Zone;
+use crate::GraphicsContext;
+use anyhow::{anyhow, bail, Context};
+use bytemuck::{Pod, Zeroable};
+use chrono::Timelike;
+use glam::{Affine2, Vec2, Vec3};
+use serde::Deserialize;
+use serde_json::Value;
+use std::f32::consts::{PI, TAU};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Radius of a point's dot, in the scene's units.
+const DOT_RADIUS: f32 = 0.012;
+
+/// Half the width of a line, in the scene's units.
+const LINE_RADIUS: f32 = 0.004;
+
+/// Lines are split into steps of at most this many degrees of latitude or longitude, so that they
+/// bend with the globe's projection.
+const MAX_STEP_DEGREES: f32 = 2.0;
+
+/// Space between a dot and its label, in logical pixels.
+const LABEL_GAP: f32 = 4.0;
+
+/// Mean radius of the Earth, for the length of routes.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+const KM_PER_MILE: f64 = 1.609344;
+
+/// Settings of the markers, from the config's `[layer.markers]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkersSettings {
+    /// GeoJSON file with the points and lines to draw.
+    pub file: Option<PathBuf>,
+    /// Color of features that have none of their own, instead of the theme's color for the hands.
+    pub color: Option<Color>,
+    /// Show the features' names next to them.
+    pub labels: bool,
+    /// Height of the labels, in logical pixels.
+    pub label_size: f32,
+    /// Great-circle routes between pairs of places, e.g. of a flight.
+    pub routes: Vec<RouteSettings>,
+    /// Unit of the routes' lengths.
+    pub distance_unit: DistanceUnit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceUnit {
+    Kilometers,
+    Miles,
+}
+
+/// A great-circle route, from a `[[layer.markers.routes]]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteSettings {
+    pub from: RouteEnd,
+    pub to: RouteEnd,
+    /// Color of the route, instead of the layer's.
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+/// One end of a route, labelled with its name and the time there.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteEnd {
+    /// Degrees north of the equator; negative in the south.
+    pub latitude: f64,
+    /// Degrees east of Greenwich; negative in the west.
+    pub longitude: f64,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// IANA name of the timezone there, e.g. `Australia/Sydney`. The time is only shown if this is
+    /// set.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl Default for MarkersSettings {
+    fn default() -> Self {
+        Self {
+            file: None,
+            color: None,
+            labels: true,
+            label_size: 14.0,
+            routes: Vec::new(),
+            distance_unit: DistanceUnit::Kilometers,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Vertex {
+    position: [f32; 2],
+    /// Position within the dot or across the line, which is covered out to a length of 1.0.
+    local: [f32; 2],
+    /// Linear, premultiplied.
+    color: [f32; 4],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Latitude and longitude, in radians.
+type Position = [f32; 2];
+
+enum Shape {
+    Dot(Position),
+    /// Already split into short steps.
+    Line(Vec<Position>),
+}
+
+struct Marker {
+    shape: Shape,
+    color: Option<Color>,
+    label: Option<String>,
+}
+
+impl Marker {
+    fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            color: None,
+            label: None,
+        }
+    }
+
+    /// Where the label goes: next to a dot, or halfway along a line.
+    fn label_position(&self) -> Option<Position> {
+        match &self.shape {
+            Shape::Dot(position) => Some(*position),
+            Shape::Line(positions) => positions.get(positions.len() / 2).copied(),
+        }
+    }
+}
+
+/// An end of a route, whose label changes with the time.
+struct End {
+    position: Position,
+    name: Option<String>,
+    timezone: Option<Zone>,
+}
+
+/// Points and lines from a GeoJSON file drawn on the globe, such as offices or travel routes, with
+/// their names next to them.
+///
+/// Features take their label from their `name` or `title` property, and their color from
+/// `marker-color` for points, `stroke` for lines, or `color` for either. Polygons are drawn as
+/// their outlines, and lines go the short way around between longitudes.
+///
+/// Routes from the settings are drawn along great circles, the shortest way between their ends,
+/// with their length halfway along and the time at each end.
+pub struct Markers {
+    gfx: GraphicsContext,
+    assets: Assets,
+    pipeline_layout: wgpu::PipelineLayout,
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    text: TextLayer,
+    settings: MarkersSettings,
+    markers: Vec<Marker>,
+    ends: Vec<End>,
+    /// What the ends' labels say at the current time, in the same order.
+    end_labels: Vec<Option<String>>,
+    locale: Locale,
+    default_color: Color,
+    text_color: Color,
+    background: Option<Color>,
+    /// Rotation of the globe that `vertices` were made for.
+    rotation: f32,
+    vertices: Vec<Vertex>,
+    /// Whether `vertices` have changed since they were written to the vertex buffer.
+    dirty: bool,
+    target_size: Vec2,
+    scene_to_pixels: Affine2,
+    scale_factor: f32,
+}
+
+impl Markers {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: MarkersSettings = cx.settings("markers")?;
+        let mut markers = match &settings.file {
+            Some(path) => {
+                let source = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read markers from {}", path.display()))?;
+                serde_json::from_str(&source)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|json| parse_geojson(&json))
+                    .with_context(|| format!("invalid GeoJSON in {}", path.display()))?
+            }
+            None => {
+                if settings.routes.is_empty() {
+                    log::warn!(
+                        "the markers layer needs a GeoJSON file or routes; set `file` or \
+                         `routes` in [layer.markers]"
+                    );
+                }
+                Vec::new()
+            }
+        };
+        let mut ends = Vec::new();
+        for (index, route) in settings.routes.iter().enumerate() {
+            let [from, to] = [&route.from, &route.to].map(|end| {
+                end_of_route(end)
+                    .with_context(|| format!("invalid layer.markers.routes[{}]", index))
+            });
+            let (from, to) = (from?, to?);
+            let (line, angle) = great_circle(from.position, to.position);
+            let length = EARTH_RADIUS_KM * f64::from(angle);
+            let length = match settings.distance_unit {
+                DistanceUnit::Kilometers => format!("{:.0} km", length),
+                DistanceUnit::Miles => format!("{:.0} mi", length / KM_PER_MILE),
+            };
+            markers.push(Marker {
+                shape: Shape::Line(line),
+                color: route.color,
+                label: Some(cx.locale.localize_digits(&length)),
+            });
+            for end in [&from, &to] {
+                markers.push(Marker {
+                    shape: Shape::Dot(end.position),
+                    color: route.color,
+                    label: None,
+                });
+            }
+            ends.extend([from, to]);
+        }
+        for marker in &mut markers {
+            marker.color = marker.color.map(|color| cx.palette.adapt(color));
+        }
+
+        let pipeline_layout =
+            cx.gfx
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Markers.pipeline_layout"),
+                    bind_group_layouts: &[cx.viewport.bind_group_layout()],
+                    push_constant_ranges: &[],
+                });
+        let render_pipeline = Self::create_render_pipeline(cx.gfx, cx.assets, &pipeline_layout)?;
+
+        let background = cx.theme.text_background;
+        let mut layer = Self {
+            gfx: cx.gfx.clone(),
+            assets: cx.assets.clone(),
+            pipeline_layout,
+            render_pipeline,
+            // Replaced below, once the number of vertices is known.
+            vertex_buffer: create_vertex_buffer(cx.gfx, 0),
+            text: TextLayer::new(cx.gfx, cx.assets)?,
+            markers,
+            end_labels: vec![None; ends.len()],
+            ends,
+            locale: cx.locale.clone(),
+            default_color: cx.palette.adapt(settings.color.unwrap_or(Color {
+                // Opaque, so that lines do not look darker where their steps overlap.
+                a: 255,
+                ..cx.theme.hands
+            })),
+            settings,
+            text_color: cx.theme.text,
+            background: (background.a > 0).then_some(background),
+            rotation: 0.0,
+            vertices: Vec::new(),
+            dirty: true,
+            target_size: cx.viewport.size(),
+            scene_to_pixels: cx.viewport.scene_to_pixels(),
+            scale_factor: 1.0,
+        };
+        layer.build_vertices();
+        layer.vertex_buffer = create_vertex_buffer(cx.gfx, layer.vertices.len());
+        Ok(layer)
+    }
+
+    fn create_render_pipeline(
+        gfx: &GraphicsContext,
+        assets: &Assets,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> anyhow::Result<wgpu::RenderPipeline> {
+        let shader_source = assets.read_to_string("shaders/markers.wgsl")?;
+        gfx.validate(|| {
+            let shader_module = gfx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("Markers.shader_module"),
+                    source: wgpu::ShaderSource::Wgsl(shader_source),
+                });
+            gfx.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Markers.render_pipeline"),
+                    layout: Some(pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::layout()],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: gfx.render_format,
+                            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
+        })
+        .context("invalid markers shader")
+    }
+
+    /// Makes the dots and lines for the current rotation of the globe. Their number never changes.
+    fn build_vertices(&mut self) {
+        let rotation = self.rotation;
+        let project =
+            |[latitude, longitude]: Position| globe::project(latitude, longitude, rotation);
+        self.vertices.clear();
+        for marker in &self.markers {
+            let [r, g, b, a] = marker.color.unwrap_or(self.default_color).to_linear();
+            let color = [r * a, g * a, b * a, a];
+            match &marker.shape {
+                Shape::Dot(position) => {
+                    push_dot(&mut self.vertices, project(*position), DOT_RADIUS, color)
+                }
+                Shape::Line(positions) => {
+                    let points: Vec<Vec2> = positions.iter().copied().map(project).collect();
+                    for pair in points.windows(2) {
+                        push_segment(&mut self.vertices, pair[0], pair[1], LINE_RADIUS, color);
+                    }
+                    // Round joins and ends.
+                    for &point in &points {
+                        push_dot(&mut self.vertices, point, LINE_RADIUS, color);
+                    }
+                }
+            }
+        }
+        self.dirty = true;
+    }
+}
+
+impl Layer for Markers {
+    fn name(&self) -> &str {
+        "markers"
+    }
+
+    fn shaders(&self) -> &[&str] {
+        &["shaders/markers.wgsl"]
+    }
+
+    fn reload_shaders(&mut self) -> anyhow::Result<()> {
+        self.render_pipeline =
+            Self::create_render_pipeline(&self.gfx, &self.assets, &self.pipeline_layout)?;
+        Ok(())
+    }
+
+    /// The same as the globe, so that the markers turn with it.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        let rotation = model::globe_rotation(frame.time.num_seconds_from_midnight() as f32);
+        if rotation != self.rotation {
+            self.rotation = rotation;
+            self.build_vertices();
+        }
+        let locale = &self.locale;
+        for (end, label) in self.ends.iter().zip(&mut self.end_labels) {
+            let time = end.timezone.as_ref().map(|timezone| {
+                let time = timezone.local(&frame.time).time();
+                locale.format_time(&time, false)
+            });
+            *label = match (&end.name, time) {
+                (Some(name), Some(time)) => Some(format!("{} {}", name, time)),
+                (Some(name), None) => Some(name.clone()),
+                (None, time) => time,
+            };
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.text.set_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.target_size = viewport.size();
+        self.scene_to_pixels = viewport.scene_to_pixels();
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if self.dirty {
+            staging.write_buffer(
+                encoder,
+                &self.vertex_buffer,
+                0,
+                bytemuck::cast_slice(&self.vertices),
+            );
+            self.dirty = false;
+        }
+        if self.settings.labels {
+            let size = self.settings.label_size * self.scale_factor;
+            let style = TextStyle {
+                size,
+                color: self.text_color,
+                anchor: [0.0, 0.5],
+                background: self.background,
+                padding: size * 0.2,
+            };
+            // Past the edge of the dot, however far the camera zooms in.
+            let dot_radius = self.scene_to_pixels.matrix2.x_axis.length() * DOT_RADIUS;
+            let offset = dot_radius + LABEL_GAP * self.scale_factor + style.padding;
+            let markers = self
+                .markers
+                .iter()
+                .filter_map(|marker| Some((marker.label.as_ref()?, marker.label_position()?)));
+            let ends = self
+                .ends
+                .iter()
+                .zip(&self.end_labels)
+                .filter_map(|(end, label)| Some((label.as_ref()?, end.position)));
+            for (label, [latitude, longitude]) in markers.chain(ends) {
+                let point = self.scene_to_pixels.transform_point2(globe::project(
+                    latitude,
+                    longitude,
+                    self.rotation,
+                ));
+                self.text.queue(label, [point.x + offset, point.y], &style);
+            }
+        }
+        let target_size = (self.target_size.x as u32, self.target_size.y as u32);
+        self.text.prepare(target_size, encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.vertex_buffer.size() + self.text.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
+        if !self.vertices.is_empty() {
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_bind_group(0, viewport.bind_group(), &[]);
+            render_pass.draw(0..self.vertices.len() as u32, 0..1);
+        }
+        self.text.draw(render_pass);
+    }
+}
+
+fn create_vertex_buffer(gfx: &GraphicsContext, vertices: usize) -> wgpu::Buffer {
+    gfx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Markers.vertex_buffer"),
+        size: (vertices * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Adds two triangles with the given corners, in the order of a triangle strip.
+fn push_quad(vertices: &mut Vec<Vertex>, corners: [(Vec2, Vec2); 4], color: [f32; 4]) {
+    for index in [0, 1, 2, 2, 1, 3] {
+        let (position, local) = corners[index];
+        vertices.push(Vertex {
+            position: position.to_array(),
+            local: local.to_array(),
+            color,
+        });
+    }
+}
+
+fn push_dot(vertices: &mut Vec<Vertex>, center: Vec2, radius: f32, color: [f32; 4]) {
+    let corner = |x: f32, y: f32| {
+        let local = Vec2::new(x, y);
+        (center + local * radius, local)
+    };
+    push_quad(
+        vertices,
+        [
+            corner(-1.0, -1.0),
+            corner(1.0, -1.0),
+            corner(-1.0, 1.0),
+            corner(1.0, 1.0),
+        ],
+        color,
+    );
+}
+
+fn push_segment(vertices: &mut Vec<Vertex>, from: Vec2, to: Vec2, radius: f32, color: [f32; 4]) {
+    let normal = (to - from).normalize_or_zero().perp() * radius;
+    let (left, right) = (Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0));
+    push_quad(
+        vertices,
+        [
+            (from + normal, left),
+            (from - normal, right),
+            (to + normal, left),
+            (to - normal, right),
+        ],
+        color,
+    );
+}
+
+/// Reads the points and lines of a GeoJSON feature collection, feature or geometry.
+fn parse_geojson(json: &Value) -> anyhow::Result<Vec<Marker>> {
+    let mut markers = Vec::new();
+    match json["type"].as_str() {
+        Some("FeatureCollection") => {
+            let features = json["features"]
+                .as_array()
+                .context("`features` must be an array")?;
+            for (index, feature) in features.iter().enumerate() {
+                parse_feature(feature, &mut markers)
+                    .with_context(|| format!("invalid feature {}", index))?;
+            }
+        }
+        Some("Feature") => parse_feature(json, &mut markers)?,
+        _ => parse_geometry(json, &mut markers)?,
+    }
+    Ok(markers)
+}
+
+fn parse_feature(feature: &Value, markers: &mut Vec<Marker>) -> anyhow::Result<()> {
+    let geometry = &feature["geometry"];
+    if geometry.is_null() {
+        // Allowed, with nothing to draw.
+        return Ok(());
+    }
+    let start = markers.len();
+    parse_geometry(geometry, markers)?;
+
+    let properties = &feature["properties"];
+    let label = ["name", "title"]
+        .iter()
+        .find_map(|key| properties[*key].as_str());
+    for (index, marker) in markers[start..].iter_mut().enumerate() {
+        let keys: &[&str] = match marker.shape {
+            Shape::Dot(_) => &["marker-color", "color"],
+            Shape::Line(_) => &["stroke", "color"],
+        };
+        marker.color = property_color(properties, keys)?;
+        // Once per feature, e.g. not at every point of a `MultiPoint`.
+        if index == 0 {
+            marker.label = label.map(str::to_owned);
+        }
+    }
+    Ok(())
+}
+
+/// The color in the first of `keys` that `properties` has.
+fn property_color(properties: &Value, keys: &[&str]) -> anyhow::Result<Option<Color>> {
+    for key in keys {
+        if let Some(value) = properties[*key].as_str() {
+            return value
+                .parse()
+                .map(Some)
+                .with_context(|| format!("invalid {:?}", key));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_geometry(geometry: &Value, markers: &mut Vec<Marker>) -> anyhow::Result<()> {
+    let kind = geometry["type"]
+        .as_str()
+        .context("geometry without a `type`")?;
+    let coordinates = &geometry["coordinates"];
+    let mut line = |positions: &Value| -> anyhow::Result<()> {
+        let positions = parse_positions(positions)?;
+        markers.push(Marker::new(Shape::Line(subdivide(&positions))));
+        Ok(())
+    };
+    match kind {
+        "Point" => markers.push(Marker::new(Shape::Dot(parse_position(coordinates)?))),
+        "MultiPoint" => {
+            for position in parse_positions(coordinates)? {
+                markers.push(Marker::new(Shape::Dot(position)));
+            }
+        }
+        "LineString" => line(coordinates)?,
+        // Polygons are drawn as the outlines of their rings.
+        "MultiLineString" | "Polygon" => {
+            for positions in array(coordinates)? {
+                line(positions)?;
+            }
+        }
+        "MultiPolygon" => {
+            for polygon in array(coordinates)? {
+                for positions in array(polygon)? {
+                    line(positions)?;
+                }
+            }
+        }
+        "GeometryCollection" => {
+            for geometry in array(&geometry["geometries"])? {
+                parse_geometry(geometry, markers)?;
+            }
+        }
+        _ => bail!("unknown geometry type {:?}", kind),
+    }
+    Ok(())
+}
+
+fn array(value: &Value) -> anyhow::Result<&Vec<Value>> {
+    value.as_array().ok_or_else(|| anyhow!("expected an array"))
+}
+
+/// Reads a position of `[longitude, latitude]` in degrees, with an optional altitude that is
+/// ignored.
+fn parse_position(value: &Value) -> anyhow::Result<Position> {
+    let (Some(longitude), Some(latitude)) = (value[0].as_f64(), value[1].as_f64()) else {
+        bail!("expected a position of [longitude, latitude]");
+    };
+    Ok([
+        (latitude as f32).to_radians(),
+        (longitude as f32).to_radians(),
+    ])
+}
+
+fn parse_positions(value: &Value) -> anyhow::Result<Vec<Position>> {
+    array(value)?.iter().map(parse_position).collect()
+}
+
+fn end_of_route(end: &RouteEnd) -> anyhow::Result<End> {
+    if !(-90.0..=90.0).contains(&end.latitude) || !(-180.0..=180.0).contains(&end.longitude) {
+        bail!(
+            "{}, {} is not on Earth; latitude must be within -90..90 and longitude within \
+             -180..180",
+            end.latitude,
+            end.longitude
+        );
+    }
+    let timezone = match &end.timezone {
+        Some(name) => Some(Zone::load(name).ok_or_else(|| anyhow!("unknown timezone {:?}", name))?),
+        None => None,
+    };
+    Ok(End {
+        position: [
+            (end.latitude as f32).to_radians(),
+            (end.longitude as f32).to_radians(),
+        ],
+        name: end.name.clone(),
+        timezone,
+    })
+}
+
+fn direction([latitude, longitude]: Position) -> Vec3 {
+    Vec3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    )
+}
+
+/// Positions along the great circle from `from` to `to`, and the angle between them in radians.
+fn great_circle(from: Position, to: Position) -> (Vec<Position>, f32) {
+    let (a, b) = (direction(from), direction(to));
+    let angle = a.dot(b).clamp(-1.0, 1.0).acos();
+    // Towards `b` at a right angle from `a`. Any great circle joins opposite points.
+    let towards = (b - a * a.dot(b))
+        .try_normalize()
+        .unwrap_or_else(|| a.any_orthonormal_vector());
+    let steps = (angle / MAX_STEP_DEGREES.to_radians()).ceil().max(1.0) as usize;
+    let positions = (0..=steps)
+        .map(|step| {
+            let swept = step as f32 / steps as f32 * angle;
+            let point = a * swept.cos() + towards * swept.sin();
+            [point.z.clamp(-1.0, 1.0).asin(), point.y.atan2(point.x)]
+        })
+        .collect();
+    (positions, angle)
+}
+
+/// Adds positions along each segment of `line`, going the short way around between longitudes.
+fn subdivide(line: &[Position]) -> Vec<Position> {
+    let max_step = MAX_STEP_DEGREES.to_radians();
+    let mut positions = Vec::new();
+    for pair in line.windows(2) {
+        let ([lat0, lon0], [lat1, lon1]) = (pair[0], pair[1]);
+        let (d_lat, d_lon) = (lat1 - lat0, (lon1 - lon0 + PI).rem_euclid(TAU) - PI);
+        let steps = (d_lat.abs().max(d_lon.abs()) / max_step).ceil().max(1.0) as usize;
+        positions.extend((0..steps).map(|step| {
+            let t = step as f32 / steps as f32;
+            [lat0 + d_lat * t, lon0 + d_lon * t]
+        }));
+    }
+    positions.extend(line.last());
+    positions
+}
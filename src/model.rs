@@ -0,0 +1,49 @@
+use chrono::{NaiveTime, Timelike};
+use std::f32::consts::TAU;
+
+pub const SECONDS_PER_DAY: f32 = 86400.0;
+pub const SECONDS_PER_HOUR: f32 = 3600.0;
+// Don't care about leap years, this is precise enough.
+pub const DAYS_PER_YEAR: f32 = 365.0;
+/// Angle between the Earth's axis and the normal of its orbit, in radians.
+pub const MAX_AXIAL_TILT: f32 = 23.4 / 360.0 * TAU;
+
+/// Seconds after midnight at `time`, including the fraction of the second, so that the hands and
+/// the globe can move smoothly between seconds.
+pub fn seconds_from_midnight(time: &NaiveTime) -> f32 {
+    time.num_seconds_from_midnight() as f32 + time.nanosecond().min(999_999_999) as f32 / 1e9
+}
+
+/// Rotation of the globe at `seconds` after midnight UTC, in radians.
+pub fn globe_rotation(seconds: f32) -> f32 {
+    // Offset to compensate for angle 0 being at 6:00 AM UTC
+    const ANGLE_OFFSET: f32 = TAU / 4.0;
+    seconds / SECONDS_PER_DAY * TAU + ANGLE_OFFSET
+}
+
+/// How far the northern hemisphere is tilted towards the Sun on the zero-based `day` of the year,
+/// in radians. This is also the solar declination: the latitude where the Sun is overhead at noon.
+pub fn axial_tilt(day: f32) -> f32 {
+    // Day 0 -> roughly March 20 (I'm too lazy to calculate this more precisely)
+    const EQUINOX_OFFSET: f32 = -78.0;
+    MAX_AXIAL_TILT * ((day + EQUINOX_OFFSET) / DAYS_PER_YEAR * TAU).sin()
+}
+
+/// Minutes by which solar time is ahead of mean time on the zero-based `day` of the year, i.e.
+/// how early a sundial runs.
+pub fn equation_of_time(day: f32) -> f32 {
+    let b = (day - 80.0) / DAYS_PER_YEAR * TAU;
+    9.87 * (2.0 * b).sin() - 7.53 * b.cos() - 1.5 * b.sin()
+}
+
+/// Angle of the hour hand on a 24-hour dial at `seconds` after midnight, clockwise from midnight
+/// at the top, in radians.
+pub fn hour_hand_angle(seconds: f32) -> f32 {
+    seconds / SECONDS_PER_DAY * TAU
+}
+
+/// Angle of the minute hand at `seconds` after midnight, clockwise from the top, in radians.
+/// Whole turns are not removed.
+pub fn minute_hand_angle(seconds: f32) -> f32 {
+    seconds / SECONDS_PER_HOUR * TAU
+}
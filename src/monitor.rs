@@ -0,0 +1,167 @@
+use crate::config::{Config, Corner, MonitorConfig, MonitorSelector, WidgetConfig, WindowConfig};
+use std::collections::BTreeMap;
+use winit::dpi::{LogicalSize, PhysicalPosition, Size};
+use winit::event_loop::ActiveEventLoop;
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window, WindowAttributes};
+
+/// Chooses the monitor that the window is placed on, and the window settings for that monitor.
+pub struct MonitorLayout {
+    window: WindowConfig,
+    widget: WidgetConfig,
+    monitors: BTreeMap<String, MonitorConfig>,
+    /// Names of the monitors that were connected at the last check, to detect hot-plugging.
+    connected: Vec<Option<String>>,
+}
+
+struct Placement {
+    monitor: Option<MonitorHandle>,
+    fullscreen: bool,
+    size: Size,
+}
+
+impl MonitorLayout {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            window: config.window.clone(),
+            widget: config.widget.clone(),
+            monitors: config.monitors.clone(),
+            connected: Vec::new(),
+        }
+    }
+
+    pub fn window_attributes(
+        &mut self,
+        attributes: WindowAttributes,
+        event_loop: &ActiveEventLoop,
+    ) -> WindowAttributes {
+        let available: Vec<_> = event_loop.available_monitors().collect();
+        self.connected = available.iter().map(MonitorHandle::name).collect();
+        let placement = self.placement(&available);
+
+        let mut attributes = attributes.with_inner_size(placement.size).with_fullscreen(
+            placement
+                .fullscreen
+                .then(|| Fullscreen::Borderless(placement.monitor.clone())),
+        );
+        if let Some(position) = self.position(&placement, event_loop.primary_monitor()) {
+            attributes = attributes.with_position(position);
+        }
+        attributes
+    }
+
+    /// Moves the window when the set of connected monitors changes, e.g. so that it returns to
+    /// the preferred monitor when that monitor is plugged back in.
+    pub fn check(&mut self, window: &Window) {
+        let available: Vec<_> = window.available_monitors().collect();
+        let connected: Vec<_> = available.iter().map(MonitorHandle::name).collect();
+        if connected == self.connected {
+            return;
+        }
+        self.connected = connected;
+
+        let placement = self.placement(&available);
+        if placement.fullscreen {
+            window.set_fullscreen(Some(Fullscreen::Borderless(placement.monitor)));
+        } else {
+            window.set_fullscreen(None);
+            // The new size, if it is applied, arrives in a `Resized` event.
+            let _ = window.request_inner_size(placement.size);
+            if let Some(position) = self.position(&placement, window.primary_monitor()) {
+                window.set_outer_position(position);
+            }
+        }
+    }
+
+    fn placement(&self, available: &[MonitorHandle]) -> Placement {
+        let found = self.window.monitor.as_ref().and_then(|selector| {
+            available
+                .iter()
+                .enumerate()
+                .find(|(index, monitor)| match selector {
+                    MonitorSelector::Index(wanted) => index == wanted,
+                    MonitorSelector::Name(wanted) => monitor.name().as_ref() == Some(wanted),
+                })
+        });
+        if self.window.monitor.is_some() && found.is_none() {
+            log::warn!(
+                "configured monitor {:?} is not connected",
+                self.window.monitor
+            );
+        }
+
+        if self.widget.enabled {
+            return Placement {
+                monitor: found.map(|(_, monitor)| monitor.clone()),
+                fullscreen: false,
+                size: LogicalSize::new(self.widget.size, self.widget.size).into(),
+            };
+        }
+
+        let overrides = found.and_then(|(index, monitor)| {
+            monitor
+                .name()
+                .and_then(|name| self.monitors.get(&name))
+                .or_else(|| self.monitors.get(&index.to_string()))
+        });
+        let default = MonitorConfig::default();
+        let overrides = overrides.unwrap_or(&default);
+
+        Placement {
+            monitor: found.map(|(_, monitor)| monitor.clone()),
+            fullscreen: overrides.fullscreen.unwrap_or(self.window.fullscreen),
+            size: self.window.size_unit.size(
+                overrides.width.unwrap_or(self.window.width),
+                overrides.height.unwrap_or(self.window.height),
+            ),
+        }
+    }
+
+    /// Where to put the window, if not where the platform chooses. Widgets in a corner go on the
+    /// primary monitor unless another one is configured.
+    fn position(
+        &self,
+        placement: &Placement,
+        primary: Option<MonitorHandle>,
+    ) -> Option<PhysicalPosition<i32>> {
+        match (self.widget.enabled, self.widget.corner) {
+            (true, Some(corner)) => {
+                let monitor = placement.monitor.clone().or(primary)?;
+                Some(cornered(&monitor, placement.size, corner))
+            }
+            _ => Some(centered(placement.monitor.as_ref()?, placement.size)),
+        }
+    }
+}
+
+/// Distance from a widget to the edges of the monitor, in logical pixels, to keep it clear of
+/// e.g. screen edges that are hard to click.
+const CORNER_MARGIN: f64 = 16.0;
+
+/// Where a window of `size` goes to sit in `corner` of `monitor`.
+pub fn cornered(monitor: &MonitorHandle, size: Size, corner: Corner) -> PhysicalPosition<i32> {
+    let size = size.to_physical::<i32>(monitor.scale_factor());
+    let margin = (CORNER_MARGIN * monitor.scale_factor()) as i32;
+    let origin = monitor.position();
+    let monitor_size = monitor.size();
+    let left = origin.x + margin;
+    let top = origin.y + margin;
+    let right = origin.x + monitor_size.width as i32 - size.width - margin;
+    let bottom = origin.y + monitor_size.height as i32 - size.height - margin;
+    match corner {
+        Corner::TopLeft => PhysicalPosition::new(left, top),
+        Corner::TopRight => PhysicalPosition::new(right, top),
+        Corner::BottomLeft => PhysicalPosition::new(left, bottom),
+        Corner::BottomRight => PhysicalPosition::new(right, bottom),
+    }
+}
+
+fn centered(monitor: &MonitorHandle, size: Size) -> PhysicalPosition<i32> {
+    let size = size.to_physical::<i32>(monitor.scale_factor());
+    let origin = monitor.position();
+    let monitor_size = monitor.size();
+    PhysicalPosition::new(
+        origin.x + (monitor_size.width as i32 - size.width).max(0) / 2,
+        origin.y + (monitor_size.height as i32 - size.height).max(0) / 2,
+    )
+}
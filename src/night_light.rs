@@ -0,0 +1,207 @@
+use crate::assets::Assets;
+use crate::astronomy::{self, SUNRISE_ALTITUDE};
+use crate::location::Location;
+use crate::staging::StagingPool;
+use crate::theme::Color;
+use crate::GraphicsContext;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use wgpu::util::DeviceExt;
+
+/// Color temperature of daylight, in kelvin, which the tint leaves as it is.
+pub const DAYLIGHT_TEMPERATURE: f32 = 6500.0;
+
+/// Altitude of the Sun at the end of civil twilight, in degrees, from which the tint is at its
+/// full strength.
+const DUSK_ALTITUDE: f64 = -6.0;
+
+/// How strongly to tint the frame at `location` at `time`: 0 while the Sun is up, rising to 1
+/// over twilight until the end of civil dusk, and back to 0 over the dawn.
+pub fn strength(location: &Location, time: &DateTime<Utc>) -> f32 {
+    let altitude = astronomy::sun_altitude(location, time);
+    ((SUNRISE_ALTITUDE - altitude) / (SUNRISE_ALTITUDE - DUSK_ALTITUDE)).clamp(0.0, 1.0) as f32
+}
+
+/// The color of a black body at `kelvin`, as an sRGB color with its brightest channel at full,
+/// from Tanner Helland's fit of the CIE 1964 color matching functions.
+pub fn blackbody(kelvin: f32) -> Color {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (t - 60.0).powf(-0.13320476)
+    };
+    let green = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.07551485)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    let channel = |c: f32| c.clamp(0.0, 255.0).round() as u8;
+    Color::rgba(channel(red), channel(green), channel(blue), 255)
+}
+
+/// A warm tint over everything drawn in a frame after sunset at the home location, so that the
+/// clock does not light up a dark room in blue. It multiplies the frame's colors by those of a
+/// black body at the configured temperature relative to daylight, as a final full-screen pass.
+pub struct NightLight {
+    home: Location,
+    /// Linear factors for red, green and blue at full strength.
+    night: [f32; 3],
+    strength: f32,
+    render_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// The strength that the uniform buffer holds the tint for.
+    written: Option<f32>,
+}
+
+impl NightLight {
+    /// A tint toward `temperature` in kelvin after sunset at `home`.
+    pub fn new(
+        gfx: &GraphicsContext,
+        assets: &Assets,
+        home: Location,
+        temperature: f32,
+    ) -> anyhow::Result<Self> {
+        let [r, g, b, _] = blackbody(temperature).to_linear();
+        let [dr, dg, db, _] = blackbody(DAYLIGHT_TEMPERATURE).to_linear();
+        let night = [r / dr, g / dg, b / db].map(|factor| factor.min(1.0));
+
+        let uniform_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("NightLight.uniform_buffer"),
+                contents: bytemuck::cast_slice(&[1.0f32; 4]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let bind_group_layout =
+            gfx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("NightLight.bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("NightLight.bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("NightLight.pipeline_layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let shader_source = assets.read_to_string("shaders/night_light.wgsl")?;
+        let render_pipeline = gfx
+            .validate(|| {
+                let shader_module = gfx
+                    .device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("NightLight.shader_module"),
+                        source: wgpu::ShaderSource::Wgsl(shader_source),
+                    });
+                gfx.device
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("NightLight.render_pipeline"),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader_module,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                        },
+                        primitive: wgpu::PrimitiveState {
+                            topology: wgpu::PrimitiveTopology::TriangleStrip,
+                            ..Default::default()
+                        },
+                        depth_stencil: None,
+                        multisample: Default::default(),
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader_module,
+                            entry_point: "fs_main",
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format: gfx.render_format,
+                                // Multiplies what is drawn by the tint, leaving alpha as it is,
+                                // so that premultiplied colors stay premultiplied.
+                                blend: Some(wgpu::BlendState {
+                                    color: wgpu::BlendComponent {
+                                        src_factor: wgpu::BlendFactor::Dst,
+                                        dst_factor: wgpu::BlendFactor::Zero,
+                                        operation: wgpu::BlendOperation::Add,
+                                    },
+                                    alpha: wgpu::BlendComponent {
+                                        src_factor: wgpu::BlendFactor::Zero,
+                                        dst_factor: wgpu::BlendFactor::One,
+                                        operation: wgpu::BlendOperation::Add,
+                                    },
+                                }),
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
+                        }),
+                        multiview: None,
+                    })
+            })
+            .context("invalid night light shader")?;
+
+        Ok(Self {
+            home,
+            night,
+            strength: 0.0,
+            render_pipeline,
+            uniform_buffer,
+            bind_group,
+            written: None,
+        })
+    }
+
+    /// Follows the Sun at the home location at `time`.
+    pub fn update(&mut self, time: &DateTime<Utc>) {
+        self.strength = strength(&self.home, time);
+    }
+
+    /// Records uploading the tint, if it changed.
+    pub fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if self.written == Some(self.strength) {
+            return;
+        }
+        let [r, g, b] = self
+            .night
+            .map(|factor| 1.0 + (factor - 1.0) * self.strength);
+        staging.write_buffer(
+            encoder,
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[r, g, b, 1.0]),
+        );
+        self.written = Some(self.strength);
+    }
+
+    /// Tints everything drawn before it in `render_pass`, unless it is day.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.strength <= 0.0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
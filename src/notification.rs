@@ -0,0 +1,45 @@
+use crate::banner::{self, Level};
+
+/// Shows a desktop notification with `summary` as its title, without waiting for it, and a
+/// banner in the windows.
+///
+/// Notifications go through the freedesktop notification service on Linux. Elsewhere, and when
+/// that fails, there is only the banner.
+pub fn notify(summary: &str, body: &str) {
+    banner::post(Level::Info, format!("{}: {}", summary, body));
+    #[cfg(target_os = "linux")]
+    {
+        let (summary, body) = (summary.to_owned(), body.to_owned());
+        std::thread::spawn(move || {
+            if let Err(err) = send(&summary, &body) {
+                log::warn!("failed to show a notification: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send(summary: &str, body: &str) -> zbus::Result<()> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let connection = zbus::blocking::Connection::session()?;
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "Global Clock",
+            0u32,
+            "",
+            summary,
+            body,
+            Vec::<&str>::new(),
+            HashMap::<&str, Value>::new(),
+            // The server's default timeout.
+            -1i32,
+        ),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! A minimal SNTP client (RFC 4330), for measuring how far the system clock is off.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use std::convert::TryInto;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+/// The port that NTP servers listen on.
+const PORT: u16 = 123;
+/// Seconds from the NTP epoch, 1900-01-01, to the Unix epoch.
+const UNIX_EPOCH: i64 = 2_208_988_800;
+
+/// Asks `server`, a host name or address with an optional port, for the time, and returns how far
+/// it is ahead of the system clock, e.g. negative if the system clock is fast.
+pub fn measure_offset(server: &str, timeout: Duration) -> anyhow::Result<chrono::Duration> {
+    let address = match server.to_socket_addrs() {
+        Ok(mut addresses) => addresses.next(),
+        // Without a port.
+        Err(_) => (server, PORT)
+            .to_socket_addrs()
+            .with_context(|| format!("failed to look up time server {}", server))?
+            .next(),
+    }
+    .with_context(|| format!("no address for time server {}", server))?;
+    let socket = UdpSocket::bind(if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(address)?;
+
+    let mut request = [0; 48];
+    // No leap second warning, version 4, client mode.
+    request[0] = 0b00_100_011;
+    let sent = timestamp(&Utc::now());
+    request[40..48].copy_from_slice(&sent.to_be_bytes());
+    socket.send(&request)?;
+    let mut reply = [0; 48];
+    let length = socket
+        .recv(&mut reply)
+        .with_context(|| format!("no answer from time server {}", server))?;
+    let received = timestamp(&Utc::now());
+
+    let field =
+        |range: std::ops::Range<usize>| u64::from_be_bytes(reply[range].try_into().unwrap());
+    if length < 48 || reply[0] & 0b111 != 4 || field(24..32) != sent {
+        bail!("invalid answer from time server {}", server);
+    }
+    if reply[1] == 0 {
+        bail!("time server {} refused to answer", server);
+    }
+    // When the server received the request and sent the reply, by its clock.
+    let (server_received, server_sent) = (field(32..40), field(40..48));
+    // Half of the difference between the two legs' apparent lengths; the network delay cancels
+    // out if it is the same both ways. Differences are taken modulo the NTP era.
+    let offset = (server_received.wrapping_sub(sent) as i64 as i128
+        + server_sent.wrapping_sub(received) as i64 as i128)
+        / 2;
+    Ok(chrono::Duration::nanoseconds(
+        ((offset * 1_000_000_000) >> 32) as i64,
+    ))
+}
+
+/// `time` as an NTP timestamp: seconds since 1900 in the upper 32 bits, and their fraction in the
+/// lower 32 bits.
+fn timestamp(time: &DateTime<Utc>) -> u64 {
+    let seconds = (time.timestamp() + UNIX_EPOCH) as u64;
+    let fraction = (u64::from(time.timestamp_subsec_nanos()) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
@@ -0,0 +1,102 @@
+use crate::theme::Color;
+use serde::{Deserialize, Serialize};
+
+/// Colors of overlays that carry meaning, such as warnings, which can be chosen to stay apart for
+/// people with a color vision deficiency, without telling things apart by red and green alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    #[default]
+    Standard,
+    /// Safe for the most common red-green deficiency, with weak or missing green cones.
+    Deuteranopia,
+    /// Safe for the red-green deficiency with weak or missing red cones, to which reds look dark.
+    Protanopia,
+    /// Safe for the blue-yellow deficiency.
+    Tritanopia,
+}
+
+impl Palette {
+    /// Text that warns about something, e.g. the clock being off.
+    pub fn warning(self) -> Color {
+        match self {
+            Self::Standard => Color::rgba(255, 170, 40, 255),
+            // Orange, and yellow where reds and oranges look dark, from the Okabe-Ito palette.
+            Self::Deuteranopia => Color::rgba(230, 159, 0, 255),
+            Self::Protanopia => Color::rgba(240, 228, 66, 255),
+            Self::Tritanopia => Color::rgba(255, 110, 140, 255),
+        }
+    }
+
+    /// Behind white text of a warning banner.
+    pub fn warning_background(self) -> Color {
+        match self {
+            Self::Standard => Color::rgba(160, 110, 0, 230),
+            Self::Deuteranopia | Self::Protanopia => Color::rgba(150, 105, 0, 230),
+            Self::Tritanopia => Color::rgba(0, 110, 120, 230),
+        }
+    }
+
+    /// Behind white text of an error banner, apart from warnings by more than red and green.
+    pub fn error_background(self) -> Color {
+        match self {
+            Self::Standard => Color::rgba(170, 30, 30, 230),
+            // Blue, which differs from the warnings' amber for red-green deficiencies.
+            Self::Deuteranopia | Self::Protanopia => Color::rgba(0, 90, 160, 230),
+            Self::Tritanopia => Color::rgba(180, 20, 60, 230),
+        }
+    }
+
+    /// `color`, with the differences that the deficiency hides shifted into ones that are still
+    /// seen (daltonization), for colors that come from data, e.g. of markers.
+    pub fn adapt(self, color: Color) -> Color {
+        if self == Self::Standard {
+            return color;
+        }
+        let [r, g, b, _] = color.to_linear();
+        let [l, m, s] = mul(&RGB_TO_LMS, [r, g, b]);
+        let seen = match self {
+            Self::Standard => [l, m, s],
+            Self::Protanopia => [2.02344 * m - 2.52581 * s, m, s],
+            Self::Deuteranopia => [l, 0.494207 * l + 1.24827 * s, s],
+            Self::Tritanopia => [l, m, -0.395913 * l + 0.801109 * m],
+        };
+        let seen = mul(&LMS_TO_RGB, seen);
+        let error = [r - seen[0], g - seen[1], b - seen[2]];
+        let adapted = [
+            r,
+            g + 0.7 * error[0] + error[1],
+            b + 0.7 * error[0] + error[2],
+        ];
+        let [r, g, b] = adapted.map(encode);
+        Color::rgba(r, g, b, color.a)
+    }
+}
+
+/// From linear sRGB to the responses of the eye's long, medium and short cones (Viénot et al.).
+const RGB_TO_LMS: [[f32; 3]; 3] = [
+    [17.8824, 43.5161, 4.11935],
+    [3.45565, 27.1554, 3.86714],
+    [0.0299566, 0.184309, 1.46709],
+];
+
+const LMS_TO_RGB: [[f32; 3]; 3] = [
+    [0.080_944_45, -0.130_504_41, 0.116_721_07],
+    [-0.010_248_534, 0.054_019_33, -0.113_614_71],
+    [-0.000_365_296_94, -0.004_121_614_7, 0.693_511_4],
+];
+
+fn mul(matrix: &[[f32; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+/// A linear-light component as an sRGB byte.
+fn encode(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
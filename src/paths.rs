@@ -0,0 +1,66 @@
+use anyhow::Context;
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+/// Locations of the app's files on disk.
+///
+/// These follow the platform conventions (XDG on Linux, `~/Library` on macOS, `AppData` on
+/// Windows), unless a base directory is given, in which case everything is kept beneath it.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    cache_dir: PathBuf,
+    state_dir: PathBuf,
+}
+
+impl Paths {
+    pub fn new(base_dir: Option<&Path>) -> anyhow::Result<Self> {
+        if let Some(base_dir) = base_dir {
+            return Ok(Self {
+                config_dir: base_dir.join("config"),
+                data_dir: base_dir.join("data"),
+                cache_dir: base_dir.join("cache"),
+                state_dir: base_dir.join("state"),
+            });
+        }
+
+        let dirs = ProjectDirs::from("io.github", "agausmann", "global-clock")
+            .context("cannot determine the home directory; use --base-dir instead")?;
+        Ok(Self {
+            config_dir: dirs.config_dir().to_owned(),
+            data_dir: dirs.data_dir().to_owned(),
+            cache_dir: dirs.cache_dir().to_owned(),
+            // Only Linux has a dedicated state directory.
+            state_dir: dirs
+                .state_dir()
+                .unwrap_or_else(|| dirs.data_local_dir())
+                .to_owned(),
+        })
+    }
+
+    /// The config file that is used when none is given explicitly.
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    /// Directory containing user-defined theme files.
+    pub fn themes_dir(&self) -> PathBuf {
+        self.config_dir.join("themes")
+    }
+
+    /// Directory for user-installed assets, which take precedence over the system-wide ones.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Directory for downloaded files that can be re-fetched if deleted.
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Directory for state that persists across restarts but is not configuration.
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+}
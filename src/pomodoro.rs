@@ -0,0 +1,380 @@
+use crate::layer::{FrameInfo, Layer};
+use crate::notification;
+use crate::registry::LayerContext;
+use crate::ring::Ring;
+use crate::staging::StagingPool;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use anyhow::bail;
+use instant::Instant;
+use serde::Deserialize;
+use std::any::Any;
+use std::time::Duration;
+
+/// Settings of the Pomodoro timer, from the config's `[layer.pomodoro]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroSettings {
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// Work phases before each long break. 0 never takes one.
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Work,
+    Break,
+    LongBreak,
+}
+
+impl Phase {
+    /// Title of the notification when the phase begins.
+    fn announcement(self) -> &'static str {
+        match self {
+            Self::Work => "Back to work",
+            Self::Break => "Time for a break",
+            Self::LongBreak => "Time for a long break",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Reset, ready to start working.
+    Idle,
+    Running {
+        ends_at: Instant,
+    },
+    Paused {
+        remaining: Duration,
+    },
+}
+
+/// Where the timer is in its cycle, which is carried over when the layer is created again.
+#[derive(Debug, Clone)]
+struct Timer {
+    phase: Phase,
+    /// Work phases finished since the last long break.
+    completed: u32,
+    state: State,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Self {
+            phase: Phase::Work,
+            completed: 0,
+            state: State::Idle,
+        }
+    }
+
+    fn remaining(&self, settings: &PomodoroSettings, now: Instant) -> Duration {
+        match self.state {
+            State::Idle => duration(settings, self.phase),
+            State::Running { ends_at } => ends_at.saturating_duration_since(now),
+            State::Paused { remaining } => remaining,
+        }
+    }
+
+    /// Starts or resumes the timer if it is stopped, or pauses it if it is running.
+    fn toggle(&mut self, settings: &PomodoroSettings, now: Instant) {
+        self.state = match self.state {
+            State::Running { ends_at } => State::Paused {
+                remaining: ends_at.saturating_duration_since(now),
+            },
+            State::Paused { remaining } => State::Running {
+                ends_at: now + remaining,
+            },
+            State::Idle => State::Running {
+                ends_at: now + duration(settings, self.phase),
+            },
+        };
+    }
+
+    /// Carries out one of the actions in [`Pomodoro`], returning whether it is one.
+    fn handle_action(&mut self, settings: &PomodoroSettings, action: &str, now: Instant) -> bool {
+        match action {
+            "pomodoro.toggle" => self.toggle(settings, now),
+            "pomodoro.reset" => *self = Timer::new(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves on to the next phase if this one is over, returning the new phase.
+    fn advance(&mut self, settings: &PomodoroSettings, now: Instant) -> Option<Phase> {
+        match self.state {
+            State::Running { ends_at } if ends_at <= now => {}
+            _ => return None,
+        }
+        self.phase = match self.phase {
+            Phase::Work => {
+                self.completed += 1;
+                if settings.long_break_every > 0 && self.completed >= settings.long_break_every {
+                    self.completed = 0;
+                    Phase::LongBreak
+                } else {
+                    Phase::Break
+                }
+            }
+            Phase::Break | Phase::LongBreak => Phase::Work,
+        };
+        // From now rather than from the end of the last phase, which may be long past if the
+        // computer was asleep.
+        self.state = State::Running {
+            ends_at: now + duration(settings, self.phase),
+        };
+        Some(self.phase)
+    }
+}
+
+fn duration(settings: &PomodoroSettings, phase: Phase) -> Duration {
+    let minutes = match phase {
+        Phase::Work => settings.work_minutes,
+        Phase::Break => settings.break_minutes,
+        Phase::LongBreak => settings.long_break_minutes,
+    };
+    Duration::from_secs(u64::from(minutes) * 60)
+}
+
+/// A Pomodoro timer, alternating work with short breaks and a long break every few cycles, drawn
+/// as an arc around the clock dial that shrinks as the current phase runs out.
+///
+/// The `pomodoro.toggle` action starts or pauses it, and `pomodoro.reset` stops it and returns to
+/// the start of a work phase. A notification is shown whenever a phase ends. The timer follows
+/// real time, even when the displayed time is simulated.
+pub struct Pomodoro {
+    settings: PomodoroSettings,
+    timer: Timer,
+    work_color: Color,
+    break_color: Color,
+    ring: Ring,
+}
+
+impl Pomodoro {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: PomodoroSettings = cx.settings("pomodoro")?;
+        if settings.work_minutes == 0 || settings.break_minutes == 0 {
+            bail!("pomodoro.work_minutes and pomodoro.break_minutes must be more than 0");
+        }
+        if settings.long_break_every > 0 && settings.long_break_minutes == 0 {
+            bail!("pomodoro.long_break_minutes must be more than 0");
+        }
+
+        Ok(Self {
+            settings,
+            timer: Timer::new(),
+            work_color: cx.theme.hands,
+            break_color: cx.theme.dial,
+            ring: Ring::new(cx.gfx, cx.viewport, cx.assets)?,
+        })
+    }
+
+    /// Sets the arc to how much of the current phase is left.
+    fn update_arc(&mut self, now: Instant) {
+        let total = duration(&self.settings, self.timer.phase);
+        let remaining = self.timer.remaining(&self.settings, now);
+        let color = match self.timer.phase {
+            Phase::Work => self.work_color,
+            Phase::Break | Phase::LongBreak => self.break_color,
+        };
+        let fraction = remaining.as_secs_f32() / total.as_secs_f32();
+        match self.timer.state {
+            State::Idle => self.ring.hide(),
+            State::Paused { .. } => self.ring.show(color, 0.5, fraction),
+            State::Running { .. } => self.ring.show(color, 1.0, fraction),
+        }
+    }
+}
+
+impl Layer for Pomodoro {
+    fn name(&self) -> &str {
+        "pomodoro"
+    }
+
+    fn shaders(&self) -> &[&str] {
+        &["shaders/ring.wgsl"]
+    }
+
+    fn reload_shaders(&mut self) -> anyhow::Result<()> {
+        self.ring.reload_shader()
+    }
+
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn update(&mut self, _frame: &FrameInfo) {
+        let now = Instant::now();
+        if let Some(phase) = self.timer.advance(&self.settings, now) {
+            let minutes = duration(&self.settings, phase).as_secs() / 60;
+            notification::notify(phase.announcement(), &format!("{} minutes", minutes));
+        }
+        self.update_arc(now);
+    }
+
+    fn handle_action(&mut self, action: &str) -> bool {
+        let now = Instant::now();
+        if !self.timer.handle_action(&self.settings, action, now) {
+            return false;
+        }
+        self.update_arc(now);
+        true
+    }
+
+    fn describe(&self) -> Option<String> {
+        let paused = match self.timer.state {
+            State::Idle => return None,
+            State::Running { .. } => "",
+            State::Paused { .. } => ", paused",
+        };
+        let phase = match self.timer.phase {
+            Phase::Work => "Work",
+            Phase::Break => "Break",
+            Phase::LongBreak => "Long break",
+        };
+        let minutes = self
+            .timer
+            .remaining(&self.settings, Instant::now())
+            .as_secs()
+            / 60
+            + 1;
+        Some(format!("{}: under {} min left{}", phase, minutes, paused))
+    }
+
+    fn take_state(&mut self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.timer.clone()))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(timer) = state.downcast::<Timer>() {
+            self.timer = *timer;
+            self.update_arc(Instant::now());
+        }
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        self.ring.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.ring.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
+        self.ring.draw(render_pass, viewport);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::minutes;
+
+    #[test]
+    fn reset_starts_the_cycle_over() {
+        let settings = PomodoroSettings {
+            long_break_every: 2,
+            ..PomodoroSettings::default()
+        };
+        let start = Instant::now();
+        let mut timer = Timer::new();
+        timer.toggle(&settings, start);
+        assert_eq!(
+            timer.advance(&settings, start + minutes(25)),
+            Some(Phase::Break)
+        );
+        assert!(timer.handle_action(&settings, "pomodoro.reset", start + minutes(26)));
+        assert_eq!(timer.phase, Phase::Work);
+        assert_eq!(timer.remaining(&settings, start + minutes(26)), minutes(25));
+        // The work phase before the reset does not count towards the long break.
+        timer.toggle(&settings, start + minutes(30));
+        assert_eq!(
+            timer.advance(&settings, start + minutes(55)),
+            Some(Phase::Break)
+        );
+        // Actions of other layers are left to them.
+        assert!(!timer.handle_action(&settings, "countdown.toggle", start + minutes(56)));
+    }
+
+    #[test]
+    fn pauses_and_resumes() {
+        let settings = PomodoroSettings::default();
+        let start = Instant::now();
+        let mut timer = Timer::new();
+        assert_eq!(timer.remaining(&settings, start), minutes(25));
+        assert!(timer.handle_action(&settings, "pomodoro.toggle", start));
+        assert_eq!(timer.remaining(&settings, start + minutes(10)), minutes(15));
+        timer.handle_action(&settings, "pomodoro.toggle", start + minutes(10));
+        // The work phase keeps its 15 minutes through a 10-minute pause.
+        assert_eq!(timer.remaining(&settings, start + minutes(20)), minutes(15));
+        timer.handle_action(&settings, "pomodoro.toggle", start + minutes(20));
+        assert_eq!(timer.remaining(&settings, start + minutes(25)), minutes(10));
+        assert!(timer.handle_action(&settings, "pomodoro.reset", start + minutes(25)));
+        assert!(matches!(timer.state, State::Idle));
+        assert_eq!(timer.remaining(&settings, start + minutes(25)), minutes(25));
+    }
+
+    #[test]
+    fn phase_does_not_end_while_paused() {
+        let settings = PomodoroSettings::default();
+        let start = Instant::now();
+        let mut timer = Timer::new();
+        timer.toggle(&settings, start);
+        timer.toggle(&settings, start + minutes(20));
+        // Paused with 5 minutes of work left, for 40 minutes.
+        assert_eq!(timer.advance(&settings, start + minutes(60)), None);
+        assert_eq!(timer.phase, Phase::Work);
+        timer.toggle(&settings, start + minutes(60));
+        assert_eq!(timer.advance(&settings, start + minutes(64)), None);
+        assert_eq!(
+            timer.advance(&settings, start + minutes(65)),
+            Some(Phase::Break)
+        );
+    }
+
+    #[test]
+    fn next_phase_starts_when_the_last_one_is_noticed() {
+        let settings = PomodoroSettings::default();
+        let start = Instant::now();
+        let mut timer = Timer::new();
+        timer.toggle(&settings, start);
+        // As if the computer slept through the end of the work phase.
+        assert_eq!(
+            timer.advance(&settings, start + minutes(40)),
+            Some(Phase::Break)
+        );
+        assert_eq!(timer.remaining(&settings, start + minutes(40)), minutes(5));
+    }
+
+    #[test]
+    fn long_break_comes_every_few_work_phases() {
+        let settings = PomodoroSettings {
+            long_break_every: 2,
+            ..PomodoroSettings::default()
+        };
+        let mut now = Instant::now();
+        let mut timer = Timer::new();
+        timer.toggle(&settings, now);
+        let mut phases = Vec::new();
+        for _ in 0..6 {
+            now += timer.remaining(&settings, now);
+            phases.extend(timer.advance(&settings, now));
+        }
+        use Phase::*;
+        assert_eq!(phases, [Break, Work, LongBreak, Work, Break, Work]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::GraphicsContext;
+use std::sync::{Arc, Mutex};
+
+/// Most scopes measured in one frame; later ones are ignored.
+const MAX_SCOPES: u32 = 32;
+
+/// Set once mapping a readback buffer has finished, to whether it succeeded.
+type MapResult = Arc<Mutex<Option<bool>>>;
+
+/// Where timestamps can be written: between passes in a command encoder, or between draws in a
+/// render pass if [`GpuProfiler::inside_passes`].
+pub trait Timestamps {
+    fn write_timestamp(&mut self, query_set: &wgpu::QuerySet, index: u32);
+}
+
+impl Timestamps for wgpu::CommandEncoder {
+    fn write_timestamp(&mut self, query_set: &wgpu::QuerySet, index: u32) {
+        wgpu::CommandEncoder::write_timestamp(self, query_set, index);
+    }
+}
+
+impl Timestamps for wgpu::RenderPass<'_> {
+    fn write_timestamp(&mut self, query_set: &wgpu::QuerySet, index: u32) {
+        wgpu::RenderPass::write_timestamp(self, query_set, index);
+    }
+}
+
+/// Measures how long the GPU spends on parts of a frame, using timestamp queries.
+///
+/// Results are read back without waiting for the GPU, so they lag a frame or two behind, and
+/// frames recorded while a readback is still in flight are not measured.
+pub struct GpuProfiler {
+    gfx: GraphicsContext,
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Whether the current frame is being measured.
+    recording: bool,
+    /// Labels of the scopes in the current frame, and whether the last one has ended.
+    scopes: Vec<String>,
+    open: bool,
+    /// Labels of the frame being read back.
+    in_flight: Option<(Vec<String>, MapResult)>,
+    results: Vec<(String, f64)>,
+}
+
+impl GpuProfiler {
+    /// Returns `None` if the device was not created with [`wgpu::Features::TIMESTAMP_QUERY`].
+    pub fn new(gfx: &GraphicsContext) -> Option<Self> {
+        if !gfx
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            return None;
+        }
+        let size = u64::from(MAX_SCOPES * 2) * wgpu::QUERY_SIZE as u64;
+        Some(Self {
+            gfx: gfx.clone(),
+            query_set: gfx.device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("GpuProfiler.query_set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_SCOPES * 2,
+            }),
+            resolve_buffer: gfx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("GpuProfiler.resolve_buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: gfx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("GpuProfiler.readback_buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            recording: false,
+            scopes: Vec::new(),
+            open: false,
+            in_flight: None,
+            results: Vec::new(),
+        })
+    }
+
+    /// Collects the results of earlier frames if they are ready, and starts measuring a new one.
+    pub fn begin_frame(&mut self) {
+        self.gfx.device.poll(wgpu::Maintain::Poll);
+        if let Some((labels, mapped)) = &self.in_flight {
+            let mapped = *mapped.lock().unwrap();
+            if mapped == Some(true) {
+                let period = f64::from(self.gfx.queue.get_timestamp_period());
+                let timestamps: Vec<u64> =
+                    bytemuck::cast_slice(&self.readback_buffer.slice(..).get_mapped_range())
+                        [..labels.len() * 2]
+                        .to_vec();
+                self.readback_buffer.unmap();
+                self.results = labels
+                    .iter()
+                    .zip(timestamps.chunks(2))
+                    .map(|(label, pair)| {
+                        let ticks = pair[1].saturating_sub(pair[0]);
+                        (label.clone(), ticks as f64 * period / 1e6)
+                    })
+                    .collect();
+            }
+            if mapped.is_some() {
+                self.in_flight = None;
+            }
+        }
+        self.recording = self.in_flight.is_none();
+        self.scopes.clear();
+        self.open = false;
+    }
+
+    /// Whether the device supports timing parts of a render pass, rather than only whole passes.
+    pub fn inside_passes(&self) -> bool {
+        self.gfx
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES)
+    }
+
+    /// Starts timing the commands recorded into `encoder` from now on, until [`Self::end`].
+    pub fn begin(&mut self, encoder: &mut impl Timestamps, label: &str) {
+        if !self.recording || self.open || self.scopes.len() as u32 >= MAX_SCOPES {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, self.scopes.len() as u32 * 2);
+        self.scopes.push(label.to_owned());
+        self.open = true;
+    }
+
+    pub fn end(&mut self, encoder: &mut impl Timestamps) {
+        if !self.open {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, self.scopes.len() as u32 * 2 - 1);
+        self.open = false;
+    }
+
+    /// Copies the frame's timestamps where they can be read. Call before finishing `encoder`.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.end(encoder);
+        if !self.recording || self.scopes.is_empty() {
+            return;
+        }
+        let count = self.scopes.len() as u32 * 2;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            u64::from(count) * wgpu::QUERY_SIZE as u64,
+        );
+    }
+
+    /// Starts reading back the frame's timestamps. Call after submitting the encoder.
+    pub fn end_frame(&mut self) {
+        if !self.recording || self.scopes.is_empty() {
+            return;
+        }
+        self.recording = false;
+        let mapped = MapResult::default();
+        let callback_mapped = mapped.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback_mapped.lock().unwrap() = Some(result.is_ok());
+            });
+        self.in_flight = Some((std::mem::take(&mut self.scopes), mapped));
+    }
+
+    /// GPU time of each scope in the latest measured frame, in milliseconds.
+    pub fn results(&self) -> &[(String, f64)] {
+        &self.results
+    }
+}
@@ -0,0 +1,392 @@
+use crate::assets::Assets;
+use crate::banner::{BannerSettings, Banners};
+#[cfg(feature = "calendar")]
+use crate::calendar::{Calendar, CalendarSettings};
+use crate::clock_face::ClockFace;
+#[cfg(feature = "clock-offset")]
+use crate::clock_offset::{ClockOffset, ClockOffsetSettings};
+#[cfg(feature = "date-strip")]
+use crate::date_strip::{DateStrip, DateStripSettings};
+#[cfg(feature = "daylight-stats")]
+use crate::daylight_stats::{DaylightStats, DaylightStatsSettings};
+#[cfg(feature = "digital-clock")]
+use crate::digital_clock::{DigitalClock, DigitalClockSettings};
+use crate::globe::Globe;
+use crate::layer::Layer;
+use crate::locale::Locale;
+use crate::location::Location;
+#[cfg(feature = "markers")]
+use crate::markers::{Markers, MarkersSettings};
+use crate::palette::Palette;
+#[cfg(feature = "pomodoro")]
+use crate::pomodoro::{Pomodoro, PomodoroSettings};
+#[cfg(feature = "sun-countdown")]
+use crate::sun_countdown::{SunCountdown, SunCountdownSettings};
+use crate::theme::Theme;
+#[cfg(feature = "ticker")]
+use crate::ticker::{Ticker, TickerSettings};
+#[cfg(feature = "time-sync")]
+use crate::time_sync::{TimeSync, TimeSyncSettings};
+#[cfg(feature = "timers")]
+use crate::timers::{Timers, TimersSettings};
+use crate::viewport::Viewport;
+#[cfg(feature = "weather")]
+use crate::weather::{Weather, WeatherSettings};
+use crate::GraphicsContext;
+use anyhow::{bail, Context};
+use serde::de::DeserializeOwned;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+
+/// What layers are created from.
+pub struct LayerContext<'a> {
+    pub gfx: &'a GraphicsContext,
+    pub viewport: &'a Viewport,
+    pub assets: &'a Assets,
+    pub theme: &'a Theme,
+    /// Directory to load the globe's textures from instead of the assets, if set.
+    pub texture_dir: Option<&'a Path>,
+    /// Bytes of GPU memory that the layer's textures should fit in, if limited.
+    pub texture_budget: Option<u64>,
+    /// Conventions for showing times and dates in text.
+    pub locale: &'a Locale,
+    /// Colors of overlays that carry meaning.
+    pub palette: Palette,
+    /// Where the user is, if configured.
+    pub home: Option<&'a Location>,
+    /// Settings of individual layers, by layer name, e.g. from the config's `[layer.<name>]`
+    /// tables.
+    pub settings: &'a BTreeMap<String, toml::Table>,
+    /// What the layers of every window share.
+    pub shared: &'a SharedState,
+}
+
+/// What the layers of every window share, such as a background thread that fetches data for all
+/// of them, by type. Each value lives as long as a layer holds on to it.
+#[derive(Default)]
+pub struct SharedState {
+    values: Mutex<HashMap<TypeId, Weak<dyn Any + Send + Sync>>>,
+}
+
+impl SharedState {
+    /// The `T` that other layers hold, if it `fits`, or else a new one from `create`, which the
+    /// layers created after this one then share.
+    pub fn get_or_try_insert_with<T: Any + Send + Sync>(
+        &self,
+        fits: impl FnOnce(&T) -> bool,
+        create: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<Arc<T>> {
+        let mut values = self.values.lock().unwrap();
+        let existing = values
+            .get(&TypeId::of::<T>())
+            .and_then(Weak::upgrade)
+            .and_then(|value| value.downcast::<T>().ok());
+        if let Some(value) = existing.filter(|value| fits(value)) {
+            return Ok(value);
+        }
+        let value = Arc::new(create()?);
+        values.insert(TypeId::of::<T>(), Arc::<T>::downgrade(&value));
+        Ok(value)
+    }
+}
+
+impl LayerContext<'_> {
+    /// The settings of the layer `name`, or the defaults if there are none.
+    pub fn settings<T: DeserializeOwned + Default>(&self, name: &str) -> anyhow::Result<T> {
+        let Some(table) = self.settings.get(name) else {
+            return Ok(T::default());
+        };
+        deserialize_settings(&mut table.clone())
+            .map_err(serde_path_to_error::Error::into_inner)
+            .with_context(|| format!("invalid settings for layer {:?}", name))
+    }
+}
+
+pub type LayerFactory = fn(&LayerContext) -> anyhow::Result<Box<dyn Layer>>;
+
+/// Checks the settings of a layer, returning every problem with them.
+type SettingsCheck = fn(&toml::Table) -> Vec<SettingsProblem>;
+
+/// A problem with a layer's settings, found by [`LayerRegistry::check_settings`].
+#[derive(Debug)]
+pub struct SettingsProblem {
+    /// Path of the offending value in the layer's table, e.g. `refresh_minutes`, or empty for the
+    /// table itself.
+    pub key: String,
+    pub message: String,
+}
+
+/// The layers that can be created by name, e.g. from the `layers` of a window in the config.
+///
+/// The default registry contains the built-in layers, including the optional ones enabled by
+/// Cargo features. Applications embedding the widgets can register their own layers on top.
+#[derive(Clone)]
+pub struct LayerRegistry {
+    factories: Vec<Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    name: &'static str,
+    factory: LayerFactory,
+    /// Whether the layer's textures take from the window's texture budget.
+    textured: bool,
+    /// Checks the layer's settings, if it has any.
+    settings: Option<SettingsCheck>,
+}
+
+impl LayerRegistry {
+    pub fn empty() -> Self {
+        Self {
+            factories: Vec::new(),
+        }
+    }
+
+    /// Adds a layer, replacing any layer that was registered with the same name.
+    pub fn register(&mut self, name: &'static str, factory: LayerFactory) {
+        self.insert(Entry {
+            name,
+            factory,
+            textured: false,
+            settings: None,
+        });
+    }
+
+    /// Adds a layer that reads its settings into `T` with [`LayerContext::settings`], so that
+    /// they can be checked before it is created, replacing any layer that was registered with the
+    /// same name.
+    pub fn register_with_settings<T: DeserializeOwned + Default>(
+        &mut self,
+        name: &'static str,
+        factory: LayerFactory,
+    ) {
+        self.insert(Entry {
+            name,
+            factory,
+            textured: false,
+            settings: Some(check_settings::<T>),
+        });
+    }
+
+    /// Adds a layer whose textures take from the texture budget, such as the globe, replacing any
+    /// layer that was registered with the same name.
+    pub fn register_textured(&mut self, name: &'static str, factory: LayerFactory) {
+        self.insert(Entry {
+            name,
+            factory,
+            textured: true,
+            settings: None,
+        });
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        match self.factories.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => self.factories.push(entry),
+        }
+    }
+
+    /// Names of the registered layers, in their default order from bottom to top.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.factories.iter().map(|entry| entry.name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names().any(|n| n == name)
+    }
+
+    /// The share of `budget` that each of the layers `names` gets for its textures: the layers
+    /// without textures take none of it.
+    pub fn texture_budget<S: AsRef<str>>(&self, budget: Option<u64>, names: &[S]) -> Option<u64> {
+        let textured = names
+            .iter()
+            .filter(|name| {
+                self.factories
+                    .iter()
+                    .any(|entry| entry.textured && entry.name == name.as_ref())
+            })
+            .count();
+        budget.map(|budget| budget / textured.max(1) as u64)
+    }
+
+    /// The problems with `table` as the settings of the layer `name`, which are only known for
+    /// layers registered with [`LayerRegistry::register_with_settings`].
+    pub fn check_settings(&self, name: &str, table: &toml::Table) -> Vec<SettingsProblem> {
+        self.factories
+            .iter()
+            .find(|entry| entry.name == name)
+            .and_then(|entry| entry.settings)
+            .map_or_else(Vec::new, |check| check(table))
+    }
+
+    pub fn create(&self, name: &str, cx: &LayerContext) -> anyhow::Result<Box<dyn Layer>> {
+        match self.factories.iter().find(|entry| entry.name == name) {
+            Some(entry) => (entry.factory)(cx),
+            None => bail!(
+                "unknown layer {:?}; expected one of {}",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+impl Default for LayerRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_textured("globe", |cx| {
+            Ok(Box::new(Globe::new(
+                cx.gfx,
+                cx.viewport,
+                cx.assets,
+                cx.texture_dir,
+                cx.theme,
+                cx.texture_budget,
+            )?))
+        });
+        registry.register_textured("clock_face", |cx| {
+            Ok(Box::new(ClockFace::new(
+                cx.gfx,
+                cx.viewport,
+                cx.assets,
+                cx.theme,
+                cx.texture_budget,
+            )?))
+        });
+        // Optional layers are registered here, each behind its own Cargo feature.
+        #[cfg(feature = "clock-offset")]
+        registry.register_with_settings::<ClockOffsetSettings>("clock_offset", |cx| {
+            Ok(Box::new(ClockOffset::new(cx)?))
+        });
+        #[cfg(feature = "digital-clock")]
+        registry.register_with_settings::<DigitalClockSettings>("digital_clock", |cx| {
+            Ok(Box::new(DigitalClock::new(cx)?))
+        });
+        #[cfg(feature = "date-strip")]
+        registry.register_with_settings::<DateStripSettings>("date_strip", |cx| {
+            Ok(Box::new(DateStrip::new(cx)?))
+        });
+        #[cfg(feature = "calendar")]
+        registry.register_with_settings::<CalendarSettings>("calendar", |cx| {
+            Ok(Box::new(Calendar::new(cx)?))
+        });
+        #[cfg(feature = "daylight-stats")]
+        registry.register_with_settings::<DaylightStatsSettings>("daylight_stats", |cx| {
+            Ok(Box::new(DaylightStats::new(cx)?))
+        });
+        #[cfg(feature = "markers")]
+        registry.register_with_settings::<MarkersSettings>("markers", |cx| {
+            Ok(Box::new(Markers::new(cx)?))
+        });
+        #[cfg(feature = "pomodoro")]
+        registry.register_with_settings::<PomodoroSettings>("pomodoro", |cx| {
+            Ok(Box::new(Pomodoro::new(cx)?))
+        });
+        #[cfg(feature = "sun-countdown")]
+        registry.register_with_settings::<SunCountdownSettings>("sun_countdown", |cx| {
+            Ok(Box::new(SunCountdown::new(cx)?))
+        });
+        #[cfg(feature = "ticker")]
+        registry.register_with_settings::<TickerSettings>("ticker", |cx| {
+            Ok(Box::new(Ticker::new(cx)?))
+        });
+        #[cfg(feature = "time-sync")]
+        registry.register_with_settings::<TimeSyncSettings>("time_sync", |cx| {
+            Ok(Box::new(TimeSync::new(cx)?))
+        });
+        #[cfg(feature = "timers")]
+        registry.register_with_settings::<TimersSettings>("timers", |cx| {
+            Ok(Box::new(Timers::new(cx)?))
+        });
+        #[cfg(feature = "weather")]
+        registry.register_with_settings::<WeatherSettings>("weather", |cx| {
+            Ok(Box::new(Weather::new(cx)?))
+        });
+        // Over everything else.
+        registry.register_with_settings::<BannerSettings>("banners", |cx| {
+            Ok(Box::new(Banners::new(cx)?))
+        });
+        registry
+    }
+}
+
+/// Deserializes the settings of a layer from `table`.
+fn deserialize_settings<T: DeserializeOwned>(
+    table: &mut toml::Table,
+) -> Result<T, serde_path_to_error::Error<toml::de::Error>> {
+    loop {
+        match serde_path_to_error::deserialize(toml::Value::Table(table.clone())) {
+            Ok(settings) => return Ok(settings),
+            Err(err) => {
+                // Settings from environment variables have their types guessed from the text, so
+                // a number or boolean where text is expected is taken as its text, e.g. a command
+                // named `true`.
+                if !stringify(table, &err.path().to_string()) {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+fn check_settings<T: DeserializeOwned>(table: &toml::Table) -> Vec<SettingsProblem> {
+    let mut table = table.clone();
+    let mut problems = Vec::new();
+    loop {
+        let Err(err) = deserialize_settings::<T>(&mut table) else {
+            return problems;
+        };
+        let key = match err.path().to_string() {
+            root if root == "." => String::new(),
+            key => key,
+        };
+        problems.push(SettingsProblem {
+            message: err.into_inner().to_string(),
+            key: key.clone(),
+        });
+        // Drop the invalid value so that the rest can still be checked.
+        if !remove(&mut table, &key) {
+            return problems;
+        }
+    }
+}
+
+/// Walks `path` in `table` to the table holding its last segment.
+fn parent<'a>(table: &'a mut toml::Table, path: &'a str) -> Option<(&'a mut toml::Table, &'a str)> {
+    let mut segments: Vec<&str> = path.split('.').collect();
+    let leaf = segments.pop()?;
+    let mut table = table;
+    for segment in segments {
+        table = match table.get_mut(segment) {
+            Some(toml::Value::Table(table)) => table,
+            _ => return None,
+        };
+    }
+    Some((table, leaf))
+}
+
+fn remove(table: &mut toml::Table, path: &str) -> bool {
+    parent(table, path).is_some_and(|(table, leaf)| table.remove(leaf).is_some())
+}
+
+/// Replaces the number, boolean or date at `path` in `table` with its text, returning whether
+/// there was one.
+fn stringify(table: &mut toml::Table, path: &str) -> bool {
+    let Some((table, leaf)) = parent(table, path) else {
+        return false;
+    };
+    match table.get_mut(leaf) {
+        Some(
+            value @ (toml::Value::Integer(_)
+            | toml::Value::Float(_)
+            | toml::Value::Boolean(_)
+            | toml::Value::Datetime(_)),
+        ) => {
+            *value = toml::Value::String(value.to_string());
+            true
+        }
+        _ => false,
+    }
+}
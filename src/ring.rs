@@ -0,0 +1,192 @@
+use crate::assets::Assets;
+use crate::staging::StagingPool;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use crate::GraphicsContext;
+use anyhow::Context;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Radii of the ring, in the scene's units, just outside the clock face's ticks.
+const INNER_RADIUS: f32 = 0.965;
+const OUTER_RADIUS: f32 = 0.995;
+
+#[derive(Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+struct Uniforms {
+    /// Premultiplied.
+    color: [f32; 4],
+    fraction: f32,
+    inner_radius: f32,
+    outer_radius: f32,
+    _padding: f32,
+}
+
+/// An arc around the clock dial, from the top clockwise, for layers that show progress, such as
+/// timers.
+pub struct Ring {
+    gfx: GraphicsContext,
+    assets: Assets,
+    pipeline_layout: wgpu::PipelineLayout,
+    render_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    uniforms: Uniforms,
+    written_uniforms: Option<Uniforms>,
+}
+
+impl Ring {
+    /// Creates a ring that is hidden until [`Self::show`] is called.
+    pub fn new(
+        gfx: &GraphicsContext,
+        viewport: &Viewport,
+        assets: &Assets,
+    ) -> anyhow::Result<Self> {
+        let uniforms = Uniforms {
+            color: [0.0; 4],
+            fraction: 0.0,
+            inner_radius: INNER_RADIUS,
+            outer_radius: OUTER_RADIUS,
+            _padding: 0.0,
+        };
+        let uniform_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Ring.uniform_buffer"),
+                contents: bytemuck::bytes_of(&uniforms),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let bind_group_layout =
+            gfx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Ring.bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ring.bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Ring.pipeline_layout"),
+                bind_group_layouts: &[&bind_group_layout, viewport.bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+        let render_pipeline = Self::create_render_pipeline(gfx, assets, &pipeline_layout)?;
+        Ok(Self {
+            gfx: gfx.clone(),
+            assets: assets.clone(),
+            pipeline_layout,
+            render_pipeline,
+            uniform_buffer,
+            bind_group,
+            uniforms,
+            written_uniforms: None,
+        })
+    }
+
+    fn create_render_pipeline(
+        gfx: &GraphicsContext,
+        assets: &Assets,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> anyhow::Result<wgpu::RenderPipeline> {
+        let shader_source = assets.read_to_string("shaders/ring.wgsl")?;
+        gfx.validate(|| {
+            let shader_module = gfx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("Ring.shader_module"),
+                    source: wgpu::ShaderSource::Wgsl(shader_source),
+                });
+            gfx.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Ring.render_pipeline"),
+                    layout: Some(pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: gfx.render_format,
+                            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
+        })
+        .context("invalid ring shader")
+    }
+
+    /// Recreates the render pipeline from the current shader source. On failure, the previous
+    /// pipeline is kept.
+    pub fn reload_shader(&mut self) -> anyhow::Result<()> {
+        self.render_pipeline =
+            Self::create_render_pipeline(&self.gfx, &self.assets, &self.pipeline_layout)?;
+        Ok(())
+    }
+
+    /// Draws `fraction` of the ring, from 0.0 to 1.0, in `color` made `opacity` times as opaque.
+    pub fn show(&mut self, color: Color, opacity: f32, fraction: f32) {
+        let [r, g, b, a] = color.to_linear();
+        let a = a * opacity;
+        self.uniforms.color = [r * a, g * a, b * a, a];
+        self.uniforms.fraction = fraction;
+    }
+
+    pub fn hide(&mut self) {
+        self.uniforms.color = [0.0; 4];
+    }
+
+    /// Records writing the ring's uniforms, if they changed.
+    pub fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if self.written_uniforms == Some(self.uniforms) {
+            return;
+        }
+        staging.write_buffer(
+            encoder,
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        self.written_uniforms = Some(self.uniforms);
+    }
+
+    pub fn gpu_memory(&self) -> u64 {
+        self.uniform_buffer.size()
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
+        if self.uniforms.color[3] == 0.0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, viewport.bind_group(), &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
@@ -0,0 +1,328 @@
+use chrono::{DateTime, Utc};
+use global_clock::layer::floor_time;
+use global_clock::time_source::JUMP_THRESHOLD;
+use global_clock::TimeSource;
+use instant::{Duration, Instant};
+
+/// Decides when to wake up, and whether anything changed that needs a new frame.
+///
+/// Each layer has its own tick interval (see [`global_clock::Layer::tick_interval`]), e.g. every
+/// second for the clock hands and every minute for the globe, so the app only wakes up as often
+/// as the fastest of them needs. No interval is shorter than the frame interval, which stands in
+/// for layers that change every frame.
+pub struct FrameScheduler {
+    interval: Duration,
+    /// The interval while the time source is in a transition, see
+    /// [`TimeSource::in_transition`].
+    animation_interval: Duration,
+    tick_intervals: Vec<Duration>,
+    /// The time shown in the last frame that was drawn.
+    drawn: Option<DateTime<Utc>>,
+    /// When the last wake-up was scheduled for, by the system clock, to notice the machine
+    /// sleeping through it.
+    expected_wakeup: Option<DateTime<Utc>>,
+    /// When the last wake-up was scheduled, by the system and monotonic clocks, to notice the
+    /// system clock being set in the meantime.
+    scheduled_at: Option<(DateTime<Utc>, Instant)>,
+}
+
+// Waking up exactly on the boundary risks reading a time just before it.
+const WAKEUP_DELAY: Duration = Duration::from_millis(1);
+
+/// Longest time between wake-ups. Wake-ups are scheduled by a clock that stops while the machine
+/// sleeps on some platforms, so this bounds how long the clock stays stale after waking.
+const MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// How much later than scheduled a wake-up must be to count as the machine having slept.
+const SLEEP_THRESHOLD: chrono::Duration = chrono::Duration::seconds(5);
+
+impl FrameScheduler {
+    pub fn new(interval: Duration, animation_interval: Duration) -> Self {
+        Self {
+            interval,
+            animation_interval,
+            tick_intervals: Vec::new(),
+            drawn: None,
+            expected_wakeup: None,
+            scheduled_at: None,
+        }
+    }
+
+    /// The time between frames, outside of transitions.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Sets the tick intervals of the layers being drawn.
+    pub fn set_tick_intervals(&mut self, intervals: impl IntoIterator<Item = Duration>) {
+        self.tick_intervals.clear();
+        self.tick_intervals.extend(intervals);
+        self.tick_intervals.sort();
+        self.tick_intervals.dedup();
+    }
+
+    fn intervals(&self) -> impl Iterator<Item = Duration> + '_ {
+        let frame_interval = self.interval;
+        let default = self.tick_intervals.is_empty().then_some(frame_interval);
+        self.tick_intervals
+            .iter()
+            .map(move |&interval| interval.max(frame_interval))
+            .chain(default)
+    }
+
+    /// When the time shown by `time_source` next reaches a multiple of any tick interval, so
+    /// that e.g. with an interval of one second, the hands move exactly when the displayed second
+    /// changes. The wake-up is computed afresh from the clock each time, so that late wake-ups do
+    /// not add up.
+    ///
+    /// Intervals that pass faster than the frame interval in real time, e.g. when the clock runs
+    /// fast or is stopped, are not aligned to, and wake up after the frame interval instead.
+    pub fn next_wakeup(&mut self, time_source: &impl TimeSource) -> Instant {
+        let now = time_source.now();
+        let speed = time_source.speed();
+        let nanos = now.timestamp() as i128 * 1_000_000_000 + now.timestamp_subsec_nanos() as i128;
+        let until_boundary = self
+            .intervals()
+            .map(|interval| {
+                if speed == 0.0
+                    || interval.as_secs_f64() / speed.abs() < self.interval.as_secs_f64()
+                {
+                    return self.interval;
+                }
+                let interval = interval.as_nanos().max(1) as i128;
+                // Running backwards, the next boundary is the one before.
+                let remaining = match nanos.rem_euclid(interval) {
+                    0 if speed < 0.0 => interval,
+                    elapsed if speed < 0.0 => elapsed,
+                    elapsed => interval - elapsed,
+                };
+                // Very slow time can put the boundary further off than a `Duration` holds.
+                let seconds = remaining as f64 / 1e9 / speed.abs();
+                Duration::from_secs_f64(seconds.min(MAX_WAIT.as_secs_f64()))
+            })
+            .min()
+            .unwrap_or_default()
+            .min(MAX_WAIT);
+        let until_boundary = if time_source.in_transition() {
+            until_boundary.min(self.animation_interval)
+        } else {
+            until_boundary
+        };
+        let scheduled_at = (Utc::now(), Instant::now());
+        self.expected_wakeup = chrono::Duration::from_std(until_boundary)
+            .ok()
+            .map(|until| scheduled_at.0 + until);
+        self.scheduled_at = Some(scheduled_at);
+        scheduled_at.1 + until_boundary + WAKEUP_DELAY
+    }
+
+    /// Whether the system clock jumped since the last wake-up was scheduled, either because the
+    /// machine slept, e.g. a laptop with its lid closed, going by how late the system clock says
+    /// it is, or because the clock was set or stepped, forwards or backwards, going by how far it
+    /// moved apart from the monotonic clock. The last frame is then stale, so the next one is
+    /// drawn whatever the tick intervals, and the wake-ups should be scheduled afresh.
+    pub fn check_jumped(&mut self) -> bool {
+        let (Some(expected), Some((wall, instant))) = (self.expected_wakeup, self.scheduled_at)
+        else {
+            return false;
+        };
+        let now = Utc::now();
+        let late = now - expected;
+        let moved = (now - wall)
+            - chrono::Duration::from_std(instant.elapsed())
+                .unwrap_or_else(|_| chrono::Duration::zero());
+        if late >= SLEEP_THRESHOLD {
+            log::info!("woke up {}s late; the machine slept", late.num_seconds());
+        } else if moved.abs() >= JUMP_THRESHOLD {
+            log::info!("the system clock was set by {}s", moved.num_seconds());
+        } else {
+            return false;
+        }
+        self.expected_wakeup = None;
+        self.scheduled_at = None;
+        self.drawn = None;
+        true
+    }
+
+    /// Whether showing `time` would look different from the last frame, i.e. whether it is in
+    /// another tick of any layer.
+    pub fn needs_redraw(&self, time: &DateTime<Utc>) -> bool {
+        let Some(drawn) = &self.drawn else {
+            return true;
+        };
+        self.intervals()
+            .any(|interval| floor_time(drawn, interval) != floor_time(time, interval))
+    }
+
+    pub fn drawn(&mut self, time: DateTime<Utc>) {
+        self.drawn = Some(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// 50 frames per second, so that frames fall on whole seconds.
+    const FRAME_INTERVAL: Duration = Duration::from_millis(20);
+    const ANIMATION_INTERVAL: Duration = Duration::from_millis(8);
+
+    struct FixedTime {
+        now: DateTime<Utc>,
+        speed: f64,
+        in_transition: bool,
+    }
+
+    impl TimeSource for FixedTime {
+        fn now(&self) -> DateTime<Utc> {
+            self.now
+        }
+
+        fn speed(&self) -> f64 {
+            self.speed
+        }
+
+        fn in_transition(&self) -> bool {
+            self.in_transition
+        }
+    }
+
+    /// A quarter past a whole second.
+    fn at_quarter(speed: f64) -> FixedTime {
+        FixedTime {
+            now: Utc.with_ymd_and_hms(2025, 6, 21, 12, 0, 0).unwrap()
+                + chrono::Duration::milliseconds(250),
+            speed,
+            in_transition: false,
+        }
+    }
+
+    fn scheduler(tick_intervals: &[Duration]) -> FrameScheduler {
+        let mut scheduler = FrameScheduler::new(FRAME_INTERVAL, ANIMATION_INTERVAL);
+        scheduler.set_tick_intervals(tick_intervals.iter().copied());
+        scheduler
+    }
+
+    /// Asserts that the next wake-up for `source` is `expected` from now.
+    fn assert_wait(scheduler: &mut FrameScheduler, source: &FixedTime, expected: Duration) {
+        let before = Instant::now();
+        let wakeup = scheduler.next_wakeup(source);
+        let after = Instant::now();
+        let expected = expected + WAKEUP_DELAY;
+        assert!(
+            wakeup >= before + expected && wakeup <= after + expected,
+            "woke up after {:?}, not {:?}",
+            wakeup - before,
+            expected
+        );
+    }
+
+    #[test]
+    fn wakeups_align_to_tick_boundaries() {
+        let mut scheduler = scheduler(&[Duration::from_secs(1), Duration::from_secs(60)]);
+        assert_wait(&mut scheduler, &at_quarter(1.0), Duration::from_millis(750));
+        // Twice as fast, the boundary comes in half the time.
+        assert_wait(&mut scheduler, &at_quarter(2.0), Duration::from_millis(375));
+    }
+
+    #[test]
+    fn wakeups_align_backwards_to_the_boundary_before() {
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        assert_wait(
+            &mut scheduler,
+            &at_quarter(-1.0),
+            Duration::from_millis(250),
+        );
+        // On a boundary, the next one is a whole interval back.
+        let mut source = at_quarter(-1.0);
+        source.now -= chrono::Duration::milliseconds(250);
+        assert_wait(&mut scheduler, &source, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn stopped_time_wakes_up_every_frame() {
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        assert_wait(&mut scheduler, &at_quarter(0.0), FRAME_INTERVAL);
+    }
+
+    #[test]
+    fn ticks_faster_than_frames_wake_up_every_frame() {
+        // A second passes in under a millisecond.
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        assert_wait(&mut scheduler, &at_quarter(3600.0), FRAME_INTERVAL);
+        assert_wait(&mut scheduler, &at_quarter(-3600.0), FRAME_INTERVAL);
+    }
+
+    #[test]
+    fn without_tick_intervals_every_frame_is_a_tick() {
+        let mut scheduler = scheduler(&[]);
+        assert_wait(&mut scheduler, &at_quarter(1.0), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn waits_are_capped() {
+        let mut scheduler = scheduler(&[Duration::from_secs(3600)]);
+        assert_wait(&mut scheduler, &at_quarter(1.0), MAX_WAIT);
+        // So slow that the boundary is further off than a `Duration` holds.
+        assert_wait(&mut scheduler, &at_quarter(1e-300), MAX_WAIT);
+        assert_wait(&mut scheduler, &at_quarter(-1e-300), MAX_WAIT);
+    }
+
+    #[test]
+    fn transitions_wake_up_for_every_animation_frame() {
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        let mut source = at_quarter(1.0);
+        source.in_transition = true;
+        assert_wait(&mut scheduler, &source, ANIMATION_INTERVAL);
+    }
+
+    #[test]
+    fn no_jump_without_a_wakeup() {
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        assert!(!scheduler.check_jumped());
+        scheduler.next_wakeup(&at_quarter(1.0));
+        assert!(!scheduler.check_jumped());
+    }
+
+    /// A scheduler that scheduled a wake-up, after which the system clock was set by `moved`
+    /// without any time passing on the monotonic clock.
+    fn moved_by(moved: chrono::Duration) -> FrameScheduler {
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        scheduler.next_wakeup(&at_quarter(1.0));
+        scheduler.drawn(Utc::now());
+        let (wall, instant) = scheduler.scheduled_at.unwrap();
+        scheduler.scheduled_at = Some((wall - moved, instant));
+        scheduler.expected_wakeup = scheduler.expected_wakeup.map(|expected| expected - moved);
+        scheduler
+    }
+
+    #[test]
+    fn clock_jumps_are_noticed_from_the_threshold() {
+        let margin = chrono::Duration::milliseconds(200);
+        for moved in [JUMP_THRESHOLD + margin, -(JUMP_THRESHOLD + margin)] {
+            let mut scheduler = moved_by(moved);
+            assert!(scheduler.check_jumped(), "missed a jump of {}", moved);
+            // The last frame is stale, and the wake-up is scheduled afresh.
+            assert!(scheduler.needs_redraw(&Utc::now()));
+            assert!(!scheduler.check_jumped());
+        }
+        for moved in [JUMP_THRESHOLD - margin, -(JUMP_THRESHOLD - margin)] {
+            assert!(!moved_by(moved).check_jumped(), "a jump of {}", moved);
+        }
+    }
+
+    #[test]
+    fn sleeping_past_a_wakeup_is_a_jump() {
+        let mut scheduler = scheduler(&[Duration::from_secs(1)]);
+        scheduler.next_wakeup(&at_quarter(1.0));
+        let late = SLEEP_THRESHOLD + chrono::Duration::seconds(1);
+        scheduler.expected_wakeup = scheduler.expected_wakeup.map(|expected| expected - late);
+        assert!(scheduler.check_jumped());
+
+        scheduler.next_wakeup(&at_quarter(1.0));
+        let late = SLEEP_THRESHOLD - chrono::Duration::seconds(1);
+        scheduler.expected_wakeup = scheduler.expected_wakeup.map(|expected| expected - late);
+        assert!(!scheduler.check_jumped());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::cli::Args;
+use crate::paths::Paths;
+use anyhow::Context;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+use native_dialog::{MessageDialog, MessageType};
+use std::ffi::OsString;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId};
+
+/// How far the cursor has to move, in pixels, to end a fullscreen screensaver. Small movements are
+/// ignored, as mice can report them without being touched.
+const MOVE_THRESHOLD: f64 = 8.0;
+
+/// How the clock was started as a screensaver.
+#[derive(Debug, Clone, Copy)]
+pub enum Mode {
+    /// Covering every monitor, until there is any input.
+    Fullscreen,
+    /// Inside a window of the screensaver host, given by its X11 window id or Win32 handle, such
+    /// as the preview in the screensaver settings. The host decides when to stop.
+    Embedded(u64),
+}
+
+impl Mode {
+    pub fn from_args(args: &Args) -> Option<Self> {
+        match (args.screensaver, args.screensaver_window) {
+            (_, Some(id)) => Some(Self::Embedded(id)),
+            (true, None) => Some(Self::Fullscreen),
+            (false, None) => None,
+        }
+    }
+}
+
+/// Rewrites the arguments that screensaver hosts start screensavers with into the clock's own
+/// options, so that it can be installed as one directly:
+///
+/// - Windows runs `.scr` files with `/s` to start, `/p HWND` to preview and `/c` (or nothing) to
+///   configure them;
+/// - XScreenSaver runs hacks with `-root` or `-window-id ID`.
+pub fn translate_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    let is_scr = args.first().is_some_and(|program| {
+        std::path::Path::new(program)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("scr"))
+    });
+    if args.len() == 1 && is_scr {
+        args.push("--screensaver-configure".into());
+        return args;
+    }
+    let Some(first) = args.get(1).and_then(|arg| arg.to_str()) else {
+        return args;
+    };
+    // Windows may also pass the window handle after a colon, e.g. `/p:1234`.
+    let (flag, value) = match first.split_once(':') {
+        Some((flag, value)) => (flag.to_ascii_lowercase(), Some(value.to_owned())),
+        None => (first.to_ascii_lowercase(), None),
+    };
+    let replacement: Vec<OsString> = match flag.as_str() {
+        "/s" | "-s" if is_scr => vec!["--screensaver".into()],
+        "/c" | "-c" if is_scr => vec!["--screensaver-configure".into()],
+        "/p" | "-p" if is_scr => {
+            let value = match value {
+                Some(value) => value.into(),
+                None if args.len() > 2 => args.remove(2),
+                None => OsString::new(),
+            };
+            vec!["--screensaver-window".into(), value]
+        }
+        "-root" => vec!["--screensaver".into()],
+        "-window-id" if args.len() > 2 => {
+            let value = args.remove(2);
+            vec!["--screensaver-window".into(), value]
+        }
+        _ => return args,
+    };
+    args.splice(1..2, replacement);
+    args
+}
+
+/// Shows where the clock's settings are, which is what screensaver hosts expect when asked to
+/// configure a screensaver.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub fn configure(paths: &Paths) -> anyhow::Result<()> {
+    MessageDialog::new()
+        .set_type(MessageType::Info)
+        .set_title("Global Clock")
+        .set_text(&format!(
+            "Global Clock is configured with a file, which can be edited with any text editor:\n\n{}",
+            paths.config_file().display()
+        ))
+        .show_alert()
+        .context("failed to show the settings dialog")
+}
+
+/// The attributes of the windows to open, the first being the main window.
+pub fn window_attributes(
+    mode: Mode,
+    event_loop: &ActiveEventLoop,
+    title: &str,
+) -> anyhow::Result<Vec<WindowAttributes>> {
+    let attributes = Window::default_attributes()
+        .with_title(title)
+        .with_decorations(false);
+    match mode {
+        Mode::Fullscreen => {
+            let primary = event_loop.primary_monitor();
+            let mut monitors: Vec<_> = primary.iter().cloned().collect();
+            monitors.extend(
+                event_loop
+                    .available_monitors()
+                    .filter(|monitor| Some(monitor) != primary.as_ref()),
+            );
+            if monitors.is_empty() {
+                // Let the platform choose, e.g. on Wayland, where monitors are not listed.
+                return Ok(vec![
+                    attributes.with_fullscreen(Some(Fullscreen::Borderless(None)))
+                ]);
+            }
+            Ok(monitors
+                .into_iter()
+                .map(|monitor| {
+                    attributes
+                        .clone()
+                        .with_position(monitor.position())
+                        .with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))))
+                })
+                .collect())
+        }
+        Mode::Embedded(id) => {
+            let size = parent_size(id)?;
+            // Safety: the handle refers to a window of the screensaver host, which outlives us.
+            let attributes = unsafe { attributes.with_parent_window(Some(parent_handle(id)?)) };
+            Ok(vec![attributes
+                .with_position(PhysicalPosition::new(0, 0))
+                .with_inner_size(size)])
+        }
+    }
+}
+
+/// Decides when a screensaver should stop.
+pub struct Screensaver {
+    mode: Mode,
+    /// Where the cursor was first seen, and in which window.
+    origin: Option<(WindowId, PhysicalPosition<f64>)>,
+}
+
+impl Screensaver {
+    pub fn new(mode: Mode) -> Self {
+        Self { mode, origin: None }
+    }
+
+    /// Whether `event` is input that should end the screensaver.
+    pub fn should_exit(&mut self, window: WindowId, event: &WindowEvent) -> bool {
+        if let Mode::Embedded(_) = self.mode {
+            return false;
+        }
+        match event {
+            WindowEvent::KeyboardInput { is_synthetic, .. } => !is_synthetic,
+            WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::Touch(..) => true,
+            WindowEvent::CursorMoved { position, .. } => match self.origin {
+                Some((origin_window, origin)) if origin_window == window => {
+                    let (dx, dy) = (position.x - origin.x, position.y - origin.y);
+                    dx.hypot(dy) > MOVE_THRESHOLD
+                }
+                _ => {
+                    self.origin = Some((window, *position));
+                    false
+                }
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parent_handle(id: u64) -> anyhow::Result<winit::raw_window_handle::RawWindowHandle> {
+    use winit::raw_window_handle::{RawWindowHandle, XlibWindowHandle};
+    Ok(RawWindowHandle::Xlib(XlibWindowHandle::new(id as _)))
+}
+
+#[cfg(target_os = "linux")]
+fn parent_size(id: u64) -> anyhow::Result<PhysicalSize<u32>> {
+    use std::convert::TryFrom;
+    use x11rb::protocol::xproto::ConnectionExt;
+    let (connection, _) = x11rb::connect(None).context("failed to connect to the X server")?;
+    let window = u32::try_from(id).context("invalid X11 window id")?;
+    let geometry = connection
+        .get_geometry(window)?
+        .reply()
+        .with_context(|| format!("no X11 window with id {:#x}", id))?;
+    Ok(PhysicalSize::new(
+        geometry.width.into(),
+        geometry.height.into(),
+    ))
+}
+
+#[cfg(windows)]
+fn parent_handle(id: u64) -> anyhow::Result<winit::raw_window_handle::RawWindowHandle> {
+    use std::num::NonZeroIsize;
+    use winit::raw_window_handle::{RawWindowHandle, Win32WindowHandle};
+    let hwnd = NonZeroIsize::new(id as isize).context("invalid window handle 0")?;
+    Ok(RawWindowHandle::Win32(Win32WindowHandle::new(hwnd)))
+}
+
+#[cfg(windows)]
+fn parent_size(id: u64) -> anyhow::Result<PhysicalSize<u32>> {
+    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetClientRect;
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    // Safety: `rect` is valid for writes; an invalid handle makes the call fail.
+    if unsafe { GetClientRect(id as isize, &mut rect) } == 0 {
+        anyhow::bail!("no window with handle {:#x}", id);
+    }
+    Ok(PhysicalSize::new(
+        (rect.right - rect.left) as u32,
+        (rect.bottom - rect.top) as u32,
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn parent_handle(_id: u64) -> anyhow::Result<winit::raw_window_handle::RawWindowHandle> {
+    anyhow::bail!("drawing into another application's window is not supported on this platform")
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn parent_size(_id: u64) -> anyhow::Result<PhysicalSize<u32>> {
+    anyhow::bail!("drawing into another application's window is not supported on this platform")
+}
@@ -0,0 +1,84 @@
+use crate::GraphicsContext;
+use anyhow::{anyhow, bail};
+use image::RgbaImage;
+
+/// Renders a frame into an offscreen texture and reads it back into memory.
+pub fn capture(
+    gfx: &GraphicsContext,
+    width: u32,
+    height: u32,
+    draw: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+) -> anyhow::Result<RgbaImage> {
+    let swap_red_blue = match gfx.render_format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => bail!("cannot capture frames in format {:?}", format),
+    };
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        ..Default::default()
+    };
+    let texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("screenshot.texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: gfx.render_format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+
+    // Rows copied out of a texture must be padded to a multiple of 256 bytes.
+    let row_bytes = width * 4;
+    let padded_row_bytes =
+        row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = gfx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("screenshot.buffer"),
+        size: u64::from(padded_row_bytes) * u64::from(height),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = gfx.device.create_command_encoder(&Default::default());
+    draw(&mut encoder, &view);
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: Some(height),
+            },
+        },
+        size,
+    );
+    gfx.queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    gfx.device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .map_err(|_| anyhow!("frame readback was cancelled"))??;
+
+    let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+    for row in slice.get_mapped_range().chunks(padded_row_bytes as usize) {
+        pixels.extend_from_slice(&row[..row_bytes as usize]);
+    }
+    buffer.unmap();
+    if swap_red_blue {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    RgbaImage::from_raw(width, height, pixels).ok_or_else(|| anyhow!("frame size mismatch"))
+}
@@ -0,0 +1,87 @@
+use global_clock::{Assets, Layer};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::SystemTime;
+
+/// Notices when shader files change, so they can be edited while the app is running.
+pub struct ShaderWatcher {
+    assets: Assets,
+    modified: HashMap<String, Option<SystemTime>>,
+    /// Layers whose latest reload failed.
+    failed: BTreeSet<String>,
+}
+
+impl ShaderWatcher {
+    pub fn new<'a>(assets: Assets, layers: impl IntoIterator<Item = &'a Box<dyn Layer>>) -> Self {
+        let mut watcher = Self {
+            assets,
+            modified: HashMap::new(),
+            failed: BTreeSet::new(),
+        };
+        for layer in layers {
+            for &shader in layer.shaders() {
+                let time = modified(&watcher.assets, shader);
+                watcher.modified.insert(shader.to_owned(), time);
+            }
+        }
+        watcher
+    }
+
+    /// Reloads the shaders of every layer whose shader files changed since the last check. Layers
+    /// of the same kind in different windows share their shader files, and are all reloaded.
+    /// Returns whether anything was reloaded.
+    pub fn check<'a>(&mut self, layers: impl IntoIterator<Item = &'a mut Box<dyn Layer>>) -> bool {
+        let mut changed = HashSet::new();
+        for (shader, last_modified) in &mut self.modified {
+            let time = modified(&self.assets, shader);
+            if *last_modified != time {
+                *last_modified = time;
+                changed.insert(shader.clone());
+            }
+        }
+        if changed.is_empty() {
+            return false;
+        }
+
+        for layer in layers {
+            if !layer
+                .shaders()
+                .iter()
+                .any(|&shader| changed.contains(shader))
+            {
+                continue;
+            }
+            match layer.reload_shaders() {
+                Ok(()) => {
+                    log::info!("reloaded shaders for {}", layer.name());
+                    self.failed.remove(layer.name());
+                }
+                Err(err) => {
+                    log::error!("failed to reload shaders for {}: {:#}", layer.name(), err);
+                    self.failed.insert(layer.name().to_owned());
+                }
+            }
+        }
+        true
+    }
+
+    /// A short summary of the shader errors that have not been fixed yet, if any, for the title
+    /// bar. The errors themselves are logged when they happen, as they are often too long for it.
+    pub fn error(&self) -> Option<String> {
+        if self.failed.is_empty() {
+            return None;
+        }
+        let layers: Vec<&str> = self.failed.iter().map(String::as_str).collect();
+        let noun = if layers.len() == 1 {
+            "shader error"
+        } else {
+            "shader errors"
+        };
+        Some(format!("{} in {} (see the log)", noun, layers.join(", ")))
+    }
+}
+
+/// When the file that `shader` is loaded from was last changed, or `None` if it is built in.
+fn modified(assets: &Assets, shader: &str) -> Option<SystemTime> {
+    let path = assets.find(shader)?;
+    std::fs::metadata(path).ok()?.modified().ok()
+}
@@ -0,0 +1,67 @@
+use crate::config::Span;
+use glam::Vec2;
+use global_clock::viewport::Region;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Fullscreen, Window, WindowAttributes};
+
+/// The attributes of the windows that show one scene across every monitor, the first being the
+/// main window, and the part of the scene that each of them shows.
+pub fn window_attributes(
+    span: Span,
+    event_loop: &ActiveEventLoop,
+    title: &str,
+) -> anyhow::Result<Vec<(WindowAttributes, Option<Region>)>> {
+    let primary = event_loop.primary_monitor();
+    let mut monitors: Vec<_> = primary.iter().cloned().collect();
+    monitors.extend(
+        event_loop
+            .available_monitors()
+            .filter(|monitor| Some(monitor) != primary.as_ref()),
+    );
+    if monitors.is_empty() {
+        // E.g. on Wayland, where windows cannot be placed either.
+        anyhow::bail!("spanning monitors is not supported on this platform");
+    }
+
+    let min_x = monitors.iter().map(|m| m.position().x).min().unwrap();
+    let min_y = monitors.iter().map(|m| m.position().y).min().unwrap();
+    let max_x = monitors
+        .iter()
+        .map(|m| m.position().x + m.size().width as i32)
+        .max()
+        .unwrap();
+    let max_y = monitors
+        .iter()
+        .map(|m| m.position().y + m.size().height as i32)
+        .max()
+        .unwrap();
+    let total = Vec2::new((max_x - min_x) as f32, (max_y - min_y) as f32);
+
+    let attributes = Window::default_attributes()
+        .with_title(title)
+        .with_decorations(false);
+    match span {
+        Span::Monitors => Ok(monitors
+            .into_iter()
+            .map(|monitor| {
+                let position = monitor.position();
+                let region = Region {
+                    offset: Vec2::new((position.x - min_x) as f32, (position.y - min_y) as f32),
+                    total,
+                };
+                let attributes = attributes
+                    .clone()
+                    .with_position(position)
+                    .with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
+                (attributes, Some(region))
+            })
+            .collect()),
+        Span::Window => Ok(vec![(
+            attributes
+                .with_position(PhysicalPosition::new(min_x, min_y))
+                .with_inner_size(PhysicalSize::new(total.x as u32, total.y as u32)),
+            None,
+        )]),
+    }
+}
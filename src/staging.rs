@@ -0,0 +1,185 @@
+use crate::GraphicsContext;
+use std::sync::{mpsc, Arc};
+use wgpu::util::align_to;
+
+/// Size of the staging buffers, which uploads share. Larger uploads get a buffer of their own.
+const CHUNK_SIZE: wgpu::BufferAddress = 1 << 20;
+
+struct Chunk {
+    buffer: Arc<wgpu::Buffer>,
+    size: wgpu::BufferAddress,
+    offset: wgpu::BufferAddress,
+}
+
+/// Uploads buffers and textures through a pool of staging buffers, which are reused from frame to
+/// frame rather than allocated for every write, like [`wgpu::util::StagingBelt`] but for textures
+/// too.
+///
+/// Each frame, call [`Self::recall`] before the first write, once the previous frame's commands
+/// were submitted, and [`Self::finish`] after the last write, before submitting its commands.
+/// [`crate::layer::draw_layers`] does both.
+pub struct StagingPool {
+    gfx: GraphicsContext,
+    /// Chunks that are mapped, and being written to this frame.
+    active: Vec<Chunk>,
+    /// Chunks that the submitted commands copy from.
+    closed: Vec<Chunk>,
+    /// Chunks that the GPU is done with, and are mapped again.
+    free: Vec<Chunk>,
+    sender: mpsc::Sender<Chunk>,
+    receiver: mpsc::Receiver<Chunk>,
+}
+
+impl StagingPool {
+    pub fn new(gfx: &GraphicsContext) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            gfx: gfx.clone(),
+            active: Vec::new(),
+            closed: Vec::new(),
+            free: Vec::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Records copying `data` to `target` at `offset` into `encoder`.
+    pub fn write_buffer(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        if data.is_empty() {
+            return;
+        }
+        let size = data.len() as wgpu::BufferAddress;
+        let (chunk, start) = self.allocate(size, wgpu::COPY_BUFFER_ALIGNMENT);
+        chunk
+            .buffer
+            .slice(start..start + size)
+            .get_mapped_range_mut()
+            .copy_from_slice(data);
+        encoder.copy_buffer_to_buffer(&chunk.buffer, start, target, offset, size);
+    }
+
+    /// Records copying `data`, laid out as described by `layout`, to `size` texels of `target`
+    /// into `encoder`, like [`wgpu::Queue::write_texture`]. Only 2D copies are supported.
+    pub fn write_texture(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: wgpu::ImageCopyTexture,
+        data: &[u8],
+        layout: wgpu::ImageDataLayout,
+        size: wgpu::Extent3d,
+    ) {
+        let format = target.texture.format();
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format.block_size(Some(target.aspect)).unwrap();
+        let row_bytes = size.width / block_width * block_size;
+        let rows = size.height / block_height;
+        if row_bytes == 0 || rows == 0 {
+            return;
+        }
+        let stride = layout.bytes_per_row.unwrap_or(row_bytes) as usize;
+        // Rows in the staging buffer have to be aligned, unlike those in `data`.
+        let padded_row = align_to(row_bytes, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let total = padded_row as wgpu::BufferAddress * rows as wgpu::BufferAddress;
+        let (chunk, start) = self.allocate(total, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT.into());
+        {
+            let mut staged = chunk
+                .buffer
+                .slice(start..start + total)
+                .get_mapped_range_mut();
+            for (row, dst) in staged.chunks_exact_mut(padded_row as usize).enumerate() {
+                let src = layout.offset as usize + row * stride;
+                dst[..row_bytes as usize].copy_from_slice(&data[src..src + row_bytes as usize]);
+            }
+        }
+        encoder.copy_buffer_to_texture(
+            wgpu::ImageCopyBuffer {
+                buffer: &chunk.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: start,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(rows),
+                },
+            },
+            target,
+            size,
+        );
+    }
+
+    /// Unmaps the buffers written to since the last call, so that the commands copying from them
+    /// can be submitted.
+    pub fn finish(&mut self) {
+        for chunk in self.active.drain(..) {
+            chunk.buffer.unmap();
+            self.closed.push(chunk);
+        }
+    }
+
+    /// Maps the buffers of submitted commands again, to be reused once the GPU is done with them.
+    pub fn recall(&mut self) {
+        self.receive();
+        for chunk in self.closed.drain(..) {
+            let sender = self.sender.clone();
+            chunk
+                .buffer
+                .clone()
+                .slice(..)
+                .map_async(wgpu::MapMode::Write, move |result| {
+                    // A chunk that failed to map, e.g. after the device was lost, cannot be
+                    // written to again, and is dropped.
+                    if result.is_ok() {
+                        let _ = sender.send(chunk);
+                    }
+                });
+        }
+    }
+
+    /// Reserves `size` bytes at a multiple of `alignment` in a mapped chunk, returning the chunk
+    /// and the offset.
+    fn allocate(
+        &mut self,
+        size: wgpu::BufferAddress,
+        alignment: wgpu::BufferAddress,
+    ) -> (&Chunk, wgpu::BufferAddress) {
+        let fits = |chunk: &Chunk| align_to(chunk.offset, alignment) + size <= chunk.size;
+        let mut chunk = match self.active.iter().position(fits) {
+            Some(index) => self.active.swap_remove(index),
+            None => {
+                self.receive();
+                match self.free.iter().position(fits) {
+                    Some(index) => self.free.swap_remove(index),
+                    None => {
+                        let size = CHUNK_SIZE.max(size);
+                        let buffer = self.gfx.device.create_buffer(&wgpu::BufferDescriptor {
+                            label: Some("StagingPool.buffer"),
+                            size,
+                            usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                            mapped_at_creation: true,
+                        });
+                        Chunk {
+                            buffer: Arc::new(buffer),
+                            size,
+                            offset: 0,
+                        }
+                    }
+                }
+            }
+        };
+        let start = align_to(chunk.offset, alignment);
+        chunk.offset = align_to(start + size, wgpu::MAP_ALIGNMENT);
+        self.active.push(chunk);
+        (self.active.last().unwrap(), start)
+    }
+
+    fn receive(&mut self) {
+        while let Ok(mut chunk) = self.receiver.try_recv() {
+            chunk.offset = 0;
+            self.free.push(chunk);
+        }
+    }
+}
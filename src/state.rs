@@ -0,0 +1,41 @@
+use crate::paths::Paths;
+use anyhow::Context;
+use global_clock::Camera;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings changed interactively, which are restored the next time the app is launched.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+    pub camera: Camera,
+}
+
+impl State {
+    /// Loads the saved state, falling back to the defaults if it is missing or unreadable.
+    pub fn load(paths: &Paths) -> Self {
+        let path = Self::path(paths);
+        let mut state: Self = match std::fs::read_to_string(&path) {
+            Ok(source) => toml::from_str(&source).unwrap_or_else(|err| {
+                log::warn!("ignoring invalid state file {}: {}", path.display(), err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        state.camera = state.camera.clamped();
+        state
+    }
+
+    pub fn save(&self, paths: &Paths) -> anyhow::Result<()> {
+        let path = Self::path(paths);
+        std::fs::create_dir_all(paths.state_dir())
+            .with_context(|| format!("failed to create {}", paths.state_dir().display()))?;
+        let contents = toml::to_string(self).context("failed to serialize state")?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("failed to write state file {}", path.display()))
+    }
+
+    fn path(paths: &Paths) -> PathBuf {
+        paths.state_dir().join("state.toml")
+    }
+}
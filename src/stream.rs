@@ -0,0 +1,188 @@
+use crate::cli::Args;
+use crate::config::Config;
+use crate::headless::{fps_interval, Scene};
+use crate::paths::Paths;
+use anyhow::Context;
+use global_clock::time_source;
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Separates the frames of the MJPEG stream.
+const BOUNDARY: &str = "frame";
+
+/// Most clients served at once, each of which takes a thread; more are turned away.
+const MAX_CLIENTS: usize = 32;
+
+/// How long a client may take to send its request, or to take a frame.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most bytes read of a request line and its headers, beyond which the rest is ignored.
+const MAX_REQUEST_SIZE: u64 = 8192;
+
+/// The last frame encoded, with a count of frames that tells clients when there is a new one.
+type Latest = (Mutex<(u64, Arc<Vec<u8>>)>, Condvar);
+
+/// Renders the main window's scene `fps` times per second as time passes, without opening a
+/// window, and serves it over HTTP on `address`: as an MJPEG stream at `/`, which browsers and
+/// dashboards show like a video, and as a single JPEG image at `/frame.jpg`.
+pub fn serve(
+    args: &Args,
+    paths: &Paths,
+    address: SocketAddr,
+    fps: f64,
+    quality: u8,
+) -> anyhow::Result<()> {
+    let interval = fps_interval(fps)?;
+    let config = Config::load(args.config.as_deref(), paths, args.overrides())?;
+    let time_source = time_source::from_settings(config.time, config.speed);
+    let mut scene = Scene::new(config, paths)?;
+    let encode = |scene: &mut Scene| -> anyhow::Result<Vec<u8>> {
+        let image = DynamicImage::ImageRgba8(scene.render(&time_source.now())?).to_rgb8();
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, quality.clamp(1, 100))
+            .encode_image(&image)
+            .context("failed to encode a frame")?;
+        Ok(jpeg)
+    };
+    let latest: Arc<Latest> = Arc::new((
+        Mutex::new((0, Arc::new(encode(&mut scene)?))),
+        Condvar::new(),
+    ));
+
+    let listener =
+        TcpListener::bind(address).with_context(|| format!("failed to listen on {}", address))?;
+    eprintln!("Serving the clock at http://{}/", address);
+    let accepting = latest.clone();
+    std::thread::Builder::new()
+        .name("stream listener".into())
+        .spawn(move || {
+            let clients = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                if let Err(err) = stream
+                    .set_read_timeout(Some(CLIENT_TIMEOUT))
+                    .and_then(|()| stream.set_write_timeout(Some(CLIENT_TIMEOUT)))
+                {
+                    log::warn!("failed to serve a stream client: {}", err);
+                    continue;
+                }
+                if clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+                    clients.fetch_sub(1, Ordering::SeqCst);
+                    log::debug!("turned a stream client away, as {} are served", MAX_CLIENTS);
+                    let _ = stream.write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\
+                          Connection: close\r\n\r\n",
+                    );
+                    continue;
+                }
+                let slot = ClientSlot(clients.clone());
+                let latest = accepting.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("stream client".into())
+                    .spawn(move || {
+                        let _slot = slot;
+                        if let Err(err) = handle_client(stream, &latest) {
+                            log::debug!("stream client left: {}", err);
+                        }
+                    });
+                if let Err(err) = spawned {
+                    log::warn!("failed to serve a stream client: {}", err);
+                }
+            }
+        })
+        .context("failed to start serving the stream")?;
+
+    scene.set_frame_interval(interval);
+    let mut next = Instant::now() + interval;
+    loop {
+        let now = Instant::now();
+        match next.checked_duration_since(now) {
+            Some(wait) => std::thread::sleep(wait),
+            // Late frames are not made up for.
+            None => next = now,
+        }
+        next += interval;
+        let jpeg = Arc::new(encode(&mut scene)?);
+        let (frame, changed) = &*latest;
+        let mut frame = frame.lock().unwrap();
+        *frame = (frame.0 + 1, jpeg);
+        changed.notify_all();
+    }
+}
+
+/// Counts a client as served until dropped.
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answers one HTTP request on `stream`, which for the MJPEG stream lasts until the client goes
+/// away.
+fn handle_client(mut stream: TcpStream, (frame, changed): &Latest) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not needed.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if method != "GET" {
+        return stream.write_all(
+            b"HTTP/1.1 405 Method Not Allowed\r\nAllow: GET\r\nContent-Length: 0\r\n\
+              Connection: close\r\n\r\n",
+        );
+    }
+    match path.split('?').next().unwrap_or("") {
+        "/" | "/stream.mjpg" => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\n\
+                 Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+                BOUNDARY
+            )?;
+            let mut sent = None;
+            loop {
+                let (count, jpeg) = {
+                    let frame = changed
+                        .wait_while(frame.lock().unwrap(), |(count, _)| Some(*count) == sent)
+                        .unwrap();
+                    (frame.0, frame.1.clone())
+                };
+                sent = Some(count);
+                write!(
+                    stream,
+                    "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                    BOUNDARY,
+                    jpeg.len()
+                )?;
+                stream.write_all(&jpeg)?;
+                stream.write_all(b"\r\n")?;
+            }
+        }
+        "/frame.jpg" => {
+            let jpeg = frame.lock().unwrap().1.clone();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\
+                 Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+                jpeg.len()
+            )?;
+            stream.write_all(&jpeg)
+        }
+        _ => stream
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::astronomy::{self, SunEventKind};
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::location::Location;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::Corner;
+use crate::text_overlay::Readout;
+use crate::viewport::Viewport;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Settings of the sunrise and sunset countdown, from the config's `[layer.sun_countdown]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SunCountdownSettings {
+    /// Corner of the target that the readout is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+}
+
+impl Default for SunCountdownSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::TopLeft,
+            size: 18.0,
+        }
+    }
+}
+
+/// The time until the next sunrise or sunset at the home location, e.g. `Sunset in 2h 13m`, in a
+/// corner of the target.
+pub struct SunCountdown {
+    readout: Readout,
+    settings: SunCountdownSettings,
+    home: Option<Location>,
+    locale: Locale,
+    line: String,
+}
+
+impl SunCountdown {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        if cx.home.is_none() {
+            log::warn!("the sun_countdown layer needs a home location; set `home` in the config");
+        }
+        let settings: SunCountdownSettings = cx.settings("sun_countdown")?;
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            home: cx.home.cloned(),
+            locale: cx.locale.clone(),
+            line: String::new(),
+        })
+    }
+}
+
+impl Layer for SunCountdown {
+    fn name(&self) -> &str {
+        "sun_countdown"
+    }
+
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn update(&mut self, frame: &FrameInfo) {
+        self.line.clear();
+        let Some(home) = &self.home else {
+            return;
+        };
+        let Some(event) = astronomy::next_sun_event(home, &frame.time) else {
+            return;
+        };
+        let kind = match event.kind {
+            SunEventKind::Sunrise => "Sunrise",
+            SunEventKind::Sunset => "Sunset",
+        };
+        let minutes = (event.time - frame.time).num_minutes();
+        let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+        let left = if days > 0 {
+            // Only near the poles.
+            format!("{}d {}h", days, hours)
+        } else {
+            format!("{}h {:02}m", hours, minutes)
+        };
+        let left = self.locale.localize_digits(&left);
+        self.line = match &home.name {
+            Some(name) => format!("{}: {} in {}", name, kind, left),
+            None => format!("{} in {}", kind, left),
+        };
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if !self.line.is_empty() {
+            let style = self.readout.style();
+            self.readout
+                .queue_in_corner(&self.line, self.settings.corner, &style);
+        }
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
@@ -0,0 +1,7 @@
+//! Helpers shared by the unit tests of several modules.
+
+use std::time::Duration;
+
+pub fn minutes(minutes: u64) -> Duration {
+    Duration::from_secs(minutes * 60)
+}
@@ -0,0 +1,867 @@
+use crate::assets::Assets;
+use crate::staging::StagingPool;
+use crate::theme::Color;
+use crate::GraphicsContext;
+use ab_glyph::{point, Font, FontVec, GlyphId, PxScale, ScaleFont};
+use anyhow::Context;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
+use rustybuzz::UnicodeBuffer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use unicode_bidi::{Level, ParagraphBidiInfo};
+use wgpu::util::DeviceExt;
+
+/// Fonts that text is drawn in, in order of preference. DejaVu Sans has scripts that the
+/// monospace font lacks, such as Hebrew.
+const FONTS: &[&str] = &["fonts/DejaVuSansMono.ttf", "fonts/DejaVuSans.ttf"];
+
+/// Side of the square texture that glyphs are cached in, in pixels.
+const ATLAS_SIZE: u32 = 1024;
+
+/// Space left between glyphs in the atlas, so that sampling one never picks up its neighbours.
+const GLYPH_GAP: u32 = 1;
+
+/// How text queued with [`TextLayer::queue`] looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /// Height of the font, in pixels.
+    pub size: f32,
+    pub color: Color,
+    /// The point of the text's bounding box that is placed at the queued position, as fractions
+    /// of its width and height: `[0.0, 0.0]` is the top left corner, `[0.5, 0.5]` the center.
+    pub anchor: [f32; 2],
+    /// Color of a box drawn behind the text, e.g. to keep it readable over anything.
+    pub background: Option<Color>,
+    /// Space between the text and the edges of its background box, in pixels.
+    pub padding: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 14.0,
+            color: Color::rgba(255, 255, 255, 255),
+            anchor: [0.0, 0.0],
+            background: None,
+            padding: 0.0,
+        }
+    }
+}
+
+/// A corner of the target, e.g. where a readout is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// The [`TextStyle::anchor`] that keeps text placed at [`Self::position`] inside the target.
+    pub fn anchor(self) -> [f32; 2] {
+        match self {
+            Self::TopLeft => [0.0, 0.0],
+            Self::TopRight => [1.0, 0.0],
+            Self::BottomLeft => [0.0, 1.0],
+            Self::BottomRight => [1.0, 1.0],
+        }
+    }
+
+    /// The corner on the other side, left for right, e.g. for right-to-left locales.
+    pub fn mirrored(self) -> Self {
+        match self {
+            Self::TopLeft => Self::TopRight,
+            Self::TopRight => Self::TopLeft,
+            Self::BottomLeft => Self::BottomRight,
+            Self::BottomRight => Self::BottomLeft,
+        }
+    }
+
+    /// The point `margin` pixels in from this corner of a target `size` pixels large.
+    pub fn position(self, size: Vec2, margin: f32) -> [f32; 2] {
+        let [x, y] = self.anchor();
+        [
+            margin + x * (size.x - 2.0 * margin),
+            margin + y * (size.y - 2.0 * margin),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Uniforms {
+    target_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// A glyph or background box, drawn as one instance.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Quad {
+    /// Left, top, right and bottom edges, in pixels.
+    rect: [f32; 4],
+    /// The same edges in the atlas, as texture coordinates.
+    uv_rect: [f32; 4],
+    /// Linear, premultiplied.
+    color: [f32; 4],
+}
+
+impl Quad {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+struct Queued {
+    text: String,
+    position: [f32; 2],
+    style: TextStyle,
+}
+
+/// The outline of a box, queued with [`TextLayer::queue_outline`].
+struct Outline {
+    /// Left, top, right and bottom edges, in pixels.
+    rect: [f32; 4],
+    width: f32,
+    color: Color,
+}
+
+impl Outline {
+    /// The sides of the outline, inside its box.
+    fn quads(&self) -> [Quad; 4] {
+        let [left, top, right, bottom] = self.rect;
+        let width = self.width;
+        [
+            [left, top, right, top + width],
+            [left, bottom - width, right, bottom],
+            [left, top + width, left + width, bottom - width],
+            [right - width, top + width, right, bottom - width],
+        ]
+        .map(|rect| Quad {
+            rect,
+            uv_rect: Atlas::solid_uv(),
+            color: premultiply(self.color),
+        })
+    }
+}
+
+/// A glyph of a [`ShapedLine`].
+struct ShapedGlyph {
+    /// Index of the font in [`Fonts`] that the glyph is from.
+    font: usize,
+    id: GlyphId,
+    /// Offset of the glyph's origin from the start of the line's baseline, in pixels.
+    offset: [f32; 2],
+}
+
+/// A line of text as laid out by [`Fonts::shape`], with its glyphs in order from left to right.
+struct ShapedLine {
+    glyphs: Vec<ShapedGlyph>,
+    width: f32,
+    /// Whether the line reads from right to left, and so is aligned to the right.
+    right_to_left: bool,
+}
+
+/// Where a glyph is in the atlas.
+#[derive(Clone, Copy)]
+struct AtlasGlyph {
+    /// Offset of the glyph's top left corner from its origin on the baseline, in pixels.
+    offset: [f32; 2],
+    /// Left, top, right and bottom edges in the atlas, in pixels.
+    rect: [u32; 4],
+}
+
+/// Coverage of rasterized glyphs, packed into rows ("shelves") as they are first used.
+struct Atlas {
+    image: image::GrayImage,
+    /// Glyphs by font, id and font size, or `None` for glyphs with nothing to draw, such as
+    /// spaces.
+    glyphs: HashMap<(usize, GlyphId, u32), Option<AtlasGlyph>>,
+    /// Top and height of the shelf being filled, and how much of its width is used.
+    shelf_top: u32,
+    shelf_height: u32,
+    shelf_width: u32,
+    /// Rows changed since the atlas was last uploaded.
+    dirty_rows: Option<Range<u32>>,
+}
+
+impl Atlas {
+    fn new() -> Self {
+        let mut atlas = Self {
+            image: image::GrayImage::new(ATLAS_SIZE, ATLAS_SIZE),
+            glyphs: HashMap::new(),
+            shelf_top: 0,
+            shelf_height: 0,
+            shelf_width: 0,
+            dirty_rows: None,
+        };
+        atlas.clear();
+        atlas
+    }
+
+    /// Removes all glyphs. The top left corner is kept fully covered, for drawing boxes.
+    fn clear(&mut self) {
+        self.image.fill(0);
+        for y in 0..2 {
+            for x in 0..2 {
+                self.image.put_pixel(x, y, image::Luma([255]));
+            }
+        }
+        self.glyphs.clear();
+        self.shelf_top = 0;
+        self.shelf_height = 2;
+        self.shelf_width = 2 + GLYPH_GAP;
+        self.dirty_rows = Some(0..ATLAS_SIZE);
+    }
+
+    /// Texture coordinates of the fully covered corner.
+    fn solid_uv() -> [f32; 4] {
+        let center = 1.0 / ATLAS_SIZE as f32;
+        [center; 4]
+    }
+
+    /// Finds room for an image `width` by `height` pixels, returning its top left corner, or
+    /// `None` if the atlas is full.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.shelf_width + width > ATLAS_SIZE || height > self.shelf_height {
+            self.shelf_top += self.shelf_height + GLYPH_GAP;
+            self.shelf_width = 0;
+            // Rounded up, so that the glyphs of a font mostly share shelves despite their
+            // different heights.
+            self.shelf_height = height.next_multiple_of(8);
+        }
+        if width > ATLAS_SIZE || self.shelf_top + self.shelf_height > ATLAS_SIZE {
+            return None;
+        }
+        let corner = (self.shelf_width, self.shelf_top);
+        self.shelf_width += width + GLYPH_GAP;
+        Some(corner)
+    }
+
+    /// The glyph `id` of font `font` in `fonts` at `scale`, rasterizing it if it is not in the
+    /// atlas yet. Returns `Err` if the atlas is full.
+    fn glyph(
+        &mut self,
+        fonts: &Fonts,
+        font: usize,
+        id: GlyphId,
+        scale: PxScale,
+    ) -> Result<Option<AtlasGlyph>, ()> {
+        let key = (font, id, scale.y.to_bits());
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return Ok(*glyph);
+        }
+        let Some(outline) =
+            fonts.0[font].outline_glyph(id.with_scale_and_position(scale, point(0.0, 0.0)))
+        else {
+            self.glyphs.insert(key, None);
+            return Ok(None);
+        };
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let (left, top) = self.allocate(width, height).ok_or(())?;
+        let image = &mut self.image;
+        outline.draw(|x, y, coverage| {
+            if x < width && y < height {
+                let value = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                image.put_pixel(left + x, top + y, image::Luma([value]));
+            }
+        });
+        let rows = top..top + height;
+        self.dirty_rows = Some(match self.dirty_rows.take() {
+            Some(dirty) => dirty.start.min(rows.start)..dirty.end.max(rows.end),
+            None => rows,
+        });
+        let glyph = AtlasGlyph {
+            offset: [bounds.min.x, bounds.min.y],
+            rect: [left, top, left + width, top + height],
+        };
+        self.glyphs.insert(key, Some(glyph));
+        Ok(Some(glyph))
+    }
+}
+
+/// Draws text anywhere in the target, for any layer or overlay that shows text.
+///
+/// Text is queued with [`TextLayer::queue`] for each frame, and [`TextLayer::prepare`] lays it out
+/// and uploads it before [`TextLayer::draw`] draws it. Glyphs are rasterized once into an atlas
+/// texture, and drawn as one quad each, in a single draw call.
+pub struct TextLayer {
+    gfx: GraphicsContext,
+    fonts: Fonts,
+    render_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    atlas_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    atlas: Atlas,
+    quad_buffer: wgpu::Buffer,
+    queued: Vec<Queued>,
+    outlines: Vec<Outline>,
+    /// What the quad buffer holds, drawn by [`Self::draw`].
+    written_quads: Vec<Quad>,
+    written_uniforms: Option<[f32; 2]>,
+    /// Applied to the size and padding of all text, see [`Self::set_scale`].
+    scale: f32,
+    /// See [`Self::set_right_to_left`].
+    right_to_left: bool,
+}
+
+impl TextLayer {
+    pub fn new(gfx: &GraphicsContext, assets: &Assets) -> anyhow::Result<Self> {
+        let fonts = Fonts::load(assets)?;
+
+        let bind_group_layout =
+            gfx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("TextLayer.bind_group_layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("TextLayer.pipeline_layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let shader_source = assets.read_to_string("shaders/text.wgsl")?;
+        let render_pipeline = gfx
+            .validate(|| {
+                let shader_module = gfx
+                    .device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("TextLayer.shader_module"),
+                        source: wgpu::ShaderSource::Wgsl(shader_source),
+                    });
+                gfx.device
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("TextLayer.render_pipeline"),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader_module,
+                            entry_point: "vs_main",
+                            buffers: &[Quad::layout()],
+                        },
+                        primitive: wgpu::PrimitiveState {
+                            topology: wgpu::PrimitiveTopology::TriangleStrip,
+                            ..Default::default()
+                        },
+                        depth_stencil: None,
+                        multisample: Default::default(),
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader_module,
+                            entry_point: "fs_main",
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format: gfx.render_format,
+                                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
+                        }),
+                        multiview: None,
+                    })
+            })
+            .context("invalid text shader")?;
+
+        let uniform_buffer = gfx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("TextLayer.uniform_buffer"),
+                contents: bytemuck::bytes_of(&Uniforms::zeroed()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let sampler = gfx.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("TextLayer.sampler"),
+            ..Default::default()
+        });
+        let atlas_texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("TextLayer.atlas_texture"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let atlas_view = atlas_texture.create_view(&Default::default());
+        let bind_group = gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TextLayer.bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+            ],
+        });
+
+        Ok(Self {
+            gfx: gfx.clone(),
+            fonts,
+            render_pipeline,
+            uniform_buffer,
+            atlas_texture,
+            bind_group,
+            atlas: Atlas::new(),
+            quad_buffer: create_quad_buffer(gfx, 0),
+            queued: Vec::new(),
+            outlines: Vec::new(),
+            written_quads: Vec::new(),
+            written_uniforms: None,
+            scale: 1.0,
+            right_to_left: false,
+        })
+    }
+
+    /// Draws and measures all text `scale` times as large as asked, e.g. for the text scale the
+    /// user chose.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    /// Lays out lines without letters to tell their direction by, such as numbers, from right to
+    /// left, e.g. for a right-to-left locale. Lines with letters follow the direction of the first
+    /// one.
+    pub fn set_right_to_left(&mut self, right_to_left: bool) {
+        self.right_to_left = right_to_left;
+    }
+
+    /// Adds `text` to the next frame, placed at `position` in pixels from the top left corner of
+    /// the target. Lines are separated by `\n`.
+    pub fn queue(&mut self, text: &str, position: [f32; 2], style: &TextStyle) {
+        self.queued.push(Queued {
+            text: text.to_owned(),
+            position,
+            style: TextStyle {
+                size: style.size * self.scale,
+                padding: style.padding * self.scale,
+                ..*style
+            },
+        });
+    }
+
+    /// Adds the outline of a box to the next frame, `width` pixels wide inside `rect`, given as
+    /// its left, top, right and bottom edges in pixels, e.g. to show which control has the
+    /// keyboard focus.
+    pub fn queue_outline(&mut self, rect: [f32; 4], width: f32, color: Color) {
+        self.outlines.push(Outline { rect, width, color });
+    }
+
+    /// The width and height of `text` in pixels, when drawn at `size`.
+    pub fn measure(&self, text: &str, size: f32) -> [f32; 2] {
+        self.measure_scaled(text, size * self.scale)
+    }
+
+    /// The background box of `text` when it is queued at `position` with `style`, as its left,
+    /// top, right and bottom edges in pixels.
+    pub fn bounds(&self, text: &str, position: [f32; 2], style: &TextStyle) -> [f32; 4] {
+        let (size, padding) = (style.size * self.scale, style.padding * self.scale);
+        let ([left, top], [width, height]) = self.place(text, position, style.anchor, size);
+        [
+            left - padding,
+            top - padding,
+            left + width + padding,
+            top + height + padding,
+        ]
+    }
+
+    /// The box around line `row` of `text` when it is queued at `position` with `style`, as its
+    /// left, top, right and bottom edges in pixels. It is as wide as the background box, and
+    /// reaches its edges above the first line and below the last.
+    pub fn line_bounds(
+        &self,
+        text: &str,
+        row: usize,
+        position: [f32; 2],
+        style: &TextStyle,
+    ) -> [f32; 4] {
+        let (size, padding) = (style.size * self.scale, style.padding * self.scale);
+        let ([left, top], [width, height]) = self.place(text, position, style.anchor, size);
+        let font = self.fonts.primary().as_scaled(PxScale::from(size));
+        let line_height = font.height() + font.line_gap();
+        let line_top = top + line_height * row as f32;
+        let last = row + 1 >= text.split('\n').count();
+        [
+            left - padding,
+            if row == 0 {
+                top - padding
+            } else {
+                line_top - font.line_gap() / 2.0
+            },
+            left + width + padding,
+            if last {
+                top + height + padding
+            } else {
+                line_top + font.height() + font.line_gap() / 2.0
+            },
+        ]
+    }
+
+    /// The top left corner of `text` placed at `position` by `anchor` at a size that the scale
+    /// has already been applied to, in whole pixels so that glyphs are drawn as crisply as they
+    /// were rasterized, and its width and height.
+    fn place(
+        &self,
+        text: &str,
+        position: [f32; 2],
+        anchor: [f32; 2],
+        size: f32,
+    ) -> ([f32; 2], [f32; 2]) {
+        let [width, height] = self.measure_scaled(text, size);
+        let left = (position[0] - anchor[0] * width).round();
+        let top = (position[1] - anchor[1] * height).round();
+        ([left, top], [width, height])
+    }
+
+    /// [`Self::measure`], at a size that the scale has already been applied to.
+    fn measure_scaled(&self, text: &str, size: f32) -> [f32; 2] {
+        let scale = PxScale::from(size);
+        let font = self.fonts.primary().as_scaled(scale);
+        let mut lines = 0;
+        let mut width = 0.0f32;
+        for line in text.split('\n') {
+            lines += 1;
+            let shaped = self.fonts.shape(scale, line, self.right_to_left);
+            width = width.max(shaped.width);
+        }
+        let height = font.height() * lines as f32 + font.line_gap() * (lines - 1) as f32;
+        [width, height]
+    }
+
+    /// Lays out the text queued since the last call, and records uploading it and any new glyphs
+    /// for a target that is `target_size` pixels large.
+    pub fn prepare(
+        &mut self,
+        target_size: (u32, u32),
+        encoder: &mut wgpu::CommandEncoder,
+        staging: &mut StagingPool,
+    ) {
+        let queued = std::mem::take(&mut self.queued);
+        let mut quads = match self.layout(&queued) {
+            Some(quads) => quads,
+            None => {
+                // Start over with only the glyphs that are still in use.
+                self.atlas.clear();
+                self.layout(&queued).unwrap_or_else(|| {
+                    log::warn!("too much text to fit in the glyph atlas");
+                    Vec::new()
+                })
+            }
+        };
+        // Outlines go over the text, so that they are not hidden by its background.
+        for outline in self.outlines.drain(..) {
+            quads.extend(outline.quads());
+        }
+
+        if let Some(rows) = self.atlas.dirty_rows.take() {
+            staging.write_texture(
+                encoder,
+                wgpu::ImageCopyTexture {
+                    texture: &self.atlas_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: rows.start,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &self.atlas.image,
+                wgpu::ImageDataLayout {
+                    offset: rows.start as u64 * ATLAS_SIZE as u64,
+                    bytes_per_row: Some(ATLAS_SIZE),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: ATLAS_SIZE,
+                    height: rows.end - rows.start,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let target_size = [target_size.0 as f32, target_size.1 as f32];
+        if self.written_uniforms != Some(target_size) {
+            let uniforms = Uniforms {
+                target_size,
+                _padding: [0.0; 2],
+            };
+            staging.write_buffer(
+                encoder,
+                &self.uniform_buffer,
+                0,
+                bytemuck::bytes_of(&uniforms),
+            );
+            self.written_uniforms = Some(target_size);
+        }
+
+        let quad_bytes: &[u8] = bytemuck::cast_slice(&quads);
+        if quad_bytes != bytemuck::cast_slice::<Quad, u8>(&self.written_quads) {
+            if self.quad_buffer.size() < quad_bytes.len() as u64 {
+                self.quad_buffer = create_quad_buffer(&self.gfx, quads.len());
+            }
+            staging.write_buffer(encoder, &self.quad_buffer, 0, quad_bytes);
+            self.written_quads = quads;
+        }
+    }
+
+    /// Draws the text laid out by the last [`Self::prepare`] over what was drawn before it in
+    /// `render_pass`.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.written_quads.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+        render_pass.draw(0..4, 0..self.written_quads.len() as u32);
+    }
+
+    /// Bytes of GPU memory held by the atlas and buffers.
+    pub fn gpu_memory(&self) -> u64 {
+        (ATLAS_SIZE * ATLAS_SIZE) as u64 + self.quad_buffer.size() + self.uniform_buffer.size()
+    }
+
+    /// The quads that draw `queued`, or `None` if their glyphs do not all fit in the atlas.
+    fn layout(&mut self, queued: &[Queued]) -> Option<Vec<Quad>> {
+        let mut quads = Vec::new();
+        for Queued {
+            text,
+            position,
+            style,
+        } in queued
+        {
+            let ([left, top], [width, height]) =
+                self.place(text, *position, style.anchor, style.size);
+            if let Some(background) = style.background {
+                quads.push(Quad {
+                    rect: [
+                        left - style.padding,
+                        top - style.padding,
+                        left + width + style.padding,
+                        top + height + style.padding,
+                    ],
+                    uv_rect: Atlas::solid_uv(),
+                    color: premultiply(background),
+                });
+            }
+
+            let scale = PxScale::from(style.size);
+            let font = self.fonts.primary().as_scaled(scale);
+            let color = premultiply(style.color);
+            let line_height = font.height() + font.line_gap();
+            for (row, line) in text.split('\n').enumerate() {
+                let baseline = (top + font.ascent() + line_height * row as f32).round();
+                let shaped = self.fonts.shape(scale, line, self.right_to_left);
+                let start = if shaped.right_to_left {
+                    left + width - shaped.width
+                } else {
+                    left
+                };
+                for ShapedGlyph { font, id, offset } in shaped.glyphs {
+                    let Some(glyph) = self.atlas.glyph(&self.fonts, font, id, scale).ok()? else {
+                        continue;
+                    };
+                    let [u0, v0, u1, v1] = glyph.rect;
+                    let x0 = (start + offset[0]).round() + glyph.offset[0];
+                    let y0 = (baseline + offset[1]).round() + glyph.offset[1];
+                    quads.push(Quad {
+                        rect: [x0, y0, x0 + (u1 - u0) as f32, y0 + (v1 - v0) as f32],
+                        uv_rect: [u0, v0, u1, v1].map(|edge| edge as f32 / ATLAS_SIZE as f32),
+                        color,
+                    });
+                }
+            }
+        }
+        Some(quads)
+    }
+}
+
+/// The fonts of [`FONTS`]. Each character is drawn in the first one that has a glyph for it.
+pub struct Fonts(Vec<FontVec>);
+
+impl Fonts {
+    pub fn load(assets: &Assets) -> anyhow::Result<Self> {
+        let mut fonts = Vec::new();
+        for path in FONTS {
+            let font = FontVec::try_from_vec(assets.read(path)?.into_owned())
+                .with_context(|| format!("invalid font {}", path))?;
+            rustybuzz::Face::from_slice(font.as_slice(), 0)
+                .with_context(|| format!("font {} cannot be shaped", path))?;
+            fonts.push(font);
+        }
+        Ok(Self(fonts))
+    }
+
+    /// The font that lines are spaced by.
+    fn primary(&self) -> &FontVec {
+        &self.0[0]
+    }
+
+    /// The glyphs that `line` is drawn with, from left to right, as indices of their fonts and
+    /// their ids in them. Characters that no font has are drawn as glyph 0, the missing glyph box.
+    pub fn glyphs(&self, line: &str, right_to_left: bool) -> Vec<(usize, GlyphId)> {
+        self.shape(PxScale::from(1.0), line, right_to_left)
+            .glyphs
+            .into_iter()
+            .map(|glyph| (glyph.font, glyph.id))
+            .collect()
+    }
+
+    /// Shapes `line` at `scale`: splits it into runs of either direction and of the font that has
+    /// their characters, puts them in the order they are read in, and joins letters such as Arabic
+    /// ones. Lines without letters are laid out from right to left if `right_to_left` is set.
+    fn shape(&self, scale: PxScale, line: &str, right_to_left: bool) -> ShapedLine {
+        let right_to_left = match unicode_bidi::get_base_direction(line) {
+            unicode_bidi::Direction::Ltr => false,
+            unicode_bidi::Direction::Rtl => true,
+            unicode_bidi::Direction::Mixed => right_to_left,
+        };
+        let mut shaped = ShapedLine {
+            glyphs: Vec::new(),
+            width: 0.0,
+            right_to_left,
+        };
+        if line.is_empty() {
+            return shaped;
+        }
+        let level = if right_to_left {
+            Level::rtl()
+        } else {
+            Level::ltr()
+        };
+        let bidi = ParagraphBidiInfo::new(line, Some(level));
+        let (levels, runs) = bidi.visual_runs(0..line.len());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let mut pieces = self.split_by_font(&line[run.clone()]);
+            if rtl {
+                pieces.reverse();
+            }
+            for (font, range) in pieces {
+                let text = &line[run.start + range.start..run.start + range.end];
+                self.shape_piece(&mut shaped, scale, font, text, rtl);
+            }
+        }
+        shaped
+    }
+
+    /// Splits `text` into ranges of characters drawn in the same font, keeping characters in the
+    /// font of the ones before them where it has them, such as spaces between Hebrew words.
+    fn split_by_font(&self, text: &str) -> Vec<(usize, Range<usize>)> {
+        let covers = |font: usize, c: char| self.0[font].glyph_id(c).0 != 0;
+        let mut pieces: Vec<(usize, Range<usize>)> = Vec::new();
+        for (i, c) in text.char_indices() {
+            let end = i + c.len_utf8();
+            if let Some((font, range)) = pieces.last_mut() {
+                if covers(*font, c) || !(0..self.0.len()).any(|font| covers(font, c)) {
+                    range.end = end;
+                    continue;
+                }
+            }
+            let font = (0..self.0.len()).find(|&font| covers(font, c)).unwrap_or(0);
+            pieces.push((font, i..end));
+        }
+        pieces
+    }
+
+    /// Appends the glyphs of `text`, all in one font and direction, to `shaped`.
+    fn shape_piece(
+        &self,
+        shaped: &mut ShapedLine,
+        scale: PxScale,
+        font: usize,
+        text: &str,
+        right_to_left: bool,
+    ) {
+        let face = rustybuzz::Face::from_slice(self.0[font].as_slice(), 0)
+            .expect("font was checked when loaded");
+        let units = self.0[font].as_scaled(scale).h_scale_factor();
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(if right_to_left {
+            rustybuzz::Direction::RightToLeft
+        } else {
+            rustybuzz::Direction::LeftToRight
+        });
+        buffer.guess_segment_properties();
+        // Right-to-left runs come out in visual order too.
+        let glyphs = rustybuzz::shape(&face, &[], buffer);
+        for (info, position) in glyphs.glyph_infos().iter().zip(glyphs.glyph_positions()) {
+            shaped.glyphs.push(ShapedGlyph {
+                font,
+                id: GlyphId(info.glyph_id as u16),
+                offset: [
+                    shaped.width + position.x_offset as f32 * units,
+                    -position.y_offset as f32 * units,
+                ],
+            });
+            shaped.width += position.x_advance as f32 * units;
+        }
+    }
+}
+
+fn create_quad_buffer(gfx: &GraphicsContext, quads: usize) -> wgpu::Buffer {
+    // Grown in powers of two, so that text that changes length is not reallocated every frame.
+    let quads = quads.max(64).next_power_of_two();
+    gfx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("TextLayer.quad_buffer"),
+        size: (quads * std::mem::size_of::<Quad>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn premultiply(color: Color) -> [f32; 4] {
+    let [r, g, b, a] = color.to_linear();
+    [r * a, g * a, b * a, a]
+}
@@ -0,0 +1,196 @@
+use crate::assets::Assets;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::{Corner, TextLayer, TextStyle};
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use crate::GraphicsContext;
+use glam::Vec2;
+
+/// Distance from the top left corner of the target to the text box, and from the edges of the box
+/// to the text, in pixels.
+const MARGIN: f32 = 8.0;
+
+/// Space between a [`Readout`] in a corner and the edges of the target, in logical pixels.
+const CORNER_MARGIN: f32 = 16.0;
+
+/// Width of the outline around text that has the keyboard focus, in logical pixels.
+pub const FOCUS_WIDTH: f32 = 2.0;
+
+/// Lines of text drawn in a box in the top left corner of the target, such as debugging
+/// information.
+pub struct TextOverlay {
+    text: TextLayer,
+    font_size: f32,
+    lines: Vec<String>,
+}
+
+impl TextOverlay {
+    pub fn new(gfx: &GraphicsContext, assets: &Assets, font_size: f32) -> anyhow::Result<Self> {
+        Ok(Self {
+            text: TextLayer::new(gfx, assets)?,
+            font_size,
+            lines: Vec::new(),
+        })
+    }
+
+    /// Changes the size of the text, e.g. to follow the scale factor of a window.
+    pub fn set_font_size(&mut self, font_size: f32) {
+        self.font_size = font_size;
+    }
+
+    /// Replaces the text. Nothing is drawn while there are no lines.
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines;
+    }
+
+    /// Records uploading the text box, and its position in a target that is `target_size` pixels
+    /// large.
+    pub fn prepare(
+        &mut self,
+        target_size: (u32, u32),
+        encoder: &mut wgpu::CommandEncoder,
+        staging: &mut StagingPool,
+    ) {
+        if !self.lines.is_empty() {
+            let style = TextStyle {
+                size: self.font_size,
+                // Translucent black, so that the text is readable over anything.
+                background: Some(Color::rgba(0, 0, 0, 160)),
+                padding: MARGIN,
+                ..Default::default()
+            };
+            self.text
+                .queue(&self.lines.join("\n"), [2.0 * MARGIN; 2], &style);
+        }
+        self.text.prepare(target_size, encoder, staging);
+    }
+
+    /// Draws the text box over what was drawn before it in `render_pass`.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.text.draw(render_pass);
+    }
+}
+
+/// Text that a layer shows over the scene, such as the date or the weather in a corner of the
+/// target, in the theme's text colors. Keeps track of the target's size and scale factor and the
+/// user's text scale, so that layers only have to format their text and queue it each frame.
+pub struct Readout {
+    text: TextLayer,
+    /// Height of the text, in logical pixels.
+    size: f32,
+    color: Color,
+    background: Option<Color>,
+    right_to_left: bool,
+    target_size: Vec2,
+    scale_factor: f32,
+}
+
+impl Readout {
+    /// A readout with text `size` logical pixels high, laid out for the locale of `cx`.
+    pub fn new(cx: &LayerContext, size: f32) -> anyhow::Result<Self> {
+        let mut text = TextLayer::new(cx.gfx, cx.assets)?;
+        text.set_right_to_left(cx.locale.right_to_left);
+        let background = cx.theme.text_background;
+        Ok(Self {
+            text,
+            size,
+            color: cx.theme.text,
+            background: (background.a > 0).then_some(background),
+            right_to_left: cx.locale.right_to_left,
+            target_size: cx.viewport.size(),
+            scale_factor: 1.0,
+        })
+    }
+
+    /// The style of the readout's text, in pixels: its size, the theme's colors, and padding
+    /// around it in proportion.
+    pub fn style(&self) -> TextStyle {
+        let size = self.size * self.scale_factor;
+        TextStyle {
+            size,
+            color: self.color,
+            background: self.background,
+            padding: size * 0.3,
+            ..Default::default()
+        }
+    }
+
+    /// The corner that text for `corner` goes in, the mirrored one in a right-to-left locale.
+    pub fn corner(&self, corner: Corner) -> Corner {
+        if self.right_to_left {
+            corner.mirrored()
+        } else {
+            corner
+        }
+    }
+
+    /// Where text in `corner` with `padding` around it is anchored, away from the edges of the
+    /// target by the margin. `corner` is not mirrored.
+    pub fn corner_position(&self, corner: Corner, padding: f32) -> [f32; 2] {
+        corner.position(
+            self.target_size,
+            CORNER_MARGIN * self.scale_factor + padding,
+        )
+    }
+
+    /// Adds `text` to the next frame in `corner`, mirrored for the locale, returning the position
+    /// of the corner of its box.
+    pub fn queue_in_corner(&mut self, text: &str, corner: Corner, style: &TextStyle) -> [f32; 2] {
+        let corner = self.corner(corner);
+        let position = self.corner_position(corner, style.padding);
+        let style = TextStyle {
+            anchor: corner.anchor(),
+            ..*style
+        };
+        self.text.queue(text, position, &style);
+        position
+    }
+
+    /// Adds `text` to the next frame at `position`, like [`TextLayer::queue`].
+    pub fn queue(&mut self, text: &str, position: [f32; 2], style: &TextStyle) {
+        self.text.queue(text, position, style);
+    }
+
+    /// The width and height of `text` in pixels, when drawn at `size`.
+    pub fn measure(&self, text: &str, size: f32) -> [f32; 2] {
+        self.text.measure(text, size)
+    }
+
+    /// Outlines line `row` of `text`, queued at `position` with `style`, in the text color to show
+    /// that it has the keyboard focus.
+    pub fn queue_focus(&mut self, text: &str, row: usize, position: [f32; 2], style: &TextStyle) {
+        let rect = self.text.line_bounds(text, row, position, style);
+        self.text
+            .queue_outline(rect, FOCUS_WIDTH * self.scale_factor, self.color);
+    }
+
+    /// Follows the scale factor of the window, see [`crate::Layer::set_scale_factor`].
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+    }
+
+    /// See [`TextLayer::set_scale`].
+    pub fn set_text_scale(&mut self, scale: f32) {
+        self.text.set_scale(scale);
+    }
+
+    pub fn resized(&mut self, viewport: &Viewport) {
+        self.target_size = viewport.size();
+    }
+
+    /// Records uploading the text queued since the last call.
+    pub fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        let target_size = (self.target_size.x as u32, self.target_size.y as u32);
+        self.text.prepare(target_size, encoder, staging);
+    }
+
+    pub fn gpu_memory(&self) -> u64 {
+        self.text.gpu_memory()
+    }
+
+    /// Draws the text over what was drawn before it in `render_pass`.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.text.draw(render_pass);
+    }
+}
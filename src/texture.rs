@@ -0,0 +1,526 @@
+//! Textures decoded from images, and BC7 compression for them.
+//!
+//! Compressing a texture takes a while, so the result is cached on disk, keyed by a hash of
+//! the encoded image. Later launches load the compressed texture from the cache, which skips
+//! decoding the image and takes a quarter of the graphics memory.
+
+use crate::GraphicsContext;
+use anyhow::Context;
+use image::RgbaImage;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Changed whenever the encoder's output changes, so that textures cached by older versions are
+/// compressed again.
+const ENCODER_VERSION: u32 = 1;
+
+/// Identifies the cache files, which start with this, then the width and height.
+const CACHE_MAGIC: &[u8; 4] = b"BC7\0";
+
+/// Interpolation weights for 4-bit indices, out of 64.
+const WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// The pixels of a texture, ready to be uploaded.
+pub enum TextureData {
+    Rgba(Arc<RgbaImage>),
+    /// Compressed in BC7 mode 6, in 16-byte blocks of 4×4 pixels.
+    Bc7 {
+        width: u32,
+        height: u32,
+        blocks: Vec<u8>,
+    },
+}
+
+impl TextureData {
+    pub fn width(&self) -> u32 {
+        match self {
+            Self::Rgba(image) => image.width(),
+            Self::Bc7 { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            Self::Rgba(image) => image.height(),
+            Self::Bc7 { height, .. } => *height,
+        }
+    }
+
+    /// The size of the texture in graphics memory, in bytes.
+    pub fn byte_size(&self) -> usize {
+        match self {
+            Self::Rgba(image) => image.as_raw().len(),
+            Self::Bc7 { blocks, .. } => blocks.len(),
+        }
+    }
+
+    pub fn create_texture(&self, gfx: &GraphicsContext, label: &str) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width: self.width(),
+            height: self.height(),
+            ..Default::default()
+        };
+        let (format, data, bytes_per_row, rows) = match self {
+            Self::Rgba(image) => (
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                image.as_raw().as_slice(),
+                size.width * 4,
+                size.height,
+            ),
+            Self::Bc7 { blocks, .. } => (
+                wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+                blocks.as_slice(),
+                size.width / 4 * 16,
+                size.height / 4,
+            ),
+        };
+        let texture = gfx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        gfx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(rows),
+            },
+            size,
+        );
+        texture
+    }
+}
+
+/// Whether textures created on the device can be BC7-compressed.
+pub fn supports_bc7(gfx: &GraphicsContext) -> bool {
+    gfx.device
+        .features()
+        .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+}
+
+/// Decodes an image with `decode`, scaled down to take at most `max_bytes` as an uncompressed
+/// texture. `name` is the image's file name, used in messages.
+pub fn load_rgba(
+    name: &str,
+    max_bytes: Option<u64>,
+    decode: impl FnOnce() -> anyhow::Result<Arc<RgbaImage>>,
+) -> anyhow::Result<TextureData> {
+    let image = decode()?;
+    let halvings = halvings(image.width(), image.height(), 4, max_bytes);
+    Ok(TextureData::Rgba(downscale(image, halvings, name)))
+}
+
+/// Loads the BC7-compressed copy of `bytes`, an encoded image such as a JPEG, from `cache_dir`,
+/// or else decodes it with `decode` and compresses it, caching the result for the next call.
+/// `name` is the image's file name, used in messages and for the cache file. The image is scaled
+/// down to take at most `max_bytes`, if set.
+///
+/// Images whose sides are not multiples of 4 pixels are left uncompressed.
+pub fn load_compressed(
+    bytes: &[u8],
+    name: &str,
+    cache_dir: &Path,
+    max_bytes: Option<u64>,
+    decode: impl FnOnce() -> anyhow::Result<Arc<RgbaImage>>,
+) -> anyhow::Result<TextureData> {
+    let (width, height) = image::io::Reader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()
+        .with_context(|| format!("failed to decode texture {}", name))?;
+    let halvings = halvings(width, height, 1, max_bytes);
+    let cache_path = cache_path(bytes, name, halvings, cache_dir);
+    match read_cache(&cache_path) {
+        Ok(Some(data)) => return Ok(data),
+        Ok(None) => {}
+        Err(err) => log::warn!("{:#}", err),
+    }
+    let image = downscale(decode()?, halvings, name);
+    if !image.width().is_multiple_of(4) || !image.height().is_multiple_of(4) {
+        return Ok(TextureData::Rgba(image));
+    }
+    let start = instant::Instant::now();
+    let data = TextureData::Bc7 {
+        width: image.width(),
+        height: image.height(),
+        blocks: encode_bc7(&image),
+    };
+    log::info!("compressed texture {} in {:?}", name, start.elapsed());
+    if let Err(err) = write_cache(&cache_path, &data) {
+        log::warn!("{:#}", err);
+    }
+    Ok(data)
+}
+
+/// Whether [`load_compressed`] would find `bytes` compressed in `cache_dir` at full size, rather
+/// than decode it.
+pub fn is_cached(bytes: &[u8], name: &str, cache_dir: &Path) -> bool {
+    cache_path(bytes, name, 0, cache_dir).exists()
+}
+
+/// How many times an image has to be halved in size to take at most `max_bytes` with
+/// `bytes_per_pixel`. Images are not made smaller than 4 pixels on either side.
+fn halvings(width: u32, height: u32, bytes_per_pixel: u64, max_bytes: Option<u64>) -> u32 {
+    let Some(max_bytes) = max_bytes else {
+        return 0;
+    };
+    let mut halvings = 0;
+    while (width >> halvings) as u64 * (height >> halvings) as u64 * bytes_per_pixel > max_bytes
+        && (width >> halvings).min(height >> halvings) >= 8
+    {
+        halvings += 1;
+    }
+    halvings
+}
+
+fn downscale(image: Arc<RgbaImage>, halvings: u32, name: &str) -> Arc<RgbaImage> {
+    if halvings == 0 {
+        return image;
+    }
+    let (width, height) = (image.width() >> halvings, image.height() >> halvings);
+    log::info!(
+        "scaling texture {} down to {}x{} to fit the texture budget",
+        name,
+        width,
+        height
+    );
+    Arc::new(image::imageops::resize(
+        &*image,
+        width,
+        height,
+        image::imageops::FilterType::Triangle,
+    ))
+}
+
+/// Where the texture `name` with the encoded image `bytes` is cached, halved in size `halvings`
+/// times: `<stem>-<key>.bc7` in the cache's `textures` directory, where the stem tells the name and
+/// size apart and the key changes with the image and the encoder.
+fn cache_path(bytes: &[u8], name: &str, halvings: u32, cache_dir: &Path) -> PathBuf {
+    let mut hash = Fnv1a::new();
+    hash.write(&ENCODER_VERSION.to_le_bytes());
+    hash.write(bytes);
+    let stem = Path::new(name)
+        .file_stem()
+        .map_or_else(|| name.into(), |stem| stem.to_string_lossy());
+    let stem = if halvings > 0 {
+        format!("{}-half{}", stem, halvings)
+    } else {
+        stem.into_owned()
+    };
+    cache_dir
+        .join("textures")
+        .join(format!("{}-{:016x}.bc7", stem, hash.finish()))
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hasher stays the same across
+/// Rust releases, so that cache keys do too.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Deletes the textures cached next to `path` with the same stem and another key, e.g. by older
+/// versions of the encoder or for an image that has since changed.
+fn remove_stale(path: &Path) -> std::io::Result<()> {
+    let stem_of = |path: &Path| {
+        let name = path.file_name()?.to_str()?.strip_suffix(".bc7")?;
+        let (stem, key) = name.rsplit_once('-')?;
+        (key.len() == 16 && key.chars().all(|c| c.is_ascii_hexdigit())).then(|| stem.to_owned())
+    };
+    let Some(stem) = stem_of(path) else {
+        return Ok(());
+    };
+    for entry in std::fs::read_dir(path.parent().unwrap())? {
+        let other = entry?.path();
+        if other != path && stem_of(&other).as_ref() == Some(&stem) {
+            log::info!("removing stale cached texture {}", other.display());
+            std::fs::remove_file(&other)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a compressed texture from the cache, or returns `None` if it is not cached.
+fn read_cache(path: &Path) -> anyhow::Result<Option<TextureData>> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    let header = contents
+        .get(..12)
+        .filter(|header| header.starts_with(CACHE_MAGIC));
+    let Some(header) = header else {
+        log::warn!("ignoring invalid cached texture {}", path.display());
+        return Ok(None);
+    };
+    let width = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    let height = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    let blocks = contents[12..].to_vec();
+    if width % 4 != 0 || height % 4 != 0 || Some(blocks.len()) != bc7_size(width, height) {
+        log::warn!("ignoring invalid cached texture {}", path.display());
+        return Ok(None);
+    }
+    Ok(Some(TextureData::Bc7 {
+        width,
+        height,
+        blocks,
+    }))
+}
+
+/// Bytes of BC7 blocks that cover an image `width` by `height` pixels, or `None` if that is more
+/// than memory holds, e.g. for the size in a corrupt cache file.
+fn bc7_size(width: u32, height: u32) -> Option<usize> {
+    (u64::from(width) / 4)
+        .checked_mul(u64::from(height) / 4)?
+        .checked_mul(16)?
+        .try_into()
+        .ok()
+}
+
+fn write_cache(path: &Path, data: &TextureData) -> anyhow::Result<()> {
+    let TextureData::Bc7 {
+        width,
+        height,
+        blocks,
+    } = data
+    else {
+        return Ok(());
+    };
+    let mut contents = Vec::with_capacity(12 + blocks.len());
+    contents.extend_from_slice(CACHE_MAGIC);
+    contents.extend_from_slice(&width.to_le_bytes());
+    contents.extend_from_slice(&height.to_le_bytes());
+    contents.extend_from_slice(blocks);
+    let write = || -> std::io::Result<()> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        // Written under another name first, so that another instance never reads half of it.
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, &contents)?;
+        std::fs::rename(&partial, path)
+    };
+    write().with_context(|| format!("failed to cache texture {}", path.display()))?;
+    if let Err(err) = remove_stale(path) {
+        log::warn!("failed to remove stale cached textures: {}", err);
+    }
+    Ok(())
+}
+
+/// Compresses an image whose sides are multiples of 4 pixels with BC7, using mode 6 for every
+/// block: one pair of RGBA endpoints with 16 shades between them.
+pub fn encode_bc7(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    assert!(width % 4 == 0 && height % 4 == 0);
+    let mut blocks = Vec::with_capacity(bc7_size(width, height).unwrap_or(0));
+    for block_y in (0..height).step_by(4) {
+        for block_x in (0..width).step_by(4) {
+            let mut pixels = [[0.0; 4]; 16];
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let [r, g, b, a] = image
+                    .get_pixel(block_x + i as u32 % 4, block_y + i as u32 / 4)
+                    .0;
+                *pixel = [r as f32, g as f32, b as f32, a as f32];
+            }
+            blocks.extend_from_slice(&encode_block(&pixels).to_le_bytes());
+        }
+    }
+    blocks
+}
+
+type Color = [f32; 4];
+
+/// An endpoint as stored: 7 bits per channel, and the shared lowest bit.
+#[derive(Clone, Copy)]
+struct Endpoint {
+    channels: [u32; 4],
+    p_bit: u32,
+}
+
+impl Endpoint {
+    /// The closest endpoint to `color`.
+    fn quantize(color: Color) -> Self {
+        let candidates = [0, 1].map(|p_bit| {
+            let channels =
+                color.map(|c| ((c - p_bit as f32) / 2.0).round().clamp(0.0, 127.0) as u32);
+            Self { channels, p_bit }
+        });
+        let error = |endpoint: &Self| distance(endpoint.color().map(|c| c as f32), color);
+        if error(&candidates[0]) <= error(&candidates[1]) {
+            candidates[0]
+        } else {
+            candidates[1]
+        }
+    }
+
+    fn color(&self) -> [u32; 4] {
+        self.channels.map(|c| c << 1 | self.p_bit)
+    }
+}
+
+fn encode_block(pixels: &[Color; 16]) -> u128 {
+    let (low, high) = principal_endpoints(pixels);
+    let (mut endpoints, mut indices, error) = fit(pixels, low, high);
+    // Refit the endpoints to the chosen shades, which usually lowers the error a little.
+    if let Some((low, high)) = least_squares_endpoints(pixels, &indices) {
+        let (refit_endpoints, refit_indices, refit_error) = fit(pixels, low, high);
+        if refit_error < error {
+            endpoints = refit_endpoints;
+            indices = refit_indices;
+        }
+    }
+
+    // The first pixel's index is stored without its highest bit, which has to be 0.
+    if indices[0] >= 8 {
+        endpoints.swap(0, 1);
+        for index in &mut indices {
+            *index = 15 - *index;
+        }
+    }
+
+    let mut block = 0u128;
+    let mut position = 0;
+    let mut put = |value: u32, bits: u32| {
+        block |= (value as u128) << position;
+        position += bits;
+    };
+    put(1 << 6, 7);
+    for channel in 0..4 {
+        put(endpoints[0].channels[channel], 7);
+        put(endpoints[1].channels[channel], 7);
+    }
+    put(endpoints[0].p_bit, 1);
+    put(endpoints[1].p_bit, 1);
+    put(indices[0], 3);
+    for &index in &indices[1..] {
+        put(index, 4);
+    }
+    block
+}
+
+/// The ends of the line through the pixels' colors that fits them best.
+fn principal_endpoints(pixels: &[Color; 16]) -> (Color, Color) {
+    let mut mean = [0.0; 4];
+    for pixel in pixels {
+        for c in 0..4 {
+            mean[c] += pixel[c] / 16.0;
+        }
+    }
+    let mut covariance = [[0.0f32; 4]; 4];
+    for pixel in pixels {
+        let d = sub(*pixel, mean);
+        for i in 0..4 {
+            for j in 0..4 {
+                covariance[i][j] += d[i] * d[j];
+            }
+        }
+    }
+    // Power iteration, starting from the diagonal, which is never orthogonal to the principal
+    // axis unless the pixels are all the same color.
+    let mut axis = [0, 1, 2, 3].map(|i| covariance[i][i]);
+    for _ in 0..8 {
+        let next = [0, 1, 2, 3].map(|i| (0..4).map(|j| covariance[i][j] * axis[j]).sum::<f32>());
+        let length = dot(next, next).sqrt();
+        if length < 1e-6 {
+            return (mean, mean);
+        }
+        axis = next.map(|c| c / length);
+    }
+    let (mut min, mut max) = (f32::MAX, f32::MIN);
+    for pixel in pixels {
+        let t = dot(sub(*pixel, mean), axis);
+        min = min.min(t);
+        max = max.max(t);
+    }
+    let along = |t: f32| [0, 1, 2, 3].map(|c| (mean[c] + axis[c] * t).clamp(0.0, 255.0));
+    (along(min), along(max))
+}
+
+/// Quantizes the endpoints and picks the closest shade between them for each pixel. Returns the
+/// endpoints, the pixels' indices and the total squared error.
+fn fit(pixels: &[Color; 16], low: Color, high: Color) -> ([Endpoint; 2], [u32; 16], f32) {
+    let endpoints = [Endpoint::quantize(low), Endpoint::quantize(high)];
+    let [e0, e1] = endpoints.map(|endpoint| endpoint.color());
+    let palette = WEIGHTS.map(|weight| {
+        [0, 1, 2, 3]
+            .map(|c| ((64 - weight) * e0[c] + weight * e1[c] + 32) >> 6)
+            .map(|c| c as f32)
+    });
+    let mut indices = [0; 16];
+    let mut total = 0.0;
+    for (pixel, index) in pixels.iter().zip(&mut indices) {
+        let (best, error) = palette
+            .iter()
+            .map(|shade| distance(*shade, *pixel))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        *index = best as u32;
+        total += error;
+    }
+    (endpoints, indices, total)
+}
+
+/// The endpoints that minimize the squared error for pixels interpolated with `indices`, or `None`
+/// if all of them use the same index.
+fn least_squares_endpoints(pixels: &[Color; 16], indices: &[u32; 16]) -> Option<(Color, Color)> {
+    let (mut aa, mut ab, mut bb) = (0.0, 0.0, 0.0);
+    let (mut ax, mut bx) = ([0.0; 4], [0.0; 4]);
+    for (pixel, &index) in pixels.iter().zip(indices) {
+        let b = WEIGHTS[index as usize] as f32 / 64.0;
+        let a = 1.0 - b;
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        for c in 0..4 {
+            ax[c] += a * pixel[c];
+            bx[c] += b * pixel[c];
+        }
+    }
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let low = [0, 1, 2, 3].map(|c| ((bb * ax[c] - ab * bx[c]) / determinant).clamp(0.0, 255.0));
+    let high = [0, 1, 2, 3].map(|c| ((aa * bx[c] - ab * ax[c]) / determinant).clamp(0.0, 255.0));
+    Some((low, high))
+}
+
+fn sub(a: Color, b: Color) -> Color {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]]
+}
+
+fn dot(a: Color, b: Color) -> f32 {
+    a.iter().zip(&b).map(|(a, b)| a * b).sum()
+}
+
+fn distance(a: Color, b: Color) -> f32 {
+    let d = sub(a, b);
+    dot(d, d)
+}
@@ -0,0 +1,188 @@
+use crate::assets::Assets;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Colors used to draw the scene.
+///
+/// Themes are TOML files, looked up by name first in a directory of user themes (the `themes`
+/// directory next to the config file), and then among the bundled themes. Keys missing from a theme file take their values from
+/// the default theme.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub background: Color,
+    /// Tick marks around the edge of the clock face.
+    pub dial: Color,
+    pub hands: Color,
+    /// Blended over the globe along the day/night boundary; transparent to disable.
+    pub terminator_tint: Color,
+    /// Text drawn over the scene, such as the digital clock.
+    pub text: Color,
+    /// Box drawn behind text to keep it readable; transparent to disable.
+    pub text_background: Color,
+    /// How many times thicker than usual the ticks and hands are drawn.
+    pub stroke_scale: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: Color::rgba(0, 0, 0, 255),
+            dial: Color::rgba(255, 255, 255, 128),
+            hands: Color::rgba(255, 255, 255, 128),
+            terminator_tint: Color::rgba(0, 0, 0, 0),
+            text: Color::rgba(255, 255, 255, 192),
+            text_background: Color::rgba(0, 0, 0, 0),
+            stroke_scale: 1.0,
+        }
+    }
+}
+
+/// The bundled theme for when the system asks for high contrast.
+pub const HIGH_CONTRAST: &str = "high-contrast";
+
+const BUNDLED: &[&str] = &["default", HIGH_CONTRAST, "midnight", "paper"];
+
+impl Theme {
+    /// Whether the background is dark, so that e.g. window decorations should be dark too.
+    pub fn is_dark(&self) -> bool {
+        let [r, g, b, _] = self.background.to_linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b < 0.2
+    }
+
+    pub fn load(name: &str, themes_dir: &Path, assets: &Assets) -> anyhow::Result<Self> {
+        let user_path = themes_dir.join(format!("{}.toml", name));
+        let source = if user_path.exists() {
+            Cow::Owned(
+                std::fs::read_to_string(&user_path)
+                    .with_context(|| format!("failed to read theme {}", user_path.display()))?,
+            )
+        } else {
+            if !BUNDLED.contains(&name) {
+                bail!(
+                    "unknown theme {:?}; available themes are {}",
+                    name,
+                    Self::available(themes_dir).join(", ")
+                );
+            }
+            assets.read_to_string(&format!("themes/{}.toml", name))?
+        };
+        toml::from_str(&source).with_context(|| format!("invalid theme {:?}", name))
+    }
+
+    /// Names of the bundled themes and those in `themes_dir`.
+    pub fn available(themes_dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = BUNDLED.iter().map(|&name| name.to_owned()).collect();
+        if let Ok(entries) = std::fs::read_dir(themes_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    if let Some(stem) = path.file_stem() {
+                        names.push(stem.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// An sRGB color with straight (non-premultiplied) alpha, written as `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Linear-light components, for use in shaders and as clear colors on sRGB targets.
+    pub fn to_linear(self) -> [f32; 4] {
+        fn decode(c: u8) -> f32 {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        [
+            decode(self.r),
+            decode(self.g),
+            decode(self.b),
+            self.a as f32 / 255.0,
+        ]
+    }
+
+    pub fn to_wgpu(self) -> wgpu::Color {
+        let [r, g, b, a] = self.to_linear();
+        wgpu::Color {
+            r: r.into(),
+            g: g.into(),
+            b: b.into(),
+            a: a.into(),
+        }
+    }
+
+    pub fn to_skia(self) -> tiny_skia::Color {
+        tiny_skia::Color::from_rgba8(self.r, self.g, self.b, self.a)
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let hex = s.strip_prefix('#').context("colors must start with `#`")?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            bail!(
+                "expected a color of the form #rrggbb or #rrggbbaa, not {:?}",
+                s
+            );
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .with_context(|| format!("invalid hex digits in color {:?}", s))
+        };
+        Ok(Self {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+            a: if hex.len() == 8 { channel(6)? } else { 255 },
+        })
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
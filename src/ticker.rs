@@ -0,0 +1,282 @@
+use crate::layer::{FrameInfo, Layer};
+use crate::process;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::{TextLayer, TextStyle};
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use anyhow::{bail, Context};
+use glam::Vec2;
+use instant::Instant;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Space between the ticker and the edges of the target, in logical pixels.
+const MARGIN: f32 = 16.0;
+
+/// How long the command may run before it is stopped and the last text kept.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings of the ticker, from the config's `[layer.ticker]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TickerSettings {
+    /// Shell command whose output is shown.
+    pub command: Option<String>,
+    /// File whose contents are shown, instead of a command's output.
+    pub file: Option<PathBuf>,
+    /// How often the command is run or the file read again.
+    pub refresh_seconds: u32,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+    /// How fast the text scrolls, in logical pixels per second. 0 keeps it still.
+    pub speed: f32,
+    /// Put between the lines of the text, which are shown on one line.
+    pub separator: String,
+}
+
+impl Default for TickerSettings {
+    fn default() -> Self {
+        Self {
+            command: None,
+            file: None,
+            refresh_seconds: 60,
+            size: 18.0,
+            speed: 80.0,
+            separator: "  ·  ".to_owned(),
+        }
+    }
+}
+
+/// Where the ticker's text comes from.
+#[derive(Debug, Clone)]
+enum Source {
+    Command(String),
+    File(PathBuf),
+}
+
+/// The text last read by the reading thread, or `None` before it has tried once, with a
+/// notification when it changes.
+type Latest = (Mutex<Option<String>>, Condvar);
+
+/// A line of text scrolling along the bottom of the target, from a command's output or a file
+/// that is read again every so often, e.g. headlines or the status of servers.
+pub struct Ticker {
+    text: TextLayer,
+    settings: TickerSettings,
+    color: Color,
+    background: Option<Color>,
+    target_size: Vec2,
+    scale_factor: f32,
+    /// Written by the reading thread.
+    latest: Arc<Latest>,
+    line: String,
+    /// When the current line started scrolling.
+    started: Instant,
+    /// Left edge of the line's background box, in pixels.
+    x: f32,
+    /// Dropped with the layer, which stops the reading thread, or `None` if there is nothing to
+    /// read.
+    stop: Option<mpsc::Sender<()>>,
+}
+
+impl Ticker {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: TickerSettings = cx.settings("ticker")?;
+        let source = match (&settings.command, &settings.file) {
+            (Some(command), None) => Some(Source::Command(command.clone())),
+            (None, Some(file)) => Some(Source::File(file.clone())),
+            (Some(_), Some(_)) => bail!("set either ticker.command or ticker.file, not both"),
+            (None, None) => {
+                log::warn!(
+                    "the ticker layer needs a command or a file; set `command` or `file` in \
+                     [layer.ticker]"
+                );
+                None
+            }
+        };
+        let latest = Arc::new(Latest::default());
+        let stop = match source {
+            Some(source) => {
+                let (stop, stopped) = mpsc::channel();
+                let refresh = Duration::from_secs(settings.refresh_seconds.max(1) as u64);
+                let separator = settings.separator.clone();
+                let latest = latest.clone();
+                std::thread::Builder::new()
+                    .name("ticker".into())
+                    .spawn(move || run(&source, &separator, refresh, &latest, &stopped))
+                    .context("failed to start reading the ticker's text")?;
+                Some(stop)
+            }
+            None => None,
+        };
+        let background = cx.theme.text_background;
+        Ok(Self {
+            text: TextLayer::new(cx.gfx, cx.assets)?,
+            settings,
+            color: cx.theme.text,
+            background: (background.a > 0).then_some(background),
+            target_size: cx.viewport.size(),
+            scale_factor: 1.0,
+            latest,
+            line: String::new(),
+            started: Instant::now(),
+            x: 0.0,
+            stop,
+        })
+    }
+
+    fn style(&self) -> TextStyle {
+        let size = self.settings.size * self.scale_factor;
+        TextStyle {
+            size,
+            color: self.color,
+            anchor: [0.0, 1.0],
+            background: self.background,
+            padding: size * 0.3,
+        }
+    }
+}
+
+impl Layer for Ticker {
+    fn name(&self) -> &str {
+        "ticker"
+    }
+
+    /// Every frame while the text scrolls.
+    fn tick_interval(&self) -> Duration {
+        if self.line.is_empty() || self.settings.speed == 0.0 {
+            Duration::from_secs(1)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn update(&mut self, _frame: &FrameInfo) {
+        let style = self.style();
+        let margin = MARGIN * self.scale_factor;
+        let width = self.target_size.x;
+        let text_width = self.text.measure(&self.line, style.size)[0] + 2.0 * style.padding;
+        // Starting at the left margin, it moves left until it is gone, and then comes back in from
+        // the right edge.
+        let scrolled =
+            self.started.elapsed().as_secs_f32() * self.settings.speed * self.scale_factor;
+        self.x = width - (scrolled + width - margin).rem_euclid(width + text_width);
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.text.set_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.target_size = viewport.size();
+    }
+
+    fn poll(&mut self) -> bool {
+        let latest = self.latest.0.lock().unwrap();
+        match &*latest {
+            Some(line) if *line != self.line => {
+                self.line = line.clone();
+                self.started = Instant::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish_loading(&mut self) {
+        if self.stop.is_none() {
+            return;
+        }
+        let (latest, changed) = &*self.latest;
+        drop(changed.wait_while(latest.lock().unwrap(), |line| line.is_none()));
+        self.poll();
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if !self.line.is_empty() {
+            let style = self.style();
+            let margin = MARGIN * self.scale_factor + style.padding;
+            let position = [self.x + style.padding, self.target_size.y - margin];
+            self.text.queue(&self.line, position, &style);
+        }
+        let target_size = (self.target_size.x as u32, self.target_size.y as u32);
+        self.text.prepare(target_size, encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.text.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.text.draw(render_pass);
+    }
+}
+
+/// Reads `source` to `latest` every `refresh`, with its lines joined by `separator`, until `stop`
+/// is disconnected. On failure, the last text is kept.
+fn run(
+    source: &Source,
+    separator: &str,
+    refresh: Duration,
+    (latest, changed): &Latest,
+    stop: &mpsc::Receiver<()>,
+) {
+    loop {
+        let text = match read(source) {
+            Ok(text) => {
+                let lines: Vec<&str> = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                Some(lines.join(separator))
+            }
+            Err(err) => {
+                log::warn!("{:#}", err);
+                None
+            }
+        };
+        {
+            let mut latest = latest.lock().unwrap();
+            if let Some(text) = text {
+                *latest = Some(text);
+            } else if latest.is_none() {
+                *latest = Some(String::new());
+            }
+        }
+        changed.notify_all();
+        match stop.recv_timeout(refresh) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+fn read(source: &Source) -> anyhow::Result<String> {
+    match source {
+        Source::File(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read the ticker's text from {}", path.display())),
+        Source::Command(command) => {
+            let output =
+                process::output_with_timeout(&mut process::shell(command), COMMAND_TIMEOUT)
+                    .with_context(|| format!("failed to run ticker command {:?}", command))?;
+            if !output.status.success() {
+                bail!(
+                    "ticker command {:?} failed with {}: {}",
+                    command,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+    }
+}
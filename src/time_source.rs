@@ -0,0 +1,263 @@
+use chrono::{DateTime, Duration, Utc};
+use instant::Instant;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// How far a clock must move from where it should be, going by the monotonic clock, to count as
+/// having jumped, e.g. by being set by hand or stepped by NTP, rather than slewed.
+pub const JUMP_THRESHOLD: Duration = Duration::seconds(1);
+
+/// How long a [`SmoothedTime`] takes to catch up with a jump.
+const TRANSITION: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Jumps larger than this are shown at once, as catching up with them would spin the globe
+/// around many times.
+const MAX_SMOOTHED_JUMP: Duration = Duration::days(1);
+
+/// Provides the instant that is displayed by the clock.
+///
+/// Everything that needs the current time should ask a time source instead of the system clock,
+/// so that the clock can be simulated, tested and corrected in one place.
+pub trait TimeSource {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// How many seconds pass on this clock per real second, e.g. to know when the displayed
+    /// second will next change.
+    fn speed(&self) -> f64 {
+        1.0
+    }
+
+    /// Whether the time is catching up with a jump, which is drawn at a higher frame rate than
+    /// usual to animate it.
+    fn in_transition(&self) -> bool {
+        false
+    }
+}
+
+impl<T: TimeSource + ?Sized> TimeSource for Box<T> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn speed(&self) -> f64 {
+        (**self).speed()
+    }
+
+    fn in_transition(&self) -> bool {
+        (**self).in_transition()
+    }
+}
+
+/// The system's real-time clock.
+pub struct SystemTime;
+
+impl TimeSource for SystemTime {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that is stopped at one instant.
+pub struct FixedTime(pub DateTime<Utc>);
+
+impl TimeSource for FixedTime {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+
+    fn speed(&self) -> f64 {
+        0.0
+    }
+}
+
+/// A clock that starts at an arbitrary instant and runs at an arbitrary speed.
+///
+/// A speed of zero freezes the clock, and negative speeds run it backwards.
+pub struct SimulatedTime {
+    start: DateTime<Utc>,
+    started_at: Instant,
+    speed: f64,
+}
+
+impl SimulatedTime {
+    pub fn new(start: DateTime<Utc>, speed: f64) -> Self {
+        Self {
+            start,
+            started_at: Instant::now(),
+            speed,
+        }
+    }
+}
+
+impl TimeSource for SimulatedTime {
+    fn now(&self) -> DateTime<Utc> {
+        advance(self.start, self.started_at.elapsed(), self.speed)
+    }
+
+    fn speed(&self) -> f64 {
+        self.speed
+    }
+}
+
+/// Another time source, corrected by an offset that can be changed at any time, e.g. by a thread
+/// that measures the system clock's error against an NTP server.
+pub struct CorrectedTime<S> {
+    source: S,
+    offset: TimeOffset,
+}
+
+impl<S: TimeSource> CorrectedTime<S> {
+    pub fn new(source: S) -> Self {
+        Self::with_offset(source, TimeOffset::default())
+    }
+
+    /// Corrected by an existing handle, e.g. to keep a correction when the source is replaced.
+    pub fn with_offset(source: S, offset: TimeOffset) -> Self {
+        Self { source, offset }
+    }
+
+    /// A handle for changing the correction, which can be sent to other threads.
+    pub fn offset(&self) -> TimeOffset {
+        self.offset.clone()
+    }
+}
+
+impl<S: TimeSource> TimeSource for CorrectedTime<S> {
+    fn now(&self) -> DateTime<Utc> {
+        self.source.now() + self.offset.get()
+    }
+
+    fn speed(&self) -> f64 {
+        self.source.speed()
+    }
+
+    fn in_transition(&self) -> bool {
+        self.source.in_transition()
+    }
+}
+
+/// The correction applied by a [`CorrectedTime`], with microsecond precision.
+#[derive(Clone, Default)]
+pub struct TimeOffset(Arc<AtomicI64>);
+
+impl TimeOffset {
+    pub fn get(&self) -> Duration {
+        Duration::microseconds(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, offset: Duration) {
+        let micros = offset
+            .num_microseconds()
+            .unwrap_or(if offset < Duration::zero() {
+                i64::MIN
+            } else {
+                i64::MAX
+            });
+        self.0.store(micros, Ordering::Relaxed);
+    }
+}
+
+/// Another time source, whose jumps are eased over a short transition instead of shown at once,
+/// so that the hands and the terminator sweep to the new time. The source is expected to run
+/// steadily at its speed by the monotonic clock, which the system clock does until it is set or
+/// stepped, or the machine sleeps.
+pub struct SmoothedTime<S> {
+    source: S,
+    state: Mutex<Smoothing>,
+}
+
+#[derive(Default)]
+struct Smoothing {
+    /// The source's time at the last reading, and when that was by the monotonic clock.
+    last: Option<(DateTime<Utc>, Instant)>,
+    /// The jump being caught up with, and when it was noticed.
+    transition: Option<(Duration, Instant)>,
+}
+
+impl Smoothing {
+    /// How much of the jump being caught up with is still to go at `instant`.
+    fn remaining(&mut self, instant: Instant) -> Duration {
+        let Some((jump, start)) = self.transition else {
+            return Duration::zero();
+        };
+        let progress =
+            instant.saturating_duration_since(start).as_secs_f64() / TRANSITION.as_secs_f64();
+        if progress >= 1.0 {
+            self.transition = None;
+            return Duration::zero();
+        }
+        // Smoothstep, so that the transition starts and ends at rest.
+        let eased = progress * progress * (3.0 - 2.0 * progress);
+        let micros = jump.num_microseconds().unwrap_or(0) as f64 * (1.0 - eased);
+        Duration::microseconds(micros as i64)
+    }
+}
+
+impl<S: TimeSource> SmoothedTime<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            state: Mutex::default(),
+        }
+    }
+}
+
+impl<S: TimeSource> TimeSource for SmoothedTime<S> {
+    fn now(&self) -> DateTime<Utc> {
+        let now = self.source.now();
+        let instant = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if let Some((last, at)) = state.last {
+            let elapsed = instant.saturating_duration_since(at);
+            let jump = now - advance(last, elapsed, self.source.speed());
+            if jump.abs() >= JUMP_THRESHOLD {
+                log::info!("the clock jumped by {}s", jump.num_seconds());
+                // A jump during a transition carries on from what is shown.
+                let shown = jump + state.remaining(instant);
+                state.transition = (shown.abs() <= MAX_SMOOTHED_JUMP).then_some((shown, instant));
+            }
+        }
+        state.last = Some((now, instant));
+        now - state.remaining(instant)
+    }
+
+    fn speed(&self) -> f64 {
+        self.source.speed()
+    }
+
+    fn in_transition(&self) -> bool {
+        let transition = self.state.lock().unwrap().transition;
+        transition.is_some_and(|(_, start)| start.elapsed() < TRANSITION)
+    }
+}
+
+/// `time` moved on by `elapsed` real time at `speed`, which is added in whole seconds and the
+/// nanoseconds left over, so that centuries at a high speed do not overflow. Times past the range
+/// of dates stop at its ends.
+fn advance(time: DateTime<Utc>, elapsed: std::time::Duration, speed: f64) -> DateTime<Utc> {
+    let seconds = elapsed.as_secs_f64() * speed;
+    let whole = seconds.trunc();
+    let nanos = Duration::nanoseconds(((seconds - whole) * 1e9) as i64);
+    let moved = Duration::try_seconds(whole as i64)
+        .and_then(|whole| whole.checked_add(&nanos))
+        .and_then(|offset| time.checked_add_signed(offset));
+    match moved {
+        Some(time) => time,
+        None if seconds < 0.0 => DateTime::<Utc>::MIN_UTC,
+        None => DateTime::<Utc>::MAX_UTC,
+    }
+}
+
+/// Runs from `start` (or the current time, if `None`) at `speed` times real time, using the
+/// simplest source that does so.
+pub fn from_settings(start: Option<DateTime<Utc>>, speed: f64) -> Box<dyn TimeSource> {
+    let start = match start {
+        None if speed == 1.0 => return Box::new(SmoothedTime::new(SystemTime)),
+        start => start.unwrap_or_else(Utc::now),
+    };
+    if speed == 0.0 {
+        Box::new(FixedTime(start))
+    } else {
+        Box::new(SimulatedTime::new(start, speed))
+    }
+}
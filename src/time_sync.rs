@@ -0,0 +1,324 @@
+use crate::banner::{self, Level};
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text_overlay::Readout;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use anyhow::{bail, Context};
+use glam::{Affine2, Vec2};
+use serde::Deserialize;
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// How far below the center of the dial the indicator is, in the scene's coordinates, where the
+/// dial's edge is at 1.
+const DIAL_OFFSET: f32 = 0.62;
+
+/// How long rendering waits for the system to be first asked, e.g. for a slow `chronyc`.
+const FIRST_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The status from the last query, which is `None` until the first query finished and holds
+/// `None` while the status is unknown, and a signal for when it changes.
+type Latest = (Mutex<Option<Option<SyncStatus>>>, Condvar);
+
+/// Settings of the time sync indicator, from the config's `[layer.time_sync]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeSyncSettings {
+    /// Seconds between asking the system.
+    pub refresh_seconds: u32,
+    /// Only show the indicator while the clock is not synchronized.
+    pub only_warnings: bool,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+}
+
+impl Default for TimeSyncSettings {
+    fn default() -> Self {
+        Self {
+            refresh_seconds: 60,
+            only_warnings: false,
+            size: 12.0,
+        }
+    }
+}
+
+/// What the system's time synchronization service reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SyncStatus {
+    /// Whether the service considers the system clock synchronized.
+    synchronized: bool,
+    /// Seconds that the system clock is ahead of the time it is synchronized to, if reported.
+    offset: Option<f64>,
+}
+
+/// Whether the system clock is synchronized, as a small indicator on the dial below its center,
+/// e.g. `Synced · drift 0.4 ms`, from chrony, systemd-timesyncd or the Windows Time service.
+pub struct TimeSync {
+    readout: Readout,
+    settings: TimeSyncSettings,
+    /// Color of the indicator while it warns.
+    warning_color: Color,
+    scene_to_pixels: Affine2,
+    /// Written by the querying thread.
+    latest: Arc<Latest>,
+    status: Option<SyncStatus>,
+    locale: Locale,
+    line: String,
+    /// Dropped with the layer, which stops the querying thread.
+    _stop: mpsc::Sender<()>,
+}
+
+impl TimeSync {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: TimeSyncSettings = cx.settings("time_sync")?;
+        let latest = Arc::new(Latest::default());
+        let (stop, stopped) = mpsc::channel();
+        {
+            let refresh = Duration::from_secs(settings.refresh_seconds.max(1) as u64);
+            let latest = latest.clone();
+            std::thread::Builder::new()
+                .name("time sync".into())
+                .spawn(move || run(refresh, &latest, &stopped))
+                .context("failed to start querying the time sync status")?;
+        }
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            warning_color: cx.palette.warning(),
+            scene_to_pixels: cx.viewport.scene_to_pixels(),
+            latest,
+            status: None,
+            locale: cx.locale.clone(),
+            line: String::new(),
+            _stop: stop,
+        })
+    }
+
+    fn format_line(&mut self) {
+        self.line = match self.status {
+            Some(status) if status.synchronized && self.settings.only_warnings => String::new(),
+            Some(status) => self.locale.localize_digits(&describe_status(&status)),
+            None => String::new(),
+        };
+    }
+}
+
+impl Layer for TimeSync {
+    fn name(&self) -> &str {
+        "time_sync"
+    }
+
+    /// Only changes when queried, which [`Layer::poll`] picks up.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    fn update(&mut self, _frame: &FrameInfo) {}
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+        self.scene_to_pixels = viewport.scene_to_pixels();
+    }
+
+    fn poll(&mut self) -> bool {
+        let latest = self.latest.0.lock().unwrap().flatten();
+        if latest == self.status {
+            return false;
+        }
+        self.status = latest;
+        self.format_line();
+        true
+    }
+
+    fn finish_loading(&mut self) {
+        let (latest, changed) = &*self.latest;
+        drop(
+            changed
+                .wait_timeout_while(latest.lock().unwrap(), FIRST_QUERY_TIMEOUT, |latest| {
+                    latest.is_none()
+                })
+                .unwrap(),
+        );
+        self.poll();
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if let Some(status) = self.status.filter(|_| !self.line.is_empty()) {
+            let mut style = self.readout.style();
+            style.anchor = [0.5, 0.5];
+            if !status.synchronized {
+                style.color = self.warning_color;
+            }
+            let position = self
+                .scene_to_pixels
+                .transform_point2(Vec2::new(0.0, -DIAL_OFFSET));
+            self.readout.queue(&self.line, position.into(), &style);
+        }
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
+
+/// Queries the sync status to `latest` every `refresh`, until `stop` is disconnected. A banner is
+/// shown when the clock stops being synchronized.
+fn run(refresh: Duration, (latest, changed): &Latest, stop: &mpsc::Receiver<()>) {
+    let mut synchronized = true;
+    let mut failed = false;
+    loop {
+        let status = match query() {
+            Ok(status) => {
+                if synchronized && !status.synchronized {
+                    banner::post(Level::Warning, "The system clock is not synchronized");
+                }
+                synchronized = status.synchronized;
+                failed = false;
+                Some(status)
+            }
+            Err(err) => {
+                // The status stays unknown on systems without a supported service, so only say
+                // so once.
+                if !failed {
+                    log::warn!("{:#}", err);
+                }
+                failed = true;
+                None
+            }
+        };
+        *latest.lock().unwrap() = Some(status);
+        changed.notify_all();
+        match stop.recv_timeout(refresh) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Asks the system's time synchronization service for its status.
+fn query() -> anyhow::Result<SyncStatus> {
+    if cfg!(windows) {
+        return Ok(parse_w32tm(&output("w32tm", &["/query", "/status"])?));
+    }
+    // chrony may be installed alongside systemd-timesyncd without running, which it reports as
+    // an error.
+    match output("chronyc", &["-c", "tracking"]) {
+        Ok(tracking) => parse_chrony(&tracking),
+        Err(chrony_err) => {
+            let synchronized = output("timedatectl", &["show", "-p", "NTPSynchronized", "--value"])
+                .with_context(|| format!("{:#}", chrony_err))?;
+            // Only systemd-timesyncd reports the offset, and not other services that timedatectl
+            // knows to be synchronized.
+            let offset = output("timedatectl", &["timesync-status"])
+                .ok()
+                .and_then(|status| parse_timesyncd_offset(&status));
+            Ok(SyncStatus {
+                synchronized: synchronized.trim() == "yes",
+                offset,
+            })
+        }
+    }
+}
+
+/// The standard output of `program` run with `args`, if it succeeded.
+fn output(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the output of `chronyc -c tracking`, comma-separated fields of which the fifth is the
+/// system clock's offset and the last is the leap status.
+fn parse_chrony(tracking: &str) -> anyhow::Result<SyncStatus> {
+    let fields: Vec<&str> = tracking.trim().split(',').collect();
+    if fields.len() < 14 {
+        bail!("unexpected output from chronyc: {}", tracking.trim());
+    }
+    Ok(SyncStatus {
+        synchronized: fields[fields.len() - 1] != "Not synchronised",
+        offset: fields[4].parse().ok(),
+    })
+}
+
+/// Reads the offset from the output of `timedatectl timesync-status`, e.g. `Offset: -2.113ms`.
+fn parse_timesyncd_offset(status: &str) -> Option<f64> {
+    let value = status
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Offset:"))?
+        .trim();
+    let unit_start = value.find(|c: char| c.is_alphabetic())?;
+    let (number, unit) = value.split_at(unit_start);
+    let number: f64 = number.trim_start_matches('+').parse().ok()?;
+    let scale = match unit {
+        "ns" => 1e-9,
+        "us" | "µs" => 1e-6,
+        "ms" => 1e-3,
+        "s" => 1.0,
+        "min" => 60.0,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+/// Reads the output of `w32tm /query /status`, where a leap indicator of 3 or a source of the
+/// local clock means that it is not synchronized, and newer versions of Windows report the offset
+/// as the phase offset, e.g. `Phase Offset: 0.0001234s`.
+fn parse_w32tm(status: &str) -> SyncStatus {
+    let field = |name: &str| {
+        status.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim())
+        })
+    };
+    let leap = field("Leap Indicator").and_then(|leap| leap.get(..1));
+    let source = field("Source").unwrap_or("");
+    let local =
+        source.is_empty() || source.contains("Local CMOS Clock") || source.contains("Free-running");
+    SyncStatus {
+        synchronized: leap != Some("3") && !local,
+        offset: field("Phase Offset").and_then(|offset| offset.trim_end_matches('s').parse().ok()),
+    }
+}
+
+/// E.g. `Synced · drift 0.4 ms`, or `Not synced`.
+fn describe_status(status: &SyncStatus) -> String {
+    if !status.synchronized {
+        return "Not synced".to_owned();
+    }
+    let Some(offset) = status.offset else {
+        return "Synced".to_owned();
+    };
+    let drift = match offset.abs() {
+        seconds if seconds < 0.01 => format!("{:.1} ms", seconds * 1e3),
+        seconds if seconds < 1.0 => format!("{:.0} ms", seconds * 1e3),
+        seconds => format!("{:.2} s", seconds),
+    };
+    format!("Synced · drift {}", drift)
+}
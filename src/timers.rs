@@ -0,0 +1,488 @@
+use crate::layer::{Control, FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::notification;
+use crate::registry::LayerContext;
+use crate::ring::Ring;
+use crate::staging::StagingPool;
+use crate::text::Corner;
+use crate::text_overlay::Readout;
+use crate::theme::Color;
+use crate::viewport::Viewport;
+use anyhow::bail;
+use instant::Instant;
+use serde::Deserialize;
+use std::any::Any;
+use std::time::Duration;
+
+/// The stopwatch and the countdown, in the order of their lines while one has the keyboard focus.
+const CONTROLS: [Control; 2] = [
+    Control {
+        activate: "stopwatch.toggle",
+        reset: Some("stopwatch.reset"),
+    },
+    Control {
+        activate: "countdown.toggle",
+        reset: Some("countdown.reset"),
+    },
+];
+
+/// Settings of the stopwatch and countdown, from the config's `[layer.timers]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimersSettings {
+    /// Corner of the target that the readout is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+    /// Length of the countdown until another is set.
+    pub countdown_minutes: u32,
+    /// Show how much of the countdown is left as an arc around the clock dial.
+    pub ring: bool,
+}
+
+impl Default for TimersSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::BottomRight,
+            size: 24.0,
+            countdown_minutes: 5,
+            ring: true,
+        }
+    }
+}
+
+/// Real time that has passed while running, which can be paused.
+#[derive(Debug, Clone, Copy, Default)]
+struct Watch {
+    /// Time passed before the last start.
+    elapsed: Duration,
+    /// When it was last started, if it is running.
+    started: Option<Instant>,
+}
+
+impl Watch {
+    fn elapsed(&self, now: Instant) -> Duration {
+        match self.started {
+            Some(started) => self.elapsed + now.saturating_duration_since(started),
+            None => self.elapsed,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    fn is_reset(&self) -> bool {
+        self.started.is_none() && self.elapsed.is_zero()
+    }
+
+    fn toggle(&mut self, now: Instant) {
+        match self.started {
+            Some(_) => {
+                self.elapsed = self.elapsed(now);
+                self.started = None;
+            }
+            None => self.started = Some(now),
+        }
+    }
+}
+
+/// What the timers are doing, which is carried over when the layer is created again.
+#[derive(Debug, Clone, Copy)]
+struct TimerState {
+    stopwatch: Watch,
+    countdown: Watch,
+    countdown_length: Duration,
+    /// Whether the countdown ran out, and has not been reset since.
+    finished: bool,
+}
+
+impl TimerState {
+    fn countdown_left(&self, now: Instant) -> Duration {
+        self.countdown_length
+            .saturating_sub(self.countdown.elapsed(now))
+    }
+
+    /// Stops the countdown if it ran out, returning whether it did just now.
+    fn finish(&mut self, now: Instant) -> bool {
+        if !self.countdown.is_running() || !self.countdown_left(now).is_zero() {
+            return false;
+        }
+        self.countdown.toggle(now);
+        self.finished = true;
+        true
+    }
+
+    /// Carries out one of the actions in [`Timers`], returning whether it is one.
+    fn handle_action(&mut self, action: &str, now: Instant) -> bool {
+        match action {
+            "stopwatch.toggle" => self.stopwatch.toggle(now),
+            "stopwatch.reset" => self.stopwatch = Watch::default(),
+            "countdown.toggle" => {
+                if self.finished {
+                    // Start over.
+                    self.countdown = Watch::default();
+                    self.finished = false;
+                }
+                self.countdown.toggle(now)
+            }
+            "countdown.reset" => {
+                self.countdown = Watch::default();
+                self.finished = false;
+            }
+            _ => {
+                let Some(length) = action
+                    .strip_prefix("countdown.set:")
+                    .and_then(parse_duration)
+                else {
+                    return false;
+                };
+                self.countdown = Watch::default();
+                self.countdown_length = length;
+                self.finished = false;
+            }
+        }
+        true
+    }
+}
+
+/// A stopwatch and a countdown timer, shown as digits in a corner of the target while they are in
+/// use, with how much of the countdown is left as an arc around the clock dial.
+///
+/// They are controlled with actions: `stopwatch.toggle` and `countdown.toggle` start or pause
+/// them, `stopwatch.reset` and `countdown.reset` stop them and set them back, and
+/// `countdown.set:<length>` resets the countdown to a new length, in seconds or as `m:ss` or
+/// `h:mm:ss`. A notification is shown when the countdown runs out. Both follow real time, even
+/// when the displayed time is simulated.
+pub struct Timers {
+    readout: Readout,
+    ring: Ring,
+    settings: TimersSettings,
+    state: TimerState,
+    color: Color,
+    locale: Locale,
+    lines: String,
+    /// Index in [`CONTROLS`] of the timer with the keyboard focus.
+    focus: Option<usize>,
+}
+
+impl Timers {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: TimersSettings = cx.settings("timers")?;
+        if settings.countdown_minutes == 0 {
+            bail!("timers.countdown_minutes must be more than 0");
+        }
+        Ok(Self {
+            readout: Readout::new(cx, settings.size)?,
+            ring: Ring::new(cx.gfx, cx.viewport, cx.assets)?,
+            state: TimerState {
+                stopwatch: Watch::default(),
+                countdown: Watch::default(),
+                countdown_length: Duration::from_secs(u64::from(settings.countdown_minutes) * 60),
+                finished: false,
+            },
+            settings,
+            color: cx.theme.text,
+            locale: cx.locale.clone(),
+            lines: String::new(),
+            focus: None,
+        })
+    }
+
+    /// Updates the readout and the ring to `now`.
+    fn refresh(&mut self, now: Instant) {
+        let timers = &self.state;
+        let mut lines = Vec::new();
+        // Both are shown while either has the focus, so that it can be moved between them.
+        let focused = self.focus.is_some();
+        if !timers.stopwatch.is_reset() || focused {
+            let elapsed = timers.stopwatch.elapsed(now);
+            lines.push(format!(
+                "Stopwatch {}.{}",
+                format_duration(elapsed),
+                elapsed.subsec_millis() / 100
+            ));
+        }
+        let left = timers.countdown_left(now);
+        if timers.finished {
+            lines.push("Countdown done".to_owned());
+        } else if !timers.countdown.is_reset() || focused {
+            // Rounded up, so that it reads 0:00 only once it has run out.
+            let shown = Duration::from_secs(left.as_secs() + u64::from(left.subsec_nanos() > 0));
+            lines.push(format!("Countdown {}", format_duration(shown)));
+        }
+        self.lines = self.locale.localize_digits(&lines.join("\n"));
+
+        if !self.settings.ring || timers.finished || timers.countdown.is_reset() {
+            self.ring.hide();
+        } else {
+            let opacity = if timers.countdown.is_running() {
+                1.0
+            } else {
+                0.5
+            };
+            let fraction = left.as_secs_f32() / timers.countdown_length.as_secs_f32();
+            self.ring.show(self.color, opacity, fraction);
+        }
+    }
+}
+
+impl Layer for Timers {
+    fn name(&self) -> &str {
+        "timers"
+    }
+
+    fn shaders(&self) -> &[&str] {
+        &["shaders/ring.wgsl"]
+    }
+
+    fn reload_shaders(&mut self) -> anyhow::Result<()> {
+        self.ring.reload_shader()
+    }
+
+    /// Tenths of a second while either timer runs.
+    fn tick_interval(&self) -> Duration {
+        if self.state.stopwatch.is_running() || self.state.countdown.is_running() {
+            Duration::from_millis(100)
+        } else {
+            Duration::from_secs(1)
+        }
+    }
+
+    fn update(&mut self, _frame: &FrameInfo) {
+        let now = Instant::now();
+        if self.state.finish(now) {
+            notification::notify(
+                "Countdown finished",
+                &format_duration(self.state.countdown_length),
+            );
+        }
+        self.refresh(now);
+    }
+
+    fn handle_action(&mut self, action: &str) -> bool {
+        let now = Instant::now();
+        if !self.state.handle_action(action, now) {
+            return false;
+        }
+        self.refresh(now);
+        true
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        CONTROLS.to_vec()
+    }
+
+    fn set_focus(&mut self, control: Option<usize>) {
+        self.focus = control;
+        self.refresh(Instant::now());
+    }
+
+    fn describe(&self) -> Option<String> {
+        let timers = &self.state;
+        if timers.finished {
+            return Some("Countdown done".to_owned());
+        }
+        if timers.countdown.is_reset() {
+            return None;
+        }
+        let minutes = timers.countdown_left(Instant::now()).as_secs() / 60 + 1;
+        let paused = if timers.countdown.is_running() {
+            ""
+        } else {
+            ", paused"
+        };
+        Some(format!("Countdown: under {} min left{}", minutes, paused))
+    }
+
+    fn take_state(&mut self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.state))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(timers) = state.downcast::<TimerState>() {
+            self.state = *timers;
+            self.refresh(Instant::now());
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        self.ring.prepare(encoder, staging);
+        if !self.lines.is_empty() {
+            let mut style = self.readout.style();
+            let position = self
+                .readout
+                .queue_in_corner(&self.lines, self.settings.corner, &style);
+            if let Some(row) = self.focus {
+                style.anchor = self.readout.corner(self.settings.corner).anchor();
+                self.readout.queue_focus(&self.lines, row, position, &style);
+            }
+        }
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory() + self.ring.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, viewport: &'a Viewport) {
+        self.ring.draw(render_pass, viewport);
+        self.readout.draw(render_pass);
+    }
+}
+
+/// Whole seconds as `m:ss`, or `h:mm:ss` from an hour.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Parses a length of time in seconds, `m:ss` or `h:mm:ss`. It must not be zero.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut seconds = 0u64;
+    for (index, part) in text.split(':').enumerate() {
+        if index > 2 {
+            return None;
+        }
+        seconds = seconds
+            .checked_mul(60)?
+            .checked_add(part.trim().parse().ok()?)?;
+    }
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::minutes;
+
+    fn state() -> TimerState {
+        TimerState {
+            stopwatch: Watch::default(),
+            countdown: Watch::default(),
+            countdown_length: minutes(5),
+            finished: false,
+        }
+    }
+
+    #[test]
+    fn lengths_are_seconds_or_minutes_and_hours() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5:00"), Some(minutes(5)));
+        assert_eq!(parse_duration("1:30:15"), Some(Duration::from_secs(5415)));
+        assert_eq!(parse_duration(" 2 : 05 "), Some(Duration::from_secs(125)));
+    }
+
+    #[test]
+    fn malformed_lengths_are_refused() {
+        for text in [
+            "",
+            "0",
+            "0:00",
+            "-5",
+            "5m",
+            "1.5",
+            "5:",
+            ":30",
+            "1:2:3:4",
+            "99999999999999999999",
+        ] {
+            assert_eq!(parse_duration(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn countdown_set_resets_to_the_new_length() {
+        let start = Instant::now();
+        let mut timers = state();
+        assert!(timers.handle_action("countdown.toggle", start));
+        assert!(timers.handle_action("countdown.set:1:30", start + minutes(1)));
+        assert_eq!(timers.countdown_length, Duration::from_secs(90));
+        assert!(timers.countdown.is_reset());
+    }
+
+    #[test]
+    fn refused_lengths_leave_the_countdown_running() {
+        let start = Instant::now();
+        let mut timers = state();
+        timers.handle_action("countdown.toggle", start);
+        for action in ["countdown.set:", "countdown.set:5:xx", "countdown.set 5:00"] {
+            assert!(!timers.handle_action(action, start), "{:?}", action);
+        }
+        assert_eq!(timers.countdown_length, minutes(5));
+        assert!(timers.countdown.is_running());
+    }
+
+    #[test]
+    fn stopwatch_and_countdown_run_independently() {
+        let start = Instant::now();
+        let mut timers = state();
+        timers.handle_action("stopwatch.toggle", start);
+        timers.handle_action("countdown.toggle", start + minutes(1));
+        timers.handle_action("stopwatch.reset", start + minutes(2));
+        assert!(timers.stopwatch.is_reset());
+        assert_eq!(timers.countdown_left(start + minutes(2)), minutes(4));
+        assert!(timers.finish(start + minutes(6)));
+        assert!(timers.stopwatch.is_reset());
+    }
+
+    #[test]
+    fn stopwatch_pauses_and_resumes() {
+        let start = Instant::now();
+        let mut timers = state();
+        timers.handle_action("stopwatch.toggle", start);
+        timers.handle_action("stopwatch.toggle", start + minutes(2));
+        assert_eq!(timers.stopwatch.elapsed(start + minutes(10)), minutes(2));
+        timers.handle_action("stopwatch.toggle", start + minutes(10));
+        assert_eq!(timers.stopwatch.elapsed(start + minutes(13)), minutes(5));
+        timers.handle_action("stopwatch.reset", start + minutes(13));
+        assert!(timers.stopwatch.is_reset());
+    }
+
+    #[test]
+    fn countdown_finishes_once_and_then_starts_over() {
+        let start = Instant::now();
+        let mut timers = state();
+        timers.handle_action("countdown.toggle", start);
+        assert!(!timers.finish(start + minutes(4)));
+        assert!(timers.finish(start + minutes(5)));
+        assert!(!timers.finish(start + minutes(6)));
+        assert!(timers.finished);
+        assert!(timers.countdown_left(start + minutes(6)).is_zero());
+
+        timers.handle_action("countdown.toggle", start + minutes(6));
+        assert!(!timers.finished);
+        assert_eq!(timers.countdown_left(start + minutes(7)), minutes(4));
+    }
+
+    #[test]
+    fn countdown_does_not_finish_while_paused() {
+        let start = Instant::now();
+        let mut timers = state();
+        timers.handle_action("countdown.toggle", start);
+        timers.handle_action("countdown.toggle", start + minutes(3));
+        // An hour on, it still has the 2 minutes it was paused with.
+        assert!(!timers.finish(start + minutes(60)));
+        assert_eq!(timers.countdown_left(start + minutes(60)), minutes(2));
+        timers.handle_action("countdown.toggle", start + minutes(60));
+        assert!(!timers.finish(start + minutes(61)));
+        assert!(timers.finish(start + minutes(62)));
+    }
+}
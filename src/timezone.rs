@@ -0,0 +1,42 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use global_clock::zoneinfo::Zone;
+
+#[derive(Debug, Clone)]
+pub enum Timezone {
+    Local,
+    Named(Zone),
+}
+
+impl Timezone {
+    pub fn from_config(name: Option<&str>) -> anyhow::Result<Self> {
+        match name {
+            None => Ok(Self::Local),
+            Some(name) => Zone::load(name)
+                .map(Self::Named)
+                .ok_or_else(|| anyhow!("unknown timezone {:?}", name)),
+        }
+    }
+
+    /// The IANA name of the timezone, or `None` for the system's.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Local => None,
+            Self::Named(zone) => Some(zone.name()),
+        }
+    }
+
+    pub fn time_of(&self, date: &DateTime<Utc>) -> NaiveTime {
+        match self {
+            Self::Local => date.with_timezone(&Local).time(),
+            Self::Named(zone) => zone.local(date).time(),
+        }
+    }
+
+    pub fn date_of(&self, date: &DateTime<Utc>) -> NaiveDate {
+        match self {
+            Self::Local => date.with_timezone(&Local).date_naive(),
+            Self::Named(zone) => zone.local(date).date_naive(),
+        }
+    }
+}
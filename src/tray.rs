@@ -0,0 +1,235 @@
+use crate::control::{Command, Controller, Response};
+use anyhow::Context;
+#[cfg(target_os = "macos")]
+use chrono::{DateTime, Utc};
+#[cfg(target_os = "macos")]
+use global_clock::zoneinfo::Zone;
+use std::sync::Arc;
+use tiny_skia::{Color, FillRule, LineCap, Paint, PathBuilder, Pixmap, Stroke, Transform};
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+#[cfg(target_os = "macos")]
+use tray_icon::{MouseButton, MouseButtonState, TrayIconEvent};
+
+const ICON_SIZE: u32 = 32;
+
+/// The tray icon, which is removed when this is dropped.
+pub struct Tray {
+    // On Linux, the icon belongs to the GTK thread instead.
+    #[cfg(not(target_os = "linux"))]
+    icon: TrayIcon,
+    /// Timezone of the time shown in the menu bar, or UTC if `None`.
+    #[cfg(target_os = "macos")]
+    timezone: Option<Zone>,
+    #[cfg(target_os = "macos")]
+    title: String,
+}
+
+/// Adds an icon to the system tray, whose menu sends commands to `controller`.
+///
+/// On macOS, the icon is a menu bar extra, which also shows the time in `timezone` (or UTC) and
+/// brings up the main window when clicked.
+pub fn start(
+    controller: Controller,
+    themes: Vec<String>,
+    timezone: Option<&str>,
+) -> anyhow::Result<Tray> {
+    // Only the macOS menu bar has room for the time.
+    #[cfg(not(target_os = "macos"))]
+    let _ = timezone;
+    let controller = Arc::new(controller);
+    #[cfg(target_os = "macos")]
+    {
+        let controller = controller.clone();
+        TrayIconEvent::set_event_handler(Some(move |event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                controller.post(Command::Show);
+            }
+        }));
+    }
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        handle(&controller, event.id().as_ref())
+    }));
+
+    // GTK needs an event loop of its own, on the thread that creates the icon.
+    #[cfg(target_os = "linux")]
+    {
+        let (result_sender, result) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("tray".into())
+            .spawn(move || {
+                if let Err(err) = gtk::init() {
+                    let _ = result_sender.send(Err(anyhow::Error::new(err)));
+                    return;
+                }
+                match create_icon(&themes) {
+                    Ok(_icon) => {
+                        let _ = result_sender.send(Ok(()));
+                        gtk::main();
+                    }
+                    Err(err) => {
+                        let _ = result_sender.send(Err(err));
+                    }
+                }
+            })
+            .context("failed to start the tray thread")?;
+        result
+            .recv()
+            .context("the tray thread stopped unexpectedly")??;
+        Ok(Tray {})
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let timezone = timezone
+            .map(|name| {
+                Zone::load(name).ok_or_else(|| anyhow::anyhow!("unknown timezone {:?}", name))
+            })
+            .transpose()?;
+        let icon = create_icon(&themes)?;
+        // Clicking shows the window; the menu is on the right button.
+        icon.set_show_menu_on_left_click(false);
+        Ok(Tray {
+            icon,
+            timezone,
+            title: String::new(),
+        })
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    Ok(Tray {
+        icon: create_icon(&themes)?,
+    })
+}
+
+impl Tray {
+    /// Updates the time shown in the menu bar.
+    #[cfg(target_os = "macos")]
+    pub fn show_time(&mut self, time: &DateTime<Utc>) {
+        let title = match &self.timezone {
+            Some(timezone) => format!(
+                "{} {}",
+                timezone.local(time).format("%H:%M"),
+                timezone.abbreviation_at(time)
+            ),
+            None => time.format("%H:%M UTC").to_string(),
+        };
+        if title != self.title {
+            self.icon.set_title(Some(&title));
+            self.title = title;
+        }
+    }
+}
+
+fn create_icon(themes: &[String]) -> anyhow::Result<TrayIcon> {
+    let theme_menu = Submenu::new("Theme", true);
+    for theme in themes {
+        theme_menu.append(&MenuItem::with_id(
+            format!("theme:{}", theme),
+            theme,
+            true,
+            None,
+        ))?;
+    }
+    let menu = Menu::new();
+    menu.append_items(&[
+        &MenuItem::with_id("toggle_visible", "Show/Hide", true, None),
+        &MenuItem::with_id("copy_utc", "Copy UTC time", true, None),
+        &theme_menu,
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id("quit", "Quit", true, None),
+    ])?;
+    TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("Global Clock")
+        .with_icon(icon()?)
+        .build()
+        .context("failed to create the tray icon")
+}
+
+/// Carries out a menu item. This runs on the thread of a platform event loop, which may be the
+/// one that answers commands, so it must not wait for them.
+fn handle(controller: &Arc<Controller>, id: &str) {
+    let command = match id {
+        "toggle_visible" => Command::ToggleVisible,
+        "quit" => Command::Quit,
+        "copy_utc" => {
+            let controller = controller.clone();
+            std::thread::spawn(move || {
+                if let Err(err) = copy_time(&controller) {
+                    log::warn!("failed to copy the time: {:#}", err);
+                }
+            });
+            return;
+        }
+        id => match id.strip_prefix("theme:") {
+            Some(theme) => Command::SetTheme(theme.to_owned()),
+            None => return,
+        },
+    };
+    controller.post(command);
+}
+
+fn copy_time(controller: &Controller) -> anyhow::Result<()> {
+    let time = match controller.send_blocking(Command::CurrentTime) {
+        Ok(Response::Time(time)) => time,
+        Ok(response) => anyhow::bail!("unexpected response {:?}", response),
+        Err(err) => anyhow::bail!("{}", err),
+    };
+    let text = time.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let mut clipboard = arboard::Clipboard::new().context("failed to open the clipboard")?;
+    // On Linux, the text is only available while the clipboard is open, so keep it open until
+    // something else is copied.
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::SetExtLinux;
+        clipboard.set().wait().text(text)?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    clipboard.set_text(text)?;
+    Ok(())
+}
+
+/// A small clock face, drawn instead of shipping an image.
+fn icon() -> anyhow::Result<Icon> {
+    let mut pixmap = Pixmap::new(ICON_SIZE, ICON_SIZE).unwrap();
+    let transform = Transform::from_scale(ICON_SIZE as f32 / 2.0, ICON_SIZE as f32 / 2.0)
+        .pre_translate(1.0, 1.0);
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Default::default()
+    };
+
+    paint.set_color(Color::from_rgba8(30, 60, 120, 255));
+    let dial = PathBuilder::from_circle(0.0, 0.0, 0.95).unwrap();
+    pixmap.fill_path(&dial, &paint, FillRule::Winding, transform, None);
+
+    paint.set_color(Color::WHITE);
+    let hands = {
+        let mut pb = PathBuilder::new();
+        pb.move_to(0.0, 0.0);
+        pb.line_to(0.0, -0.6);
+        pb.move_to(0.0, 0.0);
+        pb.line_to(0.4, 0.25);
+        pb.finish().unwrap()
+    };
+    let stroke = Stroke {
+        width: 0.15,
+        line_cap: LineCap::Round,
+        ..Default::default()
+    };
+    pixmap.stroke_path(&hands, &paint, &stroke, transform, None);
+
+    let rgba = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).context("invalid tray icon")
+}
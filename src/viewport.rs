@@ -1,17 +1,33 @@
+use crate::camera::Camera;
 use crate::GraphicsContext;
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec2, Vec4};
+use glam::{Affine2, Mat4, Vec2, Vec4};
 use wgpu::util::DeviceExt;
 
+/// The projection shared by the widgets, mapping the unit square to the center of the target.
 pub struct Viewport {
     gfx: GraphicsContext,
     uniform_buffer: wgpu::Buffer,
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
+    camera: Camera,
+    size: Vec2,
+    region: Option<Region>,
+}
+
+/// The part of a larger scene that a render target shows, e.g. one monitor of a wall of displays.
+/// The scene is centered in the whole area, and fits its shorter side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    /// Position of the target's top left corner in the whole area, in pixels.
+    pub offset: Vec2,
+    /// Size of the whole area, in pixels.
+    pub total: Vec2,
 }
 
 impl Viewport {
-    pub fn new(gfx: &GraphicsContext) -> Self {
+    /// Creates a viewport for a render target of `width` by `height` pixels.
+    pub fn new(gfx: &GraphicsContext, width: u32, height: u32, camera: Camera) -> Self {
         let uniform_buffer = gfx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -44,24 +60,77 @@ impl Viewport {
             }],
         });
 
-        Self {
+        let viewport = Self {
             gfx: gfx.clone(),
             uniform_buffer,
             bind_group_layout,
             bind_group,
+            camera,
+            size: Vec2::new(width as _, height as _),
+            region: None,
+        };
+        viewport.write_uniforms();
+        viewport
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.size = Vec2::new(width as _, height as _);
+        self.write_uniforms();
+    }
+
+    /// Size of the target, in pixels.
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.write_uniforms();
+    }
+
+    pub fn region(&self) -> Option<Region> {
+        self.region
+    }
+
+    /// Shows only `region` of the scene, or all of it if `None`.
+    pub fn set_region(&mut self, region: Option<Region>) {
+        self.region = region;
+        self.write_uniforms();
+    }
+
+    /// How many pixels the side of the scene's -1..1 square spans, before the camera's zoom.
+    pub fn scene_size(&self) -> f32 {
+        self.region
+            .map_or(self.size, |region| region.total)
+            .min_element()
+    }
+
+    /// Where the center of the scene is, in pixels from the top left corner of the target.
+    pub fn center(&self) -> Vec2 {
+        match self.region {
+            Some(region) => region.total / 2.0 - region.offset,
+            None => self.size / 2.0,
         }
     }
 
-    pub fn window_resized(&self) {
-        let window_size = self.gfx.window.inner_size();
+    /// Maps the scene's coordinates to pixels from the top left corner of the target, including
+    /// the camera, e.g. to place text over something in the scene.
+    pub fn scene_to_pixels(&self) -> Affine2 {
+        let half = self.scene_size() / 2.0;
+        Affine2::from_translation(self.center())
+            * Affine2::from_scale(Vec2::new(half, -half) * self.camera.zoom)
+            * Affine2::from_angle(self.camera.rotation)
+    }
 
+    fn write_uniforms(&self) {
         self.gfx.queue.write_buffer(
             &self.uniform_buffer,
             0,
-            bytemuck::bytes_of(&Uniforms::new(Vec2::new(
-                window_size.width as _,
-                window_size.height as _,
-            ))),
+            bytemuck::bytes_of(&Uniforms::new(self.size, self.region, &self.camera)),
         );
     }
 
@@ -87,14 +156,29 @@ impl Uniforms {
         }
     }
 
-    fn new(size: Vec2) -> Self {
-        // Preserve the -1..1 XY square, correcting for the aspect ratio of the window.
-        let proj = Mat4::from_cols(
-            size.min_element() / size.x * Vec4::X,
-            size.min_element() / size.y * Vec4::Y,
+    fn new(size: Vec2, region: Option<Region>, camera: &Camera) -> Self {
+        let total = region.map_or(size, |region| region.total);
+        // Preserve the -1..1 XY square, correcting for the aspect ratio of the whole area.
+        let mut proj = Mat4::from_cols(
+            total.min_element() / total.x * Vec4::X,
+            total.min_element() / total.y * Vec4::Y,
             Vec4::Z,
             Vec4::W,
-        );
+        ) * camera.view();
+        if let Some(Region { offset, total }) = region {
+            // Then crop the whole area to this target, whose Y axis points down in pixels.
+            let scale = total / size;
+            let translation = Vec2::new(
+                (total.x - 2.0 * offset.x - size.x) / size.x,
+                (size.y - total.y + 2.0 * offset.y) / size.y,
+            );
+            proj = Mat4::from_cols(
+                scale.x * Vec4::X,
+                scale.y * Vec4::Y,
+                Vec4::Z,
+                translation.extend(0.0).extend(1.0),
+            ) * proj;
+        }
         Self {
             proj: proj.to_cols_array_2d(),
         }
@@ -0,0 +1,391 @@
+use crate::banner::{self, Level};
+use crate::layer::{FrameInfo, Layer};
+use crate::locale::Locale;
+use crate::location::Location;
+use crate::registry::LayerContext;
+use crate::staging::StagingPool;
+use crate::text::Corner;
+use crate::text_overlay::Readout;
+use crate::viewport::Viewport;
+use anyhow::Context;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait before trying again after failing to fetch the weather, e.g. while offline.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The last reading, in the cache directory, so that it is shown right away on the next start.
+const CACHE_FILE: &str = "weather.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+/// Settings of the weather readout, from the config's `[layer.weather]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeatherSettings {
+    /// Corner of the target that the readout is placed in.
+    pub corner: Corner,
+    /// Height of the text, in logical pixels.
+    pub size: f32,
+    pub unit: TemperatureUnit,
+    /// Minutes between fetching the weather.
+    pub refresh_minutes: u32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            corner: Corner::BottomRight,
+            size: 18.0,
+            unit: TemperatureUnit::Celsius,
+            refresh_minutes: 30,
+        }
+    }
+}
+
+/// The current weather at a location, as fetched and cached.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Reading {
+    latitude: f64,
+    longitude: f64,
+    fetched_at: DateTime<Utc>,
+    /// In degrees Celsius.
+    temperature: f64,
+    /// WMO weather interpretation code.
+    weather_code: u8,
+}
+
+/// Fetches the weather for the layers of every window, on one background thread.
+struct Fetcher {
+    home: Location,
+    refresh: Duration,
+    /// Written by the fetching thread.
+    latest: Arc<Mutex<Option<Reading>>>,
+    /// Dropped with the last layer holding the fetcher, which stops the thread.
+    _stop: mpsc::Sender<()>,
+}
+
+impl Fetcher {
+    /// Starts fetching the weather at `home`, starting from the reading cached in `cache_path`.
+    fn start(
+        home: &Location,
+        refresh: Duration,
+        cache_path: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let cached = cache_path
+            .as_deref()
+            .and_then(|path| read_cache(path, home));
+        let latest = Arc::new(Mutex::new(cached.clone()));
+        let (stop, stopped) = mpsc::channel();
+        {
+            let home = home.clone();
+            let latest = latest.clone();
+            std::thread::Builder::new()
+                .name("weather".into())
+                .spawn(move || {
+                    run(
+                        &home,
+                        cached,
+                        cache_path.as_deref(),
+                        refresh,
+                        &latest,
+                        &stopped,
+                    )
+                })
+                .context("failed to start fetching the weather")?;
+        }
+        Ok(Self {
+            home: home.clone(),
+            refresh,
+            latest,
+            _stop: stop,
+        })
+    }
+}
+
+/// The temperature and conditions at the home location, e.g. `21°C Partly cloudy`, in a corner
+/// of the target.
+///
+/// The weather is fetched from [Open-Meteo](https://open-meteo.com) on a background thread, which
+/// needs no API key and is shared by the layers of every window. The last reading is cached on
+/// disk; while the weather cannot be fetched, it is shown with its age, and nothing is shown if
+/// there is none.
+pub struct Weather {
+    readout: Readout,
+    settings: WeatherSettings,
+    home: Option<Location>,
+    /// `None` without a home location.
+    fetcher: Option<Arc<Fetcher>>,
+    reading: Option<Reading>,
+    locale: Locale,
+    line: String,
+    /// When the line is formatted again as the reading ages, if it is shown.
+    reformat_at: Option<DateTime<Utc>>,
+}
+
+impl Weather {
+    pub fn new(cx: &LayerContext) -> anyhow::Result<Self> {
+        let settings: WeatherSettings = cx.settings("weather")?;
+        let fetcher = match cx.home {
+            Some(home) => {
+                let refresh = Duration::from_secs(settings.refresh_minutes.max(1) as u64 * 60);
+                let cache_path = cx.assets.cache_dir().map(|dir| dir.join(CACHE_FILE));
+                // The home location moves with a GPS receiver, which the layers are then
+                // recreated for.
+                Some(cx.shared.get_or_try_insert_with(
+                    |fetcher: &Fetcher| fetcher.home == *home && fetcher.refresh == refresh,
+                    || Fetcher::start(home, refresh, cache_path),
+                )?)
+            }
+            None => {
+                log::warn!("the weather layer needs a home location; set `home` in the config");
+                None
+            }
+        };
+        // The latest reading, e.g. the cached one, is shown right away.
+        let reading = fetcher
+            .as_ref()
+            .and_then(|fetcher| fetcher.latest.lock().unwrap().clone());
+        let mut weather = Self {
+            readout: Readout::new(cx, settings.size)?,
+            settings,
+            home: cx.home.cloned(),
+            fetcher,
+            reading,
+            locale: cx.locale.clone(),
+            line: String::new(),
+            reformat_at: None,
+        };
+        weather.format_line();
+        Ok(weather)
+    }
+
+    fn format_line(&mut self) {
+        let Some(reading) = &self.reading else {
+            self.line.clear();
+            self.reformat_at = None;
+            return;
+        };
+        let temperature = match self.settings.unit {
+            TemperatureUnit::Celsius => format!("{:.0}°C", reading.temperature),
+            TemperatureUnit::Fahrenheit => format!("{:.0}°F", reading.temperature * 1.8 + 32.0),
+        };
+        let mut parts = Vec::new();
+        if let Some(name) = self.home.as_ref().and_then(|home| home.name.as_ref()) {
+            parts.push(name.clone());
+        }
+        parts.push(self.locale.localize_digits(&temperature));
+        parts.push(describe(reading.weather_code).to_owned());
+        // Readings that missed a couple of refreshes, e.g. while offline, are marked as old. The
+        // age is real, whatever time the clock shows.
+        let now = Utc::now();
+        let age = (now - reading.fetched_at).num_minutes();
+        let old_after = 2 * self.settings.refresh_minutes as i64;
+        if age > old_after {
+            parts.push(format!("· {}", self.locale.format_age(age)));
+            self.reformat_at = Some(now + TimeDelta::minutes(1));
+        } else {
+            self.reformat_at = Some(reading.fetched_at + TimeDelta::minutes(old_after + 1));
+        }
+        self.line = parts.join(" ");
+    }
+}
+
+impl Layer for Weather {
+    fn name(&self) -> &str {
+        "weather"
+    }
+
+    /// Only changes when fetched or as an old reading ages, which [`Layer::poll`] picks up.
+    fn tick_interval(&self) -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    fn update(&mut self, _frame: &FrameInfo) {}
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.readout.set_scale_factor(scale_factor);
+    }
+
+    fn set_text_scale(&mut self, scale: f32) {
+        self.readout.set_text_scale(scale);
+    }
+
+    fn resized(&mut self, viewport: &Viewport) {
+        self.readout.resized(viewport);
+    }
+
+    fn poll(&mut self) -> bool {
+        let fetched_at = |reading: &Option<Reading>| reading.as_ref().map(|r| r.fetched_at);
+        let mut fetched = false;
+        if let Some(fetcher) = &self.fetcher {
+            let latest = fetcher.latest.lock().unwrap();
+            if fetched_at(&latest) != fetched_at(&self.reading) {
+                self.reading = latest.clone();
+                fetched = true;
+            }
+        }
+        if !fetched && self.reformat_at.is_none_or(|at| Utc::now() < at) {
+            return false;
+        }
+        let line = std::mem::take(&mut self.line);
+        self.format_line();
+        self.line != line
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, staging: &mut StagingPool) {
+        if !self.line.is_empty() {
+            let style = self.readout.style();
+            self.readout
+                .queue_in_corner(&self.line, self.settings.corner, &style);
+        }
+        self.readout.prepare(encoder, staging);
+    }
+
+    fn gpu_memory(&self) -> u64 {
+        self.readout.gpu_memory()
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, _viewport: &'a Viewport) {
+        self.readout.draw(render_pass);
+    }
+}
+
+/// Fetches the weather at `home` to `latest` whenever `reading` is more than `refresh` old,
+/// caching it in `cache_path`, until `stop` is disconnected. The first of a run of failures is
+/// shown in a banner.
+fn run(
+    home: &Location,
+    mut reading: Option<Reading>,
+    cache_path: Option<&Path>,
+    refresh: Duration,
+    latest: &Mutex<Option<Reading>>,
+    stop: &mpsc::Receiver<()>,
+) {
+    let mut failing = false;
+    loop {
+        let age = reading.as_ref().map(|reading| {
+            (Utc::now() - reading.fetched_at)
+                .to_std()
+                .unwrap_or_default()
+        });
+        let wait = match age {
+            Some(age) if age < refresh => refresh - age,
+            _ => match fetch(home) {
+                Ok(fetched) => {
+                    if let Some(path) = cache_path {
+                        if let Err(err) = write_cache(path, &fetched) {
+                            log::warn!("{:#}", err);
+                        }
+                    }
+                    reading = Some(fetched);
+                    failing = false;
+                    refresh
+                }
+                Err(err) => {
+                    log::warn!("{:#}", err);
+                    if !failing {
+                        banner::post(Level::Error, "Failed to fetch the weather");
+                        failing = true;
+                    }
+                    RETRY_INTERVAL
+                }
+            },
+        };
+        if let Some(reading) = &reading {
+            *latest.lock().unwrap() = Some(reading.clone());
+        }
+        match stop.recv_timeout(wait) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+fn fetch(home: &Location) -> anyhow::Result<Reading> {
+    #[derive(Deserialize)]
+    struct Response {
+        current: Current,
+    }
+    #[derive(Deserialize)]
+    struct Current {
+        temperature_2m: f64,
+        weather_code: u8,
+    }
+
+    let url = format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}\
+         &current=temperature_2m,weather_code",
+        home.latitude, home.longitude
+    );
+    let response = ureq::get(&url)
+        .timeout(Duration::from_secs(30))
+        .call()
+        .context("failed to fetch the weather")?;
+    let response: Response = serde_json::from_reader(response.into_reader())
+        .context("failed to read the weather from Open-Meteo")?;
+    log::debug!(
+        "fetched the weather: {}°C, code {}",
+        response.current.temperature_2m,
+        response.current.weather_code
+    );
+    Ok(Reading {
+        latitude: home.latitude,
+        longitude: home.longitude,
+        fetched_at: Utc::now(),
+        temperature: response.current.temperature_2m,
+        weather_code: response.current.weather_code,
+    })
+}
+
+fn read_cache(path: &Path, home: &Location) -> Option<Reading> {
+    let reading: Reading = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    // The home location may have changed since.
+    (reading.latitude == home.latitude && reading.longitude == home.longitude).then_some(reading)
+}
+
+fn write_cache(path: &Path, reading: &Reading) -> anyhow::Result<()> {
+    let write = || -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(reading)?)?;
+        Ok(())
+    };
+    write().with_context(|| format!("failed to cache the weather in {}", path.display()))
+}
+
+/// A short description of a WMO weather interpretation code, as used by Open-Meteo.
+fn describe(code: u8) -> &'static str {
+    match code {
+        0 => "Clear",
+        1 => "Mainly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 | 48 => "Fog",
+        51 | 53 | 55 => "Drizzle",
+        56 | 57 => "Freezing drizzle",
+        61 => "Light rain",
+        63 => "Rain",
+        65 => "Heavy rain",
+        66 | 67 => "Freezing rain",
+        71 => "Light snow",
+        73 => "Snow",
+        75 => "Heavy snow",
+        77 => "Snow grains",
+        80..=82 => "Rain showers",
+        85 | 86 => "Snow showers",
+        95 => "Thunderstorm",
+        96 | 99 => "Thunderstorm with hail",
+        _ => "Unknown conditions",
+    }
+}
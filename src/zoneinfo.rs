@@ -0,0 +1,129 @@
+//! Timezones by IANA name, from the system's tz database where there is one, e.g.
+//! `/usr/share/zoneinfo`, and otherwise from the snapshot compiled into chrono-tz. The system's
+//! database is updated along with the system, so offsets changed by law are followed without
+//! rebuilding the clock.
+
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use chrono_tz::{OffsetName, Tz};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Where systems keep their compiled tz database, searched after `$TZDIR`.
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr/share/zoneinfo",
+    "/usr/lib/zoneinfo",
+    "/usr/share/lib/zoneinfo",
+    "/etc/zoneinfo",
+];
+
+/// Zones loaded so far, by name, as files are only read once.
+static LOADED: Mutex<Option<HashMap<String, Option<Zone>>>> = Mutex::new(None);
+
+/// A timezone, by IANA name, e.g. `America/Chicago`.
+#[derive(Debug, Clone)]
+pub struct Zone {
+    name: &'static str,
+    rules: Rules,
+}
+
+#[derive(Debug, Clone)]
+enum Rules {
+    /// From the system's tz database.
+    System(Arc<tz::TimeZone>),
+    /// From the snapshot compiled into chrono-tz.
+    Bundled(Tz),
+}
+
+impl Zone {
+    /// The zone named `name`, or `None` if neither the system nor the compiled-in snapshot know
+    /// it.
+    pub fn load(name: &str) -> Option<Zone> {
+        let mut loaded = LOADED.lock().unwrap();
+        let loaded = loaded.get_or_insert_with(HashMap::new);
+        if let Some(zone) = loaded.get(name) {
+            return zone.clone();
+        }
+        let rules = match read_system(name) {
+            Some(zone) => Some(Rules::System(Arc::new(zone))),
+            None => name.parse().ok().map(Rules::Bundled),
+        };
+        let zone = rules.map(|rules| Zone {
+            // Zones are few, and live as long as the app.
+            name: Box::leak(name.to_owned().into_boxed_str()),
+            rules,
+        });
+        loaded.insert(name.to_owned(), zone.clone());
+        zone
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Whether the rules come from the system's tz database, rather than the compiled-in
+    /// snapshot.
+    pub fn is_from_system(&self) -> bool {
+        matches!(self.rules, Rules::System(_))
+    }
+
+    /// The offset from UTC in effect at `time`.
+    pub fn offset_at(&self, time: &DateTime<Utc>) -> FixedOffset {
+        match &self.rules {
+            Rules::System(zone) => {
+                let offset = zone
+                    .find_local_time_type(time.timestamp())
+                    .map_or(0, |local| local.ut_offset());
+                FixedOffset::east_opt(offset).unwrap_or(Utc.fix())
+            }
+            Rules::Bundled(tz) => time.with_timezone(tz).offset().fix(),
+        }
+    }
+
+    /// The abbreviation of the offset in effect at `time`, e.g. `CST`, or the offset itself
+    /// where the zone has no abbreviation, e.g. `+03`.
+    pub fn abbreviation_at(&self, time: &DateTime<Utc>) -> String {
+        match &self.rules {
+            Rules::System(zone) => zone.find_local_time_type(time.timestamp()).map_or_else(
+                |_| "UTC".to_owned(),
+                |local| local.time_zone_designation().to_owned(),
+            ),
+            Rules::Bundled(tz) => time.with_timezone(tz).offset().abbreviation().to_owned(),
+        }
+    }
+
+    /// `time` in this zone.
+    pub fn local(&self, time: &DateTime<Utc>) -> DateTime<FixedOffset> {
+        time.with_timezone(&self.offset_at(time))
+    }
+}
+
+/// Reads the zone named `name` from the first of the system's tz databases that has it.
+fn read_system(name: &str) -> Option<tz::TimeZone> {
+    // Names are relative paths within the database, which must not lead out of it.
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let dirs = std::env::var_os("TZDIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(SYSTEM_DIRS.iter().map(PathBuf::from));
+    for dir in dirs {
+        let Ok(bytes) = std::fs::read(dir.join(relative)) else {
+            continue;
+        };
+        match tz::TimeZone::from_tz_data(&bytes) {
+            Ok(zone) => return Some(zone),
+            Err(err) => log::warn!(
+                "invalid timezone file {}: {}",
+                dir.join(relative).display(),
+                err
+            ),
+        }
+    }
+    None
+}
@@ -0,0 +1,152 @@
+// Renders the layers at fixed times and compares them with the reference images in
+// `tests/golden`. Run with `UPDATE_GOLDEN=1` to replace the references after an intended change.
+// Without a graphics adapter they are skipped, except in CI, where they fail instead.
+
+use chrono::{DateTime, TimeZone, Utc};
+use global_clock::layer::draw_layers;
+use global_clock::{
+    screenshot, Assets, Background, ClockFace, FrameInfo, Globe, GraphicsContext,
+    GraphicsContextInner, Layer, StagingPool, Theme, Viewport,
+};
+use image::RgbaImage;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const SIZE: u32 = 256;
+
+/// Channels that differ by more than this count as a different pixel; drivers and software
+/// renderers round and filter slightly differently.
+const CHANNEL_TOLERANCE: u8 = 24;
+
+/// Fraction of pixels that may differ, e.g. along anti-aliased edges.
+const PIXEL_TOLERANCE: f64 = 0.01;
+
+/// Opens a device without a window, or returns `None` (skipping the test) if there is none. In
+/// CI, which sets `CI`, a missing adapter fails the test instead of passing without comparing.
+fn device() -> Option<GraphicsContext> {
+    let gfx = open_device();
+    if gfx.is_none() {
+        if std::env::var_os("CI").is_some() {
+            panic!("no graphics adapter; install a software renderer such as lavapipe");
+        }
+        eprintln!("no graphics adapter; skipping");
+    }
+    gfx
+}
+
+fn open_device() -> Option<GraphicsContext> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&Default::default(), None)).ok()?;
+    Some(Arc::new(GraphicsContextInner {
+        device,
+        queue,
+        render_format: wgpu::TextureFormat::Rgba8UnormSrgb,
+    }))
+}
+
+fn render(
+    gfx: &GraphicsContext,
+    time: DateTime<Utc>,
+    create: impl FnOnce(&GraphicsContext, &Viewport, &Assets, &Theme) -> Box<dyn Layer>,
+) -> RgbaImage {
+    // An empty search path loads the assets from the source tree.
+    let assets = Assets::new(Vec::new());
+    let theme = Theme::default();
+    let viewport = Viewport::new(gfx, SIZE, SIZE, Default::default());
+    let background = Background::new(gfx, &theme);
+    let mut layer = create(gfx, &viewport, &assets, &theme);
+    layer.finish_loading();
+    layer.update(&FrameInfo {
+        time,
+        local_time: time.time(),
+        local_date: time.date_naive(),
+        timezone: Some("UTC"),
+    });
+    screenshot::capture(gfx, SIZE, SIZE, |encoder, view| {
+        draw_layers(
+            encoder,
+            &mut StagingPool::new(gfx),
+            view,
+            &background,
+            std::slice::from_mut(&mut layer),
+            &viewport,
+        );
+    })
+    .unwrap()
+}
+
+fn check(name: &str, actual: &RgbaImage) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&path).unwrap();
+        return;
+    }
+    let expected = image::open(&path)
+        .unwrap_or_else(|err| panic!("failed to open {}: {}", path.display(), err))
+        .to_rgba8();
+    assert_eq!(expected.dimensions(), actual.dimensions());
+
+    let different = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(e, a)| {
+            e.0.iter()
+                .zip(a.0.iter())
+                .any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let fraction = different as f64 / (SIZE * SIZE) as f64;
+    if fraction > PIXEL_TOLERANCE {
+        let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "{:.1}% of pixels differ from {}; the rendered image was saved to {}",
+            fraction * 100.0,
+            path.display(),
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn globe_at_june_solstice() {
+    let Some(gfx) = device() else {
+        return;
+    };
+    let time = Utc.with_ymd_and_hms(2024, 6, 20, 12, 0, 0).unwrap();
+    let image = render(&gfx, time, |gfx, viewport, assets, theme| {
+        Box::new(Globe::new(gfx, viewport, assets, None, theme, None).unwrap())
+    });
+    check("globe_june_solstice", &image);
+}
+
+#[test]
+fn globe_at_december_solstice() {
+    let Some(gfx) = device() else {
+        return;
+    };
+    let time = Utc.with_ymd_and_hms(2024, 12, 21, 3, 30, 0).unwrap();
+    let image = render(&gfx, time, |gfx, viewport, assets, theme| {
+        Box::new(Globe::new(gfx, viewport, assets, None, theme, None).unwrap())
+    });
+    check("globe_december_solstice", &image);
+}
+
+#[test]
+fn clock_face_in_the_morning() {
+    let Some(gfx) = device() else {
+        return;
+    };
+    let time = Utc.with_ymd_and_hms(2024, 3, 20, 7, 45, 0).unwrap();
+    let image = render(&gfx, time, |gfx, viewport, assets, theme| {
+        Box::new(ClockFace::new(gfx, viewport, assets, theme, None).unwrap())
+    });
+    check("clock_face_morning", &image);
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use global_clock::gps::{parse_gpsd, parse_nmea};
+
+fn time(rfc3339: &str) -> DateTime<Utc> {
+    rfc3339.parse().unwrap()
+}
+
+#[test]
+fn nmea_rmc_gives_time_and_position() {
+    let reading =
+        parse_nmea("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A").unwrap();
+    assert_eq!(reading.time, Some(time("1994-03-23T12:35:19Z")));
+    let location = reading.location.unwrap();
+    assert!((location.latitude - 48.1173).abs() < 1e-4);
+    assert!((location.longitude - 11.5167).abs() < 1e-4);
+
+    let reading =
+        parse_nmea("$GNRMC,001031.50,A,3352.128,S,15112.558,W,0.0,0.0,161026,,,A*49").unwrap();
+    assert_eq!(reading.time, Some(time("2026-10-16T00:10:31.5Z")));
+    let location = reading.location.unwrap();
+    assert!((location.latitude + 33.8688).abs() < 1e-4);
+    assert!((location.longitude + 151.2093).abs() < 1e-4);
+}
+
+#[test]
+fn nmea_without_a_fix_or_corrupted_is_ignored() {
+    // No fix.
+    assert_eq!(parse_nmea("$GPRMC,123519,V,,,,,,,230394,,*33"), None);
+    // Wrong checksum.
+    assert_eq!(
+        parse_nmea("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6B"),
+        None
+    );
+    // No checksum.
+    assert_eq!(
+        parse_nmea("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W"),
+        None
+    );
+    // Other sentences.
+    assert_eq!(
+        parse_nmea("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47"),
+        None
+    );
+}
+
+#[test]
+fn nmea_coordinates_off_the_globe_are_ignored() {
+    for sentence in [
+        "$GPRMC,123519,A,9107.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6E",
+        "$GPRMC,123519,A,4807.038,N,18131.000,E,022.4,084.4,230394,003.1,W*62",
+    ] {
+        let reading = parse_nmea(sentence).unwrap();
+        assert_eq!(reading.time, Some(time("1994-03-23T12:35:19Z")));
+        assert_eq!(reading.location, None, "{}", sentence);
+    }
+}
+
+#[test]
+fn gpsd_tpv_gives_time_and_position() {
+    let reading = parse_gpsd(
+        r#"{"class":"TPV","device":"/dev/ttyUSB0","mode":3,"time":"2026-10-16T16:37:52.000Z","lat":-33.8688,"lon":151.2093,"alt":12.0}"#,
+    )
+    .unwrap();
+    assert_eq!(reading.time, Some(time("2026-10-16T16:37:52Z")));
+    let location = reading.location.unwrap();
+    assert_eq!(
+        (location.latitude, location.longitude),
+        (-33.8688, 151.2093)
+    );
+
+    assert_eq!(
+        parse_gpsd(r#"{"class":"TPV","device":"/dev/ttyUSB0","mode":1}"#),
+        None
+    );
+    assert_eq!(parse_gpsd(r#"{"class":"VERSION","release":"3.25"}"#), None);
+}
@@ -0,0 +1,93 @@
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use global_clock::locale::{DateOrder, HourCycle, LocaleConfig};
+use global_clock::{Corner, Locale};
+
+fn locale(name: &str) -> Locale {
+    Locale::from_config(&LocaleConfig {
+        name: Some(name.to_owned()),
+        ..Default::default()
+    })
+}
+
+fn new_years_eve() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2000, 12, 31).unwrap()
+}
+
+#[test]
+fn config_overrides_the_locale() {
+    let locale = Locale::from_config(&LocaleConfig {
+        name: Some("en-US".to_owned()),
+        hour_cycle: Some(HourCycle::H24),
+        date_order: Some(DateOrder::Ymd),
+        first_weekday: Some(Weekday::Mon),
+    });
+    assert_eq!(locale.format_date(&new_years_eve()), "2000-12-31");
+    assert_eq!(locale.first_weekday, Weekday::Mon);
+    let evening = NaiveTime::from_hms_opt(18, 30, 0).unwrap();
+    assert!(locale.format_time(&evening, false).contains("18"));
+}
+
+#[test]
+fn conventions_follow_the_region() {
+    let us = locale("en_US.UTF-8");
+    assert_eq!(us.hour_cycle, HourCycle::H12);
+    assert_eq!(us.date_order, DateOrder::Mdy);
+    assert_eq!(us.first_weekday, Weekday::Sun);
+    let germany = locale("de-DE");
+    assert_eq!(germany.hour_cycle, HourCycle::H24);
+    assert_eq!(germany.date_order, DateOrder::Dmy);
+    assert_eq!(germany.first_weekday, Weekday::Mon);
+}
+
+#[test]
+fn right_to_left_locales_mirror_corners() {
+    for name in ["ar-EG", "he_IL.UTF-8", "fa-IR"] {
+        let rtl = locale(name);
+        assert!(rtl.right_to_left, "{}", name);
+        assert_eq!(rtl.corner(Corner::TopLeft), Corner::TopRight);
+        assert_eq!(rtl.corner(Corner::BottomRight), Corner::BottomLeft);
+    }
+    let us = locale("en-US");
+    assert!(!us.right_to_left);
+    assert_eq!(us.corner(Corner::TopLeft), Corner::TopLeft);
+}
+
+#[cfg(feature = "icu")]
+#[test]
+fn names_are_translated() {
+    let germany = locale("de-DE");
+    assert_eq!(
+        germany.format_long_date(&new_years_eve()),
+        "Sonntag, 31. Dezember 2000"
+    );
+    assert_eq!(germany.format_month(&new_years_eve()), "Dezember 2000");
+    assert_eq!(germany.short_weekday(Weekday::Sun), "So");
+}
+
+#[cfg(feature = "icu")]
+#[test]
+fn digits_follow_the_numbering_system() {
+    let egypt = locale("ar-EG");
+    assert_eq!(egypt.localize_digits("Week 52"), "Week ٥٢");
+    assert_eq!(egypt.format_date(&new_years_eve()), "٣١.١٢.٢٠٠٠");
+    let india = locale("hi-IN-u-nu-deva");
+    assert_eq!(india.localize_digits("2000"), "२०००");
+    assert_eq!(locale("en-US").localize_digits("2000"), "2000");
+}
+
+#[cfg(not(feature = "icu"))]
+#[test]
+fn ages_switch_to_hours_from_two_hours() {
+    let us = locale("en-US");
+    assert_eq!(us.format_age(45), "45 min ago");
+    assert_eq!(us.format_age(119), "119 min ago");
+    assert_eq!(us.format_age(150), "2 h ago");
+}
+
+#[cfg(feature = "icu")]
+#[test]
+fn ages_are_translated() {
+    let germany = locale("de-DE");
+    assert_eq!(germany.format_age(45), "vor 45 Min.");
+    assert_eq!(germany.format_age(150), "vor 2 Std.");
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use global_clock::model::{self, MAX_AXIAL_TILT, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use global_clock::{astronomy, globe, night_light, Camera, Location};
+use proptest::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+const EPSILON: f32 = 1e-3;
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "expected {} ± {}, got {}",
+        expected,
+        tolerance,
+        actual
+    );
+}
+
+/// Reduces an angle to `[0, TAU)`.
+fn normalize(angle: f32) -> f32 {
+    angle.rem_euclid(TAU)
+}
+
+#[test]
+fn hour_hand_points_down_at_noon() {
+    assert_close(model::hour_hand_angle(0.0), 0.0, EPSILON);
+    assert_close(
+        model::hour_hand_angle(6.0 * SECONDS_PER_HOUR),
+        PI / 2.0,
+        EPSILON,
+    );
+    assert_close(model::hour_hand_angle(12.0 * SECONDS_PER_HOUR), PI, EPSILON);
+}
+
+#[test]
+fn minute_hand_turns_once_per_hour() {
+    assert_close(model::minute_hand_angle(15.0 * 60.0), PI / 2.0, EPSILON);
+    assert_close(model::minute_hand_angle(SECONDS_PER_HOUR), TAU, EPSILON);
+}
+
+#[test]
+fn seconds_from_midnight_include_the_fraction() {
+    let time = chrono::NaiveTime::from_hms_milli_opt(6, 0, 30, 250).unwrap();
+    assert_close(
+        model::seconds_from_midnight(&time),
+        6.0 * SECONDS_PER_HOUR + 30.25,
+        0.01,
+    );
+}
+
+#[test]
+fn globe_rotation_is_a_quarter_turn_ahead_of_utc() {
+    assert_close(model::globe_rotation(0.0), TAU / 4.0, EPSILON);
+    assert_close(model::globe_rotation(18.0 * SECONDS_PER_HOUR), TAU, EPSILON);
+}
+
+#[test]
+fn globe_is_seen_from_below_the_south_pole() {
+    assert_eq!(globe::project(-PI / 2.0, 1.0, 2.0).length(), 0.0);
+    assert_close(globe::project(PI / 2.0, 1.0, 2.0).length(), 0.8, EPSILON);
+}
+
+#[test]
+fn axial_tilt_follows_the_seasons() {
+    // March equinox, June solstice, September equinox and December solstice. The orbit is not
+    // circular, so the sine approximation is a few days off in September.
+    assert_close(model::axial_tilt(78.0), 0.0, 0.01);
+    assert_close(model::axial_tilt(171.0), MAX_AXIAL_TILT, 0.01);
+    assert_close(model::axial_tilt(264.0), 0.0, 0.03);
+    assert_close(model::axial_tilt(354.0), -MAX_AXIAL_TILT, 0.01);
+}
+
+#[test]
+fn terminator_is_a_quarter_turn_from_the_sun() {
+    let direction = |(latitude, longitude): (f64, f64)| {
+        let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+        [
+            latitude.cos() * longitude.cos(),
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+        ]
+    };
+    for time in [
+        "2025-03-20T09:00:00Z",
+        "2025-06-21T12:00:00Z",
+        "2025-12-21T21:30:00Z",
+    ] {
+        let time: DateTime<Utc> = time.parse().unwrap();
+        let sun = direction(astronomy::subsolar_point(&time));
+        for point in astronomy::terminator(&time, 37) {
+            let point = direction(point);
+            let dot: f64 = (0..3).map(|i| sun[i] * point[i]).sum();
+            assert_close(dot as f32, 0.0, EPSILON);
+        }
+    }
+}
+
+#[test]
+fn equation_of_time_matches_almanac() {
+    // Extremes of the year, in minutes; almanac values are -14.2 (Feb 11) and +16.4 (Nov 3).
+    assert_close(model::equation_of_time(41.0), -14.2, 0.5);
+    assert_close(model::equation_of_time(306.0), 16.4, 0.5);
+    // Close to zero around these dates.
+    assert_close(model::equation_of_time(104.0), 0.0, 0.5);
+    assert_close(model::equation_of_time(358.0), 0.0, 0.5);
+}
+
+#[test]
+fn night_light_ramps_over_twilight() {
+    let berlin = Location {
+        latitude: 52.5,
+        longitude: 13.4,
+        name: None,
+    };
+    let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+    // The Sun sets there at about 15:00 UTC at new year, and civil twilight ends 45 minutes later.
+    assert_eq!(
+        night_light::strength(&berlin, &at("2000-12-31T12:00:00Z")),
+        0.0
+    );
+    let dusk = night_light::strength(&berlin, &at("2000-12-31T15:20:00Z"));
+    assert!(dusk > 0.0 && dusk < 1.0, "{}", dusk);
+    assert_eq!(
+        night_light::strength(&berlin, &at("2000-12-31T22:00:00Z")),
+        1.0
+    );
+    let noon = astronomy::sun_altitude(&berlin, &at("2000-06-21T11:00:00Z"));
+    assert_close(noon as f32, 90.0 - 52.5 + MAX_AXIAL_TILT.to_degrees(), 1.0);
+}
+
+#[test]
+fn blackbody_is_warmer_at_lower_temperatures() {
+    let daylight = night_light::blackbody(night_light::DAYLIGHT_TEMPERATURE);
+    assert!(daylight.r == 255 && daylight.g > 250 && daylight.b > 245);
+    let candle = night_light::blackbody(1900.0);
+    assert!(candle.r == 255 && candle.g < 150 && candle.b < 50);
+}
+
+#[test]
+fn restored_cameras_are_clamped() {
+    let restored = |zoom: f32, rotation: f32| Camera { zoom, rotation }.clamped();
+    assert_eq!(restored(0.0, 0.0).zoom, 0.5);
+    assert_eq!(restored(1e9, 0.0).zoom, 8.0);
+    assert_eq!(restored(f32::NAN, f32::INFINITY), Camera::default());
+    assert_close(restored(2.0, -PI / 2.0).rotation, 3.0 * PI / 2.0, EPSILON);
+}
+
+proptest! {
+    #[test]
+    fn hour_hand_and_globe_turn_together(seconds in 0.0f32..SECONDS_PER_DAY) {
+        // Both make one turn per day, so they stay a fixed angle apart.
+        let difference = model::globe_rotation(seconds) - model::hour_hand_angle(seconds);
+        prop_assert!((difference - TAU / 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn minute_hand_turns_24_times_faster(seconds in 0.0f32..SECONDS_PER_DAY) {
+        let ratio = model::minute_hand_angle(seconds) / model::hour_hand_angle(seconds);
+        prop_assert!(seconds < 1.0 || (ratio - 24.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn globe_rotation_repeats_daily(seconds in 0.0f32..SECONDS_PER_DAY) {
+        let today = normalize(model::globe_rotation(seconds));
+        let tomorrow = normalize(model::globe_rotation(seconds + SECONDS_PER_DAY));
+        let difference = (today - tomorrow).abs();
+        prop_assert!(difference < EPSILON || (TAU - difference) < EPSILON);
+    }
+
+    #[test]
+    fn globe_keeps_noon_at_the_bottom(seconds in 0.0f32..SECONDS_PER_DAY) {
+        // Where the Sun is overhead on the equator, which moves west from the prime meridian at
+        // noon UTC.
+        let longitude = PI - seconds / SECONDS_PER_DAY * TAU;
+        let point = globe::project(0.0, longitude, model::globe_rotation(seconds));
+        prop_assert!(point.x.abs() < EPSILON && point.y < 0.0);
+    }
+
+    #[test]
+    fn axial_tilt_is_bounded(day in 0.0f32..366.0) {
+        prop_assert!(model::axial_tilt(day).abs() <= MAX_AXIAL_TILT + f32::EPSILON);
+    }
+
+    #[test]
+    fn equation_of_time_is_bounded(day in 0.0f32..366.0) {
+        prop_assert!(model::equation_of_time(day).abs() < 17.0);
+    }
+}
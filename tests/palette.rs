@@ -0,0 +1,49 @@
+use global_clock::theme::Color;
+use global_clock::Palette;
+
+const DEFICIENCIES: [Palette; 3] = [
+    Palette::Deuteranopia,
+    Palette::Protanopia,
+    Palette::Tritanopia,
+];
+
+#[test]
+fn standard_palette_keeps_colors() {
+    let red = Color::rgba(220, 40, 30, 200);
+    assert_eq!(Palette::Standard.adapt(red), red);
+}
+
+#[test]
+fn grays_are_seen_by_everyone() {
+    for palette in DEFICIENCIES {
+        for level in [0, 128, 255] {
+            let gray = Color::rgba(level, level, level, 255);
+            let adapted = palette.adapt(gray);
+            for (channel, expected) in [(adapted.r, level), (adapted.g, level), (adapted.b, level)]
+            {
+                assert!(
+                    (channel as i32 - expected as i32).abs() <= 2,
+                    "{:?} changed {:?} to {:?}",
+                    palette,
+                    gray,
+                    adapted
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn errors_differ_from_warnings_in_blue_for_red_green_deficiencies() {
+    for palette in [Palette::Deuteranopia, Palette::Protanopia] {
+        let warning = palette.warning_background();
+        let error = palette.error_background();
+        assert!(
+            (warning.b as i32 - error.b as i32).abs() >= 60,
+            "{:?}: {:?} and {:?}",
+            palette,
+            warning,
+            error
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use global_clock::process::{output_with_timeout, shell};
+use std::time::Duration;
+
+#[test]
+fn output_of_a_quick_command() {
+    let output = output_with_timeout(&mut shell("echo ticker"), Duration::from_secs(10)).unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ticker");
+}
+
+#[cfg(unix)]
+#[test]
+fn hanging_command_is_killed() {
+    use std::io::ErrorKind;
+    use std::time::Instant;
+
+    let started = Instant::now();
+    let err =
+        output_with_timeout(&mut shell("exec sleep 30"), Duration::from_millis(200)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,80 @@
+use global_clock::{Banners, LayerRegistry, SharedState};
+use std::sync::Arc;
+
+const BUDGET: Option<u64> = Some(64 << 20);
+
+#[test]
+fn layers_without_textures_leave_the_globe_budget_unchanged() {
+    let mut registry = LayerRegistry::default();
+    registry.register("grid", |cx| Ok(Box::new(Banners::new(cx)?)));
+    let alone = registry.texture_budget(BUDGET, &["globe"]);
+    assert_eq!(alone, BUDGET);
+    assert_eq!(
+        registry.texture_budget(BUDGET, &["globe", "grid", "banners"]),
+        alone
+    );
+}
+
+#[test]
+fn textured_layers_share_the_budget() {
+    let registry = LayerRegistry::default();
+    assert_eq!(
+        registry.texture_budget(BUDGET, &["globe", "banners", "clock_face"]),
+        Some(32 << 20)
+    );
+}
+
+#[test]
+fn replacing_a_textured_layer_follows_the_new_registration() {
+    let mut registry = LayerRegistry::default();
+    registry.register("clock_face", |cx| Ok(Box::new(Banners::new(cx)?)));
+    assert_eq!(
+        registry.texture_budget(BUDGET, &["globe", "clock_face"]),
+        BUDGET
+    );
+}
+
+#[test]
+fn unlimited_budget_stays_unlimited() {
+    let registry = LayerRegistry::default();
+    assert_eq!(
+        registry.texture_budget(None, &["globe", "clock_face"]),
+        None
+    );
+}
+
+#[test]
+fn settings_are_checked_without_creating_the_layer() {
+    let registry = LayerRegistry::default();
+    let table: toml::Table = toml::from_str("size = 20\nseconds = \"long\"").unwrap();
+    let problems = registry.check_settings("banners", &table);
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].key, "seconds");
+    assert!(registry
+        .check_settings("banners", &toml::from_str("size = 20").unwrap())
+        .is_empty());
+    // The globe has no settings to check.
+    assert!(registry.check_settings("globe", &table).is_empty());
+}
+
+#[test]
+fn shared_state_lives_while_held() {
+    let shared = SharedState::default();
+    let first = shared
+        .get_or_try_insert_with(|_: &u32| true, || Ok(1))
+        .unwrap();
+    let again = shared
+        .get_or_try_insert_with(|_: &u32| true, || Ok(2))
+        .unwrap();
+    assert!(Arc::ptr_eq(&first, &again));
+    // One that does not fit, e.g. for another location, replaces it for later layers.
+    let other = shared
+        .get_or_try_insert_with(|value: &u32| *value == 3, || Ok(3))
+        .unwrap();
+    assert_eq!((*first, *other), (1, 3));
+    drop(other);
+    let fresh = shared
+        .get_or_try_insert_with(|_: &u32| true, || Ok(4))
+        .unwrap();
+    assert_eq!(*fresh, 4);
+}
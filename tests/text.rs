@@ -0,0 +1,44 @@
+use global_clock::text::Fonts;
+use global_clock::Assets;
+
+fn fonts() -> Fonts {
+    // An empty search path loads the assets from the source tree.
+    Fonts::load(&Assets::new(Vec::new())).unwrap()
+}
+
+fn assert_drawable(fonts: &Fonts, line: &str) {
+    let glyphs = fonts.glyphs(line, false);
+    assert!(!glyphs.is_empty(), "{:?} has no glyphs", line);
+    for (font, id) in glyphs {
+        assert_ne!(id.0, 0, "{:?} has a missing glyph in font {}", line, font);
+    }
+}
+
+#[test]
+fn hebrew_is_drawable() {
+    assert_drawable(&fonts(), "תל אביב-יפו");
+    assert_drawable(&fonts(), "ירושלים 12:30");
+}
+
+#[test]
+fn arabic_is_drawable() {
+    assert_drawable(&fonts(), "القاهرة");
+}
+
+#[test]
+fn latin_keeps_the_monospace_font() {
+    for (font, _) in fonts().glyphs("Tel Aviv 12:30", false) {
+        assert_eq!(font, 0);
+    }
+}
+
+#[test]
+fn mixed_lines_read_from_the_right() {
+    let fonts = fonts();
+    // The line starts with Hebrew, so it reads from the right: the number is at the left end,
+    // in the monospace font, and the Hebrew word at the right end, in the fallback font.
+    let glyphs = fonts.glyphs("חיפה 42", false);
+    assert_eq!(glyphs.len(), 7);
+    assert_eq!(glyphs[0].0, 0);
+    assert_ne!(glyphs[6].0, 0);
+}
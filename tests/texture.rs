@@ -0,0 +1,166 @@
+use global_clock::texture::{encode_bc7, load_compressed, TextureData};
+use image::{Rgba, RgbaImage};
+use std::convert::TryInto;
+use std::sync::Arc;
+
+/// Interpolation weights for 4-bit indices, out of 64, from the BC7 specification.
+const WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// A BC7 mode 6 block, read independently of the encoder.
+struct Mode6 {
+    bits: u128,
+}
+
+impl Mode6 {
+    fn new(block: &[u8]) -> Self {
+        Self {
+            bits: u128::from_le_bytes(block.try_into().unwrap()),
+        }
+    }
+
+    fn bits(&self, offset: u32, count: u32) -> u32 {
+        ((self.bits >> offset) & ((1 << count) - 1)) as u32
+    }
+
+    /// The endpoint, `0` or `1`, with its p-bit as the lowest bit of each channel.
+    fn endpoint(&self, which: u32) -> [u32; 4] {
+        let p_bit = self.bits(63 + which, 1);
+        [0, 1, 2, 3].map(|channel| self.bits(7 + 14 * channel + 7 * which, 7) << 1 | p_bit)
+    }
+
+    /// The index of pixel `i`; the first one has 3 bits, with a highest bit of 0.
+    fn index(&self, i: u32) -> u32 {
+        match i {
+            0 => self.bits(65, 3),
+            _ => self.bits(68 + 4 * (i - 1), 4),
+        }
+    }
+
+    fn pixel(&self, i: u32) -> [u32; 4] {
+        let (e0, e1) = (self.endpoint(0), self.endpoint(1));
+        let weight = WEIGHTS[self.index(i) as usize];
+        [0, 1, 2, 3].map(|c| ((64 - weight) * e0[c] + weight * e1[c] + 32) >> 6)
+    }
+}
+
+fn block(pixel: impl Fn(u32, u32) -> [u8; 4]) -> RgbaImage {
+    RgbaImage::from_fn(4, 4, |x, y| Rgba(pixel(x, y)))
+}
+
+/// Encodes a 4×4 image and checks that it decodes to within `tolerance` in every channel.
+fn assert_round_trip(image: &RgbaImage, tolerance: u32) -> Mode6 {
+    let blocks = encode_bc7(image);
+    assert_eq!(blocks.len(), 16);
+    let decoded = Mode6::new(&blocks);
+    assert_eq!(decoded.bits(0, 7), 1 << 6, "not a mode 6 block");
+    for i in 0..16 {
+        let expected = image.get_pixel(i % 4, i / 4).0;
+        let actual = decoded.pixel(i);
+        for c in 0..4 {
+            let error = (actual[c] as i32 - expected[c] as i32).unsigned_abs();
+            assert!(
+                error <= tolerance,
+                "pixel {} decoded as {:?}, expected {:?}",
+                i,
+                actual,
+                expected
+            );
+        }
+    }
+    decoded
+}
+
+#[test]
+fn flat_block_keeps_its_color() {
+    let decoded = assert_round_trip(&block(|_, _| [200, 100, 51, 255]), 1);
+    for which in 0..2 {
+        let endpoint = decoded.endpoint(which);
+        assert!(endpoint[0].abs_diff(200) <= 1 && endpoint[3].abs_diff(255) <= 1);
+    }
+}
+
+#[test]
+fn gradient_block_keeps_its_shades() {
+    let image = block(|x, y| {
+        let t = (x + 4 * y) as u8;
+        [16 * t, 255 - 12 * t, 40 + 8 * t, 255]
+    });
+    assert_round_trip(&image, 6);
+}
+
+#[test]
+fn alpha_is_kept() {
+    let image = block(|x, y| [30, 60, 90, (x * 85) as u8 / 2 + (y * 85) as u8 / 2]);
+    assert_round_trip(&image, 6);
+}
+
+#[test]
+fn endpoints_and_indices_are_where_the_format_puts_them() {
+    // Black in the first pixel and white elsewhere: the first index must be 0, and so the
+    // endpoints run from black to white and every other index is 15.
+    let image = block(|x, y| {
+        if x == 0 && y == 0 {
+            [0, 0, 0, 0]
+        } else {
+            [255, 255, 255, 255]
+        }
+    });
+    let decoded = assert_round_trip(&image, 1);
+    assert_eq!(decoded.endpoint(0), [0; 4]);
+    assert_eq!(decoded.endpoint(1), [255; 4]);
+    assert_eq!(decoded.bits(63, 2), 0b10, "p-bits");
+    assert_eq!(decoded.index(0), 0);
+    for i in 1..16 {
+        assert_eq!(decoded.index(i), 15, "index of pixel {}", i);
+    }
+}
+
+#[test]
+fn blocks_are_in_rows() {
+    // Each block of an 8×4 image is a flat color, the left one first, in colors that 7 bits and a
+    // shared p-bit store exactly.
+    let image = RgbaImage::from_fn(8, 4, |x, _| {
+        Rgba(if x < 4 {
+            [254, 0, 0, 254]
+        } else {
+            [0, 0, 254, 254]
+        })
+    });
+    let blocks = encode_bc7(&image);
+    assert_eq!(blocks.len(), 32);
+    assert_eq!(Mode6::new(&blocks[..16]).pixel(5), [254, 0, 0, 254]);
+    assert_eq!(Mode6::new(&blocks[16..]).pixel(5), [0, 0, 254, 254]);
+}
+
+#[test]
+fn cache_with_an_impossible_size_is_a_miss() {
+    let image = Arc::new(RgbaImage::from_pixel(8, 8, Rgba([10, 20, 30, 255])));
+    let mut bytes = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageOutputFormat::Png,
+        )
+        .unwrap();
+    let cache_dir = std::env::temp_dir().join(format!("global-clock-test-{}", std::process::id()));
+    let load = || load_compressed(&bytes, "square.png", &cache_dir, None, || Ok(image.clone()));
+    load().unwrap();
+
+    // A header that claims the largest size a multiple of 4, whose blocks don't fit in 32 bits.
+    let cache_file = std::fs::read_dir(cache_dir.join("textures"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let mut contents = std::fs::read(&cache_file).unwrap();
+    contents[4..12].copy_from_slice(&[0xfc, 0xff, 0xff, 0xff, 0xfc, 0xff, 0xff, 0xff]);
+    std::fs::write(&cache_file, contents).unwrap();
+    let data = load();
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+
+    match data.unwrap() {
+        TextureData::Bc7 { width, height, .. } => assert_eq!((width, height), (8, 8)),
+        TextureData::Rgba(_) => panic!("not compressed"),
+    }
+}
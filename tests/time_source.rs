@@ -0,0 +1,65 @@
+use chrono::{DateTime, Duration, Utc};
+use global_clock::time_source::{
+    CorrectedTime, FixedTime, SimulatedTime, SmoothedTime, TimeSource,
+};
+
+fn start() -> DateTime<Utc> {
+    "2026-10-16T12:00:00Z".parse().unwrap()
+}
+
+#[test]
+fn jumps_are_caught_up_with_gradually() {
+    let source = CorrectedTime::new(FixedTime(start()));
+    let offset = source.offset();
+    let time = SmoothedTime::new(source);
+    assert_eq!(time.now(), start());
+    assert!(!time.in_transition());
+
+    offset.set(Duration::hours(-3));
+    let shown = time.now();
+    assert!(shown > start() - Duration::minutes(1), "{}", shown);
+    assert!(time.in_transition());
+
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    assert_eq!(time.now(), start() - Duration::hours(3));
+    assert!(!time.in_transition());
+}
+
+#[test]
+fn small_and_huge_jumps_are_shown_at_once() {
+    let source = CorrectedTime::new(FixedTime(start()));
+    let offset = source.offset();
+    let time = SmoothedTime::new(source);
+    time.now();
+
+    offset.set(Duration::milliseconds(200));
+    assert_eq!(time.now(), start() + Duration::milliseconds(200));
+    assert!(!time.in_transition());
+
+    offset.set(Duration::days(400));
+    assert_eq!(time.now(), start() + Duration::days(400));
+    assert!(!time.in_transition());
+}
+
+#[test]
+fn simulated_time_runs_for_centuries() {
+    // Three centuries pass in every millisecond, more nanoseconds than an `i64` holds.
+    let speed = 1e13;
+    let forwards = SimulatedTime::new(start(), speed);
+    let backwards = SimulatedTime::new(start(), -speed);
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let centuries = Duration::days(600 * 365);
+    assert!(forwards.now() > start() + centuries, "{}", forwards.now());
+    assert!(backwards.now() < start() - centuries, "{}", backwards.now());
+    let earlier = forwards.now();
+    assert!(forwards.now() >= earlier);
+}
+
+#[test]
+fn simulated_time_stops_at_the_end_of_dates() {
+    let forwards = SimulatedTime::new(start(), 1e30);
+    let backwards = SimulatedTime::new(start(), -1e30);
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    assert_eq!(forwards.now(), DateTime::<Utc>::MAX_UTC);
+    assert_eq!(backwards.now(), DateTime::<Utc>::MIN_UTC);
+}